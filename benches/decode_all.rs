@@ -1,14 +1,56 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use ruzstd::decoding::FrameDecoder;
+use ruzstd::decoding::{Dictionary, FrameDecoder, StreamingDecoder};
+use ruzstd::encoding::{CompressionLevel, FrameCompressor};
+use ruzstd::io::Read;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut fr = FrameDecoder::new();
-    let mut target_slice = &mut vec![0u8; 1024 * 1024 * 200];
+    let target_slice = &mut vec![0u8; 1024 * 1024 * 200];
     let src = include_bytes!("../decodecorpus_files/z000033.zst");
 
     c.bench_function("decode_all_slice", |b| {
         b.iter(|| {
-            fr.decode_all(src, &mut target_slice).unwrap();
+            fr.decode_all(src, target_slice).unwrap();
+        })
+    });
+
+    let mut target = Vec::with_capacity(1024 * 1024 * 200);
+    c.bench_function("decode_streaming_decoder_slice", |b| {
+        b.iter(|| {
+            target.clear();
+            let mut decoder = StreamingDecoder::new(&src[..]).unwrap();
+            decoder.read_to_end(&mut target).unwrap();
+        })
+    });
+
+    // Small frames fit into their window completely and never have to be drained while decoding
+    let small = &include_bytes!("../decodecorpus_files/z000033")[..16 * 1024];
+    let mut small_src = Vec::new();
+    let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+    compressor.set_single_segment_if_single_block(true);
+    compressor.set_source(small);
+    compressor.set_drain(&mut small_src);
+    compressor.compress().unwrap();
+    drop(compressor);
+    let small_target = &mut vec![0u8; small.len()];
+    c.bench_function("decode_all_small_frame", |b| {
+        b.iter(|| {
+            fr.decode_all(&small_src, small_target).unwrap();
+        })
+    });
+
+    // Many small frames that use the same dictionary
+    let mut dict_fr = FrameDecoder::new();
+    let dict = Dictionary::decode_dict(include_bytes!("../dict_tests/dictionary")).unwrap();
+    dict_fr.add_dict(dict).unwrap();
+    let dict_src = include_bytes!("../dict_tests/files/polkit.service.zst");
+    let mut dict_target = Vec::with_capacity(64 * 1024);
+    c.bench_function("decode_all_small_frame_with_dict", |b| {
+        b.iter(|| {
+            dict_target.clear();
+            dict_fr
+                .decode_all_to_vec(dict_src, &mut dict_target)
+                .unwrap();
         })
     });
 }
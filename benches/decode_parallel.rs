@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+use std::fs;
+
+/// A frame with many blocks, compressed by the reference implementation
+fn multi_block_frame() -> Vec<u8> {
+    let mut data = Vec::new();
+    for file in fs::read_dir("./decodecorpus_files").unwrap() {
+        let path = file.unwrap().path();
+        if path.extension().is_none() {
+            data.extend(fs::read(path).unwrap());
+        }
+        if data.len() >= 16 * 1024 * 1024 {
+            break;
+        }
+    }
+    zstd::encode_all(data.as_slice(), 3).unwrap()
+}
+
+fn decode(fr: &mut FrameDecoder, mut src: &[u8], target: &mut Vec<u8>, parallel: bool) {
+    target.clear();
+    fr.reset(&mut src).unwrap();
+    while !fr.is_finished() {
+        if parallel {
+            fr.decode_blocks_parallel(&mut src, BlockDecodingStrategy::All)
+                .unwrap();
+        } else {
+            fr.decode_blocks(&mut src, BlockDecodingStrategy::All)
+                .unwrap();
+        }
+        fr.collect_to_writer(&mut *target).unwrap();
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut fr = FrameDecoder::new();
+    let src = multi_block_frame();
+    let mut target = Vec::with_capacity(32 * 1024 * 1024);
+
+    c.bench_function("decode_blocks", |b| {
+        b.iter(|| decode(&mut fr, &src, &mut target, false))
+    });
+    c.bench_function("decode_blocks_parallel", |b| {
+        b.iter(|| decode(&mut fr, &src, &mut target, true))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+use std::num::NonZeroUsize;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ruzstd::encoding::{CompressionLevel, FrameCompressor};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut inputs: Vec<Vec<u8>> = std::fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_none_or(|ext| ext != "zst"))
+        .map(|path| std::fs::read(path).unwrap())
+        .collect();
+    inputs.sort();
+    let total_len: u64 = inputs.iter().map(|input| input.len() as u64).sum();
+
+    let mut group = c.benchmark_group("encode_fastest_suffix_stride");
+    group.throughput(Throughput::Bytes(total_len));
+    for stride in 1..=4 {
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_suffix_stride(NonZeroUsize::new(stride));
+
+        compressor.set_drain(std::io::sink());
+        let mut compressed_len = 0;
+        for input in &inputs {
+            compressor.set_source(input.as_slice());
+            compressor.compress().unwrap();
+            compressed_len += compressor.stats().output_bytes;
+        }
+        println!(
+            "stride {stride}: {total_len} -> {compressed_len} bytes (ratio {:.3})",
+            compressed_len as f64 / total_len as f64
+        );
+
+        group.bench_function(format!("stride_{stride}"), |b| {
+            b.iter(|| {
+                for input in &inputs {
+                    compressor.set_source(input.as_slice());
+                    compressor.compress().unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -9,4 +9,10 @@ fuzz_target!(|data: &[u8]| {
         let mut output = Vec::new();
         _ = decoder.read_to_end(&mut output);
     }
+
+    let mut decoder = ruzstd::decoding::FrameDecoder::new();
+    let mut output = Vec::new();
+    if let Ok(written) = decoder.decode_to_writer(data, &mut output) {
+        assert_eq!(written, output.len() as u64);
+    }
 });
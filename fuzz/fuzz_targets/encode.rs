@@ -18,4 +18,11 @@ fuzz_target!(|data: &[u8]| {
     let mut decoder = ruzstd::decoding::FrameDecoder::new();
     decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
     assert_eq!(data, &decoded);
+
+    let output = compress_to_vec(data, CompressionLevel::Default);
+
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut decoder = ruzstd::decoding::FrameDecoder::new();
+    decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+    assert_eq!(data, &decoded);
 });
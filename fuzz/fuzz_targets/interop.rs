@@ -12,19 +12,11 @@ fn decode_ruzstd(data: &mut dyn std::io::Read) -> Vec<u8> {
     result
 }
 
-fn decode_ruzstd_writer(mut data: impl Read) -> Vec<u8> {
+fn decode_ruzstd_writer(data: impl Read) -> Vec<u8> {
     let mut decoder = ruzstd::decoding::FrameDecoder::new();
-    decoder.reset(&mut data).unwrap();
     let mut result = vec![];
-    while !decoder.is_finished() || decoder.can_collect() > 0 {
-        decoder
-            .decode_blocks(
-                &mut data,
-                ruzstd::decoding::BlockDecodingStrategy::UptoBytes(1024 * 1024),
-            )
-            .unwrap();
-        decoder.collect_to_writer(&mut result).unwrap();
-    }
+    let written = decoder.decode_to_writer(data, &mut result).unwrap();
+    assert_eq!(written, result.len() as u64);
     result
 }
 
@@ -35,13 +27,13 @@ fn encode_zstd(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
 fn encode_ruzstd_uncompressed(data: &mut dyn std::io::Read) -> Vec<u8> {
     let mut input = Vec::new();
     data.read_to_end(&mut input).unwrap();
-    compress_to_vec(data, CompressionLevel::Uncompressed)
+    compress_to_vec(input.as_slice(), CompressionLevel::Uncompressed)
 }
 
-fn encode_ruzstd_compressed(data: &mut dyn std::io::Read) -> Vec<u8> {
+fn encode_ruzstd_compressed(data: &mut dyn std::io::Read, level: CompressionLevel) -> Vec<u8> {
     let mut input = Vec::new();
     data.read_to_end(&mut input).unwrap();
-    compress_to_vec(data, CompressionLevel::Uncompressed)
+    compress_to_vec(input.as_slice(), level)
 }
 
 fn decode_zstd(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
@@ -74,11 +66,13 @@ fuzz_target!(|data: &[u8]| {
         "Decoded data did not match the original input during compression"
     );
     // Compressed encoding
-    let mut input = data;
-    let compressed = encode_ruzstd_compressed(&mut input);
-    let decoded = decode_zstd(&compressed).unwrap();
-    assert_eq!(
-        decoded, data,
-        "Decoded data did not match the original input during compression"
-    );
+    for level in [CompressionLevel::Fastest, CompressionLevel::Default] {
+        let mut input = data;
+        let compressed = encode_ruzstd_compressed(&mut input, level);
+        let decoded = decode_zstd(&compressed).unwrap();
+        assert_eq!(
+            decoded, data,
+            "Decoded data did not match the original input during compression"
+        );
+    }
 });
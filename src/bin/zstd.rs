@@ -5,7 +5,6 @@ use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::io::Write;
-use std::time::Instant;
 
 use ruzstd::decoding::errors::FrameDecoderError;
 use ruzstd::decoding::errors::ReadFrameHeaderError;
@@ -162,7 +161,6 @@ fn main() {
         encoder.set_drain(Vec::new());
 
         for path in file_paths {
-            let start_instant = Instant::now();
             let file = std::fs::File::open(&path).unwrap();
             let input_len = file.metadata().unwrap().len() as usize;
             let file = PercentPrintReader {
@@ -172,19 +170,18 @@ fn main() {
                 last_percent: 0,
             };
             encoder.set_source(file);
-            encoder.compress();
-            let mut output: Vec<_> = encoder.take_drain().unwrap();
+            encoder.compress().unwrap();
+            let stats = encoder.stats();
             println!(
                 "Compressed {path:} from {} to {} ({}%) took {}ms",
-                input_len,
-                output.len(),
-                if input_len == 0 {
-                    0
-                } else {
-                    output.len() * 100 / input_len
-                },
-                start_instant.elapsed().as_millis()
+                stats.input_bytes,
+                stats.output_bytes,
+                (stats.output_bytes * 100)
+                    .checked_div(stats.input_bytes)
+                    .unwrap_or(0),
+                stats.duration.as_millis()
             );
+            let mut output: Vec<_> = encoder.take_drain().unwrap();
             output.clear();
             encoder.set_drain(output);
         }
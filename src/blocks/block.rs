@@ -28,6 +28,7 @@ impl core::fmt::Display for BlockType {
 
 /// A representation of a single block header. As well as containing a frame header,
 /// each Zstandard frame contains one or more blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BlockHeader {
     /// Whether this block is the last block in the frame.
     /// It may be followed by an optional `Content_Checksum` if it is.
@@ -17,7 +17,7 @@ pub struct SequencesHeader {
 /// - A *match* copy step that copies data from within the previously decompressed output.
 ///
 /// <https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#sequence-execution>
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Sequence {
     /// Literal length, or the number of bytes to be copied from the literals section
     /// in the copy step.
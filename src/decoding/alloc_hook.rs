@@ -0,0 +1,81 @@
+//! Lets users track or limit the memory the decoder allocates for its buffers, see [crate::decoding::FrameDecoder::set_alloc_hook]
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// The buffer of the decoder an allocation is made for, passed to [AllocHook::alloc]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AllocKind {
+    /// The buffer that holds the window and the decoded data that was not collected yet
+    Window,
+    /// The Huffman and FSE decoding tables. They are reserved at their maximum size before the first compressed block
+    Tables,
+    /// The buffer the content of raw and compressed blocks is read into
+    BlockContent,
+    /// The decoded literals of a compressed block
+    Literals,
+    /// The decoded sequences of a compressed block
+    Sequences,
+    /// The payload of a skippable frame, collected for the handler set with [crate::decoding::FrameDecoder::set_skippable_handler]
+    SkippablePayload,
+}
+
+/// Asked by the decoder before it grows one of its buffers, see [crate::decoding::FrameDecoder::set_alloc_hook].
+///
+/// Closures taking an [AllocKind] and the number of bytes implement this trait.
+pub trait AllocHook: Send {
+    /// The decoder is about to grow the buffer of the given kind by `bytes` bytes.
+    ///
+    /// Return `false` to reject the allocation, which makes the decoder fail with an `AllocationRejected` error
+    /// instead of allocating.
+    fn alloc(&mut self, kind: AllocKind, bytes: usize) -> bool;
+}
+
+impl<F: FnMut(AllocKind, usize) -> bool + Send> AllocHook for F {
+    fn alloc(&mut self, kind: AllocKind, bytes: usize) -> bool {
+        self(kind, bytes)
+    }
+}
+
+/// An allocation the [AllocHook] rejected
+#[derive(Debug)]
+pub(crate) struct AllocRejected {
+    pub kind: AllocKind,
+    pub bytes: usize,
+}
+
+/// Ask the hook whether the buffer of the given kind may grow by `bytes` bytes. Growing by 0 bytes is always allowed.
+pub(crate) fn request(
+    hook: &mut dyn AllocHook,
+    kind: AllocKind,
+    bytes: usize,
+) -> Result<(), AllocRejected> {
+    if bytes == 0 || hook.alloc(kind, bytes) {
+        Ok(())
+    } else {
+        Err(AllocRejected { kind, bytes })
+    }
+}
+
+/// If there is a hook, ask it before growing `vec` to hold `len` elements and reserve exactly that much.
+///
+/// Without a hook nothing is reserved and the vec grows as usual when it is filled.
+pub(crate) fn reserve_vec<T>(
+    hook: &mut Option<Box<dyn AllocHook>>,
+    kind: AllocKind,
+    vec: &mut Vec<T>,
+    len: usize,
+) -> Result<(), AllocRejected> {
+    if let Some(hook) = hook {
+        if len > vec.capacity() {
+            request(
+                hook.as_mut(),
+                kind,
+                (len - vec.capacity()) * core::mem::size_of::<T>(),
+            )?;
+            vec.reserve_exact(len - vec.len());
+        }
+    }
+    Ok(())
+}
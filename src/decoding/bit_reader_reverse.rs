@@ -24,6 +24,14 @@ pub struct BitReaderReversed<'s> {
 
 impl<'s> BitReaderReversed<'s> {
     /// How many bits are left to read by the reader.
+    ///
+    /// This is the number of bits in the source minus the number of bits consumed so far, regardless of how
+    /// they were read. Reading past the end of the source is not an error, the missing bits read as zeroes and
+    /// the value becomes negative by exactly the number of bits that were read past the end.
+    ///
+    /// Decoders rely on this to detect the end of a stream: A Huffman or FSE decoder keeps its state in the
+    /// last `max_num_bits` bits it read, so once all symbols are decoded it has read exactly `max_num_bits`
+    /// past the end and `bits_remaining() == -max_num_bits`. Any other value means the stream was corrupt.
     pub fn bits_remaining(&self) -> isize {
         self.index as isize * 8 + (64 - self.bits_consumed as isize) - self.extra_bits as isize
     }
@@ -181,4 +189,59 @@ mod test {
         assert_eq!(br.get_bits(4), 0b0000);
         assert_eq!(br.bits_remaining(), -7);
     }
+
+    #[test]
+    fn bits_remaining_crosses_zero() {
+        let data = [0b1111_0000, 0b1010_1010, 0b1100_1100];
+        let mut br = super::BitReaderReversed::new(&data);
+        assert_eq!(br.bits_remaining(), 24);
+        assert_eq!(br.get_bits(20), 0b1100_1100_1010_1010_1111);
+        assert_eq!(br.bits_remaining(), 4);
+        // Exactly at the end
+        assert_eq!(br.get_bits(4), 0);
+        assert_eq!(br.bits_remaining(), 0);
+        // One bit past the end
+        assert_eq!(br.get_bits(1), 0);
+        assert_eq!(br.bits_remaining(), -1);
+        // Further reads keep counting down by the number of bits read
+        assert_eq!(br.get_bits(11), 0);
+        assert_eq!(br.bits_remaining(), -12);
+        assert_eq!(br.get_bits(56), 0);
+        assert_eq!(br.bits_remaining(), -68);
+        assert_eq!(br.get_bits(0), 0);
+        assert_eq!(br.bits_remaining(), -68);
+    }
+
+    #[test]
+    fn bits_remaining_read_across_end() {
+        // A single read that starts before and ends after the end of the source returns the
+        // remaining bits in its upper bits, filled up with zeroes
+        let data = [0b0000_0101];
+        let mut br = super::BitReaderReversed::new(&data);
+        assert_eq!(br.get_bits(5), 0);
+        assert_eq!(br.bits_remaining(), 3);
+        assert_eq!(br.get_bits(6), 0b101_000);
+        assert_eq!(br.bits_remaining(), -3);
+
+        // The same with the triple reads
+        let mut br = super::BitReaderReversed::new(&data);
+        assert_eq!(br.get_bits_triple(5, 2, 3), (0, 0b10, 0b100));
+        assert_eq!(br.bits_remaining(), -2);
+    }
+
+    #[test]
+    fn bits_remaining_long_source() {
+        // Refills from the middle of the source and at its end keep the count exact
+        let data = [0xA5u8; 20];
+        let mut br = super::BitReaderReversed::new(&data);
+        let mut expected = 160;
+        for n in [7, 13, 1, 56, 30, 23, 17, 9, 4] {
+            br.get_bits(n);
+            expected -= n as isize;
+            assert_eq!(br.bits_remaining(), expected);
+        }
+        assert_eq!(br.bits_remaining(), 0);
+        br.get_bits(9);
+        assert_eq!(br.bits_remaining(), -9);
+    }
 }
@@ -2,17 +2,23 @@ use super::super::blocks::block::BlockHeader;
 use super::super::blocks::block::BlockType;
 use super::super::blocks::literals_section::LiteralsSection;
 use super::super::blocks::literals_section::LiteralsSectionType;
-use super::super::blocks::sequence_section::SequencesHeader;
+use super::super::blocks::sequence_section::{Sequence, SequencesHeader};
 use super::literals_section_decoder::decode_literals;
-use super::sequence_section_decoder::decode_sequences;
-use crate::decoding::errors::DecodeSequenceError;
+#[cfg(feature = "parallel")]
+use super::sequence_section_decoder::maybe_update_fse_tables;
+use super::sequence_section_decoder::{decode_sequences, decode_sequences_with_tables};
+use crate::decoding::alloc_hook::{reserve_vec, AllocHook, AllocKind};
+#[cfg(feature = "parallel")]
+use crate::decoding::errors::DecompressLiteralsError;
 use crate::decoding::errors::{
     BlockHeaderReadError, BlockSizeError, BlockTypeError, DecodeBlockContentError,
     DecompressBlockError,
 };
-use crate::decoding::scratch::DecoderScratch;
+use crate::decoding::scratch::{DecoderScratch, FSEScratch, HuffmanScratch};
 use crate::decoding::sequence_execution::execute_sequences;
 use crate::io::Read;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 pub struct BlockDecoder {
     header_buffer: [u8; 3],
@@ -36,12 +42,15 @@ pub fn new() -> BlockDecoder {
 
 pub(crate) const ABSOLUTE_MAXIMUM_BLOCK_SIZE: u32 = 128 * 1024;
 
+/// Upper limit for buffers that decoding puts on the stack. Everything bigger lives in the [DecoderScratch].
+pub(crate) const MAX_STACK_BUFFER_SIZE: usize = 1024;
+
 impl BlockDecoder {
     pub fn decode_block_content(
         &mut self,
         header: &BlockHeader,
         workspace: &mut DecoderScratch, //reuse this as often as possible. Not only if the trees are reused but also reuse the allocations when building new trees
-        mut source: impl Read,
+        source: impl Read,
     ) -> Result<u64, DecodeBlockContentError> {
         match self.internal_state {
             DecoderState::ReadyToDecodeNextBody => { /* Happy :) */ }
@@ -50,14 +59,26 @@ impl BlockDecoder {
                 return Err(DecodeBlockContentError::ExpectedHeaderOfPreviousBlock)
             }
         }
+        self.decode_block_body(header, workspace, source)
+    }
 
+    /// Like [BlockDecoder::decode_block_content] but without checking that the header was read by this decoder.
+    /// The block type must not be [BlockType::Reserved].
+    pub(crate) fn decode_block_body(
+        &mut self,
+        header: &BlockHeader,
+        workspace: &mut DecoderScratch,
+        mut source: impl Read,
+    ) -> Result<u64, DecodeBlockContentError> {
         let block_type = header.block_type;
         match block_type {
             BlockType::RLE => {
                 const BATCH_SIZE: usize = 512;
+                const _: () = assert!(BATCH_SIZE <= MAX_STACK_BUFFER_SIZE);
                 let mut buf = [0u8; BATCH_SIZE];
                 let full_reads = header.decompressed_size / BATCH_SIZE as u32;
                 let single_read_size = header.decompressed_size % BATCH_SIZE as u32;
+                workspace.request_window(header.decompressed_size as usize)?;
 
                 source.read_exact(&mut buf[0..1]).map_err(|err| {
                     DecodeBlockContentError::ReadError {
@@ -80,29 +101,23 @@ impl BlockDecoder {
                 Ok(1)
             }
             BlockType::Raw => {
-                const BATCH_SIZE: usize = 128 * 1024;
-                let mut buf = [0u8; BATCH_SIZE];
-                let full_reads = header.decompressed_size / BATCH_SIZE as u32;
-                let single_read_size = header.decompressed_size % BATCH_SIZE as u32;
-
-                for _ in 0..full_reads {
-                    source.read_exact(&mut buf[..]).map_err(|err| {
-                        DecodeBlockContentError::ReadError {
-                            step: block_type,
-                            source: err,
-                        }
-                    })?;
-                    workspace.buffer.push(&buf[..]);
-                }
-
-                let smaller = &mut buf[..single_read_size as usize];
+                // Read into the heap allocated scratch buffer, a raw block can be up to 128KB which is too much for small stacks
+                reserve_vec(
+                    &mut workspace.alloc_hook,
+                    AllocKind::BlockContent,
+                    &mut workspace.block_content_buffer,
+                    header.decompressed_size as usize,
+                )?;
+                workspace.request_window(header.decompressed_size as usize)?;
+                let buf = &mut workspace.block_content_buffer;
+                buf.resize(header.decompressed_size as usize, 0);
                 source
-                    .read_exact(smaller)
+                    .read_exact(buf)
                     .map_err(|err| DecodeBlockContentError::ReadError {
                         step: block_type,
                         source: err,
                     })?;
-                workspace.buffer.push(smaller);
+                workspace.buffer.push(buf);
 
                 self.internal_state = DecoderState::ReadyToDecodeNextHeader;
                 Ok(u64::from(header.decompressed_size))
@@ -127,100 +142,34 @@ impl BlockDecoder {
         workspace: &mut DecoderScratch, //reuse this as often as possible. Not only if the trees are reused but also reuse the allocations when building new trees
         mut source: impl Read,
     ) -> Result<(), DecompressBlockError> {
+        let max_block_size = workspace
+            .buffer
+            .window_size
+            .min(ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize) as u32;
+        reserve_vec(
+            &mut workspace.alloc_hook,
+            AllocKind::BlockContent,
+            &mut workspace.block_content_buffer,
+            header.content_size as usize,
+        )?;
+        workspace.request_tables()?;
+        // The exact size is only known after executing the sequences
+        workspace.request_window(max_block_size as usize)?;
         workspace
             .block_content_buffer
             .resize(header.content_size as usize, 0);
 
         source.read_exact(workspace.block_content_buffer.as_mut_slice())?;
-        let raw = workspace.block_content_buffer.as_slice();
-
-        let mut section = LiteralsSection::new();
-        let bytes_in_literals_header = section.parse_from_header(raw)?;
-        let raw = &raw[bytes_in_literals_header as usize..];
-        vprintln!(
-            "Found {} literalssection with regenerated size: {}, and compressed size: {:?}",
-            section.ls_type,
-            section.regenerated_size,
-            section.compressed_size
-        );
-
-        let upper_limit_for_literals = match section.compressed_size {
-            Some(x) => x as usize,
-            None => match section.ls_type {
-                LiteralsSectionType::RLE => 1,
-                LiteralsSectionType::Raw => section.regenerated_size as usize,
-                _ => panic!("Bug in this library"),
-            },
-        };
-
-        if raw.len() < upper_limit_for_literals {
-            return Err(DecompressBlockError::MalformedSectionHeader {
-                expected_len: upper_limit_for_literals,
-                remaining_bytes: raw.len(),
-            });
-        }
-
-        let raw_literals = &raw[..upper_limit_for_literals];
-        vprintln!("Slice for literals: {}", raw_literals.len());
-
-        workspace.literals_buffer.clear(); //all literals of the previous block must have been used in the sequence execution anyways. just be defensive here
-        let bytes_used_in_literals_section = decode_literals(
-            &section,
+        decode_literals_and_sequences(
+            workspace.block_content_buffer.as_slice(),
+            max_block_size,
             &mut workspace.huf,
-            raw_literals,
+            &mut workspace.fse,
             &mut workspace.literals_buffer,
+            &mut workspace.sequences,
+            &mut workspace.alloc_hook,
         )?;
-        assert!(
-            section.regenerated_size == workspace.literals_buffer.len() as u32,
-            "Wrong number of literals: {}, Should have been: {}",
-            workspace.literals_buffer.len(),
-            section.regenerated_size
-        );
-        assert!(bytes_used_in_literals_section == upper_limit_for_literals as u32);
-
-        let raw = &raw[upper_limit_for_literals..];
-        vprintln!("Slice for sequences with headers: {}", raw.len());
-
-        let mut seq_section = SequencesHeader::new();
-        let bytes_in_sequence_header = seq_section.parse_from_header(raw)?;
-        let raw = &raw[bytes_in_sequence_header as usize..];
-        vprintln!(
-            "Found sequencessection with sequences: {} and size: {}",
-            seq_section.num_sequences,
-            raw.len()
-        );
-
-        assert!(
-            u32::from(bytes_in_literals_header)
-                + bytes_used_in_literals_section
-                + u32::from(bytes_in_sequence_header)
-                + raw.len() as u32
-                == header.content_size
-        );
-        vprintln!("Slice for sequences: {}", raw.len());
-
-        if seq_section.num_sequences != 0 {
-            decode_sequences(
-                &seq_section,
-                raw,
-                &mut workspace.fse,
-                &mut workspace.sequences,
-            )?;
-            vprintln!("Executing sequences");
-            execute_sequences(workspace)?;
-        } else {
-            if !raw.is_empty() {
-                return Err(DecompressBlockError::DecodeSequenceError(
-                    DecodeSequenceError::ExtraBits {
-                        bits_remaining: raw.len() as isize * 8,
-                    },
-                ));
-            }
-            workspace.buffer.push(&workspace.literals_buffer);
-            workspace.sequences.clear();
-        }
-
-        Ok(())
+        execute_block(workspace)
     }
 
     /// Reads 3 bytes from the provided reader and returns
@@ -309,3 +258,240 @@ impl BlockDecoder {
             | (u32::from(self.header_buffer[2]) << 13)
     }
 }
+
+/// Write the output of a compressed block, whose literals and sequences are in the scratch, to the decode buffer.
+pub(crate) fn execute_block(workspace: &mut DecoderScratch) -> Result<(), DecompressBlockError> {
+    if workspace.sequences.is_empty() {
+        workspace.buffer.push(&workspace.literals_buffer);
+    } else {
+        vprintln!("Executing sequences");
+        execute_sequences(workspace)?;
+    }
+    Ok(())
+}
+
+/// The number of bytes the table descriptions take up in the literals and sequences sections of a compressed block
+#[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+#[derive(Clone, Copy)]
+pub(crate) struct TableSizes {
+    huffman: u32,
+    fse: usize,
+}
+
+/// Build the tables described in the content of a compressed block, without decoding the literals and sequences.
+///
+/// For valid blocks the tables end up as they would after [decode_literals_and_sequences], so the tables of the
+/// following blocks are known before this block is decoded. Invalid blocks can leave the tables in any state.
+#[cfg(feature = "parallel")]
+pub(crate) fn update_tables(
+    block_content: &[u8],
+    huf: &mut HuffmanScratch,
+    fse: &mut FSEScratch,
+) -> Result<TableSizes, DecompressBlockError> {
+    let mut sizes = TableSizes { huffman: 0, fse: 0 };
+    let mut section = LiteralsSection::new();
+    let bytes_in_literals_header = section.parse_from_header(block_content)?;
+    let raw = &block_content[bytes_in_literals_header as usize..];
+
+    let literals_size = match section.compressed_size {
+        Some(x) => x as usize,
+        None => match section.ls_type {
+            LiteralsSectionType::RLE => 1,
+            _ => section.regenerated_size as usize,
+        },
+    };
+    if raw.len() < literals_size {
+        return Err(DecompressBlockError::MalformedSectionHeader {
+            expected_len: literals_size,
+            remaining_bytes: raw.len(),
+        });
+    }
+    if let LiteralsSectionType::Compressed = section.ls_type {
+        sizes.huffman = huf
+            .table
+            .build_decoder(&raw[..literals_size])
+            .map_err(DecompressLiteralsError::from)?;
+    }
+
+    let raw = &raw[literals_size..];
+    let mut seq_section = SequencesHeader::new();
+    let bytes_in_sequence_header = seq_section.parse_from_header(raw)?;
+    if seq_section.num_sequences != 0 {
+        sizes.fse =
+            maybe_update_fse_tables(&seq_section, &raw[bytes_in_sequence_header as usize..], fse)?;
+    }
+    Ok(sizes)
+}
+
+/// Decode the literals and sequences of the content of a compressed block without executing the sequences.
+///
+/// The huffman and FSE tables are updated, so they can be repeated by the following blocks.
+/// `max_block_size` is the maximum number of bytes the block can decode to, min(window size, 128KB).
+/// The `alloc_hook` is asked before the literals and sequences buffers grow.
+pub(crate) fn decode_literals_and_sequences(
+    block_content: &[u8],
+    max_block_size: u32,
+    huf: &mut HuffmanScratch,
+    fse: &mut FSEScratch,
+    literals: &mut Vec<u8>,
+    sequences: &mut Vec<Sequence>,
+    alloc_hook: &mut Option<Box<dyn AllocHook>>,
+) -> Result<(), DecompressBlockError> {
+    decode_sections(
+        block_content,
+        max_block_size,
+        None,
+        huf,
+        fse,
+        literals,
+        sequences,
+        alloc_hook,
+    )
+}
+
+/// Like [decode_literals_and_sequences], but with the tables of the block already built by [update_tables].
+///
+/// The tables are only used, so they stay as they are.
+#[cfg(feature = "parallel")]
+pub(crate) fn decode_literals_and_sequences_with_tables(
+    block_content: &[u8],
+    max_block_size: u32,
+    sizes: TableSizes,
+    huf: &mut HuffmanScratch,
+    fse: &mut FSEScratch,
+    literals: &mut Vec<u8>,
+    sequences: &mut Vec<Sequence>,
+) -> Result<(), DecompressBlockError> {
+    decode_sections(
+        block_content,
+        max_block_size,
+        Some(sizes),
+        huf,
+        fse,
+        literals,
+        sequences,
+        &mut None,
+    )
+}
+
+/// Decode the literals and sequences. With `built_tables` the table descriptions are skipped instead of building
+/// the tables from them.
+#[allow(clippy::too_many_arguments)]
+fn decode_sections(
+    block_content: &[u8],
+    max_block_size: u32,
+    built_tables: Option<TableSizes>,
+    huf: &mut HuffmanScratch,
+    fse: &mut FSEScratch,
+    literals: &mut Vec<u8>,
+    sequences: &mut Vec<Sequence>,
+    alloc_hook: &mut Option<Box<dyn AllocHook>>,
+) -> Result<(), DecompressBlockError> {
+    let mut section = LiteralsSection::new();
+    let bytes_in_literals_header = section.parse_from_header(block_content)?;
+    let raw = &block_content[bytes_in_literals_header as usize..];
+    vprintln!(
+        "Found {} literalssection with regenerated size: {}, and compressed size: {:?}",
+        section.ls_type,
+        section.regenerated_size,
+        section.compressed_size
+    );
+
+    let upper_limit_for_literals = match section.compressed_size {
+        Some(x) => x as usize,
+        None => match section.ls_type {
+            LiteralsSectionType::RLE => 1,
+            LiteralsSectionType::Raw => section.regenerated_size as usize,
+            _ => panic!("Bug in this library"),
+        },
+    };
+
+    if raw.len() < upper_limit_for_literals {
+        return Err(DecompressBlockError::MalformedSectionHeader {
+            expected_len: upper_limit_for_literals,
+            remaining_bytes: raw.len(),
+        });
+    }
+
+    let raw_literals = &raw[..upper_limit_for_literals];
+    vprintln!("Slice for literals: {}", raw_literals.len());
+
+    literals.clear(); //all literals of the previous block must have been used in the sequence execution anyways. just be defensive here
+
+    // Sizes above the maximum are rejected while decoding the literals
+    reserve_vec(
+        alloc_hook,
+        AllocKind::Literals,
+        literals,
+        section.regenerated_size.min(max_block_size) as usize,
+    )?;
+    let bytes_used_in_literals_section = match built_tables {
+        Some(sizes) if matches!(section.ls_type, LiteralsSectionType::Compressed) => {
+            // Decode the literals like a section that repeats the huffman table
+            let treeless = LiteralsSection {
+                regenerated_size: section.regenerated_size,
+                compressed_size: section.compressed_size.map(|size| size - sizes.huffman),
+                num_streams: section.num_streams,
+                ls_type: LiteralsSectionType::Treeless,
+            };
+            let raw_literals = &raw_literals[sizes.huffman as usize..];
+            sizes.huffman + decode_literals(&treeless, huf, raw_literals, literals, max_block_size)?
+        }
+        _ => decode_literals(&section, huf, raw_literals, literals, max_block_size)?,
+    };
+    assert!(
+        section.regenerated_size == literals.len() as u32,
+        "Wrong number of literals: {}, Should have been: {}",
+        literals.len(),
+        section.regenerated_size
+    );
+
+    let raw = &raw[upper_limit_for_literals..];
+    vprintln!("Slice for sequences with headers: {}", raw.len());
+
+    let mut seq_section = SequencesHeader::new();
+    let bytes_in_sequence_header = seq_section.parse_from_header(raw)?;
+    let raw = &raw[bytes_in_sequence_header as usize..];
+    vprintln!(
+        "Found sequencessection with sequences: {} and size: {}",
+        seq_section.num_sequences,
+        raw.len()
+    );
+
+    // Without sequences the section ends after its header, otherwise the bitstream takes up the rest of the block
+    let bytes_in_sequences = if seq_section.num_sequences != 0 {
+        raw.len() as u32
+    } else {
+        0
+    };
+    let bytes_in_sections = u32::from(bytes_in_literals_header)
+        + bytes_used_in_literals_section
+        + u32::from(bytes_in_sequence_header)
+        + bytes_in_sequences;
+    if bytes_in_sections != block_content.len() as u32 {
+        return Err(DecompressBlockError::TrailingBlockBytes {
+            expected: block_content.len() as u32,
+            got: bytes_in_sections,
+        });
+    }
+    vprintln!("Slice for sequences: {}", raw.len());
+
+    if seq_section.num_sequences != 0 {
+        reserve_vec(
+            alloc_hook,
+            AllocKind::Sequences,
+            sequences,
+            seq_section.num_sequences as usize,
+        )?;
+        match built_tables {
+            Some(sizes) => {
+                decode_sequences_with_tables(&seq_section, &raw[sizes.fse..], fse, sequences)?
+            }
+            None => decode_sequences(&seq_section, raw, fse, sequences)?,
+        }
+    } else {
+        sequences.clear();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,269 @@
+//! Utilities to get at the raw content of blocks and their sequences, or to check the structure of frames,
+//! without fully decoding them
+
+use crate::blocks::block::{BlockHeader, BlockType};
+use crate::blocks::sequence_section::Sequence;
+use crate::decoding::block_decoder;
+use crate::decoding::errors::{
+    BlockSizeError, DecodeBlockContentError, DecompressBlockError, ExtractBlockError,
+    ReadFrameHeaderError, ValidateStructureError,
+};
+use crate::decoding::frame;
+use crate::decoding::scratch::{DecoderScratch, FSEScratch, HuffmanScratch};
+use crate::io::{Error, ErrorKind, Read};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Read the frame from the source and return the header and the raw (still compressed) content of the block with the given index.
+///
+/// None of the blocks are decoded, all blocks before the requested one are just skipped.
+/// This is mostly useful for debugging, e.g. to extract a block that can't be decoded from a corrupted file.
+///
+/// The source must be positioned at the start of a frame.
+pub fn extract_block(
+    mut source: impl Read,
+    index: usize,
+) -> Result<(BlockHeader, Vec<u8>), ExtractBlockError> {
+    use ExtractBlockError as err;
+    frame::read_frame_header(&mut source)?;
+
+    let mut block_dec = block_decoder::new();
+    let mut content = Vec::new();
+    let mut block_idx = 0;
+    loop {
+        let (block_header, _) = block_dec.read_block_header(&mut source)?;
+        content.resize(block_header.content_size as usize, 0);
+        source
+            .read_exact(&mut content)
+            .map_err(err::BlockContentReadError)?;
+
+        if block_idx == index {
+            return Ok((block_header, content));
+        }
+        block_idx += 1;
+
+        if block_header.last_block {
+            return Err(err::BlockIndexOutOfRange {
+                index,
+                num_blocks: block_idx,
+            });
+        }
+    }
+}
+
+/// The tables that compressed blocks can repeat from the blocks before them in the same frame.
+///
+/// Use one scratch per frame and pass the blocks to [decode_block_sequences] in order.
+pub struct BlockScratch {
+    pub(crate) huf: HuffmanScratch,
+    pub(crate) fse: FSEScratch,
+}
+
+impl BlockScratch {
+    pub fn new() -> BlockScratch {
+        BlockScratch {
+            huf: HuffmanScratch::new(),
+            fse: FSEScratch::new(),
+        }
+    }
+
+    /// A scratch with a copy of the given tables
+    #[cfg(feature = "parallel")]
+    pub(crate) fn copy_of(huf: &HuffmanScratch, fse: &FSEScratch) -> BlockScratch {
+        let mut scratch = BlockScratch::new();
+        scratch.huf.table.reinit_from(&huf.table);
+        scratch.fse.reinit_from(fse);
+        scratch
+    }
+}
+
+impl Default for BlockScratch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decode the literals and the sequences of a block without executing the sequences.
+///
+/// `raw` is the content of the block as returned by [extract_block]. The offsets of the sequences
+/// are returned as they are encoded, so values from 1 to 3 refer to the repeat offsets.
+/// Raw and RLE blocks have no sequences, all their content is returned as literals.
+///
+/// ```
+/// use ruzstd::decoding::{decode_block_sequences, extract_block, BlockScratch};
+///
+/// let data = b"abcabcabcabcabcabcabcabcabcabcabcabc";
+/// let compressed =
+///     ruzstd::encoding::compress_to_vec(&data[..], ruzstd::encoding::CompressionLevel::Fastest);
+/// let (header, raw) = extract_block(compressed.as_slice(), 0).unwrap();
+/// let mut scratch = BlockScratch::new();
+/// let (literals, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+/// let decoded_len: u32 = literals.len() as u32 + sequences.iter().map(|seq| seq.ml).sum::<u32>();
+/// assert_eq!(decoded_len as usize, data.len());
+/// ```
+pub fn decode_block_sequences(
+    header: &BlockHeader,
+    raw: &[u8],
+    scratch: &mut BlockScratch,
+) -> Result<(Vec<u8>, Vec<Sequence>), DecompressBlockError> {
+    check_block_content(header, raw)?;
+    match header.block_type {
+        BlockType::Raw => Ok((raw.to_vec(), Vec::new())),
+        BlockType::RLE => Ok((vec![raw[0]; header.decompressed_size as usize], Vec::new())),
+        BlockType::Compressed => {
+            let mut literals = Vec::new();
+            let mut sequences = Vec::new();
+            // The window size of the frame is not known here, so only the absolute maximum can be checked
+            block_decoder::decode_literals_and_sequences(
+                raw,
+                block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE,
+                &mut scratch.huf,
+                &mut scratch.fse,
+                &mut literals,
+                &mut sequences,
+                &mut None,
+            )?;
+            Ok((literals, sequences))
+        }
+        BlockType::Reserved => unreachable!("rejected by check_block_content"),
+    }
+}
+
+/// Check that `raw` can be the content of a block with this header
+fn check_block_content(header: &BlockHeader, raw: &[u8]) -> Result<(), DecompressBlockError> {
+    // The header is not necessarily read by this crate, so the sizes are checked here before anything is allocated for them
+    let size = header.decompressed_size.max(header.content_size);
+    if size > block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE {
+        return Err(BlockSizeError::BlockSizeTooLarge { size }.into());
+    }
+    if raw.len() != header.content_size as usize {
+        return Err(DecompressBlockError::TrailingBlockBytes {
+            expected: header.content_size,
+            got: raw.len() as u32,
+        });
+    }
+    match header.block_type {
+        BlockType::RLE if raw.len() != 1 => Err(DecompressBlockError::MalformedSectionHeader {
+            expected_len: 1,
+            remaining_bytes: raw.len(),
+        }),
+        BlockType::Reserved => Err(DecompressBlockError::ReservedBlockType),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes blocks that were already split from their frame, e.g. by [extract_block] or by a format that stores zstd blocks individually.
+///
+/// The blocks of a frame have to be passed to [BlockContentDecoder::decode_block] in order, because compressed blocks
+/// can reference the output and the tables of the blocks before them. Call [BlockContentDecoder::reset] before
+/// decoding the blocks of another frame.
+///
+/// ```
+/// use ruzstd::decoding::{extract_block, BlockContentDecoder};
+///
+/// let data = b"abcabcabcabcabcabcabcabcabcabcabcabc";
+/// let compressed =
+///     ruzstd::encoding::compress_to_vec(&data[..], ruzstd::encoding::CompressionLevel::Fastest);
+/// let (header, raw) = extract_block(compressed.as_slice(), 0).unwrap();
+/// let mut decoder = BlockContentDecoder::new(1024);
+/// assert_eq!(decoder.decode_block(&header, &raw).unwrap(), data);
+/// ```
+pub struct BlockContentDecoder {
+    block_dec: block_decoder::BlockDecoder,
+    scratch: DecoderScratch,
+}
+
+impl BlockContentDecoder {
+    /// Create a decoder for the blocks of a frame with the given window size.
+    pub fn new(window_size: usize) -> BlockContentDecoder {
+        BlockContentDecoder {
+            block_dec: block_decoder::new(),
+            scratch: DecoderScratch::new(window_size),
+        }
+    }
+
+    /// Forget the output and tables of the previous blocks to decode the blocks of a new frame.
+    pub fn reset(&mut self, window_size: usize) {
+        // Without an alloc hook nothing can be rejected
+        let _ = self.scratch.reset(window_size, 0);
+    }
+
+    /// Decode one block and return the bytes it decodes to.
+    ///
+    /// `raw` is the content of the block without the block header, and has to be exactly
+    /// as long as the `content_size` of the header.
+    pub fn decode_block(
+        &mut self,
+        header: &BlockHeader,
+        raw: &[u8],
+    ) -> Result<Vec<u8>, DecodeBlockContentError> {
+        check_block_content(header, raw)?;
+        let before = self.scratch.buffer.total_output();
+        self.block_dec
+            .decode_block_body(header, &mut self.scratch, raw)?;
+        let decoded = (self.scratch.buffer.total_output() - before) as usize;
+
+        let mut output = Vec::with_capacity(decoded);
+        self.scratch.buffer.copy_tail(decoded, &mut output);
+        // Only the window is needed for the next blocks
+        self.scratch.buffer.discard_to_window_size();
+        Ok(output)
+    }
+}
+
+/// Check that the source consists of complete zstd frames, without decoding them.
+///
+/// All frame and block headers are parsed and the block contents are skipped by their declared sizes.
+/// Each frame has to end with a last block and, if the frame header says so, a checksum. Skippable
+/// frames are skipped. The source has to contain at least one frame and must not contain anything after the last frame.
+///
+/// This is much cheaper than decoding, but it only finds structural problems. Corruption in the compressed
+/// content of blocks, or a checksum that does not match, is only detected by decoding.
+pub fn validate_structure(mut source: impl Read) -> Result<(), ValidateStructureError> {
+    use ValidateStructureError as err;
+    let mut block_dec = block_decoder::new();
+    let mut num_frames = 0;
+    loop {
+        let frame = match frame::read_frame_header(&mut source) {
+            Ok((frame, _)) => frame,
+            Err(ReadFrameHeaderError::IncompleteHeader { got: 0, .. }) if num_frames > 0 => {
+                return Ok(())
+            }
+            Err(ReadFrameHeaderError::SkipFrame { length, .. }) => {
+                skip_bytes(&mut source, u64::from(length)).map_err(err::SkippableFrameReadError)?;
+                num_frames += 1;
+                continue;
+            }
+            Err(e) => return Err(err::ReadFrameHeaderError(e)),
+        };
+        frame.header.window_size().map_err(err::FrameHeaderError)?;
+
+        loop {
+            let (block_header, _) = block_dec.read_block_header(&mut source)?;
+            skip_bytes(&mut source, u64::from(block_header.content_size))
+                .map_err(err::BlockContentReadError)?;
+            if block_header.last_block {
+                break;
+            }
+        }
+        if frame.header.descriptor.content_checksum_flag() {
+            skip_bytes(&mut source, 4).map_err(err::ChecksumReadError)?;
+        }
+        num_frames += 1;
+    }
+}
+
+/// Read and discard `n` bytes from the source
+fn skip_bytes(mut source: impl Read, mut n: u64) -> Result<(), Error> {
+    let mut buf = [0u8; block_decoder::MAX_STACK_BUFFER_SIZE];
+    while n > 0 {
+        let chunk = n.min(buf.len() as u64) as usize;
+        match source.read(&mut buf[..chunk]) {
+            Ok(0) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+            Ok(read) => n -= read as u64,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -8,7 +8,12 @@ use crate::decoding::errors::DecodeBufferError;
 
 pub struct DecodeBuffer {
     buffer: RingBuffer,
-    pub dict_content: Vec<u8>,
+    /// Content of the dictionary with the id `loaded_dict`. It is kept across frames,
+    /// so frames using the same dictionary don't have to copy it again.
+    dict_content: Vec<u8>,
+    loaded_dict: Option<u32>,
+    /// Whether the current frame uses `dict_content`
+    dict_in_use: bool,
 
     pub window_size: usize,
     total_output_counter: u64,
@@ -36,6 +41,8 @@ impl DecodeBuffer {
         DecodeBuffer {
             buffer: RingBuffer::new(),
             dict_content: Vec::new(),
+            loaded_dict: None,
+            dict_in_use: false,
             window_size,
             total_output_counter: 0,
             #[cfg(feature = "hash")]
@@ -43,11 +50,11 @@ impl DecodeBuffer {
         }
     }
 
-    pub fn reset(&mut self, window_size: usize) {
+    pub fn reset(&mut self, window_size: usize, initial_capacity: usize) {
         self.window_size = window_size;
         self.buffer.clear();
-        self.buffer.reserve(self.window_size);
-        self.dict_content.clear();
+        self.buffer.reserve(initial_capacity);
+        self.dict_in_use = false;
         self.total_output_counter = 0;
         #[cfg(feature = "hash")]
         {
@@ -59,6 +66,37 @@ impl DecodeBuffer {
         self.buffer.len()
     }
 
+    /// Make room for `amount` more bytes
+    pub fn reserve(&mut self, amount: usize) {
+        self.buffer.reserve(amount);
+    }
+
+    /// The number of bytes the buffer grows by if [DecodeBuffer::reserve] is called with `amount`
+    pub fn growth_for(&self, amount: usize) -> usize {
+        self.buffer.growth_for(amount)
+    }
+
+    /// Use the dictionary content for the current frame. The content is only copied if
+    /// a different dictionary was used before.
+    pub fn use_dict(&mut self, dict_id: u32, content: &[u8]) {
+        if self.loaded_dict != Some(dict_id) {
+            self.dict_content.clear();
+            self.dict_content.extend_from_slice(content);
+            self.loaded_dict = Some(dict_id);
+        }
+        self.dict_in_use = true;
+    }
+
+    /// Make the next [DecodeBuffer::use_dict] copy the content again. The current frame keeps using the loaded content.
+    pub fn forget_dict(&mut self) {
+        self.loaded_dict = None;
+    }
+
+    /// The number of bytes decoded into this buffer since the last reset, including bytes that were already drained
+    pub fn total_output(&self) -> u64 {
+        self.total_output_counter
+    }
+
     pub fn push(&mut self, data: &[u8]) {
         self.buffer.extend(data);
         self.total_output_counter += data.len() as u64;
@@ -137,24 +175,29 @@ impl DecodeBuffer {
         if self.total_output_counter <= self.window_size as u64 {
             // at least part of that repeat is from the dictionary content
             let bytes_from_dict = offset - self.buffer.len();
+            let dict = if self.dict_in_use {
+                self.dict_content.as_slice()
+            } else {
+                &[]
+            };
 
-            if bytes_from_dict > self.dict_content.len() {
+            if bytes_from_dict > dict.len() {
                 return Err(DecodeBufferError::NotEnoughBytesInDictionary {
-                    got: self.dict_content.len(),
+                    got: dict.len(),
                     need: bytes_from_dict,
                 });
             }
 
             if bytes_from_dict < match_length {
-                let dict_slice = &self.dict_content[self.dict_content.len() - bytes_from_dict..];
+                let dict_slice = &dict[dict.len() - bytes_from_dict..];
                 self.buffer.extend(dict_slice);
 
                 self.total_output_counter += bytes_from_dict as u64;
                 return self.repeat(self.buffer.len(), match_length - bytes_from_dict);
             } else {
-                let low = self.dict_content.len() - bytes_from_dict;
+                let low = dict.len() - bytes_from_dict;
                 let high = low + match_length;
-                let dict_slice = &self.dict_content[low..high];
+                let dict_slice = &dict[low..high];
                 self.buffer.extend(dict_slice);
             }
             Ok(())
@@ -205,6 +248,26 @@ impl DecodeBuffer {
         }
     }
 
+    /// Drop everything that is not needed to keep decoding with the required window_size
+    pub fn discard_to_window_size(&mut self) {
+        if let Some(can_drain) = self.can_drain_to_window_size() {
+            // Dropping the bytes can't fail
+            let _ = self.drain_to(can_drain, |buf| (buf.len(), Ok(())));
+        }
+    }
+
+    /// Append the last `amount` bytes in the buffer to `target` without draining them
+    pub fn copy_tail(&self, amount: usize, target: &mut Vec<u8>) {
+        let (slice1, slice2) = self.buffer.as_slices();
+        if amount <= slice2.len() {
+            target.extend_from_slice(&slice2[slice2.len() - amount..]);
+        } else {
+            let from_first = amount - slice2.len();
+            target.extend_from_slice(&slice1[slice1.len() - from_first..]);
+            target.extend_from_slice(slice2);
+        }
+    }
+
     /// drain the buffer completely
     pub fn drain(&mut self) -> Vec<u8> {
         let (slice1, slice2) = self.buffer.as_slices();
@@ -324,6 +387,35 @@ mod tests {
     use alloc::vec;
     use alloc::vec::Vec;
 
+    #[test]
+    fn dict_is_kept_across_frames() {
+        use crate::decoding::errors::DecodeBufferError;
+
+        let mut decode_buf = DecodeBuffer::new(100);
+        decode_buf.use_dict(1, b"dict");
+        decode_buf.repeat(4, 4).unwrap();
+        assert_eq!(decode_buf.drain(), b"dict");
+
+        // Frames without a dictionary can't reference the kept content
+        decode_buf.reset(100, 100);
+        assert!(matches!(
+            decode_buf.repeat(4, 4),
+            Err(DecodeBufferError::NotEnoughBytesInDictionary { got: 0, need: 4 })
+        ));
+
+        // The same dictionary is not copied again
+        decode_buf.reset(100, 100);
+        decode_buf.use_dict(1, b"other");
+        decode_buf.repeat(4, 4).unwrap();
+        assert_eq!(decode_buf.drain(), b"dict");
+
+        decode_buf.reset(100, 100);
+        decode_buf.forget_dict();
+        decode_buf.use_dict(1, b"other");
+        decode_buf.repeat(5, 5).unwrap();
+        assert_eq!(decode_buf.drain(), b"other");
+    }
+
     #[test]
     fn short_writer() {
         struct ShortWriter {
@@ -2,6 +2,7 @@
 
 use crate::blocks::block::BlockType;
 use crate::blocks::literals_section::LiteralsSectionType;
+use crate::decoding::alloc_hook::{AllocKind, AllocRejected};
 use crate::io::Error;
 use alloc::vec::Vec;
 use core::fmt;
@@ -45,13 +46,10 @@ impl StdError for FrameDescriptorError {}
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum FrameHeaderError {
+    /// The window descriptor describes a window of 3.75TB or more, which is more than the format allows
     WindowTooBig { got: u64 },
-    WindowTooSmall { got: u64 },
+    /// The frame header descriptor could not be interpreted
     FrameDescriptorError(FrameDescriptorError),
-    DictIdTooSmall { got: usize, expected: usize },
-    MismatchedFrameSize { got: usize, expected: u8 },
-    FrameSizeIsZero,
-    InvalidFrameSize { got: u8 },
 }
 
 impl fmt::Display for FrameHeaderError {
@@ -63,29 +61,7 @@ impl fmt::Display for FrameHeaderError {
                 got,
                 crate::decoding::frame::MAX_WINDOW_SIZE
             ),
-            Self::WindowTooSmall { got } => write!(
-                f,
-                "window_size smaller than allowed minimum. Is: {}, Should be greater than: {}",
-                got,
-                crate::decoding::frame::MIN_WINDOW_SIZE
-            ),
             Self::FrameDescriptorError(e) => write!(f, "{:?}", e),
-            Self::DictIdTooSmall { got, expected } => write!(
-                f,
-                "Not enough bytes in dict_id. Is: {}, Should be: {}",
-                got, expected
-            ),
-            Self::MismatchedFrameSize { got, expected } => write!(
-                f,
-                "frame_content_size does not have the right length. Is: {}, Should be: {}",
-                got, expected
-            ),
-            Self::FrameSizeIsZero => write!(f, "frame_content_size was zero"),
-            Self::InvalidFrameSize { got } => write!(
-                f,
-                "Invalid frame_content_size. Is: {}, Should be one of 1, 2, 4, 8 bytes",
-                got
-            ),
         }
     }
 }
@@ -116,7 +92,16 @@ pub enum ReadFrameHeaderError {
     WindowDescriptorReadError(Error),
     DictionaryIdReadError(Error),
     FrameContentSizeReadError(Error),
-    SkipFrame { magic_number: u32, length: u32 },
+    SkipFrame {
+        magic_number: u32,
+        length: u32,
+    },
+    /// The source ended before the whole header could be read. `needed` is the size of the header
+    /// as far as it was known when the source ended, so it may grow once more bytes are available.
+    IncompleteHeader {
+        got: usize,
+        needed: usize,
+    },
 }
 
 impl fmt::Display for ReadFrameHeaderError {
@@ -143,6 +128,11 @@ impl fmt::Display for ReadFrameHeaderError {
                 "SkippableFrame encountered with MagicNumber 0x{:X} and length {} bytes",
                 magic_number, length
             ),
+            Self::IncompleteHeader { got, needed } => write!(
+                f,
+                "Source ended after {} bytes of the frame header, but at least {} bytes are needed",
+                got, needed
+            ),
         }
     }
 }
@@ -277,11 +267,21 @@ pub enum DecompressBlockError {
         expected_len: usize,
         remaining_bytes: usize,
     },
+    TrailingBlockBytes {
+        expected: u32,
+        got: u32,
+    },
     DecompressLiteralsError(DecompressLiteralsError),
     LiteralsSectionParseError(LiteralsSectionParseError),
     SequencesHeaderParseError(SequencesHeaderParseError),
     DecodeSequenceError(DecodeSequenceError),
     ExecuteSequencesError(ExecuteSequencesError),
+    ReservedBlockType,
+    AllocationRejected {
+        kind: AllocKind,
+        bytes: usize,
+    },
+    BlockSizeError(BlockSizeError),
 }
 
 #[cfg(feature = "std")]
@@ -294,6 +294,7 @@ impl std::error::Error for DecompressBlockError {
             DecompressBlockError::SequencesHeaderParseError(source) => Some(source),
             DecompressBlockError::DecodeSequenceError(source) => Some(source),
             DecompressBlockError::ExecuteSequencesError(source) => Some(source),
+            DecompressBlockError::BlockSizeError(source) => Some(source),
             _ => None,
         }
     }
@@ -315,11 +316,31 @@ impl core::fmt::Display for DecompressBlockError {
                     remaining_bytes,
                 )
             }
+            DecompressBlockError::TrailingBlockBytes { expected, got } => {
+                write!(f,
+                    "The sections of the block used {} bytes but the block header says the block contains {} bytes",
+                    got,
+                    expected,
+                )
+            }
             DecompressBlockError::DecompressLiteralsError(e) => write!(f, "{:?}", e),
             DecompressBlockError::LiteralsSectionParseError(e) => write!(f, "{:?}", e),
             DecompressBlockError::SequencesHeaderParseError(e) => write!(f, "{:?}", e),
             DecompressBlockError::DecodeSequenceError(e) => write!(f, "{:?}", e),
             DecompressBlockError::ExecuteSequencesError(e) => write!(f, "{:?}", e),
+            DecompressBlockError::ReservedBlockType => {
+                write!(f, "Blocks of the reserved block type can not be decoded")
+            }
+            DecompressBlockError::AllocationRejected { kind, bytes } => {
+                write!(
+                    f,
+                    "The alloc hook rejected growing the {:?} buffer by {} bytes",
+                    kind, bytes
+                )
+            }
+            DecompressBlockError::BlockSizeError(e) => {
+                write!(f, "{}", e)
+            }
         }
     }
 }
@@ -330,6 +351,12 @@ impl From<Error> for DecompressBlockError {
     }
 }
 
+impl From<BlockSizeError> for DecompressBlockError {
+    fn from(val: BlockSizeError) -> Self {
+        Self::BlockSizeError(val)
+    }
+}
+
 impl From<DecompressLiteralsError> for DecompressBlockError {
     fn from(val: DecompressLiteralsError) -> Self {
         Self::DecompressLiteralsError(val)
@@ -360,6 +387,15 @@ impl From<ExecuteSequencesError> for DecompressBlockError {
     }
 }
 
+impl From<AllocRejected> for DecompressBlockError {
+    fn from(val: AllocRejected) -> Self {
+        Self::AllocationRejected {
+            kind: val.kind,
+            bytes: val.bytes,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum DecodeBlockContentError {
@@ -367,6 +403,7 @@ pub enum DecodeBlockContentError {
     ExpectedHeaderOfPreviousBlock,
     ReadError { step: BlockType, source: Error },
     DecompressBlockError(DecompressBlockError),
+    AllocationRejected { kind: AllocKind, bytes: usize },
 }
 
 #[cfg(feature = "std")]
@@ -398,6 +435,13 @@ impl core::fmt::Display for DecodeBlockContentError {
                 write!(f, "Error while reading bytes for {}: {}", step, source,)
             }
             DecodeBlockContentError::DecompressBlockError(e) => write!(f, "{:?}", e),
+            DecodeBlockContentError::AllocationRejected { kind, bytes } => {
+                write!(
+                    f,
+                    "The alloc hook rejected growing the {:?} buffer by {} bytes",
+                    kind, bytes
+                )
+            }
         }
     }
 }
@@ -408,6 +452,15 @@ impl From<DecompressBlockError> for DecodeBlockContentError {
     }
 }
 
+impl From<AllocRejected> for DecodeBlockContentError {
+    fn from(val: AllocRejected) -> Self {
+        Self::AllocationRejected {
+            kind: val.kind,
+            bytes: val.bytes,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum DecodeBufferError {
@@ -488,7 +541,9 @@ impl From<HuffmanTableError> for DictionaryDecodeError {
 pub enum FrameDecoderError {
     ReadFrameHeaderError(ReadFrameHeaderError),
     FrameHeaderError(FrameHeaderError),
-    WindowSizeTooBig { requested: u64 },
+    WindowSizeTooBig { requested: u64, max: u64 },
+    WindowExceedsBudget { requested: u64, budget: usize },
+    ContentSizeExceeded { declared: u64, decoded: u64 },
     DictionaryDecodeError(DictionaryDecodeError),
     FailedToReadBlockHeader(BlockHeaderReadError),
     FailedToReadBlockBody(DecodeBlockContentError),
@@ -499,6 +554,10 @@ pub enum FrameDecoderError {
     FailedToSkipFrame,
     TargetTooSmall,
     DictNotProvided { dict_id: u32 },
+    UnexpectedEof,
+    TooManyFrames { max: usize },
+    AllocationRejected { kind: AllocKind, bytes: usize },
+    SkippableFrameTooBig { length: u32, max: u64 },
 }
 
 #[cfg(feature = "std")]
@@ -527,12 +586,25 @@ impl core::fmt::Display for FrameDecoderError {
             FrameDecoderError::FrameHeaderError(e) => {
                 write!(f, "{:?}", e)
             }
-            FrameDecoderError::WindowSizeTooBig { requested } => {
+            FrameDecoderError::WindowSizeTooBig { requested, max } => {
                 write!(
                     f,
                     "Specified window_size is too big; Requested: {}, Max: {}",
-                    requested,
-                    crate::decoding::frame::MAX_WINDOW_SIZE,
+                    requested, max,
+                )
+            }
+            FrameDecoderError::WindowExceedsBudget { requested, budget } => {
+                write!(
+                    f,
+                    "Frame needs a window of {} bytes which exceeds the configured budget of {} bytes",
+                    requested, budget,
+                )
+            }
+            FrameDecoderError::ContentSizeExceeded { declared, decoded } => {
+                write!(
+                    f,
+                    "Frame decoded to at least {} bytes but the header declared a content size of {} bytes",
+                    decoded, declared,
                 )
             }
             FrameDecoderError::DictionaryDecodeError(e) => {
@@ -572,6 +644,30 @@ impl core::fmt::Display for FrameDecoderError {
             FrameDecoderError::DictNotProvided { dict_id } => {
                 write!(f, "Frame header specified dictionary id 0x{:X} that wasnt provided by add_dict() or reset_with_dict()", dict_id)
             }
+            FrameDecoderError::UnexpectedEof => {
+                write!(f, "The input ended before the frame was finished")
+            }
+            FrameDecoderError::TooManyFrames { max } => {
+                write!(
+                    f,
+                    "The input contains more than the configured maximum of {} frames",
+                    max
+                )
+            }
+            FrameDecoderError::AllocationRejected { kind, bytes } => {
+                write!(
+                    f,
+                    "The alloc hook rejected growing the {:?} buffer by {} bytes",
+                    kind, bytes
+                )
+            }
+            FrameDecoderError::SkippableFrameTooBig { length, max } => {
+                write!(
+                    f,
+                    "The payload of the skippable frame is too big to be collected; Length: {}, Max: {}",
+                    length, max
+                )
+            }
         }
     }
 }
@@ -600,27 +696,61 @@ impl From<ReadFrameHeaderError> for FrameDecoderError {
     }
 }
 
+impl From<AllocRejected> for FrameDecoderError {
+    fn from(val: AllocRejected) -> Self {
+        Self::AllocationRejected {
+            kind: val.kind,
+            bytes: val.bytes,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum DecompressLiteralsError {
     MissingCompressedSize,
     MissingNumStreams,
-    GetBitsError(GetBitsError),
+    InvalidNumStreams {
+        got: u8,
+    },
+    /// The bitstream ended while reading the huffman table description.
+    /// `context` names the part of the description that was being read.
+    GetBitsError {
+        context: &'static str,
+        source: GetBitsError,
+    },
     HuffmanTableError(HuffmanTableError),
     HuffmanDecoderError(HuffmanDecoderError),
     UninitializedHuffmanTable,
-    MissingBytesForJumpHeader { got: usize },
-    MissingBytesForLiterals { got: usize, needed: usize },
-    ExtraPadding { skipped_bits: i32 },
-    BitstreamReadMismatch { read_til: isize, expected: isize },
-    DecodedLiteralCountMismatch { decoded: usize, expected: usize },
+    MissingBytesForJumpHeader {
+        got: usize,
+    },
+    MissingBytesForLiterals {
+        got: usize,
+        needed: usize,
+    },
+    ExtraPadding {
+        skipped_bits: i32,
+    },
+    BitstreamReadMismatch {
+        read_til: isize,
+        expected: isize,
+    },
+    DecodedLiteralCountMismatch {
+        decoded: usize,
+        expected: usize,
+    },
+    RegeneratedSizeTooLarge {
+        got: u32,
+        max: u32,
+    },
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for DecompressLiteralsError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            DecompressLiteralsError::GetBitsError(source) => Some(source),
+            DecompressLiteralsError::GetBitsError { source, .. } => Some(source),
             DecompressLiteralsError::HuffmanTableError(source) => Some(source),
             DecompressLiteralsError::HuffmanDecoderError(source) => Some(source),
             _ => None,
@@ -640,7 +770,16 @@ impl core::fmt::Display for DecompressLiteralsError {
                     "num_streams was none even though it must be set to something (1 or 4) for compressed literals",
                 )
             }
-            DecompressLiteralsError::GetBitsError(e) => write!(f, "{:?}", e),
+            DecompressLiteralsError::InvalidNumStreams { got } => {
+                write!(
+                    f,
+                    "Compressed literals are split into 1 or 4 streams, but num_streams was {}",
+                    got,
+                )
+            }
+            DecompressLiteralsError::GetBitsError { context, source } => {
+                write!(f, "Error while reading the {}: {}", context, source)
+            }
             DecompressLiteralsError::HuffmanTableError(e) => write!(f, "{:?}", e),
             DecompressLiteralsError::HuffmanDecoderError(e) => write!(f, "{:?}", e),
             DecompressLiteralsError::UninitializedHuffmanTable => {
@@ -679,6 +818,13 @@ impl core::fmt::Display for DecompressLiteralsError {
                     decoded, expected,
                 )
             }
+            DecompressLiteralsError::RegeneratedSizeTooLarge { got, max } => {
+                write!(
+                    f,
+                    "The literals section declares {} literals, but a block can contain at most {} bytes",
+                    got, max,
+                )
+            }
         }
     }
 }
@@ -689,12 +835,6 @@ impl From<HuffmanDecoderError> for DecompressLiteralsError {
     }
 }
 
-impl From<GetBitsError> for DecompressLiteralsError {
-    fn from(val: GetBitsError) -> Self {
-        Self::GetBitsError(val)
-    }
-}
-
 impl From<HuffmanTableError> for DecompressLiteralsError {
     fn from(val: HuffmanTableError) -> Self {
         Self::HuffmanTableError(val)
@@ -748,25 +888,40 @@ impl From<DecodeBufferError> for ExecuteSequencesError {
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum DecodeSequenceError {
-    GetBitsError(GetBitsError),
+    /// The bitstream ended while reading one of the FSE table descriptions.
+    /// `context` names the table that was being read.
+    GetBitsError {
+        context: &'static str,
+        source: GetBitsError,
+    },
     FSEDecoderError(FSEDecoderError),
     FSETableError(FSETableError),
-    ExtraPadding { skipped_bits: i32 },
-    UnsupportedOffset { offset_code: u8 },
+    ExtraPadding {
+        skipped_bits: i32,
+    },
+    UnsupportedOffset {
+        offset_code: u8,
+    },
     ZeroOffset,
     NotEnoughBytesForNumSequences,
-    ExtraBits { bits_remaining: isize },
+    ExtraBits {
+        bits_remaining: isize,
+    },
     MissingCompressionMode,
     MissingByteForRleLlTable,
     MissingByteForRleOfTable,
     MissingByteForRleMlTable,
+    ImplausibleSequenceCount {
+        num_sequences: u32,
+        max_sequences: usize,
+    },
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for DecodeSequenceError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            DecodeSequenceError::GetBitsError(source) => Some(source),
+            DecodeSequenceError::GetBitsError { source, .. } => Some(source),
             DecodeSequenceError::FSEDecoderError(source) => Some(source),
             DecodeSequenceError::FSETableError(source) => Some(source),
             _ => None,
@@ -777,7 +932,9 @@ impl std::error::Error for DecodeSequenceError {
 impl core::fmt::Display for DecodeSequenceError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            DecodeSequenceError::GetBitsError(e) => write!(f, "{:?}", e),
+            DecodeSequenceError::GetBitsError { context, source } => {
+                write!(f, "Error while reading the {}: {}", context, source)
+            }
             DecodeSequenceError::FSEDecoderError(e) => write!(f, "{:?}", e),
             DecodeSequenceError::FSETableError(e) => write!(f, "{:?}", e),
             DecodeSequenceError::ExtraPadding { skipped_bits } => {
@@ -815,16 +972,18 @@ impl core::fmt::Display for DecodeSequenceError {
             DecodeSequenceError::MissingByteForRleMlTable => {
                 write!(f, "Need a byte to read for RLE ml table")
             }
+            DecodeSequenceError::ImplausibleSequenceCount {
+                num_sequences,
+                max_sequences,
+            } => write!(
+                f,
+                "Sequences header declares {} sequences but the section can contain at most {}",
+                num_sequences, max_sequences,
+            ),
         }
     }
 }
 
-impl From<GetBitsError> for DecodeSequenceError {
-    fn from(val: GetBitsError) -> Self {
-        Self::GetBitsError(val)
-    }
-}
-
 impl From<FSETableError> for DecodeSequenceError {
     fn from(val: FSETableError) -> Self {
         Self::FSETableError(val)
@@ -1197,3 +1356,115 @@ impl From<GetBitsError> for HuffmanDecoderError {
         Self::GetBitsError(val)
     }
 }
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExtractBlockError {
+    ReadFrameHeaderError(ReadFrameHeaderError),
+    BlockHeaderReadError(BlockHeaderReadError),
+    BlockContentReadError(Error),
+    BlockIndexOutOfRange { index: usize, num_blocks: usize },
+}
+
+impl core::fmt::Display for ExtractBlockError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExtractBlockError::ReadFrameHeaderError(e) => {
+                write!(f, "Failed to read the frame header: {}", e)
+            }
+            ExtractBlockError::BlockHeaderReadError(e) => {
+                write!(f, "Failed to read a block header: {}", e)
+            }
+            ExtractBlockError::BlockContentReadError(e) => {
+                write!(f, "Failed to read the block content: {}", e)
+            }
+            ExtractBlockError::BlockIndexOutOfRange { index, num_blocks } => {
+                write!(
+                    f,
+                    "Requested block {} but the frame only contains {} blocks",
+                    index, num_blocks,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for ExtractBlockError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ExtractBlockError::ReadFrameHeaderError(source) => Some(source),
+            ExtractBlockError::BlockHeaderReadError(source) => Some(source),
+            ExtractBlockError::BlockContentReadError(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReadFrameHeaderError> for ExtractBlockError {
+    fn from(val: ReadFrameHeaderError) -> Self {
+        Self::ReadFrameHeaderError(val)
+    }
+}
+
+impl From<BlockHeaderReadError> for ExtractBlockError {
+    fn from(val: BlockHeaderReadError) -> Self {
+        Self::BlockHeaderReadError(val)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ValidateStructureError {
+    ReadFrameHeaderError(ReadFrameHeaderError),
+    FrameHeaderError(FrameHeaderError),
+    BlockHeaderReadError(BlockHeaderReadError),
+    BlockContentReadError(Error),
+    ChecksumReadError(Error),
+    SkippableFrameReadError(Error),
+}
+
+impl core::fmt::Display for ValidateStructureError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ValidateStructureError::ReadFrameHeaderError(e) => {
+                write!(f, "Failed to read the frame header: {}", e)
+            }
+            ValidateStructureError::FrameHeaderError(e) => {
+                write!(f, "Invalid frame header: {}", e)
+            }
+            ValidateStructureError::BlockHeaderReadError(e) => {
+                write!(f, "Failed to read a block header: {}", e)
+            }
+            ValidateStructureError::BlockContentReadError(e) => {
+                write!(f, "Failed to read the block content: {}", e)
+            }
+            ValidateStructureError::ChecksumReadError(e) => {
+                write!(f, "Failed to read the checksum: {}", e)
+            }
+            ValidateStructureError::SkippableFrameReadError(e) => {
+                write!(f, "Failed to read the content of a skippable frame: {}", e)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for ValidateStructureError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ValidateStructureError::ReadFrameHeaderError(source) => Some(source),
+            ValidateStructureError::FrameHeaderError(source) => Some(source),
+            ValidateStructureError::BlockHeaderReadError(source) => Some(source),
+            ValidateStructureError::BlockContentReadError(source) => Some(source),
+            ValidateStructureError::ChecksumReadError(source) => Some(source),
+            ValidateStructureError::SkippableFrameReadError(source) => Some(source),
+        }
+    }
+}
+
+impl From<BlockHeaderReadError> for ValidateStructureError {
+    fn from(val: BlockHeaderReadError) -> Self {
+        Self::BlockHeaderReadError(val)
+    }
+}
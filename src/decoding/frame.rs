@@ -1,5 +1,5 @@
 use crate::decoding::errors::{FrameDescriptorError, FrameHeaderError, ReadFrameHeaderError};
-use crate::io::Read;
+use crate::io::{Error, ErrorKind, Read};
 
 /// This magic number is included at the start of a single Zstandard frame
 pub const MAGIC_NUM: u32 = 0xFD2F_B528;
@@ -7,6 +7,8 @@ pub const MAGIC_NUM: u32 = 0xFD2F_B528;
 pub const MIN_WINDOW_SIZE: u64 = 1024;
 /// The maximum window size is 3.75TB
 pub const MAX_WINDOW_SIZE: u64 = (1 << 41) + 7 * (1 << 38);
+/// Magic number, frame header descriptor and either a window descriptor or a 1 byte frame content size
+pub const MIN_FRAME_HEADER_SIZE: usize = 6;
 
 /// Zstandard compressed data is made of one or more [Frame]s. Each frame is independent and can be
 /// decompressed independently of other frames.
@@ -63,14 +65,11 @@ impl FrameHeader {
 
             let window_size = window_base + window_add;
 
-            if window_size >= MIN_WINDOW_SIZE {
-                if window_size < MAX_WINDOW_SIZE {
-                    Ok(window_size)
-                } else {
-                    Err(FrameHeaderError::WindowTooBig { got: window_size })
-                }
+            // The smallest window that can be described is MIN_WINDOW_SIZE
+            if window_size < MAX_WINDOW_SIZE {
+                Ok(window_size)
             } else {
-                Err(FrameHeaderError::WindowTooSmall { got: window_size })
+                Err(FrameHeaderError::WindowTooBig { got: window_size })
             }
         }
     }
@@ -182,14 +181,25 @@ pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), ReadFrameHeade
     use ReadFrameHeaderError as err;
     let mut buf = [0u8; 4];
 
-    r.read_exact(&mut buf).map_err(err::MagicNumberReadError)?;
-    let mut bytes_read = 4;
+    read_header_field(
+        &mut r,
+        &mut buf,
+        0,
+        MIN_FRAME_HEADER_SIZE,
+        err::MagicNumberReadError,
+    )?;
+    let bytes_read = 4;
     let magic_num = u32::from_le_bytes(buf);
 
     // Skippable frames have a magic number in this interval
     if (0x184D2A50..=0x184D2A5F).contains(&magic_num) {
-        r.read_exact(&mut buf)
-            .map_err(err::FrameDescriptorReadError)?;
+        read_header_field(
+            &mut r,
+            &mut buf,
+            bytes_read,
+            8,
+            err::FrameDescriptorReadError,
+        )?;
         let skip_size = u32::from_le_bytes(buf);
         return Err(ReadFrameHeaderError::SkipFrame {
             magic_number: magic_num,
@@ -201,12 +211,45 @@ pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), ReadFrameHeade
         return Err(ReadFrameHeaderError::BadMagicNumber(magic_num));
     }
 
-    r.read_exact(&mut buf[0..1])
-        .map_err(err::FrameDescriptorReadError)?;
+    read_header_fields(r, bytes_read)
+}
+
+/// Like [read_frame_header] for frames without the magic number, as written by encoders that strip it to save 4 bytes.
+///
+/// The source has to start with the frame header descriptor. The returned size does not include a magic number.
+/// Skippable frames can't be recognized without their magic number, so the source must not contain any.
+pub fn read_frame_header_magicless(r: impl Read) -> Result<(Frame, u8), ReadFrameHeaderError> {
+    read_header_fields(r, 0)
+}
+
+/// Read the frame header starting at the frame header descriptor.
+///
+/// `magic_len` is the number of bytes of the header that were read before, it is included in the returned size.
+fn read_header_fields(
+    mut r: impl Read,
+    magic_len: usize,
+) -> Result<(Frame, u8), ReadFrameHeaderError> {
+    use ReadFrameHeaderError as err;
+    let mut buf = [0u8; 4];
+    let mut bytes_read = magic_len;
+
+    read_header_field(
+        &mut r,
+        &mut buf[0..1],
+        bytes_read,
+        magic_len + MIN_FRAME_HEADER_SIZE - 4,
+        err::FrameDescriptorReadError,
+    )?;
     let desc = FrameDescriptor(buf[0]);
 
     bytes_read += 1;
 
+    // Now the size of the whole header is known
+    let dict_id_len = desc.dictionary_id_bytes()? as usize;
+    let fcs_len = desc.frame_content_size_bytes()? as usize;
+    let window_descriptor_len = if desc.single_segment_flag() { 0 } else { 1 };
+    let header_size = bytes_read + window_descriptor_len + dict_id_len + fcs_len;
+
     let mut frame_header = FrameHeader {
         descriptor: FrameDescriptor(desc.0),
         dict_id: None,
@@ -215,16 +258,26 @@ pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), ReadFrameHeade
     };
 
     if !desc.single_segment_flag() {
-        r.read_exact(&mut buf[0..1])
-            .map_err(err::WindowDescriptorReadError)?;
+        read_header_field(
+            &mut r,
+            &mut buf[0..1],
+            bytes_read,
+            header_size,
+            err::WindowDescriptorReadError,
+        )?;
         frame_header.window_descriptor = buf[0];
         bytes_read += 1;
     }
 
-    let dict_id_len = desc.dictionary_id_bytes()? as usize;
     if dict_id_len != 0 {
         let buf = &mut buf[..dict_id_len];
-        r.read_exact(buf).map_err(err::DictionaryIdReadError)?;
+        read_header_field(
+            &mut r,
+            buf,
+            bytes_read,
+            header_size,
+            err::DictionaryIdReadError,
+        )?;
         bytes_read += dict_id_len;
         let mut dict_id = 0u32;
 
@@ -237,12 +290,16 @@ pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), ReadFrameHeade
         }
     }
 
-    let fcs_len = desc.frame_content_size_bytes()? as usize;
     if fcs_len != 0 {
         let mut fcs_buf = [0u8; 8];
         let fcs_buf = &mut fcs_buf[..fcs_len];
-        r.read_exact(fcs_buf)
-            .map_err(err::FrameContentSizeReadError)?;
+        read_header_field(
+            &mut r,
+            fcs_buf,
+            bytes_read,
+            header_size,
+            err::FrameContentSizeReadError,
+        )?;
         bytes_read += fcs_len;
         let mut fcs = 0u64;
 
@@ -262,3 +319,118 @@ pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), ReadFrameHeade
 
     Ok((frame, bytes_read as u8))
 }
+
+/// Like [Read::read_exact] but reports the source running out of bytes as [ReadFrameHeaderError::IncompleteHeader].
+///
+/// `bytes_read` is the number of header bytes that were read before this field, `needed` the size of the
+/// header as far as it is known at this point.
+fn read_header_field(
+    mut r: impl Read,
+    buf: &mut [u8],
+    bytes_read: usize,
+    needed: usize,
+    wrap_err: impl FnOnce(Error) -> ReadFrameHeaderError,
+) -> Result<(), ReadFrameHeaderError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => {
+                return Err(ReadFrameHeaderError::IncompleteHeader {
+                    got: bytes_read + filled,
+                    needed,
+                })
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(wrap_err(e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_frame_header, MAGIC_NUM, MAX_WINDOW_SIZE};
+    use crate::decoding::errors::{FrameHeaderError, ReadFrameHeaderError};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn incomplete_header() {
+        // Window descriptor, 2 byte dictionary id and 4 byte frame content size
+        let mut header = Vec::new();
+        header.extend_from_slice(&MAGIC_NUM.to_le_bytes());
+        header.push(0b1000_0010);
+        header.push(0);
+        header.extend_from_slice(&[1, 2]);
+        header.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(read_frame_header(header.as_slice()).unwrap().1, 12);
+
+        for len in 0..header.len() {
+            let needed = if len < 5 { 6 } else { 12 };
+            match read_frame_header(&header[..len]) {
+                Err(ReadFrameHeaderError::IncompleteHeader { got, needed: n }) => {
+                    assert_eq!(got, len);
+                    assert_eq!(n, needed);
+                }
+                other => panic!("Unexpected result for {} bytes: {:?}", len, other.err()),
+            }
+        }
+
+        // Skippable frames need the magic number and the length
+        let skippable = [0x50, 0x2A, 0x4D, 0x18, 1, 0];
+        assert!(matches!(
+            read_frame_header(skippable.as_slice()),
+            Err(ReadFrameHeaderError::IncompleteHeader { got: 6, needed: 8 })
+        ));
+
+        // Malformed headers are still reported as such
+        assert!(matches!(
+            read_frame_header([1, 2, 3, 4].as_slice()),
+            Err(ReadFrameHeaderError::BadMagicNumber(_))
+        ));
+    }
+
+    #[test]
+    fn window_too_big() {
+        let mut header = MAGIC_NUM.to_le_bytes().to_vec();
+        header.push(0);
+        header.push(0xFF);
+        let frame = read_frame_header(header.as_slice()).unwrap().0;
+        assert!(matches!(
+            frame.header.window_size(),
+            Err(FrameHeaderError::WindowTooBig { got }) if got == MAX_WINDOW_SIZE
+        ));
+
+        header[5] = 0xF8;
+        assert_eq!(
+            read_frame_header(header.as_slice())
+                .unwrap()
+                .0
+                .header
+                .window_size()
+                .unwrap(),
+            1 << 41
+        );
+    }
+
+    #[test]
+    fn frame_content_size_fields() {
+        // Every field length that can be described is valid, including a content size of zero
+        for (flag, fcs, expected) in [
+            (0u8, &[0u8][..], 0u64),
+            (0, &[255], 255),
+            (1, &[0, 0], 256),
+            (1, &[255, 255], 65791),
+            (2, &[0, 0, 0, 0], 0),
+            (3, &[1, 0, 0, 0, 0, 0, 0, 1], (1 << 56) + 1),
+        ] {
+            let mut header = MAGIC_NUM.to_le_bytes().to_vec();
+            header.push(flag << 6 | 0b0010_0000);
+            header.extend_from_slice(fcs);
+            let (frame, size) = read_frame_header(header.as_slice()).unwrap();
+            assert_eq!(usize::from(size), header.len());
+            assert_eq!(frame.header.frame_content_size(), expected);
+            assert_eq!(frame.header.window_size().unwrap(), expected);
+        }
+    }
+}
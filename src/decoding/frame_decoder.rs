@@ -5,14 +5,18 @@
 //! and utilities that can be used to decode a frame.
 
 use super::frame;
+use crate::blocks::block::{BlockHeader, BlockType};
 use crate::decoding;
+use crate::decoding::alloc_hook::{reserve_vec, AllocHook, AllocKind};
+use crate::decoding::block_decoder::MAX_STACK_BUFFER_SIZE;
 use crate::decoding::dictionary::Dictionary;
-use crate::decoding::errors::FrameDecoderError;
+use crate::decoding::errors::{DecodeBlockContentError, DecompressBlockError, FrameDecoderError};
 use crate::decoding::scratch::DecoderScratch;
 use crate::io::{Error, Read, Write};
+use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
 
 /// Low level Zstandard decoder that can be used to decompress frames with fine control over when and how many bytes are decoded.
 ///
@@ -70,8 +74,21 @@ use core::convert::TryInto;
 pub struct FrameDecoder {
     state: Option<FrameDecoderState>,
     dicts: BTreeMap<u32, Dictionary>,
+    window_budget: Option<usize>,
+    max_window_size: u64,
+    max_frames: Option<usize>,
+    lenient: bool,
+    skippable_handler: Option<Box<SkippableHandler>>,
+    /// The hook set before the first frame, it is moved into the scratch once it exists
+    alloc_hook: Option<Box<dyn AllocHook>>,
+    /// The buffers reserved by [FrameDecoder::reserve_window] before the first frame, they are used for it
+    reserved_scratch: Option<DecoderScratch>,
+    magicless: bool,
 }
 
+/// Called with the magic number and the payload of skippable frames, see [FrameDecoder::set_skippable_handler]
+type SkippableHandler = dyn FnMut(u32, &[u8]) + Send;
+
 struct FrameDecoderState {
     pub frame: frame::Frame,
     decoder_scratch: DecoderScratch,
@@ -80,50 +97,348 @@ struct FrameDecoderState {
     bytes_read_counter: u64,
     check_sum: Option<u32>,
     using_dict: Option<u32>,
+    last_block_stats: Option<BlockStats>,
+    last_decode_consumed_checksum: bool,
+    corrupt_blocks: Vec<CorruptBlock>,
+    /// See [FrameDecoder::set_max_window_size]
+    max_window_size: u64,
+}
+
+/// Statistics about a single decoded block, see [FrameDecoder::last_block_stats]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockStats {
+    /// The type of the block
+    pub block_type: BlockType,
+    /// How many bytes of the source the block content took up, excluding the block header
+    pub compressed_size: u32,
+    /// How many bytes the block decoded to
+    pub decompressed_size: u32,
+    /// How many sequences the block contained. Always 0 for raw and RLE blocks
+    pub num_sequences: u32,
+    /// How many literals the block contained. Always 0 for raw and RLE blocks
+    pub literals_size: u32,
+}
+
+/// A block that could not be decoded and was skipped in lenient mode, see [FrameDecoder::set_lenient]
+#[derive(Debug)]
+pub struct CorruptBlock {
+    /// The index of the block in its frame
+    pub block_index: usize,
+    /// The position in the decoded content of the frame at which the block starts
+    pub output_position: u64,
+    /// How many bytes the block decoded to before the error occurred. These bytes are part of the output
+    pub decoded_size: u32,
+    /// Why the block could not be decoded
+    pub error: DecompressBlockError,
 }
 
+/// A block read by [FrameDecoder::decode_blocks_parallel]
+#[cfg(feature = "parallel")]
+struct BatchBlock {
+    header: BlockHeader,
+    header_size: u8,
+    content: Vec<u8>,
+    /// Whether the source contained the whole content
+    complete: bool,
+    /// The tables of compressed blocks that are decoded ahead of time
+    tables: Option<BatchTables>,
+    /// The literals and sequences of the block, decoded ahead of time
+    decoded: Option<Result<DecodedBlock, DecompressBlockError>>,
+}
+
+/// The tables of a block read by [FrameDecoder::decode_blocks_parallel]
+#[cfg(feature = "parallel")]
+struct BatchTables {
+    /// The tables before this block
+    before: decoding::block_extraction::BlockScratch,
+    /// The tables after this block, which it is decoded with
+    after: decoding::block_extraction::BlockScratch,
+    sizes: decoding::block_decoder::TableSizes,
+}
+
+/// The literals and sequences of a compressed block
+#[cfg(feature = "parallel")]
+type DecodedBlock = (Vec<u8>, Vec<decoding::Sequence>);
+
 pub enum BlockDecodingStrategy {
     All,
     UptoBlocks(usize),
     UptoBytes(usize),
 }
 
+/// The default for [FrameDecoder::set_max_window_size]
 const MAX_WINDOW_SIZE: u64 = 1024 * 1024 * 100;
+/// Single segment frames use their content size as window size. The declared size can't be trusted,
+/// so the buffer starts with at most this capacity and grows with the actually decoded data.
+const MAX_INITIAL_SINGLE_SEGMENT_CAPACITY: usize = 1024 * 1024;
+
+/// Reject window sizes that exceed the budget set with [FrameDecoder::set_window_budget]
+/// or the maximum set with [FrameDecoder::set_max_window_size], before anything is allocated for the window.
+///
+/// Returns the window size of the frame.
+fn check_window_size(
+    frame: &frame::Frame,
+    window_budget: Option<usize>,
+    max_window_size: u64,
+) -> Result<u64, FrameDecoderError> {
+    let window_size = frame.header.window_size()?;
+    match window_budget {
+        Some(budget) if window_size > budget as u64 => {
+            return Err(FrameDecoderError::WindowExceedsBudget {
+                requested: window_size,
+                budget,
+            })
+        }
+        _ => {}
+    }
+    // Nothing is allocated up front based on the declared size of single segment frames,
+    // their output is checked against the declared size and the maximum instead
+    if window_size > max_window_size && !frame.header.descriptor.single_segment_flag() {
+        return Err(FrameDecoderError::WindowSizeTooBig {
+            requested: window_size,
+            max: max_window_size,
+        });
+    }
+    Ok(window_size)
+}
+
+/// Read the header of the next frame, see [FrameDecoder::set_magicless]
+fn read_header(
+    source: impl Read,
+    magicless: bool,
+) -> Result<(frame::Frame, u8), crate::decoding::errors::ReadFrameHeaderError> {
+    if magicless {
+        frame::read_frame_header_magicless(source)
+    } else {
+        frame::read_frame_header(source)
+    }
+}
+
+/// The number of bytes reserved in the decode buffer when a frame is started
+fn initial_capacity(frame: &frame::Frame, window_size: usize) -> usize {
+    if frame.header.descriptor.single_segment_flag() {
+        window_size.min(MAX_INITIAL_SINGLE_SEGMENT_CAPACITY)
+    } else {
+        window_size
+    }
+}
 
 impl FrameDecoderState {
-    pub fn new(source: impl Read) -> Result<FrameDecoderState, FrameDecoderError> {
-        let (frame, header_size) = frame::read_frame_header(source)?;
-        let window_size = frame.header.window_size()?;
+    /// Read the header of the next frame. Uses `reserved_scratch` if there is one, otherwise the buffers
+    /// are allocated while decoding.
+    pub fn new(
+        source: impl Read,
+        window_budget: Option<usize>,
+        max_window_size: u64,
+        reserved_scratch: &mut Option<DecoderScratch>,
+        magicless: bool,
+    ) -> Result<FrameDecoderState, FrameDecoderError> {
+        let (frame, header_size) = read_header(source, magicless)?;
+        let window_size = check_window_size(&frame, window_budget, max_window_size)?;
+        let window_size = usize::try_from(window_size).unwrap_or(usize::MAX);
+        let decoder_scratch = match reserved_scratch.take() {
+            Some(mut scratch) => {
+                scratch.reset(window_size, initial_capacity(&frame, window_size))?;
+                scratch
+            }
+            None => DecoderScratch::new(window_size),
+        };
         Ok(FrameDecoderState {
             frame,
             frame_finished: false,
             block_counter: 0,
-            decoder_scratch: DecoderScratch::new(window_size as usize),
+            decoder_scratch,
             bytes_read_counter: u64::from(header_size),
             check_sum: None,
             using_dict: None,
+            last_block_stats: None,
+            last_decode_consumed_checksum: false,
+            corrupt_blocks: Vec::new(),
+            max_window_size,
         })
     }
 
-    pub fn reset(&mut self, source: impl Read) -> Result<(), FrameDecoderError> {
-        let (frame, header_size) = frame::read_frame_header(source)?;
-        let window_size = frame.header.window_size()?;
-
-        if window_size > MAX_WINDOW_SIZE {
-            return Err(FrameDecoderError::WindowSizeTooBig {
-                requested: window_size,
-            });
-        }
+    pub fn reset(
+        &mut self,
+        source: impl Read,
+        window_budget: Option<usize>,
+        max_window_size: u64,
+        magicless: bool,
+    ) -> Result<(), FrameDecoderError> {
+        let (frame, header_size) = read_header(source, magicless)?;
+        let window_size = check_window_size(&frame, window_budget, max_window_size)?;
+        let window_size = usize::try_from(window_size).unwrap_or(usize::MAX);
+        let initial_capacity = initial_capacity(&frame, window_size);
 
         self.frame = frame;
         self.frame_finished = false;
         self.block_counter = 0;
-        self.decoder_scratch.reset(window_size as usize);
+        self.decoder_scratch.reset(window_size, initial_capacity)?;
         self.bytes_read_counter = u64::from(header_size);
         self.check_sum = None;
         self.using_dict = None;
+        self.last_block_stats = None;
+        self.last_decode_consumed_checksum = false;
+        self.corrupt_blocks.clear();
+        self.max_window_size = max_window_size;
+        Ok(())
+    }
+
+    /// The content size from the frame header, if the header contains one
+    fn declared_content_size(&self) -> Option<u64> {
+        match self.frame.header.descriptor.frame_content_size_bytes() {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(self.frame.header.frame_content_size()),
+        }
+    }
+
+    /// Frames must not decode to more than their declared content size. Single segment frames keep all of their
+    /// output as window, so they must not decode to more than the maximum window size either.
+    fn check_content_size(&self) -> Result<(), FrameDecoderError> {
+        let decoded = self.decoder_scratch.buffer.total_output();
+        if self.frame.header.descriptor.single_segment_flag() && decoded > self.max_window_size {
+            return Err(FrameDecoderError::WindowSizeTooBig {
+                requested: self.decoder_scratch.buffer.window_size as u64,
+                max: self.max_window_size,
+            });
+        }
+        let Some(declared) = self.declared_content_size() else {
+            return Ok(());
+        };
+        if decoded > declared {
+            return Err(FrameDecoderError::ContentSizeExceeded { declared, decoded });
+        }
         Ok(())
     }
+
+    /// Handle an error that occurred while decoding the content of a block.
+    ///
+    /// In lenient mode, errors in the content of a compressed block are recorded and the block is skipped.
+    /// Its content has been read completely at that point, so the source is positioned at the next block header.
+    /// Returns the number of bytes of the block body that were read.
+    fn skip_corrupt_block(
+        &mut self,
+        header: &BlockHeader,
+        error: DecodeBlockContentError,
+        buffer_len_before: usize,
+        lenient: bool,
+    ) -> Result<u64, FrameDecoderError> {
+        match error {
+            DecodeBlockContentError::DecompressBlockError(error)
+                if lenient && is_recoverable(&error) =>
+            {
+                let decoded_size = self.decoder_scratch.buffer.len() - buffer_len_before;
+                self.corrupt_blocks.push(CorruptBlock {
+                    block_index: self.block_counter,
+                    output_position: self.decoder_scratch.buffer.total_output()
+                        - decoded_size as u64,
+                    decoded_size: decoded_size as u32,
+                    error,
+                });
+                Ok(u64::from(header.content_size))
+            }
+            error => Err(FrameDecoderError::FailedToReadBlockBody(error)),
+        }
+    }
+
+    /// Account for a block that was just decoded into the scratch, and read the checksum after the last block of the frame
+    fn finish_block(
+        &mut self,
+        header: &BlockHeader,
+        result: Result<u64, DecodeBlockContentError>,
+        buffer_len_before: usize,
+        lenient: bool,
+        mut source: impl Read,
+    ) -> Result<(), FrameDecoderError> {
+        let bytes_read_in_block_body = match result {
+            Ok(bytes_read) => bytes_read,
+            Err(e) => self.skip_corrupt_block(header, e, buffer_len_before, lenient)?,
+        };
+        self.bytes_read_counter += bytes_read_in_block_body;
+        self.update_block_stats(header, bytes_read_in_block_body, buffer_len_before);
+        self.check_content_size()?;
+
+        self.block_counter += 1;
+
+        vprintln!("Output: {}", self.decoder_scratch.buffer.len());
+
+        if header.last_block {
+            self.frame_finished = true;
+            if self.frame.header.descriptor.content_checksum_flag() {
+                let mut chksum = [0u8; 4];
+                source
+                    .read_exact(&mut chksum)
+                    .map_err(FrameDecoderError::FailedToReadChecksum)?;
+                self.bytes_read_counter += 4;
+                let chksum = u32::from_le_bytes(chksum);
+                self.check_sum = Some(chksum);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the stats of a block that was just decoded into the scratch
+    fn update_block_stats(
+        &mut self,
+        header: &BlockHeader,
+        compressed_size: u64,
+        buffer_len_before: usize,
+    ) {
+        let scratch = &self.decoder_scratch;
+        let (num_sequences, literals_size) = match header.block_type {
+            BlockType::Compressed => (
+                scratch.sequences.len() as u32,
+                scratch.literals_buffer.len() as u32,
+            ),
+            _ => (0, 0),
+        };
+        self.last_block_stats = Some(BlockStats {
+            block_type: header.block_type,
+            compressed_size: compressed_size as u32,
+            decompressed_size: (scratch.buffer.len() - buffer_len_before) as u32,
+            num_sequences,
+            literals_size,
+        });
+    }
+}
+
+/// Whether decoding can continue with the next block after a compressed block failed with `error`.
+///
+/// This is the case if the block content was read completely and only turned out to be invalid.
+fn is_recoverable(error: &DecompressBlockError) -> bool {
+    !matches!(
+        error,
+        DecompressBlockError::BlockContentReadError(_)
+            | DecompressBlockError::AllocationRejected { .. }
+    )
+}
+
+impl core::fmt::Debug for FrameDecoder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("FrameDecoder");
+        if let Some(state) = &self.state {
+            debug
+                .field("window_size", &state.decoder_scratch.buffer.window_size)
+                .field("content_size", &state.frame.header.frame_content_size())
+                .field("frame_finished", &state.frame_finished)
+                .field("blocks_decoded", &state.block_counter)
+                .field("bytes_read", &state.bytes_read_counter)
+                .field("can_collect", &self.can_collect())
+                .field("using_dict", &state.using_dict);
+        } else {
+            debug.field("initialized", &false);
+        }
+        debug
+            .field("dict_ids", &self.dicts.keys())
+            .field("window_budget", &self.window_budget)
+            .field("max_window_size", &self.max_window_size)
+            .field("max_frames", &self.max_frames)
+            .field("lenient", &self.lenient)
+            .field("magicless", &self.magicless)
+            .field("has_skippable_handler", &self.skippable_handler.is_some())
+            .field("has_alloc_hook", &self.has_alloc_hook())
+            .finish()
+    }
 }
 
 impl Default for FrameDecoder {
@@ -140,9 +455,89 @@ impl FrameDecoder {
         FrameDecoder {
             state: None,
             dicts: BTreeMap::new(),
+            window_budget: None,
+            max_window_size: MAX_WINDOW_SIZE,
+            max_frames: None,
+            lenient: false,
+            skippable_handler: None,
+            alloc_hook: None,
+            reserved_scratch: None,
+            magicless: false,
         }
     }
 
+    /// Create a decoder with its buffers already reserved for frames with a window of up to `window_size` bytes.
+    ///
+    /// A decoder created with [FrameDecoder::new] allocates its buffers while the first frame is decoded and
+    /// grows them as the window fills up. This one allocates them right away, with room for the window and one block,
+    /// so decoding a frame whose window fits does not allocate if the decoded data is collected after each block.
+    /// Frames with larger windows are still decoded, the buffers grow as needed.
+    ///
+    /// Fails with [FrameDecoderError::WindowSizeTooBig] if `window_size` is bigger than the largest window
+    /// a frame can declare, see [FrameDecoder::reserve_window].
+    ///
+    /// ```
+    /// use ruzstd::decoding::{FrameDecoder, FrameInfo};
+    /// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    ///
+    /// let compressed = compress_to_vec(&[1, 2, 3, 4][..], CompressionLevel::Fastest);
+    /// let window_size = FrameInfo::read(&compressed[..]).unwrap().window_size;
+    /// let mut decoder = FrameDecoder::with_window_capacity(window_size as usize).unwrap();
+    /// let mut output = Vec::with_capacity(4);
+    /// decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+    /// assert_eq!(output, [1, 2, 3, 4]);
+    /// ```
+    pub fn with_window_capacity(window_size: usize) -> Result<FrameDecoder, FrameDecoderError> {
+        let mut decoder = FrameDecoder::new();
+        decoder.reserve_window(window_size)?;
+        Ok(decoder)
+    }
+
+    /// Reserve the buffers for frames with a window of up to `window_size` bytes, like [FrameDecoder::with_window_capacity].
+    ///
+    /// The buffers are kept across frames and never shrink, so after reserving them for the biggest expected window,
+    /// decoding many frames in a loop does not allocate for each of them. If an alloc hook is set, it is asked first
+    /// and the reservation fails with [FrameDecoderError::AllocationRejected] if it refuses.
+    /// Windows bigger than the maximum window size (see [FrameDecoder::set_max_window_size]) can't be decoded,
+    /// so reserving one fails with [FrameDecoderError::WindowSizeTooBig] without allocating.
+    ///
+    /// ```
+    /// use ruzstd::decoding::FrameDecoder;
+    /// use ruzstd::encoding::{compress_slice_to_vec, CompressionLevel};
+    ///
+    /// let mut decoder = FrameDecoder::new();
+    /// decoder.reserve_window(64 * 1024).unwrap();
+    /// let mut output = Vec::with_capacity(1000);
+    /// for len in [10, 100, 1000] {
+    ///     let compressed = compress_slice_to_vec(&vec![7; len], CompressionLevel::Fastest);
+    ///     output.clear();
+    ///     decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+    ///     assert_eq!(output.len(), len);
+    /// }
+    /// ```
+    pub fn reserve_window(&mut self, window_size: usize) -> Result<(), FrameDecoderError> {
+        if window_size as u64 > self.max_window_size {
+            return Err(FrameDecoderError::WindowSizeTooBig {
+                requested: window_size as u64,
+                max: self.max_window_size,
+            });
+        }
+        match &mut self.state {
+            Some(state) => state.decoder_scratch.reserve_window(window_size)?,
+            None => {
+                let scratch = self
+                    .reserved_scratch
+                    .get_or_insert_with(|| DecoderScratch::new(window_size));
+                // The hook is only moved into the scratch for the first frame
+                scratch.alloc_hook = self.alloc_hook.take();
+                let reserved = scratch.reserve_window(window_size);
+                self.alloc_hook = scratch.alloc_hook.take();
+                reserved?;
+            }
+        }
+        Ok(())
+    }
+
     /// init() will allocate all needed buffers if it is the first time this decoder is used
     /// else they just reset these buffers with not further allocations
     ///
@@ -163,12 +558,24 @@ impl FrameDecoder {
         use FrameDecoderError as err;
         let state = match &mut self.state {
             Some(s) => {
-                s.reset(source)?;
+                s.reset(
+                    source,
+                    self.window_budget,
+                    self.max_window_size,
+                    self.magicless,
+                )?;
                 s
             }
             None => {
-                self.state = Some(FrameDecoderState::new(source)?);
-                self.state.as_mut().unwrap()
+                let mut state = FrameDecoderState::new(
+                    source,
+                    self.window_budget,
+                    self.max_window_size,
+                    &mut self.reserved_scratch,
+                    self.magicless,
+                )?;
+                state.decoder_scratch.alloc_hook = self.alloc_hook.take();
+                self.state.insert(state)
             }
         };
         if let Some(dict_id) = state.frame.header.dictionary_id() {
@@ -182,8 +589,140 @@ impl FrameDecoder {
         Ok(())
     }
 
+    /// Reject frames that need a window bigger than `budget` bytes with [FrameDecoderError::WindowExceedsBudget].
+    ///
+    /// The check happens in [FrameDecoder::reset]/[FrameDecoder::init] before the window buffer is allocated.
+    /// Unlike [FrameDecoder::set_max_window_size] it also applies to single segment frames, whose window is their
+    /// declared content size, so it limits the memory any frame can make the decoder allocate for its window.
+    pub fn set_window_budget(&mut self, budget: Option<usize>) {
+        self.window_budget = budget;
+    }
+
+    /// Reject frames whose window descriptor asks for a window bigger than `max_window_size` bytes
+    /// with [FrameDecoderError::WindowSizeTooBig]. The default is 100MB.
+    ///
+    /// The check happens in [FrameDecoder::reset]/[FrameDecoder::init] before the window buffer is allocated.
+    /// Single segment frames have no window descriptor, their buffer grows with the decoded data instead of being
+    /// allocated up front. They fail with the same error once they decode to more than `max_window_size` bytes.
+    pub fn set_max_window_size(&mut self, max_window_size: u64) {
+        self.max_window_size = max_window_size;
+        if let Some(state) = &mut self.state {
+            state.max_window_size = max_window_size;
+        }
+    }
+
+    /// Stop decoding inputs with more than `max_frames` frames with [FrameDecoderError::TooManyFrames]. Unlimited by default.
+    ///
+    /// The limits on the window and the content size only bound the work per frame, so an untrusted input
+    /// consisting of millions of tiny frames could still keep the decoder busy. Skippable frames are counted as well.
+    /// The limit is checked by [FrameDecoder::decode_all] and [FrameDecoder::decode_all_to_vec] before each frame.
+    pub fn set_max_frames(&mut self, max_frames: Option<usize>) {
+        self.max_frames = max_frames;
+    }
+
+    /// Skip blocks with corrupt content instead of failing, to salvage as much data as possible from a damaged frame.
+    ///
+    /// If the content of a compressed block turns out to be invalid, the error is recorded in
+    /// [FrameDecoder::corrupt_blocks] and decoding continues with the next block. Whatever the block decoded
+    /// to before the error stays in the output, the rest of its content is missing. Errors that leave the source
+    /// at an unknown position, like a broken block header or a truncated input, still stop decoding.
+    ///
+    /// This is a best effort and does not conform to the specification: Later blocks may refer to
+    /// the missing content or reuse the broken tables of the corrupt block and decode to garbage or fail as well.
+    /// Raw and RLE blocks do not depend on previous blocks and are always recovered.
+    /// The default is to fail on the first error.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Decode frames that don't start with the magic number, like the magicless format of the reference implementation
+    /// that strips it to save 4 bytes when frames are embedded in another format. Disabled by default.
+    ///
+    /// The frames are expected to start with the frame header descriptor. Skippable frames are identified by
+    /// their magic number, so inputs of magicless frames can't contain them.
+    /// [crate::encoding::FrameCompressor::set_magicless] writes frames in this format.
+    pub fn set_magicless(&mut self, magicless: bool) {
+        self.magicless = magicless;
+    }
+
+    /// The blocks of the current frame that were skipped because of corrupt content, see [FrameDecoder::set_lenient]
+    pub fn corrupt_blocks(&self) -> &[CorruptBlock] {
+        match &self.state {
+            Some(state) => &state.corrupt_blocks,
+            None => &[],
+        }
+    }
+
+    /// Call `handler` with the magic number and the payload of each skippable frame that is skipped while decoding multiple frames.
+    ///
+    /// This lets you read metadata that is embedded in skippable frames, like seek tables or comments, in the same pass
+    /// that decodes the data. Decoding continues with the next frame after the handler returns.
+    /// Without a handler, skippable frames are skipped without looking at them.
+    ///
+    /// The handler is used by [FrameDecoder::decode_all], [FrameDecoder::decode_all_to_vec] and concatenated
+    /// [crate::decoding::StreamingDecoder]s. The streaming decoder collects the payload in memory first, so there
+    /// payloads bigger than the maximum window size (see [FrameDecoder::set_max_window_size]) fail with
+    /// [FrameDecoderError::SkippableFrameTooBig].
+    /// [FrameDecoder::reset] and [FrameDecoder::init] still report a skippable frame as
+    /// [crate::decoding::errors::ReadFrameHeaderError::SkipFrame], because they do not consume the payload.
+    pub fn set_skippable_handler(&mut self, handler: impl FnMut(u32, &[u8]) + Send + 'static) {
+        self.skippable_handler = Some(Box::new(handler));
+    }
+
+    /// Stop calling the handler set with [FrameDecoder::set_skippable_handler]
+    pub fn clear_skippable_handler(&mut self) {
+        self.skippable_handler = None;
+    }
+
+    /// Ask `hook` before the decoder allocates memory for its buffers, to track or limit the memory it uses.
+    ///
+    /// The hook is called with the kind of buffer and the number of bytes it grows by. Returning `false`
+    /// rejects the allocation and decoding fails with an `AllocationRejected` error, for example
+    /// [FrameDecoderError::AllocationRejected]. Buffers are kept for the following frames and only freed
+    /// when the decoder is dropped, so the sum of all allowed allocations is the memory held in these buffers.
+    ///
+    /// The window buffer, the decoding tables and the buffers for the content, literals and sequences of a block are covered.
+    /// Small fixed size allocations and the copy of a dictionary's content are not reported.
+    /// With a hook, buffers are reserved before each block instead of growing while it is decoded,
+    /// so the window buffer can end up one block bigger than without a hook.
+    ///
+    /// ```
+    /// use ruzstd::decoding::{AllocKind, FrameDecoder};
+    /// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    ///
+    /// let compressed = compress_to_vec(&[1, 2, 3, 4][..], CompressionLevel::Fastest);
+    /// let mut decoder = FrameDecoder::new();
+    /// let mut used = 0;
+    /// decoder.set_alloc_hook(Some(Box::new(move |_: AllocKind, bytes: usize| {
+    ///     used += bytes;
+    ///     used <= 1024 * 1024
+    /// })));
+    /// let mut output = Vec::with_capacity(4);
+    /// decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+    /// ```
+    pub fn set_alloc_hook(&mut self, hook: Option<Box<dyn AllocHook>>) {
+        match &mut self.state {
+            Some(state) => state.decoder_scratch.alloc_hook = hook,
+            None => self.alloc_hook = hook,
+        }
+    }
+
+    fn has_alloc_hook(&self) -> bool {
+        match &self.state {
+            Some(state) => state.decoder_scratch.alloc_hook.is_some(),
+            None => self.alloc_hook.is_some(),
+        }
+    }
+
     /// Add a dict to the FrameDecoder that can be used when needed. The FrameDecoder uses the appropriate one dynamically
+    ///
+    /// The content of the last used dict is kept in the decoder, so decoding many frames with the same dict
+    /// does not copy the dict content for every frame.
     pub fn add_dict(&mut self, dict: Dictionary) -> Result<(), FrameDecoderError> {
+        if let Some(state) = &mut self.state {
+            // The dict might replace the one that is currently loaded
+            state.decoder_scratch.buffer.forget_dict();
+        }
         self.dicts.insert(dict.id, dict);
         Ok(())
     }
@@ -270,17 +809,30 @@ impl FrameDecoder {
         state.block_counter
     }
 
+    /// Statistics about the last block that was decoded in the current frame
+    ///
+    /// Decode with [BlockDecodingStrategy::UptoBlocks] set to 1 to see the stats of every block.
+    pub fn last_block_stats(&self) -> Option<BlockStats> {
+        self.state.as_ref()?.last_block_stats
+    }
+
     /// Decodes blocks from a reader. It requires that the framedecoder has been initialized first.
     /// The Strategy influences how many blocks will be decoded before the function returns
     /// This is important if you want to manage memory consumption carefully. If you don't care
     /// about that you can just choose the strategy "All" and have all blocks of the frame decoded into the buffer
+    ///
+    /// Once the frame is finished this does not read from the source anymore and just returns `Ok(true)`.
     pub fn decode_blocks(
         &mut self,
         mut source: impl Read,
         strat: BlockDecodingStrategy,
     ) -> Result<bool, FrameDecoderError> {
         use FrameDecoderError as err;
+        let lenient = self.lenient;
         let state = self.state.as_mut().ok_or(err::NotYetInitialized)?;
+        if state.frame_finished {
+            return Ok(true);
+        }
 
         let mut block_dec = decoding::block_decoder::new();
 
@@ -303,26 +855,225 @@ impl FrameDecoder {
                 block_header.decompressed_size
             );
 
-            let bytes_read_in_block_body = block_dec
-                .decode_block_content(&block_header, &mut state.decoder_scratch, &mut source)
-                .map_err(err::FailedToReadBlockBody)?;
-            state.bytes_read_counter += bytes_read_in_block_body;
+            let buffer_len_before_block = state.decoder_scratch.buffer.len();
+            let result = block_dec.decode_block_content(
+                &block_header,
+                &mut state.decoder_scratch,
+                &mut source,
+            );
+            state.finish_block(
+                &block_header,
+                result,
+                buffer_len_before_block,
+                lenient,
+                &mut source,
+            )?;
+            if block_header.last_block {
+                break;
+            }
 
-            state.block_counter += 1;
+            match strat {
+                BlockDecodingStrategy::All => { /* keep going */ }
+                BlockDecodingStrategy::UptoBlocks(n) => {
+                    if state.block_counter - block_counter_before >= n {
+                        break;
+                    }
+                }
+                BlockDecodingStrategy::UptoBytes(n) => {
+                    if state.decoder_scratch.buffer.len() - buffer_size_before >= n {
+                        break;
+                    }
+                }
+            }
+        }
 
-            vprintln!("Output: {}", state.decoder_scratch.buffer.len());
+        Ok(state.frame_finished)
+    }
 
-            if block_header.last_block {
-                state.frame_finished = true;
-                if state.frame.header.descriptor.content_checksum_flag() {
-                    let mut chksum = [0u8; 4];
-                    source
-                        .read_exact(&mut chksum)
-                        .map_err(err::FailedToReadChecksum)?;
-                    state.bytes_read_counter += 4;
-                    let chksum = u32::from_le_bytes(chksum);
-                    state.check_sum = Some(chksum);
+    /// Like [FrameDecoder::decode_blocks], but the literals and sequences of several compressed blocks are decoded
+    /// at the same time on the rayon thread pool. Only executing the sequences, which needs the window, is done one
+    /// block after another. The output is exactly the same as with [FrameDecoder::decode_blocks].
+    ///
+    /// Blocks are read from the source in batches of twice the number of threads in the pool. With
+    /// [BlockDecodingStrategy::UptoBytes] the whole batch is decoded, even if the limit is reached within it.
+    ///
+    /// If an alloc hook is set this decodes like [FrameDecoder::decode_blocks], the hook can't limit the buffers of a batch.
+    ///
+    /// If an error is returned, the source may have been read past the block that failed, by up to the rest of its
+    /// batch. So the position of the source can't be used to find the failing block or to continue after it.
+    ///
+    /// ```
+    /// use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+    ///
+    /// let data = vec![7u8; 1024 * 1024];
+    /// let compressed =
+    ///     ruzstd::encoding::compress_to_vec(&data[..], ruzstd::encoding::CompressionLevel::Fastest);
+    /// let mut source = compressed.as_slice();
+    /// let mut decoder = FrameDecoder::new();
+    /// decoder.reset(&mut source).unwrap();
+    /// decoder
+    ///     .decode_blocks_parallel(&mut source, BlockDecodingStrategy::All)
+    ///     .unwrap();
+    /// assert_eq!(decoder.collect().unwrap(), data);
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn decode_blocks_parallel(
+        &mut self,
+        mut source: impl Read,
+        strat: BlockDecodingStrategy,
+    ) -> Result<bool, FrameDecoderError> {
+        use crate::decoding::block_decoder::{self, ABSOLUTE_MAXIMUM_BLOCK_SIZE};
+        use crate::decoding::block_extraction::BlockScratch;
+        use rayon::prelude::*;
+        use FrameDecoderError as err;
+
+        if self.has_alloc_hook() {
+            return self.decode_blocks(source, strat);
+        }
+        let lenient = self.lenient;
+        let state = self.state.as_mut().ok_or(err::NotYetInitialized)?;
+        if state.frame_finished {
+            return Ok(true);
+        }
+
+        let mut block_dec = block_decoder::new();
+        let batch_size = 2 * rayon::current_num_threads();
+        let max_block_size = state
+            .decoder_scratch
+            .buffer
+            .window_size
+            .min(ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize) as u32;
+
+        let buffer_size_before = state.decoder_scratch.buffer.len();
+        let block_counter_before = state.block_counter;
+        loop {
+            let batch_limit = match strat {
+                BlockDecodingStrategy::UptoBlocks(n) => {
+                    batch_size.min(n.saturating_sub(state.block_counter - block_counter_before))
+                }
+                _ => batch_size,
+            };
+
+            // Read the blocks of the batch
+            let mut batch: Vec<BatchBlock> = Vec::new();
+            let mut header_error = None;
+            loop {
+                let (header, header_size) = match block_dec.read_block_header(&mut source) {
+                    Ok(header) => header,
+                    Err(e) => {
+                        header_error = Some(err::FailedToReadBlockHeader(e));
+                        break;
+                    }
+                };
+                let mut content = Vec::new();
+                let complete = (&mut source)
+                    .take(u64::from(header.content_size))
+                    .read_to_end(&mut content)
+                    .is_ok_and(|read| read == header.content_size as usize);
+                let last_block = header.last_block;
+                batch.push(BatchBlock {
+                    header,
+                    header_size,
+                    content,
+                    complete,
+                    tables: None,
+                    decoded: None,
+                });
+                if last_block || !complete || batch.len() >= batch_limit {
+                    break;
+                }
+            }
+
+            // Build the tables serially, they can be repeated by the following blocks. Each block
+            // gets a copy of the tables it is decoded with, so it can be decoded independently.
+            let scratch = &mut state.decoder_scratch;
+            for block in &mut batch {
+                if block.header.block_type != BlockType::Compressed || !block.complete {
+                    continue;
                 }
+                let before = BlockScratch::copy_of(&scratch.huf, &scratch.fse);
+                match block_decoder::update_tables(
+                    &block.content,
+                    &mut scratch.huf,
+                    &mut scratch.fse,
+                ) {
+                    Ok(sizes) => {
+                        block.tables = Some(BatchTables {
+                            before,
+                            after: BlockScratch::copy_of(&scratch.huf, &scratch.fse),
+                            sizes,
+                        });
+                    }
+                    Err(_) => {
+                        // This block and all after it are decoded serially, starting from the tables before it
+                        scratch.huf.table.reinit_from(&before.huf.table);
+                        scratch.fse.reinit_from(&before.fse);
+                        break;
+                    }
+                }
+            }
+
+            batch.par_iter_mut().for_each(|block| {
+                if let Some(tables) = &mut block.tables {
+                    let mut literals = Vec::new();
+                    let mut sequences = Vec::new();
+                    let result = block_decoder::decode_literals_and_sequences_with_tables(
+                        &block.content,
+                        max_block_size,
+                        tables.sizes,
+                        &mut tables.after.huf,
+                        &mut tables.after.fse,
+                        &mut literals,
+                        &mut sequences,
+                    );
+                    block.decoded = Some(result.map(|()| (literals, sequences)));
+                }
+            });
+
+            // Execute the blocks in order. From the first compressed block that could not be decoded ahead
+            // of time on, blocks are decoded like in decode_blocks, so errors are the same as there.
+            let mut serial = false;
+            for block in batch {
+                state.bytes_read_counter += u64::from(block.header_size);
+                let scratch = &mut state.decoder_scratch;
+                let buffer_len_before_block = scratch.buffer.len();
+                let content_size = block.header.content_size;
+                let result = match block.decoded {
+                    Some(Ok((literals, sequences))) if !serial => {
+                        scratch.literals_buffer = literals;
+                        scratch.sequences = sequences;
+                        block_decoder::execute_block(scratch)
+                            .map(|()| u64::from(content_size))
+                            .map_err(DecodeBlockContentError::from)
+                    }
+                    _ => {
+                        if block.header.block_type == BlockType::Compressed && !serial {
+                            serial = true;
+                            if let Some(tables) = &block.tables {
+                                scratch.huf.table.reinit_from(&tables.before.huf.table);
+                                scratch.fse.reinit_from(&tables.before.fse);
+                            }
+                        }
+                        // The rest of an incomplete block is read from the source again, to get the same error
+                        block_dec.decode_block_body(
+                            &block.header,
+                            scratch,
+                            block.content.as_slice().chain(&mut source),
+                        )
+                    }
+                };
+                state.finish_block(
+                    &block.header,
+                    result,
+                    buffer_len_before_block,
+                    lenient,
+                    &mut source,
+                )?;
+            }
+            if let Some(e) = header_error {
+                return Err(e);
+            }
+            if state.frame_finished {
                 break;
             }
 
@@ -371,6 +1122,99 @@ impl FrameDecoder {
         }
     }
 
+    /// Decode one frame from the source and write all of its content into the sink.
+    ///
+    /// This calls [`FrameDecoder::reset`], and all bytes currently in the decoder will be lost.
+    /// While decoding, only window_size bytes are retained in the decoder, everything else is
+    /// written to the sink as soon as possible.
+    ///
+    /// Returns the number of bytes written to the sink.
+    pub fn decode_to_writer(
+        &mut self,
+        mut source: impl Read,
+        sink: impl Write,
+    ) -> Result<u64, FrameDecoderError> {
+        self.reset(&mut source)?;
+        self.decode_remaining_to_writer(source, sink)
+    }
+
+    /// Decode the rest of the current frame and write it into the sink, see [FrameDecoder::decode_to_writer]
+    pub(crate) fn decode_remaining_to_writer(
+        &mut self,
+        mut source: impl Read,
+        mut sink: impl Write,
+    ) -> Result<u64, FrameDecoderError> {
+        use FrameDecoderError as err;
+        let mut total_bytes_written = 0u64;
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBytes(1024 * 1024))?;
+            let bytes_written = self
+                .collect_to_writer(&mut sink)
+                .map_err(err::FailedToDrainDecodebuffer)?;
+            total_bytes_written += bytes_written as u64;
+        }
+        while self.can_collect() > 0 {
+            let bytes_written = self
+                .collect_to_writer(&mut sink)
+                .map_err(err::FailedToDrainDecodebuffer)?;
+            if bytes_written == 0 {
+                // The sink does not accept any more bytes, looping would never terminate
+                return Err(err::FailedToDrainDecodebuffer(Error::from(
+                    crate::io::ErrorKind::Other,
+                )));
+            }
+            total_bytes_written += bytes_written as u64;
+        }
+
+        Ok(total_bytes_written)
+    }
+
+    /// Decode only the first `n` bytes of the frame from the source.
+    ///
+    /// Only as many blocks as are needed to produce `n` bytes are decoded. The returned vec contains exactly `n` bytes
+    /// or fewer if the frame contains less than `n` bytes.
+    ///
+    /// This calls [`FrameDecoder::reset`], and all bytes currently in the decoder will be lost.
+    /// After this returns the rest of the frame can not be decoded anymore, the decoder needs to be reset before decoding the next frame.
+    pub fn decode_prefix(
+        &mut self,
+        mut source: impl Read,
+        n: usize,
+    ) -> Result<Vec<u8>, FrameDecoderError> {
+        use FrameDecoderError as err;
+        self.reset(&mut source)?;
+
+        let mut result = Vec::new();
+        loop {
+            let decoded = match &self.state {
+                Some(s) => result.len() + s.decoder_scratch.buffer.len(),
+                None => return Err(err::NotYetInitialized),
+            };
+            if decoded >= n || self.is_finished() {
+                break;
+            }
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            self.collect_to_writer(&mut result)
+                .map_err(err::FailedToDrainDecodebuffer)?;
+        }
+
+        if result.len() < n {
+            let state = self.state.as_mut().ok_or(err::NotYetInitialized)?;
+            let start = result.len();
+            let missing = core::cmp::min(n - start, state.decoder_scratch.buffer.len());
+            result.resize(start + missing, 0);
+            let read = state
+                .decoder_scratch
+                .buffer
+                .read_all(&mut result[start..])
+                .map_err(err::FailedToDrainDecodebuffer)?;
+            result.truncate(start + read);
+        }
+        result.truncate(n);
+
+        Ok(result)
+    }
+
     /// How many bytes can currently be collected from the decodebuffer, while decoding is going on this will be lower than the actual decodbuffer size
     /// because window_size bytes need to be retained for decoding.
     /// After decoding of the frame (is_finished() == true) has finished it will report all remaining bytes
@@ -391,6 +1235,18 @@ impl FrameDecoder {
         }
     }
 
+    /// How many bytes the decodebuffer currently holds, including the window that is retained for decoding.
+    ///
+    /// This is the part of the decoder's memory that grows with the decoded data, while [FrameDecoder::can_collect]
+    /// only reports the bytes that can be collected right now. As long as the decoded bytes are collected regularly,
+    /// this stays close to the window size of the frame, which can be limited with [FrameDecoder::set_window_budget].
+    pub fn buffered_bytes(&self) -> usize {
+        match &self.state {
+            None => 0,
+            Some(s) => s.decoder_scratch.buffer.len(),
+        }
+    }
+
     /// Decodes as many blocks as possible from the source slice and reads from the decodebuffer into the target slice
     /// The source slice may contain only parts of a frame but must contain at least one full block to make progress
     ///
@@ -398,12 +1254,17 @@ impl FrameDecoder {
     /// which try to serve an old-style c api
     ///
     /// Returns (read, written), if read == 0 then the source did not contain a full block and further calls with the same
-    /// input will not make any progress!
+    /// input will not make any progress! Use [FrameDecoder::last_decode_consumed_checksum] to find out whether `read`
+    /// includes the checksum of the frame.
     ///
     /// Note that no kind of block can be bigger than 128kb.
     /// So to be safe use at least 128*1024 (max block content size) + 3 (block_header size) + 18 (max frame_header size) bytes as your source buffer
     ///
     /// You may call this function with an empty source after all bytes have been decoded. This is equivalent to just call decoder.read(&mut target)
+    ///
+    /// An empty source signals the end of the input: If the frame is not finished and no more bytes can be written to the target,
+    /// [FrameDecoderError::UnexpectedEof] is returned instead of `(0, 0)`, so a loop that feeds the remaining input
+    /// terminates on a truncated frame. So only pass an empty source if no more input will follow.
     pub fn decode_from_to(
         &mut self,
         source: &[u8],
@@ -424,11 +1285,13 @@ impl FrameDecoder {
 
             //pseudo block to scope "state" so we can borrow self again after the block
             {
+                let lenient = self.lenient;
                 let state = match &mut self.state {
                     Some(s) => s,
                     None => panic!("Bug in library"),
                 };
                 let mut block_dec = decoding::block_decoder::new();
+                state.last_decode_consumed_checksum = false;
 
                 if state.frame.header.descriptor.content_checksum_flag()
                     && state.frame_finished
@@ -440,8 +1303,11 @@ impl FrameDecoder {
                         state.bytes_read_counter += 4;
                         let chksum = u32::from_le_bytes(chksum);
                         state.check_sum = Some(chksum);
+                        state.last_decode_consumed_checksum = true;
+                        return Ok((4, 0));
                     }
-                    return Ok((4, 0));
+                    // The decoded content can still be collected while waiting for the checksum
+                    mt_source = &[];
                 }
 
                 loop {
@@ -460,14 +1326,27 @@ impl FrameDecoder {
                     }
                     state.bytes_read_counter += u64::from(block_header_size);
 
-                    let bytes_read_in_block_body = block_dec
-                        .decode_block_content(
+                    let buffer_len_before_block = state.decoder_scratch.buffer.len();
+                    let bytes_read_in_block_body = match block_dec.decode_block_content(
+                        &block_header,
+                        &mut state.decoder_scratch,
+                        &mut mt_source,
+                    ) {
+                        Ok(bytes_read) => bytes_read,
+                        Err(e) => state.skip_corrupt_block(
                             &block_header,
-                            &mut state.decoder_scratch,
-                            &mut mt_source,
-                        )
-                        .map_err(err::FailedToReadBlockBody)?;
+                            e,
+                            buffer_len_before_block,
+                            lenient,
+                        )?,
+                    };
                     state.bytes_read_counter += bytes_read_in_block_body;
+                    state.update_block_stats(
+                        &block_header,
+                        bytes_read_in_block_body,
+                        buffer_len_before_block,
+                    );
+                    state.check_content_size()?;
                     state.block_counter += 1;
 
                     if block_header.last_block {
@@ -479,6 +1358,7 @@ impl FrameDecoder {
                                 state.bytes_read_counter += 4;
                                 let chksum = u32::from_le_bytes(chksum);
                                 state.check_sum = Some(chksum);
+                                state.last_decode_consumed_checksum = true;
                             }
                         }
                         break;
@@ -488,6 +1368,9 @@ impl FrameDecoder {
         }
 
         let result_len = self.read(target).map_err(err::FailedToDrainDecodebuffer)?;
+        if source.is_empty() && result_len == 0 && self.can_collect() == 0 && !self.is_finished() {
+            return Err(err::UnexpectedEof);
+        }
         let bytes_read_at_end = match &mut self.state {
             Some(s) => s.bytes_read_counter,
             None => panic!("Bug in library"),
@@ -496,9 +1379,21 @@ impl FrameDecoder {
         Ok((read_len as usize, result_len))
     }
 
+    /// Whether the last call to [FrameDecoder::decode_from_to] consumed the 4 byte checksum at the end of the frame.
+    ///
+    /// The checksum is counted in the number of bytes read that was returned by that call.
+    pub fn last_decode_consumed_checksum(&self) -> bool {
+        match &self.state {
+            Some(s) => s.last_decode_consumed_checksum,
+            None => false,
+        }
+    }
+
     /// Decode multiple frames into the output slice.
     ///
-    /// `input` must contain an exact number of frames.
+    /// `input` must contain an exact number of frames. They are decoded one after another until `input`
+    /// is exhausted, so concatenated frames are decoded into one continuous output. Skippable frames are
+    /// skipped, see [`FrameDecoder::set_skippable_handler`] to get their content.
     ///
     /// `output` must be large enough to hold the decompressed data. If you don't know
     /// how large the output will be, use [`FrameDecoder::decode_blocks`] instead.
@@ -512,19 +1407,46 @@ impl FrameDecoder {
         mut output: &mut [u8],
     ) -> Result<usize, FrameDecoderError> {
         let mut total_bytes_written = 0;
+        let mut num_frames = 0;
         while !input.is_empty() {
+            self.check_frame_limit(num_frames)?;
+            num_frames += 1;
             match self.init(&mut input) {
                 Ok(_) => {}
                 Err(FrameDecoderError::ReadFrameHeaderError(
-                    crate::decoding::errors::ReadFrameHeaderError::SkipFrame { length, .. },
+                    crate::decoding::errors::ReadFrameHeaderError::SkipFrame {
+                        magic_number,
+                        length,
+                    },
                 )) => {
-                    input = input
-                        .get(length as usize..)
+                    let payload = input
+                        .get(..length as usize)
                         .ok_or(FrameDecoderError::FailedToSkipFrame)?;
+                    if let Some(handler) = &mut self.skippable_handler {
+                        handler(magic_number, payload);
+                    }
+                    input = &input[length as usize..];
                     continue;
                 }
                 Err(e) => return Err(e),
             };
+            if let Some(content_size) = self.content_fits_in_window() {
+                // The whole frame stays in the decode buffer anyway, so decode it in one go
+                // and hand it out at the end instead of draining after every megabyte
+                if content_size > output.len() as u64 {
+                    return Err(FrameDecoderError::TargetTooSmall);
+                }
+                self.decode_blocks(&mut input, BlockDecodingStrategy::All)?;
+                let bytes_written = self
+                    .read(output)
+                    .map_err(FrameDecoderError::FailedToDrainDecodebuffer)?;
+                output = &mut output[bytes_written..];
+                total_bytes_written += bytes_written;
+                if self.can_collect() != 0 {
+                    return Err(FrameDecoderError::TargetTooSmall);
+                }
+                continue;
+            }
             loop {
                 self.decode_blocks(&mut input, BlockDecodingStrategy::UptoBytes(1024 * 1024))?;
                 let bytes_written = self
@@ -544,18 +1466,105 @@ impl FrameDecoder {
         Ok(total_bytes_written)
     }
 
+    /// Check the limit set with [FrameDecoder::set_max_frames] before starting another frame after `num_frames` frames
+    pub(crate) fn check_frame_limit(&self, num_frames: usize) -> Result<(), FrameDecoderError> {
+        match self.max_frames {
+            Some(max) if num_frames >= max => Err(FrameDecoderError::TooManyFrames { max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Consume the `length` bytes of payload of a skippable frame from `source`, after its header has been read by [FrameDecoder::init].
+    ///
+    /// The payload is only collected if a handler was set with [FrameDecoder::set_skippable_handler].
+    /// It has to fit into the maximum window size and is reserved through the alloc hook before it is read.
+    pub(crate) fn skip_frame(
+        &mut self,
+        mut source: impl Read,
+        magic_number: u32,
+        length: u32,
+    ) -> Result<(), FrameDecoderError> {
+        let mut payload = Vec::new();
+        if self.skippable_handler.is_some() {
+            if u64::from(length) > self.max_window_size {
+                return Err(FrameDecoderError::SkippableFrameTooBig {
+                    length,
+                    max: self.max_window_size,
+                });
+            }
+            let hook = match &mut self.state {
+                Some(state) => &mut state.decoder_scratch.alloc_hook,
+                None => &mut self.alloc_hook,
+            };
+            reserve_vec(
+                hook,
+                AllocKind::SkippablePayload,
+                &mut payload,
+                length as usize,
+            )?;
+        }
+
+        const CHUNK_SIZE: usize = 1024;
+        const _: () = assert!(CHUNK_SIZE <= MAX_STACK_BUFFER_SIZE);
+        let mut buf = [0u8; CHUNK_SIZE];
+        let mut remaining = length as usize;
+        while remaining > 0 {
+            let chunk = remaining.min(buf.len());
+            let read = source
+                .read(&mut buf[..chunk])
+                .map_err(|_| FrameDecoderError::FailedToSkipFrame)?;
+            if read == 0 {
+                return Err(FrameDecoderError::FailedToSkipFrame);
+            }
+            if self.skippable_handler.is_some() {
+                payload.extend_from_slice(&buf[..read]);
+            }
+            remaining -= read;
+        }
+        if let Some(handler) = &mut self.skippable_handler {
+            handler(magic_number, &payload);
+        }
+        Ok(())
+    }
+
+    /// Returns the declared content size of the current frame if all of its content fits
+    /// into the window, so nothing can be drained before the frame is finished.
+    fn content_fits_in_window(&self) -> Option<u64> {
+        let state = self.state.as_ref()?;
+        let content_size = state.declared_content_size()?;
+        if content_size <= state.decoder_scratch.buffer.window_size as u64 {
+            Some(content_size)
+        } else {
+            None
+        }
+    }
+
     /// Decode multiple frames into the extra capacity of the output vector.
     ///
-    /// `input` must contain an exact number of frames.
+    /// `input` must contain an exact number of frames. They are decoded one after another until `input`
+    /// is exhausted, so concatenated frames are decoded into one continuous output. Skippable frames are
+    /// skipped, see [`FrameDecoder::set_skippable_handler`] to get their content.
     ///
     /// `output` must have enough extra capacity to hold the decompressed data.
     /// This function will not reallocate or grow the vector. If you don't know
-    /// how large the output will be, use [`FrameDecoder::decode_blocks`] instead.
+    /// how large the output will be, use [`crate::decoding::read_to_vec`] for a single frame
+    /// or [`FrameDecoder::decode_blocks`] instead.
     ///
     /// This calls [`FrameDecoder::init`], and all bytes currently in the decoder will be lost.
     ///
     /// The length of the output vector is updated to include the decompressed data.
     /// The length is not changed if an error occurs.
+    ///
+    /// ```
+    /// use ruzstd::decoding::FrameDecoder;
+    /// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    /// let mut input = compress_to_vec(&b"Hello "[..], CompressionLevel::Fastest);
+    /// input.extend(compress_to_vec(&b"World"[..], CompressionLevel::Fastest));
+    ///
+    /// let mut output = Vec::with_capacity(11);
+    /// FrameDecoder::new().decode_all_to_vec(&input, &mut output).unwrap();
+    /// assert_eq!(output, b"Hello World");
+    /// ```
     pub fn decode_all_to_vec(
         &mut self,
         input: &[u8],
@@ -0,0 +1,104 @@
+//! Cheap inspection of frame headers without setting up a decoder
+
+use crate::decoding::errors::FrameDecoderError;
+use crate::decoding::frame;
+use crate::io::Read;
+
+/// The information from a frame header that is needed to decide how to handle the frame.
+///
+/// Reading it with [FrameInfo::read] only parses the frame header. Unlike [crate::decoding::FrameDecoder::reset]
+/// it does not allocate anything, so it is suited to classify lots of frames, e.g. by their dictionary id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FrameInfo {
+    /// The ID of the dictionary required to decode this frame, if the header contains one
+    pub dictionary_id: Option<u32>,
+    /// The size of the window the decoder needs, in bytes. Equals the content size for single segment frames
+    pub window_size: u64,
+    /// The size of the decoded frame content, if the header declares it
+    pub content_size: Option<u64>,
+    /// Whether a checksum of the content follows the last block
+    pub content_checksum: bool,
+    /// The number of bytes of the frame header, including the magic number
+    pub header_size: u8,
+}
+
+impl FrameInfo {
+    /// Read the header of a frame from the source and return the information it contains.
+    ///
+    /// Exactly the bytes of the frame header are read from the source, so it is positioned at the first block afterwards.
+    /// Skippable frames are reported as [crate::decoding::errors::ReadFrameHeaderError::SkipFrame].
+    ///
+    /// ```
+    /// use ruzstd::decoding::FrameInfo;
+    /// let compressed = ruzstd::encoding::compress_to_vec(
+    ///     &[1u8, 2, 3][..],
+    ///     ruzstd::encoding::CompressionLevel::Fastest,
+    /// );
+    /// let info = FrameInfo::read(compressed.as_slice()).unwrap();
+    /// assert_eq!(info.dictionary_id, None);
+    /// assert!(!info.content_checksum);
+    /// ```
+    pub fn read(source: impl Read) -> Result<Self, FrameDecoderError> {
+        let (frame, header_size) = frame::read_frame_header(source)?;
+        let header = &frame.header;
+        let content_size_present = header.descriptor.frame_content_size_flag() != 0
+            || header.descriptor.single_segment_flag();
+        Ok(Self {
+            dictionary_id: header.dictionary_id(),
+            window_size: header.window_size()?,
+            content_size: content_size_present.then(|| header.frame_content_size()),
+            content_checksum: header.descriptor.content_checksum_flag(),
+            header_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameInfo;
+    use crate::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+    use crate::encoding::{CompressionLevel, FrameCompressor};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn read_frame_info() {
+        let data = [7u8; 3000];
+        for single_segment in [false, true] {
+            let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+            compressor.set_single_segment_if_single_block(single_segment);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let compressed = compressor.take_drain().unwrap();
+
+            let mut source = compressed.as_slice();
+            let info = FrameInfo::read(&mut source).unwrap();
+            assert_eq!(info.dictionary_id, None);
+            assert!(!info.content_checksum);
+            assert_eq!(
+                usize::from(info.header_size),
+                compressed.len() - source.len()
+            );
+            if single_segment {
+                assert_eq!(info.content_size, Some(3000));
+                assert_eq!(info.window_size, 3000);
+            } else {
+                assert_eq!(info.content_size, None);
+                assert!(info.window_size >= 3000);
+            }
+        }
+
+        let dict_frame = include_bytes!("../../dict_tests/files/ModemManager.service.zst");
+        let info = FrameInfo::read(dict_frame.as_slice()).unwrap();
+        assert!(info.dictionary_id.is_some());
+
+        let skippable = [0x50, 0x2A, 0x4D, 0x18, 2, 0, 0, 0, 1, 2];
+        assert!(matches!(
+            FrameInfo::read(skippable.as_slice()),
+            Err(FrameDecoderError::ReadFrameHeaderError(
+                ReadFrameHeaderError::SkipFrame { length: 2, .. }
+            ))
+        ));
+    }
+}
@@ -4,17 +4,27 @@
 use super::super::blocks::literals_section::{LiteralsSection, LiteralsSectionType};
 use super::bit_reader_reverse::BitReaderReversed;
 use super::scratch::HuffmanScratch;
-use crate::decoding::errors::DecompressLiteralsError;
+use crate::decoding::errors::{DecompressLiteralsError, FSETableError, HuffmanTableError};
 use crate::huff0::HuffmanDecoder;
 use alloc::vec::Vec;
 
 /// Decode and decompress the provided literals section into `target`, returning the number of bytes read.
+///
+/// A block can't contain more than `max_block_size` literals. Sections that declare more are rejected before
+/// anything is reserved for them.
 pub fn decode_literals(
     section: &LiteralsSection,
     scratch: &mut HuffmanScratch,
     source: &[u8],
     target: &mut Vec<u8>,
+    max_block_size: u32,
 ) -> Result<u32, DecompressLiteralsError> {
+    if section.regenerated_size > max_block_size {
+        return Err(DecompressLiteralsError::RegeneratedSizeTooLarge {
+            got: section.regenerated_size,
+            max: max_block_size,
+        });
+    }
     match section.ls_type {
         LiteralsSectionType::Raw => {
             target.extend(&source[0..section.regenerated_size as usize]);
@@ -47,6 +57,9 @@ fn decompress_literals(
 
     let compressed_size = section.compressed_size.ok_or(err::MissingCompressedSize)? as usize;
     let num_streams = section.num_streams.ok_or(err::MissingNumStreams)?;
+    if num_streams != 1 && num_streams != 4 {
+        return Err(err::InvalidNumStreams { got: num_streams });
+    }
 
     target.reserve(section.regenerated_size as usize);
     let source = &source[0..compressed_size];
@@ -55,13 +68,23 @@ fn decompress_literals(
     match section.ls_type {
         LiteralsSectionType::Compressed => {
             //read Huffman tree description
-            bytes_read += scratch.table.build_decoder(source)?;
+            bytes_read += scratch.table.build_decoder(source).map_err(|e| match e {
+                HuffmanTableError::GetBitsError(source) => err::GetBitsError {
+                    context: "huffman weights",
+                    source,
+                },
+                HuffmanTableError::FSETableError(FSETableError::GetBitsError(source)) => {
+                    err::GetBitsError {
+                        context: "FSE table of the huffman weights",
+                        source,
+                    }
+                }
+                e => err::HuffmanTableError(e),
+            })?;
             vprintln!("Built huffman table using {} bytes", bytes_read);
         }
-        LiteralsSectionType::Treeless => {
-            if scratch.table.max_num_bits == 0 {
-                return Err(err::UninitializedHuffmanTable);
-            }
+        LiteralsSectionType::Treeless if scratch.table.max_num_bits == 0 => {
+            return Err(err::UninitializedHuffmanTable);
         }
         _ => { /* nothing to do, huffman tree has been provided by previous block */ }
     }
@@ -125,7 +148,6 @@ fn decompress_literals(
         bytes_read += source.len() as u32;
     } else {
         //just decode the one stream
-        assert!(num_streams == 1);
         let mut decoder = HuffmanDecoder::new(&scratch.table);
         let mut br = BitReaderReversed::new(source);
         let mut skipped_bits = 0;
@@ -1,12 +1,76 @@
 //! Structures and utilities used for decoding zstd formatted data
+//!
+//! ## Stack usage
+//! Decoding puts at most 1KB of buffers on the stack, blocks are read into buffers on the heap that are reused
+//! for all blocks. This keeps decoding usable on targets with small stacks. Note that `read_to_end` of the
+//! `no_std` [crate::io] module uses a 16KB buffer on the stack.
 
+mod alloc_hook;
+mod block_extraction;
 pub mod errors;
 mod frame_decoder;
+mod frame_info;
 mod streaming_decoder;
 
-pub use frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+pub use crate::blocks::block::{BlockHeader, BlockType};
+pub use crate::blocks::sequence_section::Sequence;
+pub use alloc_hook::{AllocHook, AllocKind};
+pub use block_extraction::{
+    decode_block_sequences, extract_block, validate_structure, BlockContentDecoder, BlockScratch,
+};
+pub use dictionary::Dictionary;
+pub use frame_decoder::{BlockDecodingStrategy, BlockStats, CorruptBlock, FrameDecoder};
+pub use frame_info::FrameInfo;
 pub use streaming_decoder::StreamingDecoder;
 
+use crate::io::Read;
+use alloc::vec::Vec;
+use errors::FrameDecoderError;
+
+/// The content size declared in the frame header can't be trusted, so at most this much is reserved up front
+const MAX_PREALLOCATION: u64 = 1024 * 1024;
+
+/// Convenience function to decode a frame from the source into a Vec without reusing any resources of the decoder
+///
+/// The output is preallocated based on the content size in the frame header, if the header contains one.
+/// Only one frame is decoded, anything after it is left in the source.
+/// ```rust
+/// use ruzstd::decoding::read_to_vec;
+/// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+/// let data: &[u8] = &[0,0,0,0,0,0,0,0,0,0,0,0];
+/// let compressed = compress_to_vec(data, CompressionLevel::Fastest);
+/// let decompressed = read_to_vec(compressed.as_slice()).unwrap();
+/// assert_eq!(decompressed, data);
+/// ```
+pub fn read_to_vec<R: Read>(mut source: R) -> Result<Vec<u8>, FrameDecoderError> {
+    let mut decoder = FrameDecoder::new();
+    decoder.reset(&mut source)?;
+    let capacity = decoder.content_size().min(MAX_PREALLOCATION) as usize;
+    let mut output = Vec::with_capacity(capacity);
+    decoder.decode_remaining_to_writer(source, &mut output)?;
+    Ok(output)
+}
+
+/// Decode the frame at the start of `source` into a Vec and return it together with the length of the frame.
+///
+/// The length includes the checksum at the end of the frame, so `source[length..]` is the data that follows the frame.
+/// This is the slice based counterpart to [read_to_vec], useful for formats that embed frames in a larger buffer.
+/// ```rust
+/// use ruzstd::decoding::decode_frame_from_slice;
+/// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+/// let data: &[u8] = &[1, 2, 3, 4, 5, 6, 7, 8];
+/// let mut container = compress_to_vec(data, CompressionLevel::Fastest);
+/// container.extend_from_slice(b"more data");
+/// let (decompressed, frame_len) = decode_frame_from_slice(&container).unwrap();
+/// assert_eq!(decompressed, data);
+/// assert_eq!(&container[frame_len..], b"more data");
+/// ```
+pub fn decode_frame_from_slice(source: &[u8]) -> Result<(Vec<u8>, usize), FrameDecoderError> {
+    let mut remaining = source;
+    let output = read_to_vec(&mut remaining)?;
+    Ok((output, source.len() - remaining.len()))
+}
+
 pub(crate) mod bit_reader;
 pub(crate) mod bit_reader_reverse;
 pub(crate) mod block_decoder;
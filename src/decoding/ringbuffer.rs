@@ -67,17 +67,32 @@ impl RingBuffer {
         self.reserve_amortized(amount - free);
     }
 
+    /// The number of bytes the buffer grows by if [RingBuffer::reserve] is called with `amount`.
+    pub fn growth_for(&self, amount: usize) -> usize {
+        let free = self.free();
+        if free >= amount {
+            0
+        } else {
+            self.amortized_capacity(amount - free) - self.cap
+        }
+    }
+
+    /// The capacity after growing the buffer by at least `amount` elements
+    fn amortized_capacity(&self, amount: usize) -> usize {
+        // Always have at least 1 unused element as the sentinel.
+        usize::max(
+            self.cap.next_power_of_two(),
+            (self.cap + amount).next_power_of_two(),
+        ) + 1
+    }
+
     #[inline(never)]
     #[cold]
     fn reserve_amortized(&mut self, amount: usize) {
         // SAFETY: if we were succesfully able to construct this layout when we allocated then it's also valid do so now
         let current_layout = unsafe { Layout::array::<u8>(self.cap).unwrap_unchecked() };
 
-        // Always have at least 1 unused element as the sentinel.
-        let new_cap = usize::max(
-            self.cap.next_power_of_two(),
-            (self.cap + amount).next_power_of_two(),
-        ) + 1;
+        let new_cap = self.amortized_capacity(amount);
 
         // Check that the capacity isn't bigger than isize::MAX, which is the max allowed by LLVM, or that
         // we are on a >= 64 bit system which will never allow that much memory to be allocated
@@ -154,7 +169,7 @@ impl RingBuffer {
 
         self.reserve(len);
 
-        debug_assert!(self.len() + len <= self.cap - 1);
+        debug_assert!(self.len() + len < self.cap);
         debug_assert!(self.free() >= len, "free: {} len: {}", self.free(), len);
 
         let ((f1_ptr, f1_len), (f2_ptr, f2_len)) = self.free_slice_parts();
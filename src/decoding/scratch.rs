@@ -2,9 +2,13 @@
 
 use super::super::blocks::sequence_section::Sequence;
 use super::decodebuffer::DecodeBuffer;
+use crate::decoding::alloc_hook::{request, reserve_vec, AllocHook, AllocKind, AllocRejected};
+use crate::decoding::block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE;
 use crate::decoding::dictionary::Dictionary;
+use crate::decoding::sequence_section_decoder::{LL_MAX_LOG, ML_MAX_LOG, OF_MAX_LOG};
 use crate::fse::FSETable;
 use crate::huff0::HuffmanTable;
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 
 use crate::blocks::sequence_section::{
@@ -24,6 +28,9 @@ pub struct DecoderScratch {
     pub literals_buffer: Vec<u8>,
     pub sequences: Vec<Sequence>,
     pub block_content_buffer: Vec<u8>,
+
+    /// Asked before the buffers grow, see [crate::decoding::FrameDecoder::set_alloc_hook]
+    pub alloc_hook: Option<Box<dyn AllocHook>>,
 }
 
 impl DecoderScratch {
@@ -46,17 +53,67 @@ impl DecoderScratch {
             block_content_buffer: Vec::new(),
             literals_buffer: Vec::new(),
             sequences: Vec::new(),
+            alloc_hook: None,
         }
     }
 
-    pub fn reset(&mut self, window_size: usize) {
+    /// Reserve the decode buffer for a window of `window_size` bytes and one block, and the decoding tables and
+    /// block buffers at the biggest size such a frame can need, so decoding it does not have to grow them.
+    ///
+    /// If there is an alloc hook, it is asked before anything is reserved.
+    pub fn reserve_window(&mut self, window_size: usize) -> Result<(), AllocRejected> {
+        let max_block_size = window_size.min(ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize);
+        // The window is kept while the next block is decoded
+        let buffer_size = window_size.saturating_add(max_block_size);
+        self.request_window(buffer_size)?;
+        self.buffer.reserve(buffer_size);
+
+        self.request_tables()?;
+        self.huf.table.reserve_max();
+        self.fse.literal_lengths.reserve_max(LL_MAX_LOG);
+        self.fse.match_lengths.reserve_max(ML_MAX_LOG);
+        self.fse.offsets.reserve_max(OF_MAX_LOG);
+
+        // Each sequence has a match of at least 3 bytes
+        let max_sequences = max_block_size / 3;
+        reserve_vec(
+            &mut self.alloc_hook,
+            AllocKind::BlockContent,
+            &mut self.block_content_buffer,
+            max_block_size,
+        )?;
+        reserve_vec(
+            &mut self.alloc_hook,
+            AllocKind::Literals,
+            &mut self.literals_buffer,
+            max_block_size,
+        )?;
+        reserve_vec(
+            &mut self.alloc_hook,
+            AllocKind::Sequences,
+            &mut self.sequences,
+            max_sequences,
+        )?;
+        reserve_len(&mut self.block_content_buffer, max_block_size);
+        reserve_len(&mut self.literals_buffer, max_block_size);
+        reserve_len(&mut self.sequences, max_sequences);
+        Ok(())
+    }
+
+    /// Prepare for decoding a new frame. `initial_capacity` is the number of bytes reserved in the decode buffer,
+    /// it grows as needed if the frame decodes to more than that.
+    ///
+    /// All buffers keep their capacity, so nothing is allocated for frames that don't need bigger buffers than the previous ones.
+    pub fn reset(
+        &mut self,
+        window_size: usize,
+        initial_capacity: usize,
+    ) -> Result<(), AllocRejected> {
         self.offset_hist = [1, 4, 8];
         self.literals_buffer.clear();
         self.sequences.clear();
         self.block_content_buffer.clear();
 
-        self.buffer.reset(window_size);
-
         self.fse.literal_lengths.reset();
         self.fse.match_lengths.reset();
         self.fse.offsets.reset();
@@ -65,19 +122,56 @@ impl DecoderScratch {
         self.fse.of_rle = None;
 
         self.huf.table.reset();
+
+        self.buffer.reset(window_size, 0);
+        self.request_window(initial_capacity)?;
+        self.buffer.reserve(initial_capacity);
+        Ok(())
+    }
+
+    /// If there is an alloc hook, make room for `amount` more bytes in the decode buffer after asking the hook.
+    /// Reserving up front makes sure the buffer does not grow while the bytes are decoded.
+    pub fn request_window(&mut self, amount: usize) -> Result<(), AllocRejected> {
+        if let Some(hook) = &mut self.alloc_hook {
+            request(
+                hook.as_mut(),
+                AllocKind::Window,
+                self.buffer.growth_for(amount),
+            )?;
+            self.buffer.reserve(amount);
+        }
+        Ok(())
+    }
+
+    /// If there is an alloc hook, reserve the decoding tables at the biggest size the format allows after asking the hook
+    pub fn request_tables(&mut self) -> Result<(), AllocRejected> {
+        if let Some(hook) = &mut self.alloc_hook {
+            let bytes = self.huf.table.max_growth()
+                + self.fse.literal_lengths.max_growth(LL_MAX_LOG)
+                + self.fse.match_lengths.max_growth(ML_MAX_LOG)
+                + self.fse.offsets.max_growth(OF_MAX_LOG);
+            request(hook.as_mut(), AllocKind::Tables, bytes)?;
+            self.huf.table.reserve_max();
+            self.fse.literal_lengths.reserve_max(LL_MAX_LOG);
+            self.fse.match_lengths.reserve_max(ML_MAX_LOG);
+            self.fse.offsets.reserve_max(OF_MAX_LOG);
+        }
+        Ok(())
     }
 
     pub fn init_from_dict(&mut self, dict: &Dictionary) {
         self.fse.reinit_from(&dict.fse);
         self.huf.table.reinit_from(&dict.huf.table);
         self.offset_hist = dict.offset_hist;
-        self.buffer.dict_content.clear();
-        self.buffer
-            .dict_content
-            .extend_from_slice(&dict.dict_content);
+        self.buffer.use_dict(dict.id, &dict.dict_content);
     }
 }
 
+/// Make sure `vec` can hold `len` elements without growing
+fn reserve_len<T>(vec: &mut Vec<T>, len: usize) {
+    vec.reserve_exact(len.saturating_sub(vec.len()));
+}
+
 pub struct HuffmanScratch {
     pub table: HuffmanTable,
 }
@@ -56,7 +56,7 @@ pub fn execute_sequences(scratch: &mut DecoderScratch) -> Result<(), ExecuteSequ
 /// Update the most recently used offsets to reflect the provided offset value, and return the
 /// "actual" offset needed because offsets are not stored in a raw way, some transformations are needed
 /// before you get a functional number.
-fn do_offset_history(offset_value: u32, lit_len: u32, scratch: &mut [u32; 3]) -> u32 {
+pub(crate) fn do_offset_history(offset_value: u32, lit_len: u32, scratch: &mut [u32; 3]) -> u32 {
     let actual_offset = if lit_len > 0 {
         match offset_value {
             1..=3 => scratch[offset_value as usize - 1],
@@ -113,3 +113,45 @@ fn do_offset_history(offset_value: u32, lit_len: u32, scratch: &mut [u32; 3]) ->
 
     actual_offset
 }
+
+#[cfg(test)]
+mod tests {
+    use super::do_offset_history;
+
+    #[test]
+    fn offset_history() {
+        // (offset value, literals length, actual offset, history afterwards), starting from the initial history
+        let cases = [
+            (1, 5, 1, [1, 4, 8]),
+            (2, 5, 4, [4, 1, 8]),
+            (3, 5, 8, [8, 1, 4]),
+            (10, 5, 7, [7, 1, 4]),
+            // Without literals the repeat offsets are shifted by one
+            (1, 0, 4, [4, 1, 8]),
+            (2, 0, 8, [8, 1, 4]),
+            (10, 0, 7, [7, 1, 4]),
+        ];
+        for (offset_value, lit_len, actual, history) in cases {
+            let mut scratch = [1, 4, 8];
+            assert_eq!(
+                do_offset_history(offset_value, lit_len, &mut scratch),
+                actual
+            );
+            assert_eq!(scratch, history);
+        }
+
+        // The third repeat offset without literals is the most recent offset minus one
+        let mut scratch = [5, 4, 8];
+        assert_eq!(do_offset_history(3, 0, &mut scratch), 4);
+        assert_eq!(scratch, [4, 5, 4]);
+        let mut scratch = [1, 4, 8];
+        assert_eq!(do_offset_history(3, 0, &mut scratch), 0);
+
+        // The history carries over from one sequence to the next
+        let mut scratch = [1, 4, 8];
+        let offsets: [u32; 5] = [103, 203, 2, 1, 2]
+            .map(|offset_value| do_offset_history(offset_value, 1, &mut scratch));
+        assert_eq!(offsets, [100, 200, 100, 100, 200]);
+        assert_eq!(scratch, [200, 100, 1]);
+    }
+}
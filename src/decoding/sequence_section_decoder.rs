@@ -6,9 +6,10 @@ use super::scratch::FSEScratch;
 use crate::blocks::sequence_section::{
     MAX_LITERAL_LENGTH_CODE, MAX_MATCH_LENGTH_CODE, MAX_OFFSET_CODE,
 };
-use crate::decoding::errors::DecodeSequenceError;
+use crate::decoding::errors::{DecodeSequenceError, FSETableError};
 use crate::fse::FSEDecoder;
 use alloc::vec::Vec;
+use core::convert::TryFrom;
 
 /// Decode the provided source as a series of sequences into the supplied `target`.
 pub fn decode_sequences(
@@ -21,8 +22,18 @@ pub fn decode_sequences(
 
     vprintln!("Updating tables used {} bytes", bytes_read);
 
-    let bit_stream = &source[bytes_read..];
+    decode_sequences_with_tables(section, &source[bytes_read..], scratch, target)
+}
 
+/// Decode the bitstream of a sequences section with the tables that are already in `scratch`.
+///
+/// `bit_stream` is the rest of the section after the table descriptions.
+pub(crate) fn decode_sequences_with_tables(
+    section: &SequencesHeader,
+    bit_stream: &[u8],
+    scratch: &FSEScratch,
+    target: &mut Vec<Sequence>,
+) -> Result<(), DecodeSequenceError> {
     let mut br = BitReaderReversed::new(bit_stream);
 
     //skip the 0 padding at the end of the last byte of the bit stream and throw away the first 1 found
@@ -39,6 +50,15 @@ pub fn decode_sequences(
         return Err(DecodeSequenceError::ExtraPadding { skipped_bits });
     }
 
+    // Check num_sequences against what this section could possibly contain before reserving space for it
+    let max_sequences = max_plausible_sequences(scratch, br.bits_remaining());
+    if section.num_sequences as usize > max_sequences {
+        return Err(DecodeSequenceError::ImplausibleSequenceCount {
+            num_sequences: section.num_sequences,
+            max_sequences,
+        });
+    }
+
     if scratch.ll_rle.is_some() || scratch.ml_rle.is_some() || scratch.of_rle.is_some() {
         decode_sequences_with_rle(section, &mut br, scratch, target)
     } else {
@@ -46,6 +66,41 @@ pub fn decode_sequences(
     }
 }
 
+/// Upper bound for the number of sequences a section can encode.
+///
+/// Each sequence has a match of at least 3 bytes and a block decodes to at most 128kb.
+/// Additionally the initial states and each state update of the tables that are not RLE need
+/// at least a few bits from the bitstream.
+fn max_plausible_sequences(scratch: &FSEScratch, bits_remaining: isize) -> usize {
+    let max_by_block_size =
+        crate::decoding::block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize / 3;
+
+    let mut initial_bits = 0;
+    let mut min_bits_per_sequence = 0;
+    for (table, rle) in [
+        (&scratch.literal_lengths, scratch.ll_rle),
+        (&scratch.match_lengths, scratch.ml_rle),
+        (&scratch.offsets, scratch.of_rle),
+    ] {
+        if rle.is_none() {
+            initial_bits += usize::from(table.accuracy_log);
+            min_bits_per_sequence += table
+                .decode
+                .iter()
+                .map(|entry| usize::from(entry.num_bits))
+                .min()
+                .unwrap_or(0);
+        }
+    }
+    if min_bits_per_sequence == 0 {
+        return max_by_block_size;
+    }
+    // The last sequence does not update the states
+    let bits_remaining = usize::try_from(bits_remaining).unwrap_or(0);
+    let max_by_bits = bits_remaining.saturating_sub(initial_bits) / min_bits_per_sequence + 1;
+    usize::min(max_by_block_size, max_by_bits)
+}
+
 fn decode_sequences_with_rle(
     section: &SequencesHeader,
     br: &mut BitReaderReversed<'_>,
@@ -71,18 +126,18 @@ fn decode_sequences_with_rle(
 
     for _seq_idx in 0..section.num_sequences {
         //get the codes from either the RLE byte or from the decoder
-        let ll_code = if scratch.ll_rle.is_some() {
-            scratch.ll_rle.unwrap()
+        let ll_code = if let Some(ll_rle) = scratch.ll_rle {
+            ll_rle
         } else {
             ll_dec.decode_symbol()
         };
-        let ml_code = if scratch.ml_rle.is_some() {
-            scratch.ml_rle.unwrap()
+        let ml_code = if let Some(ml_rle) = scratch.ml_rle {
+            ml_rle
         } else {
             ml_dec.decode_symbol()
         };
-        let of_code = if scratch.of_rle.is_some() {
-            scratch.of_rle.unwrap()
+        let of_code = if let Some(of_rle) = scratch.of_rle {
+            of_rle
         } else {
             of_dec.decode_symbol()
         };
@@ -224,7 +279,7 @@ fn decode_sequences_without_rle(
 /// by the Zstandard reference document. Returns a tuple of (value, number of bits).
 ///
 /// <https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#appendix-a---decoding-tables-for-predefined-codes>
-fn lookup_ll_code(code: u8) -> (u32, u8) {
+pub(crate) fn lookup_ll_code(code: u8) -> (u32, u8) {
     match code {
         0..=15 => (u32::from(code), 0),
         16 => (16, 1),
@@ -255,7 +310,7 @@ fn lookup_ll_code(code: u8) -> (u32, u8) {
 /// by the Zstandard reference document. Returns a tuple of (value, number of bits).
 ///
 /// <https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#appendix-a---decoding-tables-for-predefined-codes>
-fn lookup_ml_code(code: u8) -> (u32, u8) {
+pub(crate) fn lookup_ml_code(code: u8) -> (u32, u8) {
     match code {
         0..=31 => (u32::from(code) + 3, 0),
         32 => (35, 1),
@@ -291,7 +346,17 @@ pub const ML_MAX_LOG: u8 = 9;
 /// "The maximum accuracy log for the offset table is 8."
 pub const OF_MAX_LOG: u8 = 8;
 
-fn maybe_update_fse_tables(
+/// Attach the name of the table that was being read to errors caused by a truncated table description
+fn table_error(context: &'static str) -> impl FnOnce(FSETableError) -> DecodeSequenceError {
+    move |err| match err {
+        FSETableError::GetBitsError(source) => {
+            DecodeSequenceError::GetBitsError { context, source }
+        }
+        err => DecodeSequenceError::FSETableError(err),
+    }
+}
+
+pub(crate) fn maybe_update_fse_tables(
     section: &SequencesHeader,
     source: &[u8],
     scratch: &mut FSEScratch,
@@ -304,7 +369,10 @@ fn maybe_update_fse_tables(
 
     match modes.ll_mode() {
         ModeType::FSECompressed => {
-            let bytes = scratch.literal_lengths.build_decoder(source, LL_MAX_LOG)?;
+            let bytes = scratch
+                .literal_lengths
+                .build_decoder(source, LL_MAX_LOG)
+                .map_err(table_error("literal lengths table"))?;
             bytes_read += bytes;
 
             vprintln!("Updating ll table");
@@ -326,7 +394,7 @@ fn maybe_update_fse_tables(
             vprintln!("Use predefined ll table");
             scratch.literal_lengths.build_from_probabilities(
                 LL_DEFAULT_ACC_LOG,
-                &Vec::from(&LITERALS_LENGTH_DEFAULT_DISTRIBUTION[..]),
+                &LITERALS_LENGTH_DEFAULT_DISTRIBUTION[..],
             )?;
             scratch.ll_rle = None;
         }
@@ -340,7 +408,10 @@ fn maybe_update_fse_tables(
 
     match modes.of_mode() {
         ModeType::FSECompressed => {
-            let bytes = scratch.offsets.build_decoder(of_source, OF_MAX_LOG)?;
+            let bytes = scratch
+                .offsets
+                .build_decoder(of_source, OF_MAX_LOG)
+                .map_err(table_error("offsets table"))?;
             vprintln!("Updating of table");
             vprintln!("Used bytes: {}", bytes);
             bytes_read += bytes;
@@ -359,10 +430,9 @@ fn maybe_update_fse_tables(
         }
         ModeType::Predefined => {
             vprintln!("Use predefined of table");
-            scratch.offsets.build_from_probabilities(
-                OF_DEFAULT_ACC_LOG,
-                &Vec::from(&OFFSET_DEFAULT_DISTRIBUTION[..]),
-            )?;
+            scratch
+                .offsets
+                .build_from_probabilities(OF_DEFAULT_ACC_LOG, &OFFSET_DEFAULT_DISTRIBUTION[..])?;
             scratch.of_rle = None;
         }
         ModeType::Repeat => {
@@ -375,7 +445,10 @@ fn maybe_update_fse_tables(
 
     match modes.ml_mode() {
         ModeType::FSECompressed => {
-            let bytes = scratch.match_lengths.build_decoder(ml_source, ML_MAX_LOG)?;
+            let bytes = scratch
+                .match_lengths
+                .build_decoder(ml_source, ML_MAX_LOG)
+                .map_err(table_error("match lengths table"))?;
             bytes_read += bytes;
             vprintln!("Updating ml table");
             vprintln!("Used bytes: {}", bytes);
@@ -396,7 +469,7 @@ fn maybe_update_fse_tables(
             vprintln!("Use predefined ml table");
             scratch.match_lengths.build_from_probabilities(
                 ML_DEFAULT_ACC_LOG,
-                &Vec::from(&MATCH_LENGTH_DEFAULT_DISTRIBUTION[..]),
+                &MATCH_LENGTH_DEFAULT_DISTRIBUTION[..],
             )?;
             scratch.ml_rle = None;
         }
@@ -410,34 +483,34 @@ fn maybe_update_fse_tables(
 }
 
 // The default Literal Length decoding table uses an accuracy logarithm of 6 bits.
-const LL_DEFAULT_ACC_LOG: u8 = 6;
+pub(crate) const LL_DEFAULT_ACC_LOG: u8 = 6;
 /// If [ModeType::Predefined] is selected for a symbol type, its FSE decoding
 /// table is generated using a predefined distribution table.
 ///
 /// https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#literals-length
-const LITERALS_LENGTH_DEFAULT_DISTRIBUTION: [i32; 36] = [
+pub(crate) const LITERALS_LENGTH_DEFAULT_DISTRIBUTION: [i32; 36] = [
     4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
     -1, -1, -1, -1,
 ];
 
 // The default Match Length decoding table uses an accuracy logarithm of 6 bits.
-const ML_DEFAULT_ACC_LOG: u8 = 6;
+pub(crate) const ML_DEFAULT_ACC_LOG: u8 = 6;
 /// If [ModeType::Predefined] is selected for a symbol type, its FSE decoding
 /// table is generated using a predefined distribution table.
 ///
 /// https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#match-length
-const MATCH_LENGTH_DEFAULT_DISTRIBUTION: [i32; 53] = [
+pub(crate) const MATCH_LENGTH_DEFAULT_DISTRIBUTION: [i32; 53] = [
     1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
     1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
 ];
 
 // The default Match Length decoding table uses an accuracy logarithm of 5 bits.
-const OF_DEFAULT_ACC_LOG: u8 = 5;
+pub(crate) const OF_DEFAULT_ACC_LOG: u8 = 5;
 /// If [ModeType::Predefined] is selected for a symbol type, its FSE decoding
 /// table is generated using a predefined distribution table.
 ///
 /// https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#match-length
-const OFFSET_DEFAULT_DISTRIBUTION: [i32; 29] = [
+pub(crate) const OFFSET_DEFAULT_DISTRIBUTION: [i32; 29] = [
     1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
 ];
 
@@ -447,7 +520,7 @@ fn test_ll_default() {
     table
         .build_from_probabilities(
             LL_DEFAULT_ACC_LOG,
-            &Vec::from(&LITERALS_LENGTH_DEFAULT_DISTRIBUTION[..]),
+            &LITERALS_LENGTH_DEFAULT_DISTRIBUTION[..],
         )
         .unwrap();
 
@@ -485,3 +558,57 @@ fn test_ll_default() {
     assert!(table.decode[59].num_bits == 5);
     assert!(table.decode[59].base_line == 32);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decode_sequences;
+    use crate::blocks::sequence_section::SequencesHeader;
+    use crate::decoding::errors::DecodeSequenceError;
+    use crate::decoding::scratch::FSEScratch;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn implausible_sequence_count() {
+        // 0x17EFF sequences, all tables predefined
+        let mut header = SequencesHeader::new();
+        let header_size = header.parse_from_header(&[0xFF, 0xFF, 0xFF, 0x00]).unwrap();
+        assert_eq!(header_size, 4);
+        assert_eq!(header.num_sequences, 0x17EFF);
+
+        let mut scratch = FSEScratch::new();
+        let mut target = Vec::new();
+        let err =
+            decode_sequences(&header, &[0x12, 0x34, 0x80], &mut scratch, &mut target).unwrap_err();
+        assert!(matches!(
+            err,
+            DecodeSequenceError::ImplausibleSequenceCount {
+                num_sequences: 0x17EFF,
+                ..
+            }
+        ));
+        assert_eq!(target.capacity(), 0);
+    }
+
+    #[test]
+    fn truncated_table_names_the_table() {
+        // one sequence, offsets table FSE compressed, other tables predefined
+        let mut header = SequencesHeader::new();
+        let header_size = header.parse_from_header(&[0x01, 0x20]).unwrap();
+        assert_eq!(header_size, 2);
+
+        let mut scratch = FSEScratch::new();
+        let mut target = Vec::new();
+        let err = decode_sequences(&header, &[], &mut scratch, &mut target).unwrap_err();
+        assert!(matches!(
+            err,
+            DecodeSequenceError::GetBitsError {
+                context: "offsets table",
+                ..
+            }
+        ));
+        assert_eq!(
+            alloc::format!("{}", err),
+            "Error while reading the offsets table: Can't read 4 bits, only have 0 bits left"
+        );
+    }
+}
@@ -2,9 +2,9 @@
 
 use core::borrow::BorrowMut;
 
-use crate::decoding::errors::FrameDecoderError;
+use crate::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
 use crate::decoding::{BlockDecodingStrategy, FrameDecoder};
-use crate::io::{Error, ErrorKind, Read};
+use crate::io::{Error, Read};
 
 /// High level Zstandard frame decoder that can be used to decompress a given Zstandard frame.
 ///
@@ -17,13 +17,11 @@ use crate::io::{Error, ErrorKind, Read};
 /// [FrameDecoder::decode_blocks] repeatedly to decode the entire frame.
 ///
 /// ## Caveat
-/// [StreamingDecoder] expects the underlying stream to only contain a single frame,
+/// [StreamingDecoder::new] expects the underlying stream to only contain a single frame,
 /// yet the specification states that a single archive may contain multiple frames.
 ///
-/// To decode all the frames in a finite stream, the calling code needs to recreate
-/// the instance of the decoder and handle
-/// [crate::decoding::errors::ReadFrameHeaderError::SkipFrame]
-/// errors by skipping forward the `length` amount of bytes, see <https://github.com/KillingSpark/zstd-rs/issues/57>
+/// To decode all the frames in a stream, use [StreamingDecoder::new_concatenated], which continues with the next frame
+/// once a frame is finished and skips skippable frames, see <https://github.com/KillingSpark/zstd-rs/issues/57>
 ///
 /// ```no_run
 /// // `read_to_end` is not implemented by the no_std implementation.
@@ -43,6 +41,12 @@ use crate::io::{Error, ErrorKind, Read};
 pub struct StreamingDecoder<READ: Read, DEC: BorrowMut<FrameDecoder>> {
     pub decoder: DEC,
     source: READ,
+    /// Whether the next frame is decoded once the current one is finished
+    concatenated: bool,
+    /// How many frames have been started so far, including skippable frames. Only counted if `concatenated` is set
+    frames: usize,
+    /// The source ended at a frame boundary, no more frames follow
+    exhausted: bool,
 }
 
 impl<READ: Read, DEC: BorrowMut<FrameDecoder>> StreamingDecoder<READ, DEC> {
@@ -51,7 +55,64 @@ impl<READ: Read, DEC: BorrowMut<FrameDecoder>> StreamingDecoder<READ, DEC> {
         mut decoder: DEC,
     ) -> Result<StreamingDecoder<READ, DEC>, FrameDecoderError> {
         decoder.borrow_mut().init(&mut source)?;
-        Ok(StreamingDecoder { decoder, source })
+        Ok(StreamingDecoder {
+            decoder,
+            source,
+            concatenated: false,
+            frames: 1,
+            exhausted: false,
+        })
+    }
+
+    /// Like [StreamingDecoder::new_concatenated], but with the given [FrameDecoder]
+    pub fn new_concatenated_with_decoder(
+        source: READ,
+        decoder: DEC,
+    ) -> Result<StreamingDecoder<READ, DEC>, FrameDecoderError> {
+        let mut decoder = StreamingDecoder {
+            decoder,
+            source,
+            concatenated: true,
+            frames: 0,
+            exhausted: false,
+        };
+        decoder.exhausted = !decoder.next_frame()?;
+        Ok(decoder)
+    }
+
+    /// Start decoding the next frame, skipping any skippable frames before it.
+    ///
+    /// Returns false if the source ended right at the frame boundary. A source that ends within
+    /// a frame header or contains anything but a frame there results in an error.
+    fn next_frame(&mut self) -> Result<bool, FrameDecoderError> {
+        let decoder = self.decoder.borrow_mut();
+        loop {
+            // Read the first byte on its own, so the end of the source can be told apart from a truncated frame header
+            let mut first_byte = [0u8; 1];
+            let read = self.source.read(&mut first_byte).map_err(|e| {
+                FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::MagicNumberReadError(
+                    e,
+                ))
+            })?;
+            if read == 0 {
+                return Ok(false);
+            }
+            decoder.check_frame_limit(self.frames)?;
+            self.frames += 1;
+
+            let header_source = Prefixed {
+                prefix: &first_byte,
+                source: &mut self.source,
+            };
+            match decoder.init(header_source) {
+                Ok(()) => return Ok(true),
+                Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame {
+                    magic_number,
+                    length,
+                })) => decoder.skip_frame(&mut self.source, magic_number, length)?,
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
 
@@ -61,7 +122,89 @@ impl<READ: Read> StreamingDecoder<READ, FrameDecoder> {
     ) -> Result<StreamingDecoder<READ, FrameDecoder>, FrameDecoderError> {
         let mut decoder = FrameDecoder::new();
         decoder.init(&mut source)?;
-        Ok(StreamingDecoder { decoder, source })
+        Ok(StreamingDecoder {
+            decoder,
+            source,
+            concatenated: false,
+            frames: 1,
+            exhausted: false,
+        })
+    }
+
+    /// Create a decoder that decodes all frames in `source` one after another, as one continuous output.
+    ///
+    /// Once a frame is finished, the header of the next frame is read from the same source.
+    /// Skippable frames are skipped, the handler set with [FrameDecoder::set_skippable_handler] is called with their content
+    /// and the limit set with [FrameDecoder::set_max_frames] applies.
+    /// Reading returns 0 only once the source ends right after a frame, so an empty source is an empty output.
+    /// If anything but a frame follows a frame, like trailing garbage or a truncated frame, reading returns an error
+    /// after the content of the preceding frames has been returned.
+    ///
+    /// ```
+    /// use ruzstd::decoding::StreamingDecoder;
+    /// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    /// use ruzstd::io::Read;
+    ///
+    /// let mut stream = compress_to_vec(&b"Hello "[..], CompressionLevel::Fastest);
+    /// stream.extend(compress_to_vec(&b"world!"[..], CompressionLevel::Fastest));
+    ///
+    /// let mut decoder = StreamingDecoder::new_concatenated(stream.as_slice()).unwrap();
+    /// let mut decoded = [0u8; 12];
+    /// decoder.read_exact(&mut decoded).unwrap();
+    /// assert_eq!(&decoded, b"Hello world!");
+    /// assert_eq!(decoder.read(&mut [0u8; 1]).unwrap(), 0);
+    /// ```
+    pub fn new_concatenated(
+        source: READ,
+    ) -> Result<StreamingDecoder<READ, FrameDecoder>, FrameDecoderError> {
+        Self::new_concatenated_with_decoder(source, FrameDecoder::new())
+    }
+}
+
+impl<'a> StreamingDecoder<&'a [u8], FrameDecoder> {
+    /// Create a decoder that decodes the frame at the start of `source`.
+    ///
+    /// This works the same with and without the `std` feature. Anything after the frame is not read,
+    /// use [StreamingDecoder::bytes_consumed] once the frame has been read to find out where the next frame starts.
+    ///
+    /// ```
+    /// use ruzstd::decoding::StreamingDecoder;
+    /// use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    /// use ruzstd::io::Read;
+    ///
+    /// let mut frames = compress_to_vec(&b"Hello "[..], CompressionLevel::Fastest);
+    /// frames.extend(compress_to_vec(&b"world!"[..], CompressionLevel::Fastest));
+    ///
+    /// let mut decoded = [0u8; 12];
+    /// let mut decoder = StreamingDecoder::from_slice(&frames).unwrap();
+    /// decoder.read_exact(&mut decoded[..6]).unwrap();
+    /// assert_eq!(decoder.read(&mut [0u8; 1]).unwrap(), 0);
+    /// let next_frame = decoder.bytes_consumed();
+    ///
+    /// let mut decoder = StreamingDecoder::from_slice(&frames[next_frame..]).unwrap();
+    /// decoder.read_exact(&mut decoded[6..]).unwrap();
+    /// assert_eq!(&decoded, b"Hello world!");
+    /// ```
+    pub fn from_slice(
+        source: &'a [u8],
+    ) -> Result<StreamingDecoder<&'a [u8], FrameDecoder>, FrameDecoderError> {
+        Self::new(source)
+    }
+}
+
+/// Yields the bytes in `prefix` before reading from `source`
+struct Prefixed<'a, READ: Read> {
+    prefix: &'a [u8],
+    source: &'a mut READ,
+}
+
+impl<READ: Read> Read for Prefixed<'_, READ> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.prefix.is_empty() {
+            self.source.read(buf)
+        } else {
+            self.prefix.read(buf)
+        }
     }
 }
 
@@ -78,7 +221,18 @@ impl<READ: Read, DEC: BorrowMut<FrameDecoder>> StreamingDecoder<READ, DEC> {
         &mut self.source
     }
 
+    /// How many bytes of the current frame have been read from the source so far, including the frame header.
+    ///
+    /// Once all data of the frame has been read from this decoder, this is the length of the whole frame including its checksum.
+    /// For decoders created with [StreamingDecoder::new_concatenated], only the frame that is currently decoded is counted.
+    pub fn bytes_consumed(&self) -> usize {
+        self.decoder.borrow().bytes_read_from_source() as usize
+    }
+
     /// Destructures this object into the inner reader.
+    ///
+    /// Once all data of the frame has been read from this decoder, the reader is positioned right after the end of the frame,
+    /// so whatever follows the frame in the stream can be read from it.
     pub fn into_inner(self) -> READ
     where
         READ: Sized,
@@ -100,8 +254,19 @@ impl<READ: Read, DEC: BorrowMut<FrameDecoder>> StreamingDecoder<READ, DEC> {
     }
 }
 
-impl<READ: Read, DEC: BorrowMut<FrameDecoder>> Read for StreamingDecoder<READ, DEC> {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+impl<READ: Read, DEC: BorrowMut<FrameDecoder>> core::fmt::Debug for StreamingDecoder<READ, DEC> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StreamingDecoder")
+            .field("decoder", self.decoder.borrow())
+            .field("concatenated", &self.concatenated)
+            .field("frames", &self.frames)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<READ: Read, DEC: BorrowMut<FrameDecoder>> StreamingDecoder<READ, DEC> {
+    /// Read decoded data of the current frame, returns 0 only once the frame is finished and all of its content has been read
+    fn read_frame(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         let decoder = self.decoder.borrow_mut();
         if decoder.is_finished() && decoder.can_collect() == 0 {
             //No more bytes can ever be decoded
@@ -116,26 +281,41 @@ impl<READ: Read, DEC: BorrowMut<FrameDecoder>> Read for StreamingDecoder<READ, D
         while decoder.can_collect() < buf.len() && !decoder.is_finished() {
             //More bytes can be decoded
             let additional_bytes_needed = buf.len() - decoder.can_collect();
-            match decoder.decode_blocks(
-                &mut self.source,
-                BlockDecodingStrategy::UptoBytes(additional_bytes_needed),
-            ) {
-                Ok(_) => { /*Nothing to do*/ }
-                Err(e) => {
-                    let err;
-                    #[cfg(feature = "std")]
-                    {
-                        err = Error::new(ErrorKind::Other, e);
-                    }
-                    #[cfg(not(feature = "std"))]
-                    {
-                        err = Error::new(ErrorKind::Other, alloc::boxed::Box::new(e));
-                    }
-                    return Err(err);
-                }
-            }
+            decoder
+                .decode_blocks(
+                    &mut self.source,
+                    BlockDecodingStrategy::UptoBytes(additional_bytes_needed),
+                )
+                .map_err(to_io_error)?;
         }
 
         decoder.read(buf)
     }
 }
+
+fn to_io_error(e: FrameDecoderError) -> Error {
+    #[cfg(feature = "std")]
+    {
+        Error::other(e)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        Error::new(crate::io::ErrorKind::Other, alloc::boxed::Box::new(e))
+    }
+}
+
+impl<READ: Read, DEC: BorrowMut<FrameDecoder>> Read for StreamingDecoder<READ, DEC> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            if self.exhausted {
+                return Ok(0);
+            }
+            let read = self.read_frame(buf)?;
+            if read > 0 || buf.is_empty() || !self.concatenated {
+                return Ok(read);
+            }
+            // The current frame has been read completely, continue with the next one
+            self.exhausted = !self.next_frame().map_err(to_io_error)?;
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Chooses the content of the dictionary with the COVER algorithm of the reference implementation
+//!
+//! The sample is split into epochs. From each epoch the segment is chosen that contains the most frequent
+//! d-mers, counting each distinct d-mer once. The d-mers of a chosen segment are then forgotten, so the
+//! following segments cover different content. This is repeated for all epochs until the content is full.
+
+use alloc::vec::Vec;
+
+use super::frequency::{dmer_hash, num_dmers, DmerFrequencies, DMER_SIZE, HASH_LOG};
+
+/// Size of the segments the content is made of
+const SEGMENT_SIZE: usize = 256;
+
+/// Choose up to `content_size` bytes of the sample as the content of the dictionary.
+///
+/// The most valuable segments are put at the end, where they are the cheapest to refer to.
+pub(super) fn select_content(sample: &[u8], content_size: usize) -> Vec<u8> {
+    if sample.len() <= content_size {
+        return sample.to_vec();
+    }
+
+    let mut frequencies = DmerFrequencies::count(sample);
+    // How often each d-mer occurs in the current window, each distinct d-mer only counts once for the score
+    let mut active = alloc::vec![0u16; 1 << HASH_LOG];
+
+    let num_epochs = (content_size / SEGMENT_SIZE)
+        .min(sample.len() / SEGMENT_SIZE)
+        .max(1);
+    let epoch_size = sample.len() / num_epochs;
+
+    let mut segments: Vec<&[u8]> = Vec::new();
+    let mut remaining = content_size;
+    let mut epochs_without_segment = 0;
+    let mut epoch = 0;
+    while remaining >= DMER_SIZE && epochs_without_segment < num_epochs {
+        let start = epoch * epoch_size;
+        let end = if epoch == num_epochs - 1 {
+            sample.len()
+        } else {
+            start + epoch_size
+        };
+        let segment_size = remaining.min(SEGMENT_SIZE);
+        match best_segment(&sample[start..end], segment_size, &frequencies, &mut active) {
+            Some(begin) => {
+                let segment = &sample[start + begin..][..segment_size];
+                for pos in 0..num_dmers(segment) {
+                    frequencies.clear(dmer_hash(segment, pos));
+                }
+                segments.push(segment);
+                remaining -= segment_size;
+                epochs_without_segment = 0;
+            }
+            None => epochs_without_segment += 1,
+        }
+        epoch = (epoch + 1) % num_epochs;
+    }
+
+    let mut content = Vec::with_capacity(content_size - remaining);
+    for segment in segments.iter().rev() {
+        content.extend_from_slice(segment);
+    }
+    content
+}
+
+/// Find the start of the segment of `segment_size` bytes in `epoch` with the highest score,
+/// or `None` if no segment contains a d-mer that is still counted.
+fn best_segment(
+    epoch: &[u8],
+    segment_size: usize,
+    frequencies: &DmerFrequencies,
+    active: &mut [u16],
+) -> Option<usize> {
+    let dmers_per_segment = segment_size + 1 - DMER_SIZE;
+    let mut best = None;
+    let mut best_score = 0;
+    let mut score = 0u64;
+    let mut begin = 0;
+    let end = num_dmers(epoch);
+    for pos in 0..end {
+        let hash = dmer_hash(epoch, pos);
+        if active[hash] == 0 {
+            score += u64::from(frequencies.get(hash));
+        }
+        active[hash] += 1;
+
+        if pos + 1 - begin == dmers_per_segment {
+            if score > best_score {
+                best = Some(begin);
+                best_score = score;
+            }
+            let hash = dmer_hash(epoch, begin);
+            active[hash] -= 1;
+            if active[hash] == 0 {
+                score -= u64::from(frequencies.get(hash));
+            }
+            begin += 1;
+        }
+    }
+
+    // Leave the table empty for the next epoch
+    for pos in begin..end {
+        active[dmer_hash(epoch, pos)] = 0;
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select_content;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn frequent_segments_are_chosen() {
+        // Noise with a 256 byte record that repeats every 4KB
+        let mut state = 1u32;
+        let mut sample: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let record: Vec<u8> = (0..=255u8).collect();
+        for start in (0..sample.len()).step_by(4096) {
+            sample[start..start + 256].copy_from_slice(&record);
+        }
+
+        let content = select_content(&sample, 1024);
+        assert_eq!(content.len(), 1024);
+        // The record is the most valuable segment, so it comes last. It is only chosen once.
+        assert_eq!(&content[content.len() - 256..], record.as_slice());
+        assert_eq!(
+            content
+                .windows(record.len())
+                .filter(|window| *window == record.as_slice())
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn small_sample_is_taken_whole() {
+        assert_eq!(select_content(b"abcdefgh", 1024), b"abcdefgh");
+    }
+
+    #[test]
+    fn content_without_repetitions() {
+        // Every d-mer occurs once, segments are still chosen until the content is full
+        let sample: Vec<u8> = (0..4096u32).flat_map(|x| x.to_le_bytes()).collect();
+        let content = select_content(&sample, 1000);
+        assert_eq!(content.len(), 1000);
+    }
+}
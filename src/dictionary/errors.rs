@@ -0,0 +1,50 @@
+//! Errors that might occur while building a dictionary
+
+use core::fmt;
+
+use crate::io::Error;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DictionaryBuildError {
+    /// Reading the samples from the source failed
+    ReadError(Error),
+    /// Writing the dictionary to the output failed
+    WriteError(Error),
+    /// The source contained fewer bytes than a dictionary needs as content
+    SampleTooSmall { size: usize, min: usize },
+    /// The requested dictionary size leaves no room for the content after the header and the entropy tables
+    DictSizeTooSmall { size: usize, min: usize },
+}
+
+impl fmt::Display for DictionaryBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryBuildError::ReadError(e) => write!(f, "Error while reading the samples: {}", e),
+            DictionaryBuildError::WriteError(e) => {
+                write!(f, "Error while writing the dictionary: {}", e)
+            }
+            DictionaryBuildError::SampleTooSmall { size, min } => write!(
+                f,
+                "The source contained {} bytes, but at least {} bytes are needed to build a dictionary",
+                size, min
+            ),
+            DictionaryBuildError::DictSizeTooSmall { size, min } => write!(
+                f,
+                "A dictionary of {} bytes is too small, it needs at least {} bytes",
+                size, min
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DictionaryBuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DictionaryBuildError::ReadError(source) => Some(source),
+            DictionaryBuildError::WriteError(source) => Some(source),
+            _ => None,
+        }
+    }
+}
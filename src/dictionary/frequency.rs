@@ -0,0 +1,69 @@
+//! Counts how often short byte sequences occur in the sample
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Length of the byte sequences (d-mers) that are counted
+pub(super) const DMER_SIZE: usize = 8;
+/// The d-mers are counted in a table of `1 << HASH_LOG` entries indexed by their hash.
+/// Collisions make some counts too high, which only makes the choice of content slightly worse.
+pub(super) const HASH_LOG: u32 = 20;
+
+/// How often each d-mer occurs in the sample, indexed by [dmer_hash]
+pub(super) struct DmerFrequencies {
+    counts: Vec<u32>,
+}
+
+impl DmerFrequencies {
+    /// Count the d-mers starting at each position of the sample
+    pub fn count(sample: &[u8]) -> Self {
+        let mut counts = alloc::vec![0u32; 1 << HASH_LOG];
+        for pos in 0..num_dmers(sample) {
+            let count = &mut counts[dmer_hash(sample, pos)];
+            *count = count.saturating_add(1);
+        }
+        DmerFrequencies { counts }
+    }
+
+    pub fn get(&self, hash: usize) -> u32 {
+        self.counts[hash]
+    }
+
+    /// Forget the d-mer, once it is in the content it doesn't make other segments more valuable
+    pub fn clear(&mut self, hash: usize) {
+        self.counts[hash] = 0;
+    }
+}
+
+/// How many d-mers start in `data`
+pub(super) fn num_dmers(data: &[u8]) -> usize {
+    (data.len() + 1).saturating_sub(DMER_SIZE)
+}
+
+/// The hash of the d-mer that starts at `pos`, `HASH_LOG` bits long
+pub(super) fn dmer_hash(data: &[u8], pos: usize) -> usize {
+    let dmer = u64::from_le_bytes(data[pos..pos + DMER_SIZE].try_into().unwrap());
+    (dmer.wrapping_mul(0x9E37_79B1_85EB_CA87) >> (64 - HASH_LOG)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dmer_hash, num_dmers, DmerFrequencies};
+
+    #[test]
+    fn counts() {
+        let sample = b"abcdefgh_abcdefgh_abcdefgh";
+        assert_eq!(num_dmers(sample), 19);
+        assert_eq!(num_dmers(b"abcdefg"), 0);
+        assert_eq!(num_dmers(b"abcdefgh"), 1);
+
+        let mut frequencies = DmerFrequencies::count(sample);
+        let abcdefgh = dmer_hash(sample, 0);
+        assert_eq!(dmer_hash(sample, 9), abcdefgh);
+        assert_eq!(frequencies.get(abcdefgh), 3);
+        assert_eq!(frequencies.get(dmer_hash(sample, 1)), 2);
+
+        frequencies.clear(abcdefgh);
+        assert_eq!(frequencies.get(abcdefgh), 0);
+    }
+}
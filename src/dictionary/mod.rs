@@ -0,0 +1,243 @@
+//! Structures and utilities used for building dictionaries from sample data.
+//!
+//! Small inputs compress badly on their own, because each frame starts with an empty window and
+//! without any statistics about the data. A dictionary provides both, see [crate::decoding::Dictionary].
+//! It pays off for many small inputs that have a lot in common, like messages, records or config files.
+//!
+//! [create_dict_from_source] builds a dictionary in the format of the reference implementation:
+//! * A sample of bounded size is taken from the source with reservoir sampling, so sources of any size can be used.
+//! * The content is assembled from the segments of the sample that cover the most frequent byte sequences,
+//!   following the COVER algorithm of the reference implementation.
+//! * The entropy tables are built from the literals and sequences found while compressing the sample.
+//!
+//! The output is deterministic, the same source always results in the same dictionary.
+
+use alloc::vec::Vec;
+
+use crate::decoding::dictionary::MAGIC_NUM;
+use crate::decoding::sequence_section_decoder::{LL_MAX_LOG, ML_MAX_LOG, OF_MAX_LOG};
+use crate::encoding::bit_writer::BitWriter;
+use crate::encoding::blocks::{encode_literal_length, encode_match_len, encode_offset};
+use crate::encoding::match_generator::MatchGeneratorDriver;
+use crate::encoding::{CompressionLevel, Matcher, Sequence};
+use crate::fse::fse_encoder::build_table_from_counts;
+use crate::huff0::huff0_encoder::{HuffmanEncoder, HuffmanTable};
+use crate::io::{Read, Write};
+
+mod cover;
+pub mod errors;
+mod frequency;
+mod reservoir;
+
+use errors::DictionaryBuildError;
+
+/// At most this much of the source is used to build the dictionary
+const MAX_SAMPLE_SIZE: usize = 32 * 1024 * 1024;
+/// The repeat offsets every frame starts with, the content must be at least as long as the biggest one
+const REPEAT_OFFSETS: [u32; 3] = [1, 4, 8];
+const MIN_CONTENT_SIZE: usize = 8;
+/// Size of the magic number and the dictionary id
+const HEADER_SIZE: usize = 8;
+const MAX_HUFFMAN_BITS: u8 = 11;
+/// The sample is fed to the matcher in blocks of this size to collect the statistics for the entropy tables
+const BLOCK_SIZE: usize = 128 * 1024;
+
+/// Build a dictionary of at most `dict_size` bytes from the data in `source` and write it to `output`.
+///
+/// The source is read to its end. Pass the concatenation of many representative inputs, the reference implementation
+/// recommends about 100 times the size of the dictionary. Of bigger sources a random sample of 32MB is used.
+/// A typical size for a dictionary is around 100KB. The header and the entropy tables take up a few hundred bytes,
+/// the rest is filled with content.
+///
+/// The resulting dictionary can be used by the reference implementation to compress data, and by
+/// [crate::decoding::FrameDecoder::add_dict] after parsing it with [crate::decoding::Dictionary::decode_dict]
+/// to decompress frames compressed with it.
+///
+/// ```
+/// use ruzstd::decoding::Dictionary;
+/// use ruzstd::dictionary::create_dict_from_source;
+///
+/// let samples: Vec<u8> = (0..1000u32)
+///     .flat_map(|i| format!("{{\"id\": {}, \"name\": \"user{}\", \"active\": true}}\n", i, i % 7).into_bytes())
+///     .collect();
+/// let mut raw_dict = Vec::new();
+/// create_dict_from_source(samples.as_slice(), &mut raw_dict, 4096).unwrap();
+/// assert!(raw_dict.len() <= 4096);
+///
+/// let dict = Dictionary::decode_dict(&raw_dict).unwrap();
+/// assert!(dict.id != 0);
+/// ```
+pub fn create_dict_from_source<R: Read, W: Write>(
+    source: R,
+    mut output: W,
+    dict_size: usize,
+) -> Result<(), DictionaryBuildError> {
+    let sample =
+        reservoir::sample(source, MAX_SAMPLE_SIZE).map_err(DictionaryBuildError::ReadError)?;
+    if sample.len() < MIN_CONTENT_SIZE {
+        return Err(DictionaryBuildError::SampleTooSmall {
+            size: sample.len(),
+            min: MIN_CONTENT_SIZE,
+        });
+    }
+
+    let tables = entropy_tables(&sample);
+    let min_size = HEADER_SIZE + tables.len() + MIN_CONTENT_SIZE;
+    if dict_size < min_size {
+        return Err(DictionaryBuildError::DictSizeTooSmall {
+            size: dict_size,
+            min: min_size,
+        });
+    }
+    let content = cover::select_content(&sample, dict_size - HEADER_SIZE - tables.len());
+
+    let mut dict = Vec::with_capacity(HEADER_SIZE + tables.len() + content.len());
+    dict.extend_from_slice(&MAGIC_NUM);
+    dict.extend_from_slice(&dict_id(&tables, &content).to_le_bytes());
+    dict.extend_from_slice(&tables);
+    dict.extend_from_slice(&content);
+    output
+        .write_all(&dict)
+        .map_err(DictionaryBuildError::WriteError)
+}
+
+/// Build a dictionary of at most `dict_size` bytes from all files in the directory at `path` and its subdirectories,
+/// and write it to `output`.
+///
+/// The files are read in the order of their paths, see [create_dict_from_source] for how the dictionary is built.
+#[cfg(feature = "std")]
+pub fn create_dict_from_dir<P: AsRef<std::path::Path>, W: Write>(
+    path: P,
+    output: W,
+    dict_size: usize,
+) -> Result<(), DictionaryBuildError> {
+    let mut files = Vec::new();
+    collect_files(path.as_ref(), &mut files).map_err(DictionaryBuildError::ReadError)?;
+    files.sort();
+    let source = Files {
+        paths: files.into_iter(),
+        current: None,
+    };
+    create_dict_from_source(source, output, dict_size)
+}
+
+#[cfg(feature = "std")]
+fn collect_files(
+    dir: &std::path::Path,
+    files: &mut Vec<std::path::PathBuf>,
+) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the files one after another
+#[cfg(feature = "std")]
+struct Files {
+    paths: alloc::vec::IntoIter<std::path::PathBuf>,
+    current: Option<std::fs::File>,
+}
+
+#[cfg(feature = "std")]
+impl Read for Files {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        loop {
+            if let Some(file) = &mut self.current {
+                let read = file.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+            }
+            match self.paths.next() {
+                Some(path) => self.current = Some(std::fs::File::open(path)?),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// The Huffman table for the literals and the FSE tables for the offsets, match lengths and literal lengths,
+/// followed by the repeat offsets, as they are stored in the dictionary
+fn entropy_tables(sample: &[u8]) -> Vec<u8> {
+    // Every symbol gets counted at least once, so the tables can encode everything
+    let mut literals = [1usize; 256];
+    let mut literal_lengths = [0usize; 36];
+    let mut match_lengths = [0usize; 53];
+    let mut offsets = [0usize; 32];
+
+    let mut matcher = MatchGeneratorDriver::new(BLOCK_SIZE, 1);
+    matcher.reset(CompressionLevel::Fastest);
+    for block in sample.chunks(BLOCK_SIZE) {
+        let mut space = matcher.get_next_space();
+        space.clear();
+        space.extend_from_slice(block);
+        matcher.commit_space(space);
+        matcher.start_matching(|seq| match seq {
+            Sequence::Literals { literals: lits } => {
+                for lit in lits {
+                    literals[*lit as usize] += 1;
+                }
+            }
+            Sequence::Triple {
+                literals: lits,
+                offset,
+                match_len,
+            } => {
+                for lit in lits {
+                    literals[*lit as usize] += 1;
+                }
+                literal_lengths[encode_literal_length(lits.len() as u32).0 as usize] += 1;
+                match_lengths[encode_match_len(match_len as u32).0 as usize] += 1;
+                // Offsets are encoded as offset + 3, the smaller values are the repeat offsets
+                offsets[encode_offset(offset as u32 + 3).0 as usize] += 1;
+            }
+        });
+    }
+
+    let mut writer = BitWriter::new();
+    let huffman = HuffmanTable::build_from_counts(&literals, MAX_HUFFMAN_BITS);
+    HuffmanEncoder::new(huffman, &mut writer).write_table();
+    build_table_from_counts(&smoothed(&offsets, OF_MAX_LOG), OF_MAX_LOG, false)
+        .write_table(&mut writer);
+    build_table_from_counts(&smoothed(&match_lengths, ML_MAX_LOG), ML_MAX_LOG, false)
+        .write_table(&mut writer);
+    build_table_from_counts(&smoothed(&literal_lengths, LL_MAX_LOG), LL_MAX_LOG, false)
+        .write_table(&mut writer);
+    let mut tables = writer.dump();
+    for offset in REPEAT_OFFSETS {
+        tables.extend_from_slice(&offset.to_le_bytes());
+    }
+    tables
+}
+
+/// Scale the counts down so they add up to at most `1 << max_log`.
+///
+/// Every symbol keeps a count of at least one, so the table built from them can encode all symbols,
+/// not only the ones that occurred in the sample.
+fn smoothed(counts: &[usize], max_log: u8) -> Vec<usize> {
+    let total = counts.iter().sum::<usize>().max(1) as u64;
+    let budget = ((1usize << max_log) - counts.len()) as u64;
+    counts
+        .iter()
+        .map(|count| 1 + (*count as u64 * budget / total) as usize)
+        .collect()
+}
+
+/// Derive an id from the dictionary, in the range the specification recommends for dictionaries that are not registered
+fn dict_id(tables: &[u8], content: &[u8]) -> u32 {
+    // FNV-1a
+    let mut hash = 0xCBF2_9CE4_8422_2325u64;
+    for byte in tables.iter().chain(content) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    const MIN_ID: u64 = 32768;
+    const MAX_ID: u64 = (1 << 31) - 1;
+    (MIN_ID + hash % (MAX_ID - MIN_ID)) as u32
+}
@@ -0,0 +1,105 @@
+//! Takes a sample of bounded size from a source of any size
+
+use alloc::vec::Vec;
+
+use crate::io::{Error, ErrorKind, Read};
+
+/// The source is sampled in chunks of this size
+const CHUNK_SIZE: usize = 4 * 1024;
+
+/// Read all of `source` and keep a uniformly random selection of chunks that add up to at most `max_size` bytes.
+///
+/// Uses reservoir sampling, so only `max_size` bytes are kept in memory. The chosen chunks are returned
+/// concatenated in the order they appeared in the source. If the source is smaller than `max_size`
+/// all of it is returned. The random choices are seeded with a constant, so the sample is reproducible.
+pub(super) fn sample(mut source: impl Read, max_size: usize) -> Result<Vec<u8>, Error> {
+    let max_chunks = (max_size / CHUNK_SIZE).max(1);
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    // The index of the chunk in the source and its content
+    let mut reservoir: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut chunk_idx = 0;
+    loop {
+        let chunk = read_chunk(&mut source)?;
+        if chunk.is_empty() {
+            break;
+        }
+        if reservoir.len() < max_chunks {
+            reservoir.push((chunk_idx, chunk));
+        } else {
+            let slot = (rng.next() % (chunk_idx as u64 + 1)) as usize;
+            if slot < max_chunks {
+                reservoir[slot] = (chunk_idx, chunk);
+            }
+        }
+        chunk_idx += 1;
+    }
+
+    reservoir.sort_unstable_by_key(|(idx, _)| *idx);
+    let mut sample = Vec::with_capacity(reservoir.iter().map(|(_, chunk)| chunk.len()).sum());
+    for (_, chunk) in reservoir {
+        sample.extend_from_slice(&chunk);
+    }
+    Ok(sample)
+}
+
+/// Read the next chunk from the source, only the last chunk of the source is shorter than [CHUNK_SIZE]
+fn read_chunk(mut source: impl Read) -> Result<Vec<u8>, Error> {
+    let mut chunk = alloc::vec![0; CHUNK_SIZE];
+    let mut filled = 0;
+    while filled < CHUNK_SIZE {
+        match source.read(&mut chunk[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if matches!(e.kind(), ErrorKind::Interrupted) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    chunk.truncate(filled);
+    Ok(chunk)
+}
+
+/// Small pseudo random number generator, good enough to pick the chunks
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sample, CHUNK_SIZE};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn small_source_is_kept() {
+        let data: Vec<u8> = (0..10_000u32).map(|x| x as u8).collect();
+        assert_eq!(sample(data.as_slice(), 1024 * 1024).unwrap(), data);
+        assert!(sample(&[][..], 1024).unwrap().is_empty());
+    }
+
+    #[test]
+    fn big_source_is_sampled() {
+        // Every chunk is filled with its index
+        let data: Vec<u8> = (0..100 * CHUNK_SIZE)
+            .map(|x| (x / CHUNK_SIZE) as u8)
+            .collect();
+        let sampled = sample(data.as_slice(), 10 * CHUNK_SIZE).unwrap();
+        assert_eq!(sampled.len(), 10 * CHUNK_SIZE);
+
+        // Whole chunks in the order of the source
+        let indices: Vec<u8> = sampled.chunks(CHUNK_SIZE).map(|chunk| chunk[0]).collect();
+        for (chunk, idx) in sampled.chunks(CHUNK_SIZE).zip(&indices) {
+            assert!(chunk.iter().all(|x| x == idx));
+        }
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        // Not just the start of the source
+        assert!(*indices.last().unwrap() >= 10);
+
+        assert_eq!(sample(data.as_slice(), 10 * CHUNK_SIZE).unwrap(), sampled);
+    }
+}
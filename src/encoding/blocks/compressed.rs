@@ -1,39 +1,176 @@
 use alloc::vec::Vec;
 
 use crate::{
-    encoding::{bit_writer::BitWriter, Matcher, Sequence},
-    fse::fse_encoder::{default_ll_table, default_ml_table, default_of_table, FSETable, State},
+    decoding::sequence_execution::do_offset_history,
+    decoding::sequence_section_decoder::{LL_MAX_LOG, ML_MAX_LOG, OF_MAX_LOG},
+    encoding::{
+        bit_writer::BitWriter, scratch::EncoderScratch, EncoderParams, MatchStats, Matcher,
+        Sequence,
+    },
+    fse::fse_encoder::{build_table_from_counts_into, FSETable, State},
     huff0::huff0_encoder,
 };
 
-pub fn compress_block<M: Matcher>(matcher: &mut M, output: &mut Vec<u8>) {
-    let mut literals_vec = Vec::new();
-    let mut sequences = Vec::new();
+/// The FSE tables of one of the sequence fields (literal lengths, match lengths or offsets).
+///
+/// Keeps the table used by the previous compressed block around so it can be reused with the repeat mode.
+/// The storage of newly built tables is reused from block to block.
+pub(crate) struct FseTableScratch {
+    default: FSETable,
+    /// Storage for the table built from the codes of the current block
+    candidate: FSETable,
+    /// Storage for the table built for the previous block
+    custom: FSETable,
+    previous: PreviousTable,
+}
+
+/// Which table the previous block used
+enum PreviousTable {
+    /// Nothing that can be repeated
+    None,
+    Default,
+    Custom,
+}
+
+impl FseTableScratch {
+    pub fn new(default: FSETable) -> Self {
+        Self {
+            default,
+            candidate: FSETable::empty(),
+            custom: FSETable::empty(),
+            previous: PreviousTable::None,
+        }
+    }
+
+    /// Forget the table of the previous block. Needs to be called at the start of each frame
+    /// and whenever a compressed block is not emitted as such (e.g. if it was replaced by a raw block)
+    pub fn forget_previous(&mut self) {
+        self.previous = PreviousTable::None;
+    }
+
+    fn previous(&self) -> Option<&FSETable> {
+        match self.previous {
+            PreviousTable::None => None,
+            PreviousTable::Default => Some(&self.default),
+            PreviousTable::Custom => Some(&self.custom),
+        }
+    }
+
+    fn choose(&mut self, codes: &[u8], max_log: u8) -> TableChoice {
+        let previous = match self.previous {
+            PreviousTable::None => None,
+            PreviousTable::Default => Some(&self.default),
+            PreviousTable::Custom => Some(&self.custom),
+        };
+        choose_table(codes, max_log, &self.default, previous, &mut self.candidate)
+    }
+
+    /// The table used to encode the codes. `None` for the RLE mode
+    fn table(&self, choice: &TableChoice) -> Option<&FSETable> {
+        match choice {
+            TableChoice::Predefined => Some(&self.default),
+            TableChoice::Rle(_) => None,
+            TableChoice::Encoded => Some(&self.candidate),
+            TableChoice::Repeat => self.previous(),
+        }
+    }
+
+    fn write_description(&self, choice: &TableChoice, writer: &mut BitWriter<&mut Vec<u8>>) {
+        match choice {
+            TableChoice::Rle(symbol) => writer.write_bits(*symbol, 8),
+            TableChoice::Encoded => self.candidate.write_table(writer),
+            TableChoice::Predefined | TableChoice::Repeat => {}
+        }
+    }
+
+    fn update_previous(&mut self, choice: &TableChoice) {
+        match choice {
+            TableChoice::Predefined => self.previous = PreviousTable::Default,
+            // Repeating an RLE symbol is allowed but not worth the bookkeeping
+            TableChoice::Rle(_) => self.previous = PreviousTable::None,
+            TableChoice::Encoded => {
+                core::mem::swap(&mut self.candidate, &mut self.custom);
+                self.previous = PreviousTable::Custom;
+            }
+            TableChoice::Repeat => {}
+        }
+    }
+}
+
+pub fn compress_block<M: Matcher>(
+    matcher: &mut M,
+    scratch: &mut EncoderScratch,
+    params: &EncoderParams,
+    stats: &mut MatchStats,
+    output: &mut Vec<u8>,
+) {
+    // Taken out of the scratch while the block is encoded, the tables and codes are still needed
+    let mut literals_vec = core::mem::take(&mut scratch.literals);
+    let mut sequences = core::mem::take(&mut scratch.sequences);
+    literals_vec.clear();
+    sequences.clear();
+    scratch.previous_offset_hist = scratch.offset_hist;
+    let offset_hist = &mut scratch.offset_hist;
     matcher.start_matching(|seq| {
         match seq {
-            Sequence::Literals { literals } => literals_vec.extend_from_slice(literals),
+            // Literals without a following match end the block. They don't get a sequence,
+            // the decoder appends the literals that no sequence consumed after executing the sequences
+            Sequence::Literals { literals } => {
+                stats.record_literals(literals.len());
+                literals_vec.extend_from_slice(literals)
+            }
             Sequence::Triple {
                 literals,
                 offset,
                 match_len,
             } => {
+                stats.record_literals(literals.len());
+                stats.record_match(match_len, offset);
                 literals_vec.extend_from_slice(literals);
+                let ll = literals.len() as u32;
                 sequences.push(crate::blocks::sequence_section::Sequence {
-                    ll: literals.len() as u32,
+                    ll,
                     ml: match_len as u32,
-                    of: (offset + 3) as u32, // TODO make use of the offset history
+                    of: offset_value(offset as u32, ll, offset_hist),
                 });
             }
         }
     });
 
+    encode_block_content(&literals_vec, &sequences, scratch, params, output);
+    scratch.literals = literals_vec;
+    scratch.sequences = sequences;
+}
+
+/// Write the literals section and the sequences section of a compressed block to `output`.
+///
+/// The offsets of the sequences are the values that are written to the block, so repeat offsets
+/// have to be resolved already. The tables of the previous block in `scratch` are reused if that is cheaper.
+pub fn encode_block_content(
+    literals: &[u8],
+    sequences: &[crate::blocks::sequence_section::Sequence],
+    scratch: &mut EncoderScratch,
+    params: &EncoderParams,
+    output: &mut Vec<u8>,
+) {
+    let EncoderScratch {
+        codes,
+        ll_tables,
+        ml_tables,
+        of_tables,
+        ..
+    } = scratch;
+
     // literals section
 
     let mut writer = BitWriter::from(output);
-    if literals_vec.len() > 1024 {
-        compress_literals(&literals_vec, &mut writer);
+    if literals.len() > 1 && literals.iter().all(|lit| *lit == literals[0]) {
+        // A huffman table needs at least two symbols
+        rle_literals(literals, &mut writer);
+    } else if literals.len() > 1024 {
+        compress_literals(literals, params.max_huffman_bits, &mut writer);
     } else {
-        raw_literals(&literals_vec, &mut writer);
+        raw_literals(literals, &mut writer);
     }
 
     // sequences section
@@ -43,69 +180,201 @@ pub fn compress_block<M: Matcher>(matcher: &mut M, output: &mut Vec<u8>) {
     } else {
         encode_seqnum(sequences.len(), &mut writer);
 
-        // use standard FSE tables
-        writer.write_bits(0u8, 8);
+        codes.clear();
+        codes.extend(sequences.iter().map(|seq| encode_literal_length(seq.ll).0));
+        let ll_choice = ll_tables.choose(codes, params.max_ll_log.min(LL_MAX_LOG));
+        codes.clear();
+        codes.extend(sequences.iter().map(|seq| encode_match_len(seq.ml).0));
+        let ml_choice = ml_tables.choose(codes, params.max_ml_log.min(ML_MAX_LOG));
+        codes.clear();
+        codes.extend(sequences.iter().map(|seq| encode_offset(seq.of).0));
+        let of_choice = of_tables.choose(codes, params.max_of_log.min(OF_MAX_LOG));
 
-        let ll_table: FSETable = default_ll_table();
-        let ml_table: FSETable = default_ml_table();
-        let of_table: FSETable = default_of_table();
+        // Compression_Modes: Literals_Lengths_Mode, Offsets_Mode, Match_Lengths_Mode, Reserved
+        writer.write_bits(
+            ml_choice.mode() << 2 | of_choice.mode() << 4 | ll_choice.mode() << 6,
+            8,
+        );
+        ll_tables.write_description(&ll_choice, &mut writer);
+        of_tables.write_description(&of_choice, &mut writer);
+        ml_tables.write_description(&ml_choice, &mut writer);
 
-        let sequence = sequences[sequences.len() - 1];
+        encode_sequences(
+            sequences,
+            ll_tables.table(&ll_choice),
+            ml_tables.table(&ml_choice),
+            of_tables.table(&of_choice),
+            &mut writer,
+        );
+
+        ll_tables.update_previous(&ll_choice);
+        ml_tables.update_previous(&ml_choice);
+        of_tables.update_previous(&of_choice);
+    }
+    writer.flush();
+}
+
+/// Encode the sequences into the bitstream. A table of `None` means the symbols are encoded in the RLE mode
+/// and don't need any bits in the bitstream.
+fn encode_sequences(
+    sequences: &[crate::blocks::sequence_section::Sequence],
+    ll_table: Option<&FSETable>,
+    ml_table: Option<&FSETable>,
+    of_table: Option<&FSETable>,
+    writer: &mut BitWriter<&mut Vec<u8>>,
+) {
+    let sequence = sequences[sequences.len() - 1];
+    let (ll_code, ll_add_bits, ll_num_bits) = encode_literal_length(sequence.ll);
+    let (of_code, of_add_bits, of_num_bits) = encode_offset(sequence.of);
+    let (ml_code, ml_add_bits, ml_num_bits) = encode_match_len(sequence.ml);
+    let mut ll_state = ll_table.map(|table| table.start_state(ll_code));
+    let mut ml_state = ml_table.map(|table| table.start_state(ml_code));
+    let mut of_state = of_table.map(|table| table.start_state(of_code));
+
+    writer.write_bits(ll_add_bits, ll_num_bits);
+    writer.write_bits(ml_add_bits, ml_num_bits);
+    writer.write_bits(of_add_bits, of_num_bits);
+
+    // encode backwards so the decoder reads the first sequence first
+    for sequence in sequences[..sequences.len() - 1].iter().rev() {
         let (ll_code, ll_add_bits, ll_num_bits) = encode_literal_length(sequence.ll);
         let (of_code, of_add_bits, of_num_bits) = encode_offset(sequence.of);
         let (ml_code, ml_add_bits, ml_num_bits) = encode_match_len(sequence.ml);
-        let mut ll_state: &State = ll_table.start_state(ll_code);
-        let mut ml_state: &State = ml_table.start_state(ml_code);
-        let mut of_state: &State = of_table.start_state(of_code);
+
+        next_state(of_table, &mut of_state, of_code, writer);
+        next_state(ml_table, &mut ml_state, ml_code, writer);
+        next_state(ll_table, &mut ll_state, ll_code, writer);
 
         writer.write_bits(ll_add_bits, ll_num_bits);
         writer.write_bits(ml_add_bits, ml_num_bits);
         writer.write_bits(of_add_bits, of_num_bits);
+    }
+    if let (Some(table), Some(state)) = (ml_table, ml_state) {
+        writer.write_bits(state.index as u64, table.acc_log() as usize);
+    }
+    if let (Some(table), Some(state)) = (of_table, of_state) {
+        writer.write_bits(state.index as u64, table.acc_log() as usize);
+    }
+    if let (Some(table), Some(state)) = (ll_table, ll_state) {
+        writer.write_bits(state.index as u64, table.acc_log() as usize);
+    }
 
-        // encode backwards so the decoder reads the first sequence first
-        if sequences.len() > 1 {
-            for sequence in (0..=sequences.len() - 2).rev() {
-                let sequence = sequences[sequence];
-                let (ll_code, ll_add_bits, ll_num_bits) = encode_literal_length(sequence.ll);
-                let (of_code, of_add_bits, of_num_bits) = encode_offset(sequence.of);
-                let (ml_code, ml_add_bits, ml_num_bits) = encode_match_len(sequence.ml);
-
-                {
-                    let next = of_table.next_state(of_code, of_state.index);
-                    let diff = of_state.index - next.baseline;
-                    writer.write_bits(diff as u64, next.num_bits as usize);
-                    of_state = next;
-                }
-                {
-                    let next = ml_table.next_state(ml_code, ml_state.index);
-                    let diff = ml_state.index - next.baseline;
-                    writer.write_bits(diff as u64, next.num_bits as usize);
-                    ml_state = next;
-                }
-                {
-                    let next = ll_table.next_state(ll_code, ll_state.index);
-                    let diff = ll_state.index - next.baseline;
-                    writer.write_bits(diff as u64, next.num_bits as usize);
-                    ll_state = next;
-                }
-
-                writer.write_bits(ll_add_bits, ll_num_bits);
-                writer.write_bits(ml_add_bits, ml_num_bits);
-                writer.write_bits(of_add_bits, of_num_bits);
-            }
+    let bits_to_fill = writer.misaligned();
+    if bits_to_fill == 0 {
+        writer.write_bits(1u32, 8);
+    } else {
+        writer.write_bits(1u32, bits_to_fill);
+    }
+}
+
+fn next_state<'t>(
+    table: Option<&'t FSETable>,
+    state: &mut Option<&'t State>,
+    code: u8,
+    writer: &mut BitWriter<&mut Vec<u8>>,
+) {
+    if let (Some(table), Some(current)) = (table, *state) {
+        let next = table.next_state(code, current.index);
+        let diff = current.index - next.baseline;
+        writer.write_bits(diff as u64, next.num_bits as usize);
+        *state = Some(next);
+    }
+}
+
+/// How the symbols of one of the sequence fields are encoded
+#[derive(Debug)]
+enum TableChoice {
+    Predefined,
+    Rle(u8),
+    /// A new table that was built from the codes of the block
+    Encoded,
+    Repeat,
+}
+
+impl TableChoice {
+    fn mode(&self) -> u8 {
+        match self {
+            TableChoice::Predefined => 0,
+            TableChoice::Rle(_) => 1,
+            TableChoice::Encoded => 2,
+            TableChoice::Repeat => 3,
         }
-        writer.write_bits(ml_state.index as u64, ml_table.table_size.ilog2() as usize);
-        writer.write_bits(of_state.index as u64, of_table.table_size.ilog2() as usize);
-        writer.write_bits(ll_state.index as u64, ll_table.table_size.ilog2() as usize);
+    }
+}
+
+/// Choose the cheapest way to encode the codes, comparing the estimated size of the encoded codes
+/// using the predefined table, the table of the previous block and a new table built from the codes
+/// including the size of its description.
+///
+/// The new table is built into `candidate`.
+fn choose_table(
+    codes: &[u8],
+    max_log: u8,
+    default: &FSETable,
+    previous: Option<&FSETable>,
+    candidate: &mut FSETable,
+) -> TableChoice {
+    let mut counts = [0usize; 256];
+    for code in codes {
+        counts[*code as usize] += 1;
+    }
+    let num_symbols = counts.iter().filter(|count| **count > 0).count();
+    if num_symbols == 1 {
+        return TableChoice::Rle(codes[0]);
+    }
+    // The table needs a slot for each symbol, and the format does not allow accuracy logs below 5
+    let max_log = max_log
+        .max(num_symbols.next_power_of_two().ilog2() as u8)
+        .max(5);
+
+    let predefined_cost = estimate_cost(default, &counts);
+    let repeat_cost = previous.and_then(|previous| estimate_cost(previous, &counts));
+
+    build_table_from_counts_into(&counts, max_log, false, candidate);
+    let mut description = BitWriter::new();
+    candidate.write_table(&mut description);
+    let new_cost =
+        estimate_cost(candidate, &counts).map(|cost| cost + description.index() * COST_SCALE);
+
+    let mut best = (predefined_cost, TableChoice::Predefined);
+    if repeat_cost.is_some() && (best.0.is_none() || repeat_cost <= best.0) {
+        best = (repeat_cost, TableChoice::Repeat);
+    }
+    if new_cost.is_some() && (best.0.is_none() || new_cost < best.0) {
+        best = (new_cost, TableChoice::Encoded);
+    }
+    best.1
+}
 
-        let bits_to_fill = writer.misaligned();
-        if bits_to_fill == 0 {
-            writer.write_bits(1u32, 8);
-        } else {
-            writer.write_bits(1u32, bits_to_fill);
+/// The costs are calculated in fractions of a bit
+const COST_SCALE: usize = 256;
+
+/// Estimate the number of bits (times [COST_SCALE]) needed to encode the symbols with the given counts using the table.
+/// Returns None if the table can't encode one of the symbols.
+///
+/// A symbol with a normalized probability of `p` in a table of size `2^acc_log` costs about `acc_log - log2(p)` bits.
+fn estimate_cost(table: &FSETable, counts: &[usize; 256]) -> Option<usize> {
+    let acc_log = table.acc_log() as usize;
+    let mut cost = 0;
+    for (symbol, count) in counts.iter().copied().enumerate() {
+        if count == 0 {
+            continue;
         }
+        let symbol_cost = match table.symbol_probability(symbol as u8) {
+            0 => return None,
+            -1 => acc_log * COST_SCALE,
+            prob => acc_log * COST_SCALE - log2_scaled(prob as usize),
+        };
+        cost += symbol_cost * count;
     }
-    writer.flush();
+    Some(cost)
+}
+
+/// Approximates `log2(x) * COST_SCALE` by interpolating linearly between powers of two
+fn log2_scaled(x: usize) -> usize {
+    let log = x.ilog2() as usize;
+    let fraction = ((x << 8) >> log) - (1 << 8);
+    log * COST_SCALE + fraction * COST_SCALE / (1 << 8)
 }
 
 fn encode_seqnum(seqnum: usize, writer: &mut BitWriter<impl AsMut<Vec<u8>>>) {
@@ -130,7 +399,7 @@ fn encode_seqnum(seqnum: usize, writer: &mut BitWriter<impl AsMut<Vec<u8>>>) {
     }
 }
 
-fn encode_literal_length(len: u32) -> (u8, u32, usize) {
+pub(crate) fn encode_literal_length(len: u32) -> (u8, u32, usize) {
     match len {
         0..=15 => (len as u8, 0, 0),
         16..=17 => (16, len - 16, 1),
@@ -157,7 +426,7 @@ fn encode_literal_length(len: u32) -> (u8, u32, usize) {
     }
 }
 
-fn encode_match_len(len: u32) -> (u8, u32, usize) {
+pub(crate) fn encode_match_len(len: u32) -> (u8, u32, usize) {
     match len {
         0..=2 => unreachable!(),
         3..=34 => (len as u8 - 3, 0, 0),
@@ -181,12 +450,36 @@ fn encode_match_len(len: u32) -> (u8, u32, usize) {
         8195..=16386 => (49, len - 8195, 13),
         16387..=32770 => (50, len - 16387, 14),
         32771..=65538 => (51, len - 32771, 15),
-        65539..=131074 => (52, len - 32771, 16),
+        65539..=131074 => (52, len - 65539, 16),
         131075.. => unreachable!(),
     }
 }
 
-fn encode_offset(len: u32) -> (u8, u32, usize) {
+/// Find the value that encodes `offset` for a sequence with `lit_len` literals, using one of the
+/// repeat offsets if possible, and update the repeat offsets like the decoder will.
+fn offset_value(offset: u32, lit_len: u32, offset_hist: &mut [u32; 3]) -> u32 {
+    // Without literals the repeat offsets are shifted by one, and the most recent offset can't be repeated
+    let repeat = if lit_len > 0 {
+        offset_hist.iter().position(|&rep| rep == offset)
+    } else if offset == offset_hist[1] {
+        Some(0)
+    } else if offset == offset_hist[2] {
+        Some(1)
+    } else if offset == offset_hist[0] - 1 {
+        Some(2)
+    } else {
+        None
+    };
+    let value = match repeat {
+        Some(idx) => idx as u32 + 1,
+        None => offset + 3,
+    };
+    let actual = do_offset_history(value, lit_len, offset_hist);
+    debug_assert_eq!(actual, offset);
+    value
+}
+
+pub(crate) fn encode_offset(len: u32) -> (u8, u32, usize) {
     let log = len.ilog2();
     let lower = len & ((1 << log) - 1);
     (log as u8, lower, log as usize)
@@ -199,11 +492,18 @@ fn raw_literals(literals: &[u8], writer: &mut BitWriter<&mut Vec<u8>>) {
     writer.append_bytes(literals);
 }
 
-fn compress_literals(literals: &[u8], writer: &mut BitWriter<&mut Vec<u8>>) {
+fn rle_literals(literals: &[u8], writer: &mut BitWriter<&mut Vec<u8>>) {
+    writer.write_bits(1u8, 2);
+    writer.write_bits(0b11u8, 2);
+    writer.write_bits(literals.len() as u32, 20);
+    writer.write_bits(literals[0], 8);
+}
+
+fn compress_literals(literals: &[u8], max_num_bits: u8, writer: &mut BitWriter<&mut Vec<u8>>) {
     let reset_idx = writer.index();
     writer.write_bits(2u8, 2); // compressed literals type
 
-    let encoder_table = huff0_encoder::HuffmanTable::build_from_data(literals);
+    let encoder_table = huff0_encoder::HuffmanTable::build_from_data(literals, max_num_bits);
 
     let (size_format, size_bits) = match literals.len() {
         0..6 => (0b00u8, 10),
@@ -234,3 +534,323 @@ fn compress_literals(literals: &[u8], writer: &mut BitWriter<&mut Vec<u8>>) {
         raw_literals(literals, writer);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        choose_table, compress_literals, encode_literal_length, encode_match_len, offset_value,
+        TableChoice,
+    };
+    use crate::decoding::sequence_section_decoder::{lookup_ll_code, lookup_ml_code, LL_MAX_LOG};
+    use crate::fse::fse_encoder::{default_ll_table, FSETable};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn literals_use_four_streams() {
+        use crate::blocks::literals_section::{LiteralsSection, LiteralsSectionType};
+        use crate::decoding::literals_section_decoder::decode_literals;
+        use crate::decoding::scratch::HuffmanScratch;
+        use crate::encoding::bit_writer::BitWriter;
+
+        for len in [1025, 20_000, 100_000] {
+            // Skewed enough for the huffman coding to pay off
+            let literals: Vec<u8> = (0..len as u32)
+                .map(|i| b'a' + (i % 7 * (i % 3)) as u8)
+                .collect();
+            let mut output = Vec::new();
+            let mut writer = BitWriter::from(&mut output);
+            compress_literals(&literals, 11, &mut writer);
+            writer.flush();
+
+            let mut section = LiteralsSection::new();
+            let header_len = section.parse_from_header(&output).unwrap() as usize;
+            assert!(matches!(section.ls_type, LiteralsSectionType::Compressed));
+            assert_eq!(section.num_streams, Some(4));
+            assert_eq!(section.regenerated_size, len as u32);
+
+            let mut decoded = Vec::new();
+            let read = decode_literals(
+                &section,
+                &mut HuffmanScratch::new(),
+                &output[header_len..],
+                &mut decoded,
+                128 * 1024,
+            )
+            .unwrap();
+            assert_eq!(header_len + read as usize, output.len());
+            assert_eq!(decoded, literals);
+        }
+    }
+
+    #[test]
+    fn repeat_chosen_for_stable_statistics() {
+        let default = default_ll_table();
+        // Skewed distribution that the predefined table does not fit well
+        let codes: Vec<u8> = (0..2000u32)
+            .map(|i| match i % 10 {
+                0..=5 => 20,
+                6..=8 => 21,
+                _ => 3,
+            })
+            .collect();
+
+        let mut candidate = FSETable::empty();
+        let first = choose_table(&codes, LL_MAX_LOG, &default, None, &mut candidate);
+        assert!(
+            matches!(first, TableChoice::Encoded),
+            "Expected a new table for the first block, got {:?}",
+            first
+        );
+        let previous = core::mem::replace(&mut candidate, FSETable::empty());
+
+        // The next block has the same statistics, so the table of the previous block is just as good
+        let second = choose_table(
+            &codes,
+            LL_MAX_LOG,
+            &default,
+            Some(&previous),
+            &mut candidate,
+        );
+        assert!(matches!(second, TableChoice::Repeat), "{:?}", second);
+
+        // A symbol the previous table can't encode forces a new decision
+        let mut codes = codes;
+        codes.push(30);
+        let third = choose_table(
+            &codes,
+            LL_MAX_LOG,
+            &default,
+            Some(&previous),
+            &mut candidate,
+        );
+        assert!(!matches!(third, TableChoice::Repeat), "{:?}", third);
+    }
+
+    #[test]
+    fn trailing_literals_follow_last_sequence() {
+        use crate::decoding::errors::ExtractBlockError;
+        use crate::decoding::{decode_block_sequences, extract_block, BlockScratch, BlockType};
+        use crate::encoding::{compress_to_vec, CompressionLevel};
+
+        let mut data = include_bytes!("../../../decodecorpus_files/z000033")[..200 * 1024].to_vec();
+        // End the data with bytes that can't be part of a match
+        data.extend_from_slice(&[0xF1, 0x03, 0xB7, 0x5E, 0x88, 0x21, 0xD9, 0x0C]);
+        let compressed = compress_to_vec(data.as_slice(), CompressionLevel::Fastest);
+
+        let mut scratch = BlockScratch::new();
+        let mut block_start = 0;
+        let mut index = 0;
+        loop {
+            let (header, raw) = match extract_block(compressed.as_slice(), index) {
+                Ok(block) => block,
+                Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+                Err(e) => panic!("{:?}", e),
+            };
+            assert_eq!(header.block_type, BlockType::Compressed);
+            let (literals, sequences) =
+                decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+            assert!(!sequences.is_empty());
+
+            // The literals that are not consumed by a sequence end the block
+            let sequence_literals: usize = sequences.iter().map(|seq| seq.ll as usize).sum();
+            let match_len: usize = sequences.iter().map(|seq| seq.ml as usize).sum();
+            let trailing = &literals[sequence_literals..];
+            let block_end = block_start + literals.len() + match_len;
+            assert_eq!(trailing, &data[block_end - trailing.len()..block_end]);
+
+            if header.last_block {
+                assert_eq!(block_end, data.len());
+                assert!(trailing.ends_with(&data[data.len() - 8..]));
+            } else {
+                assert_eq!(block_end - block_start, 128 * 1024);
+            }
+            block_start = block_end;
+            index += 1;
+        }
+        assert_eq!(index, 2);
+
+        let mut decoded = Vec::with_capacity(data.len());
+        crate::decoding::FrameDecoder::new()
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn rle_and_predefined_chosen() {
+        let default = default_ll_table();
+        let mut candidate = FSETable::empty();
+        let codes = [7u8; 100];
+        let choice = choose_table(&codes, LL_MAX_LOG, &default, None, &mut candidate);
+        assert!(matches!(choice, TableChoice::Rle(7)), "{:?}", choice);
+
+        // Very few sequences do not justify the cost of a table description
+        let codes = [0u8, 1, 2];
+        let choice = choose_table(&codes, LL_MAX_LOG, &default, None, &mut candidate);
+        assert!(matches!(choice, TableChoice::Predefined), "{:?}", choice);
+    }
+
+    #[test]
+    fn max_log_limits_table_size() {
+        let default = default_ll_table();
+        // Many symbols with very uneven probabilities benefit from a high accuracy log
+        let codes: Vec<u8> = (0..20000u32)
+            .map(|i| if i % 4 == 0 { (i / 4 % 30) as u8 } else { 35 })
+            .collect();
+
+        let mut candidate = FSETable::empty();
+        let mut acc_logs = Vec::new();
+        for max_log in [LL_MAX_LOG, 6, 1] {
+            let choice = choose_table(&codes, max_log, &default, None, &mut candidate);
+            assert!(
+                matches!(choice, TableChoice::Encoded),
+                "Expected a new table for {}, got {:?}",
+                max_log,
+                choice
+            );
+            acc_logs.push(candidate.acc_log());
+        }
+        assert_eq!(acc_logs[0], LL_MAX_LOG);
+        assert_eq!(acc_logs[1], 6);
+        // 30 different symbols still need at least 32 slots
+        assert_eq!(acc_logs[2], 5);
+    }
+
+    #[test]
+    fn length_codes_match_decoder() {
+        for len in 0..131072 {
+            let (code, value, bits) = encode_literal_length(len);
+            let (base, num_bits) = lookup_ll_code(code);
+            assert_eq!(usize::from(num_bits), bits, "literal length {}", len);
+            assert!(u64::from(value) < 1 << bits, "literal length {}", len);
+            assert_eq!(base + value, len, "literal length {}", len);
+        }
+        for len in 3..=131074 {
+            let (code, value, bits) = encode_match_len(len);
+            let (base, num_bits) = lookup_ml_code(code);
+            assert_eq!(usize::from(num_bits), bits, "match length {}", len);
+            assert!(u64::from(value) < 1 << bits, "match length {}", len);
+            assert_eq!(base + value, len, "match length {}", len);
+        }
+    }
+
+    #[test]
+    fn offset_values() {
+        // (offset, literals length, offset value), starting from the initial repeat offsets
+        let cases = [
+            (1, 5, 1),
+            (4, 5, 2),
+            (8, 5, 3),
+            (7, 5, 10),
+            (4, 0, 1),
+            (8, 0, 2),
+            (1, 0, 4),
+        ];
+        for (offset, lit_len, value) in cases {
+            let mut offset_hist = [1, 4, 8];
+            assert_eq!(offset_value(offset, lit_len, &mut offset_hist), value);
+        }
+        let mut offset_hist = [5, 4, 8];
+        assert_eq!(offset_value(4, 0, &mut offset_hist), 1);
+        assert_eq!(offset_hist, [4, 5, 8]);
+        assert_eq!(offset_value(3, 0, &mut offset_hist), 3);
+        assert_eq!(offset_hist, [3, 4, 5]);
+        assert_eq!(offset_value(5, 2, &mut offset_hist), 3);
+        assert_eq!(offset_hist, [5, 3, 4]);
+    }
+
+    #[test]
+    fn repeat_offsets_round_trip() {
+        use crate::decoding::errors::ExtractBlockError;
+        use crate::decoding::{decode_block_sequences, extract_block, BlockScratch, BlockType};
+        use crate::encoding::{compress_to_vec, CompressionLevel};
+        use alloc::format;
+        use rand::{RngCore, SeedableRng};
+
+        // Records of the same layout, with a block of random data in between that is stored raw
+        let records = |ids: core::ops::Range<u32>| {
+            let mut records = Vec::new();
+            for i in ids {
+                let record = format!(
+                    "{{\"id\":{},\"name\":\"user{}\",\"group\":{}}}\n",
+                    i,
+                    i.wrapping_mul(2_654_435_761) % 1000,
+                    i % 7
+                );
+                records.extend_from_slice(record.as_bytes());
+            }
+            records
+        };
+        let mut random = alloc::vec![0u8; 128 * 1024];
+        rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut random);
+        let mut data = records(0..3_000);
+        data.resize(128 * 1024, b' ');
+        data.extend_from_slice(&random);
+        data.extend_from_slice(&records(3_000..5_000));
+
+        for level in [CompressionLevel::Fastest, CompressionLevel::Default] {
+            let compressed = compress_to_vec(data.as_slice(), level);
+
+            let mut scratch = BlockScratch::new();
+            let mut block_types = Vec::new();
+            let mut repeat_offsets = 0;
+            let mut index = 0;
+            loop {
+                let (header, raw) = match extract_block(compressed.as_slice(), index) {
+                    Ok(block) => block,
+                    Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+                    Err(e) => panic!("{:?}", e),
+                };
+                let (_, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+                repeat_offsets += sequences.iter().filter(|seq| seq.of <= 3).count();
+                block_types.push(header.block_type);
+                index += 1;
+            }
+            assert_eq!(
+                block_types,
+                [BlockType::Compressed, BlockType::Raw, BlockType::Compressed]
+            );
+            assert!(repeat_offsets > 500, "{}", repeat_offsets);
+
+            let mut decoded = Vec::with_capacity(data.len());
+            crate::decoding::FrameDecoder::new()
+                .decode_all_to_vec(&compressed, &mut decoded)
+                .unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(zstd::decode_all(compressed.as_slice()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn discarded_block_keeps_offsets() {
+        use crate::encoding::match_generator::MatchGeneratorDriver;
+        use crate::encoding::scratch::EncoderScratch;
+        use crate::encoding::{CompressionLevel, EncoderParams, MatchStats, Matcher};
+
+        let mut matcher = MatchGeneratorDriver::new(1024, 4);
+        matcher.reset(CompressionLevel::Fastest);
+        let mut scratch = EncoderScratch::new();
+        scratch.begin_frame();
+        let compress =
+            |matcher: &mut MatchGeneratorDriver, scratch: &mut EncoderScratch, data: &[u8]| {
+                matcher.commit_space(data.to_vec());
+                let mut output = Vec::new();
+                super::compress_block(
+                    matcher,
+                    scratch,
+                    &EncoderParams::default(),
+                    &mut MatchStats::default(),
+                    &mut output,
+                );
+            };
+
+        compress(&mut matcher, &mut scratch, b"abcdefgh-abcdefgh-abcdefgh");
+        let offset_hist = scratch.offset_hist;
+        assert_eq!(offset_hist, [9, 1, 4]);
+        compress(&mut matcher, &mut scratch, b"0123456789-01234567-xyz");
+        assert_ne!(scratch.offset_hist, offset_hist);
+        // The block is stored raw, so the decoder does not see its offsets
+        scratch.discard_block();
+        assert_eq!(scratch.offset_hist, offset_hist);
+    }
+}
@@ -6,3 +6,4 @@
 mod compressed;
 
 pub(super) use compressed::*;
+pub(crate) use compressed::{encode_literal_length, encode_match_len, encode_offset};
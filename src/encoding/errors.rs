@@ -0,0 +1,115 @@
+//! Errors that might occur while encoding data into the zstd format
+
+use core::fmt;
+
+use crate::encoding::CompressionLevel;
+use crate::io::Error;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CompressError {
+    /// Decoding the produced frame did not result in the original input.
+    /// Only returned if verification was enabled with [crate::encoding::FrameCompressor::set_verify].
+    VerificationFailed,
+    /// The compressed frame did not fit into the target buffer of [crate::encoding::compress_into],
+    /// or into the budget of [crate::encoding::compress_to_budget] with any level.
+    /// [crate::encoding::compress_bound] gives a target size that is always big enough.
+    TargetTooSmall { needed: usize, available: usize },
+    /// The source ended before the content size set with [crate::encoding::FrameCompressor::set_content_size] was read.
+    /// The frame header declares the wrong size, so decoders reject the frame.
+    ContentSizeMismatch { declared: u64, actual: u64 },
+    /// Reading from the source failed. The frame is incomplete, the blocks before the error may have been written to the drain.
+    ReadError(Error),
+    /// Writing to the drain failed. The frame is incomplete.
+    WriteError(Error),
+    /// The literals and sequences given to [crate::encoding::encode_block_from_sequences] do not form a valid block.
+    /// Nothing was written.
+    InvalidSequences(InvalidSequencesError),
+    /// The compression level is not implemented yet. Nothing was written.
+    UnsupportedLevel(CompressionLevel),
+}
+
+/// Why the literals and sequences given to [crate::encoding::encode_block_from_sequences] do not form a valid block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidSequencesError {
+    /// The sequence at `index` has a match shorter than 3 bytes
+    MatchTooShort { index: usize, ml: u32 },
+    /// The sequence at `index` has the offset value 0
+    ZeroOffset { index: usize },
+    /// The sequences need more literals than there are
+    MissingLiterals { needed: usize, available: usize },
+    /// The block would decode to more than 128kb
+    BlockTooBig { size: usize },
+}
+
+impl fmt::Display for InvalidSequencesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidSequencesError::MatchTooShort { index, ml } => write!(
+                f,
+                "The match length {} of sequence {} is shorter than 3",
+                ml, index
+            ),
+            InvalidSequencesError::ZeroOffset { index } => {
+                write!(f, "Sequence {} has the invalid offset value 0", index)
+            }
+            InvalidSequencesError::MissingLiterals { needed, available } => write!(
+                f,
+                "The sequences need {} literals but there are only {}",
+                needed, available
+            ),
+            InvalidSequencesError::BlockTooBig { size } => {
+                write!(f, "The block decodes to {} bytes, more than 128kb", size)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidSequencesError {}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressError::VerificationFailed => write!(
+                f,
+                "Decoding the compressed frame did not reproduce the original input"
+            ),
+            CompressError::TargetTooSmall { needed, available } => write!(
+                f,
+                "The compressed frame needs {} bytes but the target only has {} bytes",
+                needed, available
+            ),
+            CompressError::ContentSizeMismatch { declared, actual } => write!(
+                f,
+                "The frame header declares {} bytes of content but the source only had {} bytes",
+                declared, actual
+            ),
+            CompressError::ReadError(e) => write!(f, "Error while reading from the source: {}", e),
+            CompressError::WriteError(e) => write!(f, "Error while writing to the drain: {}", e),
+            CompressError::InvalidSequences(e) => write!(f, "Invalid sequences: {}", e),
+            CompressError::UnsupportedLevel(level) => {
+                write!(f, "The compression level {:?} is not implemented", level)
+            }
+        }
+    }
+}
+
+impl From<InvalidSequencesError> for CompressError {
+    fn from(val: InvalidSequencesError) -> Self {
+        Self::InvalidSequences(val)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompressError::ReadError(source) => Some(source),
+            CompressError::WriteError(source) => Some(source),
+            CompressError::InvalidSequences(source) => Some(source),
+            _ => None,
+        }
+    }
+}
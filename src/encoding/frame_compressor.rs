@@ -1,17 +1,28 @@
 //! Utilities and interfaces for encoding an entire frame. Allows reusing resources
 
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::convert::TryInto;
+#[cfg(feature = "hash")]
+use core::hash::Hasher;
+use core::num::NonZeroUsize;
 
 use super::{
-    block_header::BlockHeader, blocks::compress_block, frame_header::FrameHeader,
-    match_generator::MatchGeneratorDriver, CompressionLevel, Matcher,
+    block_header::BlockHeader, blocks::compress_block, errors::CompressError,
+    frame_header::FrameHeader, match_generator::MatchGeneratorDriver, scratch::EncoderScratch,
+    CompressionLevel, EncoderParams, Matcher,
 };
+use crate::blocks::block::BlockType;
+use crate::decoding::FrameDecoder;
 
-use crate::io::{Read, Write};
+use crate::io::{Error, ErrorKind, Read, Write};
 
 /// Blocks cannot be larger than 128KB in size.
 const MAX_BLOCK_SIZE: usize = 128 * 1024 - 20;
+/// The maximum amount of uncompressed data in a block, also the default for [FrameCompressor::set_block_size]
+const MAX_BLOCK_CONTENT_SIZE: usize = 128 * 1024;
+/// A run of a single byte at the start of a block that is at least this long is split off into its own RLE block
+const MIN_RLE_PREFIX_LEN: usize = 1024;
 
 /// An interface for compressing arbitrary data with the ZStandard compression algorithm.
 ///
@@ -30,13 +41,158 @@ const MAX_BLOCK_SIZE: usize = 128 * 1024 - 20;
 /// compressor.set_drain(&mut output);
 ///
 /// // `compress` writes the compressed output into the provided buffer.
-/// compressor.compress();
+/// compressor.compress().unwrap();
 /// ```
 pub struct FrameCompressor<R: Read, W: Write, M: Matcher> {
     uncompressed_data: Option<R>,
     compressed_data: Option<W>,
     compression_level: CompressionLevel,
     match_generator: M,
+    scratch: EncoderScratch,
+    stats: CompressStats,
+    single_segment_if_single_block: bool,
+    /// The content size set for the next frame
+    content_size: Option<u64>,
+    /// The content size declared in the header of the current frame
+    frame_content_size: Option<u64>,
+    #[cfg(feature = "hash")]
+    content_checksum: bool,
+    /// Hashes the input of the current frame for the content checksum
+    #[cfg(feature = "hash")]
+    hasher: twox_hash::XxHash64,
+    params: EncoderParams,
+    verify: bool,
+    frame_state: FrameState,
+    magicless: bool,
+    progress: Option<Box<dyn FnMut(ProgressInfo) + Send>>,
+    /// How the blocks produced by the last call to `encode_next_block` were encoded
+    block_reports: Vec<BlockReport>,
+    block_size: usize,
+}
+
+/// How far the compression of the current frame has progressed, passed to the callback set with [FrameCompressor::set_progress]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressInfo {
+    /// How many bytes were read from the source so far
+    pub input_bytes: u64,
+    /// How many bytes of the frame were produced so far, including the frame header
+    pub output_bytes: u64,
+}
+
+/// How a single block was encoded, see [crate::encoding::compressibility_report]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockReport {
+    /// Whether the block was stored as is, as a single repeated byte or compressed
+    pub block_type: BlockType,
+    /// How many bytes of the input the block contains
+    pub input_bytes: u32,
+    /// How many bytes the block takes up in the frame, including the 3 byte block header.
+    /// The frame header is not included.
+    pub output_bytes: u32,
+}
+
+/// Where the compressor is within the frame produced by [FrameCompressor::next_compressed_block]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameState {
+    /// No frame has been started
+    Idle,
+    /// A frame has been started but the frame header has not been written yet
+    HeaderPending,
+    /// The frame header has been written and more blocks follow
+    Blocks,
+    /// The last block of the frame has been written
+    Finished,
+}
+
+/// Statistics about the last frame compressed with [FrameCompressor::compress]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressStats {
+    /// How many bytes were read from the source
+    pub input_bytes: u64,
+    /// How many bytes were written to the drain, including the frame header
+    pub output_bytes: u64,
+    /// How long the call to [FrameCompressor::compress] took
+    #[cfg(feature = "std")]
+    pub duration: std::time::Duration,
+    /// What the matcher found in the input
+    pub matches: MatchStats,
+}
+
+impl CompressStats {
+    /// The compression ratio `input_bytes / output_bytes`, e.g. 3.0 if the output is a third of the input.
+    ///
+    /// Returns 0 if nothing was written yet, like before the first call to [FrameCompressor::compress].
+    pub fn ratio(&self) -> f64 {
+        if self.output_bytes == 0 {
+            0.0
+        } else {
+            self.input_bytes as f64 / self.output_bytes as f64
+        }
+    }
+}
+
+/// Statistics about the sequences the [Matcher] produced for the last frame, to help tuning the compression.
+///
+/// Only blocks that went through the matcher are counted, which excludes blocks at [CompressionLevel::Uncompressed]
+/// and blocks that consist of a single repeated byte. Blocks that ended up stored uncompressed because
+/// compressing them did not save space are still counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    /// How many matches were found
+    pub matches: u64,
+    /// How many bytes are covered by matches
+    pub match_bytes: u64,
+    /// How many bytes are not covered by a match and are encoded as literals
+    pub literal_bytes: u64,
+    /// `match_length_histogram[i]` counts the matches with a length in `2^i..2^(i+1)`
+    pub match_length_histogram: [u64; 32],
+    /// `offset_histogram[i]` counts the matches with an offset in `2^i..2^(i+1)`
+    pub offset_histogram: [u64; 32],
+}
+
+impl MatchStats {
+    /// The average length of the matches, 0 if no matches were found
+    pub fn average_match_len(&self) -> f64 {
+        if self.matches == 0 {
+            0.0
+        } else {
+            self.match_bytes as f64 / self.matches as f64
+        }
+    }
+
+    pub(crate) fn record_literals(&mut self, len: usize) {
+        self.literal_bytes += len as u64;
+    }
+
+    pub(crate) fn record_match(&mut self, match_len: usize, offset: usize) {
+        self.matches += 1;
+        self.match_bytes += match_len as u64;
+        self.match_length_histogram[match_len.ilog2() as usize] += 1;
+        self.offset_histogram[offset.ilog2().min(31) as usize] += 1;
+    }
+}
+
+impl<R: Read, W: Write, M: Matcher> core::fmt::Debug for FrameCompressor<R, W, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FrameCompressor")
+            .field("compression_level", &self.compression_level)
+            .field("window_size", &self.match_generator.window_size())
+            .field("params", &self.params)
+            .field(
+                "single_segment_if_single_block",
+                &self.single_segment_if_single_block,
+            )
+            .field("content_size", &self.content_size)
+            .field("content_checksum", &self.content_checksum())
+            .field("verify", &self.verify)
+            .field("magicless", &self.magicless)
+            .field("block_size", &self.block_size)
+            .field("has_progress", &self.progress.is_some())
+            .field("has_source", &self.uncompressed_data.is_some())
+            .field("has_drain", &self.compressed_data.is_some())
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<R: Read, W: Write> FrameCompressor<R, W, MatchGeneratorDriver> {
@@ -47,8 +203,31 @@ impl<R: Read, W: Write> FrameCompressor<R, W, MatchGeneratorDriver> {
             compressed_data: None,
             compression_level,
             match_generator: MatchGeneratorDriver::new(1024 * 128, 1),
+            scratch: EncoderScratch::new(),
+            stats: CompressStats::default(),
+            single_segment_if_single_block: false,
+            content_size: None,
+            frame_content_size: None,
+            #[cfg(feature = "hash")]
+            content_checksum: false,
+            #[cfg(feature = "hash")]
+            hasher: twox_hash::XxHash64::with_seed(0),
+            params: EncoderParams::default(),
+            verify: false,
+            frame_state: FrameState::Idle,
+            magicless: false,
+            progress: None,
+            block_reports: Vec::new(),
+            block_size: MAX_BLOCK_CONTENT_SIZE,
         }
     }
+
+    /// Only insert every n-th position of the input into the match finder, trading compression ratio for speed.
+    ///
+    /// By default the stride is chosen based on the compression level. Passing `None` goes back to that default.
+    pub fn set_suffix_stride(&mut self, suffix_stride: Option<NonZeroUsize>) {
+        self.match_generator.set_suffix_stride(suffix_stride);
+    }
 }
 
 impl<R: Read, W: Write, M: Matcher> FrameCompressor<R, W, M> {
@@ -59,6 +238,22 @@ impl<R: Read, W: Write, M: Matcher> FrameCompressor<R, W, M> {
             compressed_data: None,
             match_generator: matcher,
             compression_level,
+            scratch: EncoderScratch::new(),
+            stats: CompressStats::default(),
+            single_segment_if_single_block: false,
+            content_size: None,
+            frame_content_size: None,
+            #[cfg(feature = "hash")]
+            content_checksum: false,
+            #[cfg(feature = "hash")]
+            hasher: twox_hash::XxHash64::with_seed(0),
+            params: EncoderParams::default(),
+            verify: false,
+            frame_state: FrameState::Idle,
+            magicless: false,
+            progress: None,
+            block_reports: Vec::new(),
+            block_size: MAX_BLOCK_CONTENT_SIZE,
         }
     }
 
@@ -72,120 +267,583 @@ impl<R: Read, W: Write, M: Matcher> FrameCompressor<R, W, M> {
         self.compressed_data.replace(compressed_data)
     }
 
+    /// Set the parameters used for the following frames
+    pub fn set_params(&mut self, params: EncoderParams) {
+        self.params = params;
+    }
+
+    /// The parameters used for compression
+    pub fn params(&self) -> EncoderParams {
+        self.params
+    }
+
+    /// If the whole input turns out to fit into a single block, write a single segment frame header
+    /// that contains the content size instead of a window descriptor. Disabled by default.
+    ///
+    /// The compressed first block is held back until the next read on the source either
+    /// returns more data or signals the end of the input. So enabling this costs at most one
+    /// compressed block (~128kB) of buffering, which the compressor already uses for its output buffer,
+    /// but it does delay the first write to the drain by one read call.
+    pub fn set_single_segment_if_single_block(&mut self, enabled: bool) {
+        self.single_segment_if_single_block = enabled;
+    }
+
+    /// Put at most `block_size` bytes of the input into each block. The default and maximum is 128KB,
+    /// bigger sizes are limited to that.
+    ///
+    /// Smaller blocks compress worse, because each block has its own header and entropy tables,
+    /// but they let decoders hand out data earlier. Tiny block sizes are mostly useful to produce frames with
+    /// many blocks from little data, for example to test decoders.
+    /// [crate::encoding::compress_bound] assumes the default block size.
+    pub fn set_block_size(&mut self, block_size: NonZeroUsize) {
+        self.block_size = block_size.get().min(MAX_BLOCK_CONTENT_SIZE);
+    }
+
+    /// Declare the size of the input of the next frame, so it is written into the frame header.
+    ///
+    /// Decoders can then allocate the whole output up front. With the builtin matcher, inputs of up to 1MB
+    /// are written as a single segment frame, which lets decoders skip the window handling. The decoder keeps the whole content
+    /// of such a frame, so the matcher finds matches anywhere in the input, even further back than the window of the compression level.
+    /// The window is not grown beyond what [EncoderParams::max_memory] allows.
+    /// The size only applies to the next frame, so it needs to be set again before each frame.
+    ///
+    /// No more than `content_size` bytes are read from the source for that frame, anything after them stays in the source.
+    /// If the source ends before `content_size` bytes were read the frame is invalid, and [FrameCompressor::compress]
+    /// returns [CompressError::ContentSizeMismatch]. Unless [FrameCompressor::set_verify] is enabled, the frame has
+    /// already been written to the drain by then.
+    pub fn set_content_size(&mut self, content_size: Option<u64>) {
+        self.content_size = content_size;
+    }
+
+    /// Leave out the 4 byte magic number at the start of each frame, like the magicless format of the reference
+    /// implementation. Disabled by default.
+    ///
+    /// This saves 4 bytes per frame for formats that embed zstd frames and know where they are. The frames can only
+    /// be decoded by decoders that expect them to be magicless, for example a [FrameDecoder] with
+    /// [FrameDecoder::set_magicless] enabled.
+    pub fn set_magicless(&mut self, magicless: bool) {
+        self.magicless = magicless;
+    }
+
+    /// Append a checksum of the uncompressed content to each frame, so decoders can detect corrupted data. Disabled by default.
+    ///
+    /// The checksum is the lower 32 bits of the XxHash64 of the input. Hashing the input takes a little time
+    /// and the checksum adds 4 bytes to each frame.
+    #[cfg(feature = "hash")]
+    pub fn set_content_checksum(&mut self, content_checksum: bool) {
+        self.content_checksum = content_checksum;
+    }
+
+    /// Whether a content checksum is appended to the frames
+    fn content_checksum(&self) -> bool {
+        #[cfg(feature = "hash")]
+        {
+            self.content_checksum || self.params.content_checksum
+        }
+        #[cfg(not(feature = "hash"))]
+        {
+            false
+        }
+    }
+
+    /// The number of bytes a frame with `input_len` bytes of content written by this compressor takes up in addition to the block content.
+    ///
+    /// Like [crate::encoding::frame_overhead], but with the block size, the magic number, the single segment header and the checksum
+    /// set on this compressor. A content size declared with [FrameCompressor::set_content_size] is not accounted for.
+    ///
+    /// ```
+    /// use ruzstd::encoding::{CompressionLevel, FrameCompressor};
+    /// let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    /// let mut compressor = FrameCompressor::new(CompressionLevel::Uncompressed);
+    /// compressor.set_magicless(true);
+    /// compressor.set_source(&data[..]);
+    /// compressor.set_drain(std::vec::Vec::new());
+    /// compressor.compress().unwrap();
+    /// assert_eq!(compressor.drain().unwrap().len(), data.len() + compressor.frame_overhead(10));
+    /// ```
+    pub fn frame_overhead(&self, input_len: u64) -> usize {
+        super::framing_size(
+            input_len,
+            self.block_size as u64,
+            self.single_segment_if_single_block || self.params.content_size,
+            self.content_checksum(),
+            self.magicless,
+        )
+    }
+
+    /// Decode each compressed frame again and compare it to the input before writing it to the drain.
+    /// Disabled by default.
+    ///
+    /// If the decoded data differs from the input, [FrameCompressor::compress] returns
+    /// [CompressError::VerificationFailed] and nothing is written to the drain.
+    ///
+    /// This catches encoder bugs before corrupted data is stored somewhere, but it is expensive:
+    /// Both the whole input and the whole compressed frame are kept in memory until the frame has been verified,
+    /// and decoding it again roughly doubles the time spent in [FrameCompressor::compress].
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Call `progress` after each block that has been compressed, with the number of bytes read and produced so far in the current frame.
+    ///
+    /// This works for any source, unlike wrapping the source to count the bytes read from it, and it also reports the output size.
+    /// The callback is called from [FrameCompressor::compress] and [FrameCompressor::next_compressed_block].
+    /// With [FrameCompressor::set_verify] enabled, the output is written to the drain only after the last callback.
+    pub fn set_progress(&mut self, progress: impl FnMut(ProgressInfo) + Send + 'static) {
+        self.progress = Some(Box::new(progress));
+    }
+
+    /// Stop calling the callback set with [FrameCompressor::set_progress]
+    pub fn clear_progress(&mut self) {
+        self.progress = None;
+    }
+
     /// Compress the uncompressed data from the provided source as one Zstd frame and write it to the provided drain
     ///
     /// This will repeatedly call [Read::read] on the source to fill up blocks until the source returns 0 on the read call.
     /// Also [Write::write_all] will be called on the drain after each block has been encoded.
+    /// If either fails, compression stops and the error is returned as [CompressError::ReadError] or [CompressError::WriteError].
+    /// Reads that fail with [ErrorKind::Interrupted] are retried.
     ///
     /// To avoid endlessly encoding from a potentially endless source (like a network socket) you can use the
     /// [Read::take] function
-    pub fn compress(&mut self) {
-        self.match_generator.reset(self.compression_level);
-        let source = self.uncompressed_data.as_mut().unwrap();
-        let drain = self.compressed_data.as_mut().unwrap();
+    ///
+    /// The output only depends on the input data and the settings of the compressor. Compressing the same data
+    /// with the same settings produces byte-identical frames, regardless of what the compressor was used for before.
+    ///
+    /// A frame that was partially produced with [FrameCompressor::next_compressed_block] is abandoned and a new one is started.
+    pub fn compress(&mut self) -> Result<(), CompressError> {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        self.begin_frame()?;
 
         let mut output = Vec::with_capacity(1024 * 130);
-        let output = &mut output;
-        let header = FrameHeader {
-            frame_content_size: None,
-            single_segment: false,
-            content_checksum: false,
-            dictionary_id: None,
-            window_size: Some(self.match_generator.window_size()),
-        };
-        header.serialize(output);
+        // With verification enabled the input and the whole frame are collected here instead of writing to the drain
+        let mut verification: Option<(Vec<u8>, Vec<u8>)> =
+            self.verify.then(|| (Vec::new(), Vec::new()));
 
         loop {
-            let mut uncompressed_data = self.match_generator.get_next_space();
-            let mut read_bytes = 0;
-            let last_block;
-            'read_loop: loop {
-                let new_bytes = source.read(&mut uncompressed_data[read_bytes..]).unwrap();
-                if new_bytes == 0 {
-                    last_block = true;
-                    break 'read_loop;
+            let last_block = match self.encode_next_block(
+                &mut output,
+                verification.as_mut().map(|(input, _)| input),
+                true,
+            ) {
+                Ok(last_block) => last_block,
+                Err(e) => return Err(self.abandon_frame(e)),
+            };
+            match &mut verification {
+                Some((_, frame)) => frame.extend_from_slice(&output),
+                None => {
+                    let drain = self.compressed_data.as_mut().unwrap();
+                    if let Err(e) = drain.write_all(&output) {
+                        self.frame_state = FrameState::Idle;
+                        return Err(CompressError::WriteError(e));
+                    }
                 }
-                read_bytes += new_bytes;
-                if read_bytes == uncompressed_data.len() {
-                    last_block = false;
-                    break 'read_loop;
+            }
+            output.clear();
+            if last_block {
+                break;
+            }
+        }
+        self.frame_state = FrameState::Idle;
+        if let Some(declared) = self.frame_content_size {
+            if declared != self.stats.input_bytes {
+                return Err(CompressError::ContentSizeMismatch {
+                    declared,
+                    actual: self.stats.input_bytes,
+                });
+            }
+        }
+
+        if let Some((input, frame)) = verification {
+            let mut decoded = Vec::with_capacity(input.len());
+            let mut decoder = FrameDecoder::new();
+            decoder.set_magicless(self.magicless);
+            let decoded_ok = decoder.decode_all_to_vec(&frame, &mut decoded).is_ok();
+            if !decoded_ok || decoded != input {
+                return Err(CompressError::VerificationFailed);
+            }
+            self.compressed_data
+                .as_mut()
+                .unwrap()
+                .write_all(&frame)
+                .map_err(CompressError::WriteError)?;
+        }
+        #[cfg(feature = "std")]
+        {
+            self.stats.duration = start.elapsed();
+        }
+        Ok(())
+    }
+
+    /// Produce the next part of a frame compressed from the source, without using the drain.
+    ///
+    /// The first call starts a new frame and returns the frame header together with the first block.
+    /// Each following call reads just enough from the source to fill one block and returns that block with its header.
+    /// After the last block of the frame has been returned, this returns `None` once. The call after that starts the next frame.
+    ///
+    /// Concatenating all returned parts of a frame gives the same bytes [FrameCompressor::compress] would have written
+    /// to the drain. Unlike [FrameCompressor::compress] the frame is not verified, even if [FrameCompressor::set_verify] is enabled,
+    /// and [CompressStats] does not include a duration for it.
+    ///
+    /// If reading from the source fails, the partially produced frame is abandoned and the next call starts a new one.
+    ///
+    /// ```
+    /// use ruzstd::encoding::{CompressionLevel, FrameCompressor};
+    /// let data = std::vec![42u8; 1000];
+    /// let mut compressor: FrameCompressor<&[u8], std::vec::Vec<u8>, _> =
+    ///     FrameCompressor::new(CompressionLevel::Fastest);
+    /// compressor.set_source(data.as_slice());
+    ///
+    /// let mut frame = std::vec::Vec::new();
+    /// while let Some(block) = compressor.next_compressed_block().unwrap() {
+    ///     frame.extend_from_slice(&block);
+    /// }
+    /// let mut decoded = std::vec::Vec::with_capacity(data.len());
+    /// ruzstd::decoding::FrameDecoder::new().decode_all_to_vec(&frame, &mut decoded).unwrap();
+    /// assert_eq!(decoded, data);
+    /// ```
+    pub fn next_compressed_block(&mut self) -> Result<Option<Vec<u8>>, CompressError> {
+        match self.frame_state {
+            FrameState::Finished => {
+                self.frame_state = FrameState::Idle;
+                return Ok(None);
+            }
+            FrameState::Idle => self.begin_frame()?,
+            FrameState::HeaderPending | FrameState::Blocks => {}
+        }
+        let mut output = Vec::new();
+        self.encode_next_block(&mut output, None, true)
+            .map_err(|e| self.abandon_frame(e))?;
+        Ok(Some(output))
+    }
+
+    /// Like [FrameCompressor::next_compressed_block], but the end of the source only ends the block, not the frame.
+    ///
+    /// Used to push out all data that is available so far while more data might follow later.
+    /// The source must not be empty.
+    pub(crate) fn next_partial_block(&mut self) -> Result<Vec<u8>, CompressError> {
+        if self.frame_state == FrameState::Idle {
+            self.begin_frame()?;
+        }
+        let mut output = Vec::new();
+        self.encode_next_block(&mut output, None, false)
+            .map_err(|e| self.abandon_frame(e))?;
+        Ok(output)
+    }
+
+    /// Give up on the current frame after reading from the source failed
+    fn abandon_frame(&mut self, e: Error) -> CompressError {
+        self.frame_state = FrameState::Idle;
+        CompressError::ReadError(e)
+    }
+
+    /// Reset all per frame state before encoding the first block of a new frame
+    ///
+    /// Fails without changing anything if the compression level is not implemented.
+    fn begin_frame(&mut self) -> Result<(), CompressError> {
+        if let level @ (CompressionLevel::Better | CompressionLevel::Best) = self.compression_level
+        {
+            return Err(CompressError::UnsupportedLevel(level));
+        }
+        self.stats = CompressStats::default();
+        self.frame_content_size = self.content_size.take();
+        #[cfg(feature = "hash")]
+        {
+            self.hasher = twox_hash::XxHash64::with_seed(0);
+        }
+        self.scratch.begin_frame();
+        self.match_generator.set_max_memory(self.params.max_memory);
+        self.match_generator
+            .set_content_size(self.frame_content_size);
+        self.match_generator.reset(self.compression_level);
+        self.frame_state = FrameState::HeaderPending;
+        Ok(())
+    }
+
+    /// Read the next block from the source and append it, preceded by the frame header if it is the first block, to `output`.
+    ///
+    /// If `input_copy` is given, the uncompressed data of the block is appended to it as well.
+    /// If `source_ends_frame` is false, running out of data in the source does not end the frame.
+    /// Returns whether this was the last block of the frame, or the error if reading from the source failed.
+    fn encode_next_block(
+        &mut self,
+        output: &mut Vec<u8>,
+        input_copy: Option<&mut Vec<u8>>,
+        source_ends_frame: bool,
+    ) -> Result<bool, Error> {
+        let mut output_len_before = output.len();
+        self.block_reports.clear();
+        // With a declared content size the frame ends after that many bytes, without reading further
+        let remaining = self
+            .frame_content_size
+            .map(|size| size.saturating_sub(self.stats.input_bytes));
+        let source = self.uncompressed_data.as_mut().unwrap();
+
+        let mut uncompressed_data = self.match_generator.get_next_space();
+        let space_len = uncompressed_data.len().min(self.block_size);
+        let block_len = match remaining {
+            Some(remaining) => remaining.min(space_len as u64) as usize,
+            None => space_len,
+        };
+        let mut read_bytes = 0;
+        let last_block;
+        'read_loop: loop {
+            if read_bytes == block_len {
+                last_block = remaining == Some(read_bytes as u64);
+                break 'read_loop;
+            }
+            let new_bytes = match source.read(&mut uncompressed_data[read_bytes..block_len]) {
+                Ok(new_bytes) => new_bytes,
+                Err(e) if matches!(e.kind(), ErrorKind::Interrupted) => continue,
+                Err(e) => return Err(e),
+            };
+            if new_bytes == 0 {
+                last_block = source_ends_frame;
+                break 'read_loop;
+            }
+            read_bytes += new_bytes;
+        }
+        uncompressed_data.resize(read_bytes, 0);
+        self.stats.input_bytes += read_bytes as u64;
+        #[cfg(feature = "hash")]
+        if self.content_checksum() {
+            self.hasher.write(&uncompressed_data);
+        }
+        if let Some(input) = input_copy {
+            input.extend_from_slice(&uncompressed_data);
+        }
+
+        // The header is only written once we know whether the first block is also the last one
+        if self.frame_state == FrameState::HeaderPending {
+            let window_size = self.match_generator.window_size();
+            let (frame_content_size, single_segment) = match self.frame_content_size {
+                Some(size) => (Some(size), size <= window_size),
+                None => {
+                    let single_segment = (self.single_segment_if_single_block
+                        || self.params.content_size)
+                        && last_block;
+                    (single_segment.then_some(read_bytes as u64), single_segment)
                 }
+            };
+            let header = FrameHeader {
+                frame_content_size,
+                single_segment,
+                content_checksum: self.content_checksum(),
+                dictionary_id: None,
+                window_size: (!single_segment).then_some(window_size),
+            };
+            if self.magicless {
+                header.serialize_magicless(output);
+            } else {
+                header.serialize(output);
             }
-            uncompressed_data.resize(read_bytes, 0);
+        }
+        self.frame_state = if last_block {
+            FrameState::Finished
+        } else {
+            FrameState::Blocks
+        };
 
-            // Special handling is needed for compression of a totally empty file (why you'd want to do that, I don't know)
-            if uncompressed_data.is_empty() {
+        let mut block_start = output.len();
+        // Special handling is needed for compression of a totally empty file (why you'd want to do that, I don't know)
+        if uncompressed_data.is_empty() {
+            let header = BlockHeader {
+                last_block: true,
+                block_type: BlockType::Raw,
+                block_size: 0,
+            };
+            // Write the header, then the block
+            header.serialize(output);
+            self.finish_block(
+                output,
+                output_len_before,
+                block_start,
+                0,
+                BlockType::Raw,
+                true,
+            );
+            return Ok(true);
+        }
+
+        let block_type;
+        match self.compression_level {
+            CompressionLevel::Uncompressed => {
                 let header = BlockHeader {
-                    last_block: true,
-                    block_type: crate::blocks::block::BlockType::Raw,
-                    block_size: 0,
+                    last_block,
+                    block_type: BlockType::Raw,
+                    block_size: read_bytes.try_into().unwrap(),
                 };
+                block_type = header.block_type;
                 // Write the header, then the block
                 header.serialize(output);
-                drain.write_all(output).unwrap();
-                output.clear();
-                break;
+                output.extend_from_slice(&uncompressed_data);
             }
-
-            match self.compression_level {
-                CompressionLevel::Uncompressed => {
-                    let header = BlockHeader {
-                        last_block,
-                        block_type: crate::blocks::block::BlockType::Raw,
-                        block_size: read_bytes.try_into().unwrap(),
-                    };
-                    // Write the header, then the block
-                    header.serialize(output);
+            CompressionLevel::RleOnly => {
+                let rle = uncompressed_data.iter().all(|x| uncompressed_data[0].eq(x));
+                let header = BlockHeader {
+                    last_block,
+                    block_type: if rle { BlockType::RLE } else { BlockType::Raw },
+                    block_size: read_bytes.try_into().unwrap(),
+                };
+                block_type = header.block_type;
+                // Write the header, then the block
+                header.serialize(output);
+                if rle {
+                    output.push(uncompressed_data[0]);
+                } else {
                     output.extend_from_slice(&uncompressed_data);
                 }
-                CompressionLevel::Fastest => {
-                    if uncompressed_data.iter().all(|x| uncompressed_data[0].eq(x)) {
-                        let rle_byte = uncompressed_data[0];
-                        self.match_generator.commit_space(uncompressed_data);
-                        self.match_generator.skip_matching();
+            }
+            CompressionLevel::Fastest | CompressionLevel::Default => {
+                // A run at the start of the block is encoded as its own RLE block, the rest is compressed as usual.
+                // Runs that reach into the next block become the prefix of that block.
+                let run_len = uncompressed_data
+                    .iter()
+                    .take_while(|x| uncompressed_data[0].eq(x))
+                    .count();
+                let rle = run_len == read_bytes;
+                if run_len >= MIN_RLE_PREFIX_LEN && !rle {
+                    // The matcher only hands out the next space once the current one is committed,
+                    // so the rest is kept aside until the run has been encoded
+                    let rest = uncompressed_data.split_off(run_len);
+                    self.encode_rle_block(output, uncompressed_data, false);
+                    self.finish_block(
+                        output,
+                        output_len_before,
+                        block_start,
+                        run_len,
+                        BlockType::RLE,
+                        false,
+                    );
+                    output_len_before = output.len();
+                    block_start = output.len();
+                    read_bytes -= run_len;
+                    uncompressed_data = self.match_generator.get_next_space();
+                    uncompressed_data.clear();
+                    uncompressed_data.extend_from_slice(&rest);
+                }
+                if rle {
+                    self.encode_rle_block(output, uncompressed_data, last_block);
+                    block_type = BlockType::RLE;
+                } else {
+                    let mut compressed = Vec::new();
+                    self.match_generator.commit_space(uncompressed_data);
+                    compress_block(
+                        &mut self.match_generator,
+                        &mut self.scratch,
+                        &self.params,
+                        &mut self.stats.matches,
+                        &mut compressed,
+                    );
+                    if compressed.len() >= MAX_BLOCK_SIZE || compressed.len() >= read_bytes {
+                        // The decoder never sees the tables and offsets of this block
+                        self.scratch.discard_block();
                         let header = BlockHeader {
                             last_block,
-                            block_type: crate::blocks::block::BlockType::RLE,
+                            block_type: BlockType::Raw,
                             block_size: read_bytes.try_into().unwrap(),
                         };
+                        block_type = header.block_type;
                         // Write the header, then the block
                         header.serialize(output);
-                        output.push(rle_byte);
+                        output.extend_from_slice(self.match_generator.get_last_space());
                     } else {
-                        let mut compressed = Vec::new();
-                        self.match_generator.commit_space(uncompressed_data);
-                        compress_block(&mut self.match_generator, &mut compressed);
-                        if compressed.len() >= MAX_BLOCK_SIZE {
-                            let header = BlockHeader {
-                                last_block,
-                                block_type: crate::blocks::block::BlockType::Raw,
-                                block_size: read_bytes.try_into().unwrap(),
-                            };
-                            // Write the header, then the block
-                            header.serialize(output);
-                            output.extend_from_slice(self.match_generator.get_last_space());
-                        } else {
-                            let header = BlockHeader {
-                                last_block,
-                                block_type: crate::blocks::block::BlockType::Compressed,
-                                block_size: (compressed.len()).try_into().unwrap(),
-                            };
-                            // Write the header, then the block
-                            header.serialize(output);
-                            output.extend(compressed);
-                        }
+                        let header = BlockHeader {
+                            last_block,
+                            block_type: BlockType::Compressed,
+                            block_size: (compressed.len()).try_into().unwrap(),
+                        };
+                        block_type = header.block_type;
+                        // Write the header, then the block
+                        header.serialize(output);
+                        output.extend(compressed);
                     }
                 }
-                _ => {
-                    unimplemented!();
-                }
             }
-            drain.write_all(output).unwrap();
-            output.clear();
-            if last_block {
-                break;
+            CompressionLevel::Better | CompressionLevel::Best => {
+                unreachable!("Rejected by begin_frame")
             }
         }
+        self.finish_block(
+            output,
+            output_len_before,
+            block_start,
+            read_bytes,
+            block_type,
+            last_block,
+        );
+        Ok(last_block)
+    }
+
+    /// Hand the space to the matcher without looking for matches and append it to `output` as an RLE block.
+    ///
+    /// All bytes of the space must be the same.
+    fn encode_rle_block(&mut self, output: &mut Vec<u8>, space: Vec<u8>, last_block: bool) {
+        let rle_byte = space[0];
+        let header = BlockHeader {
+            last_block,
+            block_type: BlockType::RLE,
+            block_size: space.len().try_into().unwrap(),
+        };
+        self.match_generator.commit_space(space);
+        self.match_generator.skip_matching();
+        // Write the header, then the block
+        header.serialize(output);
+        output.push(rle_byte);
+    }
+
+    /// Append the content checksum after the last block, update the stats and report the progress after a block has been encoded.
+    ///
+    /// `output_len_before` is the length of the output before the frame header (if any), `block_start` before the block header.
+    // The output is only extended if the checksum is available
+    #[cfg_attr(not(feature = "hash"), allow(clippy::ptr_arg))]
+    fn finish_block(
+        &mut self,
+        output: &mut Vec<u8>,
+        output_len_before: usize,
+        block_start: usize,
+        input_bytes: usize,
+        block_type: BlockType,
+        last_block: bool,
+    ) {
+        // The checksum is not part of the block
+        let block_output_bytes = output.len() - block_start;
+        #[cfg(feature = "hash")]
+        if last_block && self.content_checksum() {
+            // Only the lower 32 bits of the hash are stored
+            let checksum = self.hasher.finish() as u32;
+            output.extend_from_slice(&checksum.to_le_bytes());
+        }
+        #[cfg(not(feature = "hash"))]
+        let _ = last_block;
+        self.stats.output_bytes += (output.len() - output_len_before) as u64;
+        self.block_reports.push(BlockReport {
+            block_type,
+            input_bytes: input_bytes as u32,
+            output_bytes: block_output_bytes as u32,
+        });
+        self.report_progress();
+    }
+
+    /// How the blocks in the last part produced by [FrameCompressor::next_compressed_block] were encoded
+    pub(crate) fn last_block_reports(&self) -> &[BlockReport] {
+        &self.block_reports
+    }
+
+    fn report_progress(&mut self) {
+        if let Some(progress) = &mut self.progress {
+            progress(ProgressInfo {
+                input_bytes: self.stats.input_bytes,
+                output_bytes: self.stats.output_bytes,
+            });
+        }
+    }
+
+    /// Statistics about the last call to [FrameCompressor::compress]
+    pub fn stats(&self) -> CompressStats {
+        self.stats
     }
 
     /// Get a mutable reference to the source
@@ -256,10 +914,46 @@ mod tests {
         compressor.set_source(mock_data);
         compressor.set_drain(&mut output);
 
-        compressor.compress();
+        compressor.compress().unwrap();
         assert!(output.starts_with(&MAGIC_NUM.to_le_bytes()));
     }
 
+    #[test]
+    fn magicless_frames() {
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i % 97) as u8 ^ (i / 1000) as u8)
+            .collect();
+        let compress = |magicless: bool| {
+            let mut output: Vec<u8> = Vec::new();
+            let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+            compressor.set_magicless(magicless);
+            compressor.set_verify(true);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(&mut output);
+            compressor.compress().unwrap();
+            output
+        };
+        let with_magic = compress(false);
+        let magicless = compress(true);
+        assert_eq!(magicless.as_slice(), &with_magic[4..]);
+
+        // Two frames in a row
+        let mut frames = magicless.clone();
+        frames.extend_from_slice(&magicless);
+        let mut decoder = FrameDecoder::new();
+        decoder.set_magicless(true);
+        let mut decoded = Vec::with_capacity(2 * data.len());
+        decoder.decode_all_to_vec(&frames, &mut decoded).unwrap();
+        assert_eq!(decoded.len(), 2 * data.len());
+        assert_eq!(&decoded[..data.len()], data.as_slice());
+        assert_eq!(&decoded[data.len()..], data.as_slice());
+
+        let mut decoded = Vec::with_capacity(data.len());
+        assert!(FrameDecoder::new()
+            .decode_all_to_vec(&magicless, &mut decoded)
+            .is_err());
+    }
+
     #[test]
     fn very_simple_raw_compress() {
         let mock_data = [1_u8, 2, 3].as_slice();
@@ -268,7 +962,7 @@ mod tests {
         compressor.set_source(mock_data);
         compressor.set_drain(&mut output);
 
-        compressor.compress();
+        compressor.compress().unwrap();
     }
 
     #[test]
@@ -283,7 +977,7 @@ mod tests {
         compressor.set_source(mock_data.as_slice());
         compressor.set_drain(&mut output);
 
-        compressor.compress();
+        compressor.compress().unwrap();
 
         let mut decoder = FrameDecoder::new();
         let mut decoded = Vec::with_capacity(mock_data.len());
@@ -303,7 +997,7 @@ mod tests {
         compressor.set_source(mock_data.as_slice());
         compressor.set_drain(&mut output);
 
-        compressor.compress();
+        compressor.compress().unwrap();
 
         let mut decoder = FrameDecoder::new();
         let mut decoded = Vec::with_capacity(mock_data.len());
@@ -311,6 +1005,1219 @@ mod tests {
         assert_eq!(mock_data, decoded);
     }
 
+    #[test]
+    fn multi_block_compress() {
+        use crate::blocks::block::BlockType;
+        use crate::decoding::BlockDecodingStrategy;
+
+        // Pseudo random data that does not compress on its own, repeated so each block mostly consists
+        // of one match that is longer than 65538 bytes
+        let mut state = 0x1234_5678u32;
+        let chunk: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let mut mock_data = Vec::new();
+        for _ in 0..40 {
+            mock_data.extend_from_slice(&chunk);
+        }
+        let mut output: Vec<u8> = Vec::new();
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(mock_data.as_slice());
+        compressor.set_drain(&mut output);
+        compressor.compress().unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        let mut source = output.as_slice();
+        decoder.reset(&mut source).unwrap();
+        let mut decoded = Vec::new();
+        let mut compressed_blocks = 0;
+        while !decoder.is_finished() {
+            decoder
+                .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                .unwrap();
+            let stats = decoder.last_block_stats().unwrap();
+            if stats.block_type == BlockType::Compressed {
+                compressed_blocks += 1;
+            }
+            decoder.collect_to_writer(&mut decoded).unwrap();
+        }
+        assert!(compressed_blocks >= 2);
+        assert!(output.len() < mock_data.len() / 10);
+        assert_eq!(mock_data, decoded);
+
+        let mut decoded = Vec::new();
+        zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+        assert_eq!(mock_data, decoded);
+    }
+
+    #[test]
+    fn tiny_blocks() {
+        use crate::blocks::block::BlockType;
+        use crate::decoding::BlockDecodingStrategy;
+        use core::num::NonZeroUsize;
+
+        let mut mock_data = Vec::new();
+        for i in 0..300u32 {
+            mock_data.extend_from_slice(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+            mock_data.extend_from_slice(&(i % 7).to_le_bytes());
+            mock_data.extend_from_slice(b"abcabcabcabcabcabcabcabc");
+        }
+        // Not a multiple of the block size, otherwise the frame ends with an empty block
+        mock_data.push(b'!');
+        let num_blocks = mock_data.len().div_ceil(16);
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::RleOnly,
+            super::CompressionLevel::Fastest,
+            super::CompressionLevel::Default,
+        ] {
+            let mut compressor = FrameCompressor::new(level);
+            compressor.set_block_size(NonZeroUsize::new(16).unwrap());
+            #[cfg(feature = "hash")]
+            compressor.set_content_checksum(true);
+            compressor.set_source(mock_data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let output = compressor.take_drain().unwrap();
+
+            // Decode block by block, collecting in between
+            let mut decoder = FrameDecoder::new();
+            let mut source = output.as_slice();
+            decoder.reset(&mut source).unwrap();
+            let mut decoded = Vec::new();
+            let mut block_types = Vec::new();
+            while !decoder.is_finished() {
+                decoder
+                    .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                    .unwrap();
+                let stats = decoder.last_block_stats().unwrap();
+                assert!(stats.decompressed_size <= 16);
+                block_types.push(stats.block_type);
+                decoder.collect_to_writer(&mut decoded).unwrap();
+            }
+            assert!(source.is_empty());
+            assert_eq!(decoder.blocks_decoded(), num_blocks);
+            assert_eq!(decoded, mock_data);
+            #[cfg(feature = "hash")]
+            assert_eq!(
+                decoder.get_checksum_from_data(),
+                decoder.get_calculated_checksum()
+            );
+            match level {
+                super::CompressionLevel::Uncompressed => {
+                    assert!(block_types.iter().all(|ty| *ty == BlockType::Raw))
+                }
+                super::CompressionLevel::RleOnly => {
+                    assert!(block_types.contains(&BlockType::RLE))
+                }
+                _ => assert!(block_types.contains(&BlockType::Compressed)),
+            }
+
+            let mut decoded = Vec::new();
+            zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+            assert_eq!(decoded, mock_data);
+        }
+
+        // Bigger sizes are limited to the maximum block size
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Uncompressed);
+        compressor.set_block_size(NonZeroUsize::new(usize::MAX).unwrap());
+        let data = vec![1u8; 300 * 1024];
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let output = compressor.take_drain().unwrap();
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::with_capacity(data.len());
+        decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+        assert_eq!(decoder.blocks_decoded(), 3);
+    }
+
+    #[test]
+    fn io_errors() {
+        use super::CompressError;
+        use crate::io::{Error, ErrorKind, Read, Write};
+
+        /// Returns its data in small pieces, interrupted in between, then fails
+        struct FailingSource {
+            data: Vec<u8>,
+            reads: usize,
+        }
+        impl Read for FailingSource {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                self.reads += 1;
+                if self.reads & 1 == 0 {
+                    return Err(Error::from(ErrorKind::Interrupted));
+                }
+                if self.data.is_empty() {
+                    return Err(Error::from(ErrorKind::Other));
+                }
+                let len = buf.len().min(self.data.len()).min(1000);
+                buf[..len].copy_from_slice(&self.data[..len]);
+                self.data.drain(..len);
+                Ok(len)
+            }
+        }
+
+        /// Accepts a limited number of bytes
+        struct FullDrain(usize);
+        impl Write for FullDrain {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                if self.0 == 0 {
+                    return Err(Error::from(ErrorKind::Other));
+                }
+                let len = buf.len().min(self.0);
+                self.0 -= len;
+                Ok(len)
+            }
+            fn flush(&mut self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let data = vec![7u8; 300 * 1024];
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(FailingSource {
+            data: data.clone(),
+            reads: 0,
+        });
+        compressor.set_drain(Vec::new());
+        let err = compressor.compress().unwrap_err();
+        assert!(matches!(err, CompressError::ReadError(_)));
+        // The complete blocks were written before the error
+        let written = compressor.take_drain().unwrap();
+        assert!(!written.is_empty());
+
+        // The compressor can be used again
+        compressor.set_source(FailingSource {
+            data: data.clone(),
+            reads: 0,
+        });
+        compressor.set_drain(Vec::new());
+        assert!(compressor.compress().is_err());
+        assert_eq!(compressor.drain().unwrap(), &written);
+
+        // Producing the frame block by block returns the error as well and starts a new frame afterwards
+        compressor.set_source(FailingSource {
+            data: data.clone(),
+            reads: 0,
+        });
+        let mut blocks = Vec::new();
+        let err = loop {
+            match compressor.next_compressed_block() {
+                Ok(block) => blocks.push(block.unwrap()),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(err, CompressError::ReadError(_)));
+        assert_eq!(blocks.concat(), written);
+        compressor.set_source(FailingSource {
+            data: data.clone(),
+            reads: 0,
+        });
+        let block = compressor.next_compressed_block().unwrap().unwrap();
+        assert_eq!(block, written[..block.len()]);
+
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(FullDrain(10));
+        let err = compressor.compress().unwrap_err();
+        assert!(matches!(err, CompressError::WriteError(_)));
+
+        // The same with verification, which writes the whole frame at the end
+        compressor.set_source(data.as_slice());
+        compressor.set_verify(true);
+        let err = compressor.compress().unwrap_err();
+        assert!(matches!(err, CompressError::WriteError(_)));
+
+        assert!(matches!(
+            super::super::try_compress_to_vec(
+                FailingSource {
+                    data: Vec::new(),
+                    reads: 0
+                },
+                super::CompressionLevel::Fastest
+            ),
+            Err(CompressError::ReadError(_))
+        ));
+        let mut target = [0u8; 10];
+        assert!(matches!(
+            super::super::try_compress(
+                data.as_slice(),
+                &mut target[..],
+                super::CompressionLevel::Fastest
+            ),
+            Err(CompressError::WriteError(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_levels() {
+        use crate::encoding::errors::CompressError;
+        use crate::encoding::{try_compress, try_compress_to_vec, StreamingEncoder};
+        use crate::io::Write;
+
+        let data = vec![7u8; 1000];
+        for level in [
+            super::CompressionLevel::Better,
+            super::CompressionLevel::Best,
+        ] {
+            assert!(matches!(
+                try_compress_to_vec(data.as_slice(), level),
+                Err(CompressError::UnsupportedLevel(_))
+            ));
+
+            // Nothing is written to the drain
+            let mut target = Vec::new();
+            assert!(try_compress(data.as_slice(), &mut target, level).is_err());
+            assert!(target.is_empty());
+            let mut compressor: FrameCompressor<&[u8], Vec<u8>, _> = FrameCompressor::new(level);
+            compressor.set_source(data.as_slice());
+            assert!(matches!(
+                compressor.next_compressed_block(),
+                Err(CompressError::UnsupportedLevel(_))
+            ));
+
+            let mut encoder = StreamingEncoder::new(Vec::new(), level);
+            encoder.write_all(&data).unwrap();
+            assert!(encoder.flush().is_err());
+        }
+    }
+
+    #[test]
+    fn progress_callback() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        let mock_data: Vec<u8> = (0..300_000u32).map(|x| (x % 251) as u8).collect();
+        // Number of reports, input and output bytes of the last report
+        let reports = Arc::new([AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)]);
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        let reports_clone = reports.clone();
+        compressor.set_progress(move |info| {
+            let [count, input, output] = &*reports_clone;
+            assert!(input.load(Ordering::Relaxed) < info.input_bytes);
+            assert!(output.load(Ordering::Relaxed) < info.output_bytes);
+            count.fetch_add(1, Ordering::Relaxed);
+            input.store(info.input_bytes, Ordering::Relaxed);
+            output.store(info.output_bytes, Ordering::Relaxed);
+        });
+        compressor.set_source(mock_data.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+
+        let [count, input, output] = &*reports;
+        // One report per block
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+        assert_eq!(input.load(Ordering::Relaxed), mock_data.len() as u64);
+        assert_eq!(
+            output.load(Ordering::Relaxed),
+            compressor.drain().unwrap().len() as u64
+        );
+    }
+
+    #[test]
+    fn compress_stats() {
+        let mut mock_data = vec![0; 1 << 17];
+        mock_data.extend(vec![1, 2, 3, 4, 5]);
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(mock_data.as_slice());
+        compressor.set_drain(Vec::new());
+
+        compressor.compress().unwrap();
+        let stats = compressor.stats();
+        assert_eq!(stats.input_bytes, mock_data.len() as u64);
+        assert_eq!(stats.output_bytes, compressor.drain().unwrap().len() as u64);
+        assert_eq!(
+            stats.ratio(),
+            mock_data.len() as f64 / compressor.drain().unwrap().len() as f64
+        );
+        assert!(stats.ratio() > 1000.0);
+
+        compressor.set_source([].as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let stats = compressor.stats();
+        assert_eq!(stats.input_bytes, 0);
+        assert_eq!(stats.output_bytes, compressor.drain().unwrap().len() as u64);
+        assert_eq!(stats.matches, super::MatchStats::default());
+        assert_eq!(stats.ratio(), 0.0);
+        assert_eq!(super::CompressStats::default().ratio(), 0.0);
+    }
+
+    #[test]
+    fn match_stats() {
+        let mut data = Vec::new();
+        for i in 0..20_000u32 {
+            data.extend_from_slice(&(i % 97).to_le_bytes());
+            data.extend_from_slice(b"matched");
+        }
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+
+        let matches = compressor.stats().matches;
+        assert!(matches.matches > 0);
+        assert_eq!(
+            matches.match_bytes + matches.literal_bytes,
+            data.len() as u64
+        );
+        assert_eq!(
+            matches.match_length_histogram.iter().sum::<u64>(),
+            matches.matches
+        );
+        assert_eq!(
+            matches.offset_histogram.iter().sum::<u64>(),
+            matches.matches
+        );
+        // Matches are at least 5 bytes long
+        assert_eq!(matches.match_length_histogram[..2], [0, 0]);
+        assert!(matches.average_match_len() >= 5.0);
+    }
+
+    #[test]
+    fn small_inputs_have_valid_window() {
+        use crate::decoding::frame::{read_frame_header, MIN_WINDOW_SIZE};
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::Fastest,
+        ] {
+            for len in [0, 1, 100, 1023, 1024, 1025] {
+                let mock_data = vec![7u8; len];
+                let mut output: Vec<u8> = Vec::new();
+                let mut compressor = FrameCompressor::new(level);
+                compressor.set_source(mock_data.as_slice());
+                compressor.set_drain(&mut output);
+                compressor.compress().unwrap();
+
+                let header = read_frame_header(output.as_slice()).unwrap().0.header;
+                if !header.descriptor.single_segment_flag() {
+                    assert!(header.window_size().unwrap() >= MIN_WINDOW_SIZE);
+                }
+
+                let mut decoder = FrameDecoder::new();
+                let mut decoded = Vec::with_capacity(mock_data.len());
+                decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+                assert_eq!(mock_data, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let mut data = Vec::new();
+        for i in 0..40_000u32 {
+            data.extend_from_slice(&(i % 1009).to_le_bytes());
+            data.extend_from_slice(b"deterministic");
+        }
+        let other: Vec<u8> = (0..300_000u32).map(|x| (x % 7 + x % 13) as u8).collect();
+
+        fn compress<'a>(
+            compressor: &mut FrameCompressor<&'a [u8], Vec<u8>, super::MatchGeneratorDriver>,
+            input: &'a [u8],
+        ) -> Vec<u8> {
+            compressor.set_source(input);
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            compressor.take_drain().unwrap()
+        }
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::Fastest,
+        ] {
+            let expected = compress(&mut FrameCompressor::new(level), data.as_slice());
+            assert_eq!(
+                expected,
+                compress(&mut FrameCompressor::new(level), data.as_slice())
+            );
+
+            // Buffers and tables reused from a previous frame must not influence the output.
+            // Compressing a shorter input in between leaves stale bytes in the pooled buffers.
+            let mut compressor = FrameCompressor::new(level);
+            compress(&mut compressor, other.as_slice());
+            compress(&mut compressor, &data[..1000]);
+            assert_eq!(expected, compress(&mut compressor, data.as_slice()));
+            assert_eq!(expected, compress(&mut compressor, data.as_slice()));
+        }
+    }
+
+    #[test]
+    fn pull_blocks() {
+        let mut data = Vec::new();
+        for i in 0..70_000u32 {
+            data.extend_from_slice(&(i % 1009).to_le_bytes());
+            data.extend_from_slice(b"pull");
+        }
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::Fastest,
+        ] {
+            let mut compressor = FrameCompressor::new(level);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let expected = compressor.take_drain().unwrap();
+
+            compressor.set_source(data.as_slice());
+            let mut blocks = Vec::new();
+            while let Some(block) = compressor.next_compressed_block().unwrap() {
+                blocks.push(block);
+            }
+            // The input spans several blocks
+            assert!(blocks.len() > 2);
+            assert_eq!(expected, blocks.concat());
+            assert_eq!(compressor.stats().input_bytes, data.len() as u64);
+            assert_eq!(compressor.stats().output_bytes, expected.len() as u64);
+
+            // The next call starts a new frame, which is empty because the source is exhausted
+            let empty = compressor.next_compressed_block().unwrap().unwrap();
+            assert!(compressor.next_compressed_block().unwrap().is_none());
+            let mut decoded = Vec::new();
+            FrameDecoder::new()
+                .decode_all_to_vec(&empty, &mut decoded)
+                .unwrap();
+            assert!(decoded.is_empty());
+        }
+    }
+
+    #[test]
+    fn verify_output() {
+        use super::{CompressionLevel, MatchGeneratorDriver, Matcher};
+        use crate::encoding::errors::CompressError;
+        use crate::encoding::Sequence;
+
+        let mut data = Vec::new();
+        for i in 0..20_000u32 {
+            data.extend_from_slice(&(i % 101).to_le_bytes());
+            data.extend_from_slice(b"verify me");
+        }
+
+        let mut plain = Vec::new();
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(&mut plain);
+        compressor.compress().unwrap();
+
+        let mut verified = Vec::new();
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_verify(true);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(&mut verified);
+        compressor.compress().unwrap();
+        assert_eq!(plain, verified);
+
+        // A matcher that reports wrong offsets produces frames that decode to something else
+        struct BrokenMatcher(MatchGeneratorDriver);
+        impl Matcher for BrokenMatcher {
+            fn get_next_space(&mut self) -> Vec<u8> {
+                self.0.get_next_space()
+            }
+            fn get_last_space(&mut self) -> &[u8] {
+                self.0.get_last_space()
+            }
+            fn commit_space(&mut self, space: Vec<u8>) {
+                self.0.commit_space(space)
+            }
+            fn skip_matching(&mut self) {
+                self.0.skip_matching()
+            }
+            fn start_matching(&mut self, mut handle_sequence: impl for<'a> FnMut(Sequence<'a>)) {
+                self.0.start_matching(|seq| match seq {
+                    Sequence::Triple {
+                        literals,
+                        offset,
+                        match_len,
+                    } => handle_sequence(Sequence::Triple {
+                        literals,
+                        offset: offset + 1,
+                        match_len,
+                    }),
+                    seq => handle_sequence(seq),
+                })
+            }
+            fn reset(&mut self, level: CompressionLevel) {
+                self.0.reset(level)
+            }
+            fn window_size(&self) -> u64 {
+                self.0.window_size()
+            }
+        }
+
+        let mut drain = Vec::new();
+        let mut compressor = FrameCompressor::new_with_matcher(
+            BrokenMatcher(MatchGeneratorDriver::new(1024 * 128, 1)),
+            CompressionLevel::Fastest,
+        );
+        compressor.set_verify(true);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(&mut drain);
+        assert!(matches!(
+            compressor.compress(),
+            Err(CompressError::VerificationFailed)
+        ));
+        drop(compressor);
+        assert!(drain.is_empty());
+    }
+
+    #[test]
+    fn frame_overhead_matches_output() {
+        use crate::encoding::{frame_overhead, CompressionLevel, EncoderParams};
+        assert_eq!(frame_overhead(&EncoderParams::default(), 0), 9);
+        // The content size replaces the window descriptor, it takes 2 bytes from 256 bytes on
+        assert_eq!(frame_overhead(&EncoderParams::compatibility(), 255), 9);
+        assert_eq!(frame_overhead(&EncoderParams::compatibility(), 256), 10);
+
+        let block_size = 128 * 1024;
+        for len in [0, 10, 300, block_size - 1, block_size, 2 * block_size + 1] {
+            let data: Vec<u8> = (0..len as u32).map(|i| (i % 7 + i % 251) as u8).collect();
+            for params in [EncoderParams::default(), EncoderParams::compatibility()] {
+                for magicless in [false, true] {
+                    let mut compressor = FrameCompressor::new(CompressionLevel::Uncompressed);
+                    compressor.set_params(params);
+                    compressor.set_magicless(magicless);
+                    compressor.set_source(data.as_slice());
+                    compressor.set_drain(Vec::new());
+                    compressor.compress().unwrap();
+                    let compressed = compressor.take_drain().unwrap();
+                    let overhead = compressor.frame_overhead(len as u64);
+                    assert_eq!(compressed.len(), len + overhead);
+                    if !magicless {
+                        assert_eq!(overhead, frame_overhead(&params, len as u64));
+                    } else {
+                        assert_eq!(overhead + 4, frame_overhead(&params, len as u64));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compress_to_budget() {
+        use crate::encoding::{compress_to_budget, compress_to_vec, errors::CompressError};
+        use rand::{RngCore, SeedableRng};
+
+        let text = &include_bytes!("../../decodecorpus_files/z000033")[..20_000];
+        let raw = compress_to_vec(text, super::CompressionLevel::Uncompressed);
+        let fastest = compress_to_vec(text, super::CompressionLevel::Fastest);
+        let default = compress_to_vec(text, super::CompressionLevel::Default);
+        assert!(fastest.len() < raw.len());
+        assert!(default.len() < fastest.len());
+
+        // The least effort that fits is used
+        assert_eq!(compress_to_budget(text, raw.len()).unwrap(), raw);
+        assert_eq!(compress_to_budget(text, raw.len() - 1).unwrap(), fastest);
+        assert_eq!(compress_to_budget(text, fastest.len()).unwrap(), fastest);
+        assert_eq!(
+            compress_to_budget(text, fastest.len() - 1).unwrap(),
+            default
+        );
+        let err = compress_to_budget(text, default.len() - 1).unwrap_err();
+        assert!(matches!(
+            err,
+            CompressError::TargetTooSmall { needed, available }
+                if needed == default.len() && available == default.len() - 1
+        ));
+
+        // Random data doesn't get smaller than storing it raw
+        let mut random = vec![0u8; 1000];
+        rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut random);
+        let raw = compress_to_vec(random.as_slice(), super::CompressionLevel::Uncompressed);
+        assert!(compress_to_budget(&random, raw.len() - 1).is_err());
+    }
+
+    #[test]
+    fn compress_bound_holds() {
+        use crate::encoding::{compress_bound, compress_into, errors::CompressError};
+        use rand::{RngCore, SeedableRng};
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for len in [0, 1, 10, 1000, 128 * 1024, 128 * 1024 + 1, 300 * 1024] {
+            // Random data does not compress at all, so it hits the bound
+            let mut data = vec![0u8; len];
+            rng.fill_bytes(&mut data);
+            for level in [
+                super::CompressionLevel::Uncompressed,
+                super::CompressionLevel::Fastest,
+            ] {
+                let mut target = vec![0u8; compress_bound(len)];
+                let written = compress_into(&data, &mut target, level).unwrap();
+                assert!(written <= compress_bound(len));
+
+                let mut decoded = Vec::with_capacity(len);
+                FrameDecoder::new()
+                    .decode_all_to_vec(&target[..written], &mut decoded)
+                    .unwrap();
+                assert_eq!(decoded, data);
+
+                let err = compress_into(&data, &mut target[..written - 1], level).unwrap_err();
+                assert!(matches!(
+                    err,
+                    CompressError::TargetTooSmall { needed, available }
+                        if needed == written && available == written - 1
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn compressibility_report() {
+        use crate::decoding::BlockType;
+        use crate::encoding::{compress_to_vec, compressibility_report};
+        use rand::{RngCore, SeedableRng};
+
+        // One block of random data, one of a repeated byte and one of compressible text
+        let mut data = vec![0u8; 128 * 1024];
+        rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut data);
+        data.extend_from_slice(&[7u8; 128 * 1024]);
+        data.extend_from_slice(&include_bytes!("../../decodecorpus_files/z000033")[..100 * 1024]);
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::RleOnly,
+            super::CompressionLevel::Fastest,
+        ] {
+            let report = compressibility_report(&data, level);
+            let compressed = compress_to_vec(data.as_slice(), level);
+            assert_eq!(report.compressed_bytes, compressed.len() as u64);
+            assert_eq!(report.input_bytes, data.len() as u64);
+
+            let block_types: Vec<_> = report.blocks.iter().map(|b| b.block_type).collect();
+            let expected = match level {
+                super::CompressionLevel::Uncompressed => [BlockType::Raw; 3],
+                super::CompressionLevel::RleOnly => {
+                    [BlockType::Raw, BlockType::RLE, BlockType::Raw]
+                }
+                _ => [BlockType::Raw, BlockType::RLE, BlockType::Compressed],
+            };
+            assert_eq!(block_types, expected);
+            let input: u64 = report.blocks.iter().map(|b| u64::from(b.input_bytes)).sum();
+            assert_eq!(input, data.len() as u64);
+            let blocks: u64 = report
+                .blocks
+                .iter()
+                .map(|b| u64::from(b.output_bytes))
+                .sum();
+            let frame_header = crate::decoding::frame::read_frame_header(compressed.as_slice())
+                .unwrap()
+                .1;
+            assert_eq!(blocks + u64::from(frame_header), report.compressed_bytes);
+        }
+
+        let report = compressibility_report(&data, super::CompressionLevel::Fastest);
+        assert!(report.ratio() > 1.5);
+        let report = compressibility_report(&data[..128 * 1024], super::CompressionLevel::Fastest);
+        assert!(report.ratio() < 1.0);
+        let report = compressibility_report(&[], super::CompressionLevel::Fastest);
+        assert_eq!(report.blocks.len(), 1);
+        assert_eq!(report.ratio(), 0.0);
+    }
+
+    #[test]
+    fn rle_prefix_blocks() {
+        use super::{CompressionLevel, MatchGeneratorDriver, Matcher};
+        use crate::decoding::BlockType;
+        use crate::encoding::{compress_to_vec, compressibility_report, Sequence};
+
+        let text = &include_bytes!("../../decodecorpus_files/z000033")[..];
+        for level in [
+            super::CompressionLevel::Fastest,
+            super::CompressionLevel::Default,
+        ] {
+            // The run straddles the boundary between the first two blocks, its end becomes an RLE block of its own
+            let mut data = vec![0u8; 200 * 1024];
+            data.extend_from_slice(&text[..50 * 1024]);
+            let report = compressibility_report(&data, level);
+            let blocks: Vec<_> = report
+                .blocks
+                .iter()
+                .map(|b| (b.block_type, b.input_bytes))
+                .collect();
+            assert_eq!(
+                blocks,
+                [
+                    (BlockType::RLE, 128 * 1024),
+                    (BlockType::RLE, 72 * 1024),
+                    (BlockType::Compressed, 50 * 1024),
+                ]
+            );
+            let compressed = compress_to_vec(data.as_slice(), level);
+            assert_eq!(report.compressed_bytes, compressed.len() as u64);
+            let mut decoded = Vec::with_capacity(data.len());
+            FrameDecoder::new()
+                .decode_all_to_vec(&compressed, &mut decoded)
+                .unwrap();
+            assert_eq!(data, decoded);
+            assert_eq!(data, zstd::decode_all(compressed.as_slice()).unwrap());
+
+            // Runs that start within a block are only split off at the start of the next block
+            let mut data = text[..100 * 1024].to_vec();
+            data.extend_from_slice(&[7u8; 100 * 1024]);
+            data.extend_from_slice(&text[..1000]);
+            data.extend_from_slice(&[7u8; 100]);
+            data.extend_from_slice(&text[..1000]);
+            data.extend_from_slice(&[8u8; 200 * 1024]);
+            let report = compressibility_report(&data, level);
+            let blocks: Vec<_> = report
+                .blocks
+                .iter()
+                .map(|b| (b.block_type, b.input_bytes))
+                .collect();
+            assert_eq!(
+                blocks,
+                [
+                    (BlockType::Compressed, 128 * 1024),
+                    (BlockType::RLE, 72 * 1024),
+                    (BlockType::Compressed, 56 * 1024),
+                    (BlockType::RLE, 128 * 1024),
+                    (BlockType::RLE, 16 * 1024 + 2100),
+                ]
+            );
+            let compressed = compress_to_vec(data.as_slice(), level);
+            let mut decoded = Vec::with_capacity(data.len());
+            FrameDecoder::new()
+                .decode_all_to_vec(&compressed, &mut decoded)
+                .unwrap();
+            assert_eq!(data, decoded);
+            assert_eq!(data, zstd::decode_all(compressed.as_slice()).unwrap());
+        }
+
+        // Splitting off the run commits its space before the space for the rest of the block is taken
+        struct OneSpaceMatcher {
+            inner: MatchGeneratorDriver,
+            space_taken: bool,
+        }
+        impl Matcher for OneSpaceMatcher {
+            fn get_next_space(&mut self) -> Vec<u8> {
+                assert!(!self.space_taken, "the previous space was not committed");
+                self.space_taken = true;
+                self.inner.get_next_space()
+            }
+            fn get_last_space(&mut self) -> &[u8] {
+                self.inner.get_last_space()
+            }
+            fn commit_space(&mut self, space: Vec<u8>) {
+                self.space_taken = false;
+                self.inner.commit_space(space)
+            }
+            fn skip_matching(&mut self) {
+                self.inner.skip_matching()
+            }
+            fn start_matching(&mut self, handle_sequence: impl for<'a> FnMut(Sequence<'a>)) {
+                self.inner.start_matching(handle_sequence)
+            }
+            fn reset(&mut self, level: CompressionLevel) {
+                self.inner.reset(level)
+            }
+            fn window_size(&self) -> u64 {
+                self.inner.window_size()
+            }
+        }
+        let mut data = vec![0u8; 2000];
+        data.extend_from_slice(&text[..1000]);
+        let mut compressor = FrameCompressor::new_with_matcher(
+            OneSpaceMatcher {
+                inner: MatchGeneratorDriver::new(1024 * 128, 1),
+                space_taken: false,
+            },
+            CompressionLevel::Fastest,
+        );
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let compressed = compressor.take_drain().unwrap();
+        assert_eq!(data, zstd::decode_all(compressed.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn no_stale_bytes_in_raw_blocks() {
+        use crate::decoding::{extract_block, BlockType};
+        use rand::{RngCore, SeedableRng};
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        // Random data does not compress, so the encoder falls back to Raw blocks
+        let mut large = vec![0u8; 1024 * 128];
+        rng.fill_bytes(&mut large);
+        let mut small = vec![0u8; 1024 * 128 - 10];
+        rng.fill_bytes(&mut small);
+
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(large.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+
+        // The buffer used for the large input is now in the pool and gets reused for the small input
+        compressor.set_source(small.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let output = compressor.take_drain().unwrap();
+
+        let (header, content) = extract_block(output.as_slice(), 0).unwrap();
+        assert_eq!(header.block_type, BlockType::Raw);
+        assert!(header.last_block);
+        assert_eq!(header.decompressed_size as usize, small.len());
+        assert_eq!(content, small);
+
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::with_capacity(small.len());
+        decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+        assert_eq!(small, decoded);
+    }
+
+    #[test]
+    fn entropy_limits_round_trip() {
+        let mut data = Vec::new();
+        for i in 0..60_000u32 {
+            data.extend_from_slice(&(i % 1009).to_le_bytes());
+            data.push((i.trailing_zeros() * 7) as u8);
+            data.extend_from_slice(&b"limits"[..(i % 6) as usize]);
+        }
+
+        let mut sizes = Vec::new();
+        for limit in [u8::MAX, 8, 6, 0] {
+            let params = super::EncoderParams {
+                max_huffman_bits: limit,
+                max_ll_log: limit,
+                max_ml_log: limit,
+                max_of_log: limit,
+                max_memory: None,
+                content_size: false,
+                #[cfg(feature = "hash")]
+                content_checksum: false,
+            };
+
+            let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+            compressor.set_params(params);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let output = compressor.take_drain().unwrap();
+            sizes.push(output.len());
+
+            let mut decoder = FrameDecoder::new();
+            let mut decoded = Vec::with_capacity(data.len());
+            decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+
+            let mut decoded = Vec::new();
+            zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+        }
+        // Limits above what the format allows do not change anything
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        assert_eq!(sizes[0], compressor.take_drain().unwrap().len());
+    }
+
+    #[test]
+    fn compatibility_params() {
+        use crate::decoding::FrameInfo;
+        use zstd::zstd_safe::get_frame_content_size;
+
+        for (len, expect_content_size) in [
+            (0, true),
+            (300, true),
+            ((1 << 17) - 1, true),
+            (1 << 17, false),
+        ] {
+            let data: Vec<u8> = (0..len as u32).map(|i| (i % 7 + i % 251) as u8).collect();
+            let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+            compressor.set_params(super::EncoderParams::compatibility());
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let output = compressor.take_drain().unwrap();
+
+            let info = FrameInfo::read(output.as_slice()).unwrap();
+            assert!(!info.content_checksum);
+            assert!(info.window_size <= 256 * 1024);
+            let content_size = get_frame_content_size(&output).unwrap();
+            if expect_content_size {
+                assert_eq!(info.content_size, Some(len as u64));
+                assert_eq!(content_size, Some(len as u64));
+            } else {
+                assert_eq!(info.content_size, None);
+                assert_eq!(content_size, None);
+            }
+
+            let mut decoded = Vec::new();
+            zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+        }
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn archival_params() {
+        use crate::decoding::FrameInfo;
+        use crate::encoding::{frame_overhead, EncoderParams};
+
+        assert_eq!(
+            frame_overhead(&EncoderParams::archival(), 300),
+            frame_overhead(&EncoderParams::compatibility(), 300) + 4
+        );
+        for len in [0, 300, 1 << 18] {
+            let data: Vec<u8> = (0..len as u32).map(|i| (i % 7 + i % 251) as u8).collect();
+            let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+            compressor.set_params(EncoderParams::archival());
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let mut output = compressor.take_drain().unwrap();
+
+            let info = FrameInfo::read(output.as_slice()).unwrap();
+            assert!(info.content_checksum);
+            let raw = {
+                let mut compressor = FrameCompressor::new(super::CompressionLevel::Uncompressed);
+                compressor.set_params(EncoderParams::archival());
+                compressor.set_source(data.as_slice());
+                compressor.set_drain(Vec::new());
+                compressor.compress().unwrap();
+                compressor.take_drain().unwrap()
+            };
+            assert_eq!(
+                raw.len(),
+                len + frame_overhead(&EncoderParams::archival(), len as u64)
+            );
+            if len < 1 << 17 {
+                assert_eq!(info.content_size, Some(len as u64));
+            }
+
+            // The reference decoder checks the checksum
+            let mut decoded = Vec::new();
+            zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+            let last = output.len() - 1;
+            output[last] ^= 1;
+            let mut decoded = Vec::new();
+            assert!(zstd::stream::copy_decode(output.as_slice(), &mut decoded).is_err());
+        }
+    }
+
+    #[test]
+    fn single_segment_if_single_block() {
+        use crate::decoding::frame::read_frame_header;
+
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::Fastest,
+        ] {
+            for (len, expect_single_segment) in [(0, true), (300, true), (1 << 18, false)] {
+                let mock_data: Vec<u8> = (0..len).map(|x| (x % 13) as u8).collect();
+                let mut output: Vec<u8> = Vec::new();
+                let mut compressor = FrameCompressor::new(level);
+                compressor.set_single_segment_if_single_block(true);
+                compressor.set_source(mock_data.as_slice());
+                compressor.set_drain(&mut output);
+                compressor.compress().unwrap();
+
+                let header = read_frame_header(output.as_slice()).unwrap().0.header;
+                assert_eq!(
+                    header.descriptor.single_segment_flag(),
+                    expect_single_segment
+                );
+                if expect_single_segment {
+                    assert_eq!(header.frame_content_size(), len as u64);
+                }
+
+                let mut decoder = FrameDecoder::new();
+                let mut decoded = Vec::with_capacity(mock_data.len());
+                decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+                assert_eq!(mock_data, decoded);
+
+                let mut decoded = Vec::new();
+                zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+                assert_eq!(mock_data, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn single_segment_window() {
+        use crate::decoding::FrameInfo;
+        use rand::{RngCore, SeedableRng};
+
+        // The repetition at the end is further back than the window of 128kB the matcher uses at Fastest
+        let mut data = vec![0u8; 290 * 1024];
+        rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut data[..190 * 1024]);
+        data.copy_within(..100 * 1024, 190 * 1024);
+
+        let compress = |content_size: Option<u64>| {
+            let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+            compressor.set_content_size(content_size);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            (compressor.take_drain().unwrap(), compressor.stats())
+        };
+
+        let (unknown_size, stats) = compress(None);
+        assert_eq!(stats.matches.offset_histogram[17], 0);
+        let (single_segment, stats) = compress(Some(data.len() as u64));
+        // The matches reach back 190kB
+        assert!(stats.matches.offset_histogram[17] > 0);
+        assert!(single_segment.len() < unknown_size.len() - 90 * 1024);
+
+        let info = FrameInfo::read(single_segment.as_slice()).unwrap();
+        assert_eq!(info.window_size, data.len() as u64);
+        let mut decoded = Vec::with_capacity(data.len());
+        FrameDecoder::new()
+            .decode_all_to_vec(&single_segment, &mut decoded)
+            .unwrap();
+        assert_eq!(data, decoded);
+        assert_eq!(data, zstd::decode_all(single_segment.as_slice()).unwrap());
+
+        // Bigger inputs keep the usual window
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_content_size(Some(2 * 1024 * 1024));
+        compressor.set_source(&[0u8; 2 * 1024 * 1024][..]);
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let output = compressor.take_drain().unwrap();
+        assert_eq!(
+            FrameInfo::read(output.as_slice()).unwrap().window_size,
+            256 * 1024
+        );
+    }
+
+    #[test]
+    fn content_size() {
+        use crate::decoding::FrameInfo;
+        use crate::encoding::errors::CompressError;
+        use zstd::zstd_safe::get_frame_content_size;
+
+        let data: Vec<u8> = (0..300 * 1024u32)
+            .map(|i| (i % 7 + i % 251 + i / 1000) as u8)
+            .collect();
+        for level in [
+            super::CompressionLevel::Uncompressed,
+            super::CompressionLevel::Fastest,
+            super::CompressionLevel::Default,
+        ] {
+            for len in [
+                0,
+                1,
+                255,
+                256,
+                65791,
+                65792,
+                1 << 17,
+                (1 << 17) + 1,
+                256 * 1024,
+                256 * 1024 + 1,
+                300 * 1024,
+            ] {
+                let data = &data[..len];
+                let mut compressor = FrameCompressor::new(level);
+                compressor.set_content_size(Some(len as u64));
+                compressor.set_source(data);
+                compressor.set_drain(Vec::new());
+                compressor.compress().unwrap();
+                let output = compressor.take_drain().unwrap();
+
+                let info = FrameInfo::read(output.as_slice()).unwrap();
+                assert_eq!(info.content_size, Some(len as u64));
+                assert_eq!(get_frame_content_size(&output).unwrap(), Some(len as u64));
+                // Inputs of up to 1MB are written as single segment frames,
+                // which use the content size as the window size
+                assert_eq!(info.window_size, len as u64);
+
+                let mut decoded = Vec::with_capacity(len);
+                FrameDecoder::new()
+                    .decode_all_to_vec(&output, &mut decoded)
+                    .unwrap();
+                assert_eq!(data, decoded);
+                assert_eq!(data, zstd::decode_all(output.as_slice()).unwrap());
+            }
+        }
+
+        // Reading stops at the declared size
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_content_size(Some(1000));
+        compressor.set_source(&data[..2000]);
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let output = compressor.take_drain().unwrap();
+        assert_eq!(zstd::decode_all(output.as_slice()).unwrap(), &data[..1000]);
+
+        // The content size only applies to the next frame, which gets the rest of the source
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let output = compressor.take_drain().unwrap();
+        assert_eq!(
+            FrameInfo::read(output.as_slice()).unwrap().content_size,
+            None
+        );
+        assert_eq!(
+            zstd::decode_all(output.as_slice()).unwrap(),
+            &data[1000..2000]
+        );
+
+        // A source that is too short produces an invalid frame
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_content_size(Some(1000));
+        compressor.set_source(&data[..500]);
+        compressor.set_drain(Vec::new());
+        assert!(matches!(
+            compressor.compress(),
+            Err(CompressError::ContentSizeMismatch {
+                declared: 1000,
+                actual: 500
+            })
+        ));
+        let output = compressor.take_drain().unwrap();
+        assert!(zstd::decode_all(output.as_slice()).is_err());
+    }
+
+    #[test]
+    fn multi_block_structured_compress() {
+        // Multiple blocks of data with similar statistics, so tables of previous blocks can be repeated
+        let words: [&[u8]; 8] = [
+            b"zstd",
+            b"frame",
+            b"block",
+            b"literals",
+            b"sequences",
+            b"offset",
+            b"match",
+            b"huffman",
+        ];
+        let mut mock_data = Vec::new();
+        let mut state = 0x1234_5678u32;
+        while mock_data.len() < 1024 * 512 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            mock_data.extend_from_slice(words[(state >> 29) as usize]);
+            mock_data.push(b' ');
+        }
+        let mut output: Vec<u8> = Vec::new();
+        let mut compressor = FrameCompressor::new(super::CompressionLevel::Fastest);
+        compressor.set_source(mock_data.as_slice());
+        compressor.set_drain(&mut output);
+
+        compressor.compress().unwrap();
+        assert!(output.len() < mock_data.len());
+
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::with_capacity(mock_data.len());
+        decoder.decode_all_to_vec(&output, &mut decoded).unwrap();
+        assert_eq!(mock_data, decoded);
+
+        let mut decoded = Vec::new();
+        zstd::stream::copy_decode(output.as_slice(), &mut decoded).unwrap();
+        assert_eq!(mock_data, decoded);
+    }
+
     #[test]
     fn aaa_compress() {
         let mock_data = vec![0, 1, 3, 4, 5];
@@ -319,7 +2226,7 @@ mod tests {
         compressor.set_source(mock_data.as_slice());
         compressor.set_drain(&mut output);
 
-        compressor.compress();
+        compressor.compress().unwrap();
 
         let mut decoder = FrameDecoder::new();
         let mut decoded = Vec::with_capacity(mock_data.len());
@@ -342,19 +2249,11 @@ mod tests {
             result
         }
 
-        fn decode_ruzstd_writer(mut data: impl Read) -> Vec<u8> {
+        fn decode_ruzstd_writer(data: impl Read) -> Vec<u8> {
             let mut decoder = crate::decoding::FrameDecoder::new();
-            decoder.reset(&mut data).unwrap();
             let mut result = vec![];
-            while !decoder.is_finished() || decoder.can_collect() > 0 {
-                decoder
-                    .decode_blocks(
-                        &mut data,
-                        crate::decoding::BlockDecodingStrategy::UptoBytes(1024 * 1024),
-                    )
-                    .unwrap();
-                decoder.collect_to_writer(&mut result).unwrap();
-            }
+            let written = decoder.decode_to_writer(data, &mut result).unwrap();
+            assert_eq!(written, result.len() as u64);
             result
         }
 
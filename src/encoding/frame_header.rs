@@ -12,7 +12,7 @@ use alloc::vec::Vec;
 #[derive(Debug)]
 pub struct FrameHeader {
     /// Optionally, the original (uncompressed) size of the data within the frame in bytes.
-    /// If not present, `window_size` must be set.
+    /// Must be present if `single_segment` is set, serializing the header panics otherwise.
     pub frame_content_size: Option<u64>,
     /// If set to true, data must be regenerated within a single
     /// continuous memory segment.
@@ -21,9 +21,10 @@ pub struct FrameHeader {
     /// at the end of the frame.
     pub content_checksum: bool,
     /// If a dictionary ID is provided, the ID of that dictionary.
-    pub dictionary_id: Option<u64>,
-    /// The minimum memory buffer required to compress a frame. If not present,
-    /// `single_segment` will be set to true. If present, this value must be greater than 1KB
+    /// An ID of 0 means that no specific dictionary is used, so it is not written.
+    pub dictionary_id: Option<u32>,
+    /// The minimum memory buffer required to compress a frame. Must be present if `single_segment`
+    /// is not set, serializing the header panics otherwise. This value must be greater than 1KB
     /// and less than 3.75TB. Encoders should not generate a frame that requires a window size larger than
     /// 8mb.
     pub window_size: Option<u64>,
@@ -34,27 +35,55 @@ impl FrameHeader {
     ///
     /// The returned header *does include* a frame header descriptor.
     pub fn serialize(self, output: &mut Vec<u8>) {
-        vprintln!("Serializing frame with header: {self:?}");
         // https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#frame_header
         // Magic Number:
         output.extend_from_slice(&frame::MAGIC_NUM.to_le_bytes());
+        self.serialize_magicless(output);
+    }
 
+    /// Writes the serialized frame header without the magic number into the provided buffer.
+    pub fn serialize_magicless(self, output: &mut Vec<u8>) {
+        vprintln!("Serializing frame with header: {self:?}");
         // `Frame_Header_Descriptor`:
         output.push(self.descriptor());
 
         // `Window_Descriptor
-        // TODO: https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#window_descriptor
+        // https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#window_descriptor
+        // Writing the descriptor made sure that the window size is present without the `Single_Segment_flag`
         if !self.single_segment {
-            let exponent = 7;
-            output.push(exponent << 3);
+            output.push(window_descriptor(self.window_size.unwrap()));
         }
 
-        if let Some(id) = self.dictionary_id {
-            output.extend(minify_val(id));
+        if let Some(id) = self.dictionary_id.filter(|id| *id != 0) {
+            output.extend(minify_val(u64::from(id)));
         }
 
         if let Some(frame_content_size) = self.frame_content_size {
-            output.extend(minify_val_fcs(frame_content_size));
+            output.extend(minify_val_fcs(frame_content_size, self.fcs_field_size()));
+        }
+    }
+
+    /// The number of bytes used for the `Dictionary_ID` field.
+    ///
+    /// The smallest of 1, 2 or 4 bytes that fits the id. An id of 0 is not written at all.
+    fn did_field_size(&self) -> usize {
+        match self.dictionary_id {
+            None | Some(0) => 0,
+            Some(id) => find_min_size(u64::from(id)),
+        }
+    }
+
+    /// The number of bytes used for the `Frame_Content_Size` field.
+    ///
+    /// A 1 byte field is only possible with the `Single_Segment_flag` set, the 2 byte field
+    /// covers the values 256 up to 65791 because of the offset of 256.
+    fn fcs_field_size(&self) -> usize {
+        match self.frame_content_size {
+            None => 0,
+            Some(0..=255) if self.single_segment => 1,
+            Some(256..=65791) => 2,
+            Some(fcs) if fcs <= u64::from(u32::MAX) => 4,
+            Some(_) => 8,
         }
     }
 
@@ -70,7 +99,6 @@ impl FrameHeader {
         // `Frame_Content_Size_flag`:
         // The Frame_Content_Size_flag specifies if
         // the Frame_Content_Size field is provided within the header.
-        // TODO: The Frame_Content_Size field isn't set at all, we should prefer to include it always.
         // If the `Single_Segment_flag` is set and this value is zero,
         // the size of the FCS field is 1 byte.
         // Otherwise, the FCS field is omitted.
@@ -81,19 +109,15 @@ impl FrameHeader {
         // | 3     | 8
 
         // `Dictionary_ID_flag`:
-        if let Some(id) = self.dictionary_id {
-            let flag_value: u8 = match find_min_size(id) {
-                0 => 0,
-                1 => 1,
-                2 => 2,
-                4 => 3,
-                _ => panic!(),
-            };
-            bw.write_bits(flag_value, 2);
-        } else {
+        let flag_value: u8 = match self.did_field_size() {
             // A `Dictionary_ID` was not provided
-            bw.write_bits(0u8, 2);
-        }
+            0 => 0,
+            1 => 1,
+            2 => 2,
+            4 => 3,
+            _ => panic!(),
+        };
+        bw.write_bits(flag_value, 2);
 
         // `Content_Checksum_flag`:
         if self.content_checksum {
@@ -125,38 +149,56 @@ impl FrameHeader {
             bw.write_bits(0u8, 1);
         }
 
-        if let Some(frame_content_size) = self.frame_content_size {
-            let field_size = find_min_size(frame_content_size);
-            let flag_value: u8 = match field_size {
-                1 => 0,
-                2 => 1,
-                4 => 2,
-                3 => 8,
-                _ => panic!(),
-            };
-
-            bw.write_bits(flag_value, 2);
-        } else {
-            // `Frame_Content_Size` was not provided
-            bw.write_bits(0u8, 2);
-        }
+        let flag_value: u8 = match self.fcs_field_size() {
+            // `Frame_Content_Size` was not provided or fits into one byte
+            0 | 1 => 0,
+            2 => 1,
+            4 => 2,
+            8 => 3,
+            _ => panic!(),
+        };
+        bw.write_bits(flag_value, 2);
 
         bw.dump()[0]
     }
 }
 
-/// Identical to [`minify_val`], but it implements the following edge case:
+/// Similar to [`minify_val`], but it uses the provided field size and implements the following edge case:
 ///
 /// > When FCS_Field_Size is 1, 4 or 8 bytes, the value is read directly. When FCS_Field_Size is 2, the offset of 256 is added.
 ///
 /// https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#frame_content_size
-fn minify_val_fcs(val: u64) -> Vec<u8> {
-    let new_size = find_min_size(val);
+fn minify_val_fcs(val: u64, field_size: usize) -> Vec<u8> {
     let mut val = val;
-    if new_size == 2 {
+    if field_size == 2 {
         val -= 256;
     }
-    val.to_le_bytes()[0..new_size].to_vec()
+    val.to_le_bytes()[0..field_size].to_vec()
+}
+
+/// Find the smallest `Window_Descriptor` that describes a window of at least `window_size` bytes.
+///
+/// > windowLog = 10 + Exponent;
+/// > windowBase = 1 << windowLog;
+/// > windowAdd = (windowBase / 8) * Mantissa;
+/// > Window_Size = windowBase + windowAdd;
+///
+/// https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#window_descriptor
+fn window_descriptor(window_size: u64) -> u8 {
+    let window_size = core::cmp::max(window_size, frame::MIN_WINDOW_SIZE);
+    assert!(
+        window_size < frame::MAX_WINDOW_SIZE,
+        "the window size must be smaller than {}",
+        frame::MAX_WINDOW_SIZE
+    );
+    let mut exponent = 63 - window_size.leading_zeros() as u64 - 10;
+    let window_base = 1u64 << (exponent + 10);
+    let mut mantissa = (window_size - window_base).div_ceil(window_base / 8);
+    if mantissa == 8 {
+        exponent += 1;
+        mantissa = 0;
+    }
+    ((exponent << 3) | mantissa) as u8
 }
 
 #[cfg(test)]
@@ -202,7 +244,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "a frame content size must be provided")]
     fn catches_single_segment_no_fcs() {
         let header = FrameHeader {
             frame_content_size: None,
@@ -217,7 +259,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "a window size must be provided")]
     fn catches_single_segment_no_winsize() {
         let header = FrameHeader {
             frame_content_size: Some(7),
@@ -230,4 +272,222 @@ mod tests {
         let mut serialized_header = Vec::new();
         header.serialize(&mut serialized_header);
     }
+
+    #[test]
+    #[should_panic(expected = "a window size must be provided")]
+    fn catches_multi_segment_no_winsize_magicless() {
+        let header = FrameHeader {
+            frame_content_size: None,
+            single_segment: false,
+            content_checksum: false,
+            dictionary_id: None,
+            window_size: None,
+        };
+
+        let mut serialized_header = Vec::new();
+        header.serialize_magicless(&mut serialized_header);
+    }
+
+    #[test]
+    fn window_descriptor_round_trip() {
+        use super::window_descriptor;
+        use crate::decoding::frame::{MAX_WINDOW_SIZE, MIN_WINDOW_SIZE};
+
+        for exponent in 0..=31u64 {
+            for mantissa in 0..=7u64 {
+                let window_base = 1u64 << (exponent + 10);
+                let window_size = window_base + (window_base / 8) * mantissa;
+                if window_size >= MAX_WINDOW_SIZE {
+                    continue;
+                }
+                assert_eq!(
+                    window_descriptor(window_size),
+                    ((exponent << 3) | mantissa) as u8
+                );
+            }
+        }
+
+        assert_eq!(window_descriptor(0), 0);
+        assert_eq!(window_descriptor(MIN_WINDOW_SIZE - 1), 0);
+        assert_eq!(window_descriptor(MIN_WINDOW_SIZE + 1), 1);
+        assert_eq!(window_descriptor(1024 * 1024 * 2 - 1), 11 << 3);
+    }
+
+    #[test]
+    fn tiny_window_is_rounded_up() {
+        use crate::decoding::frame::MIN_WINDOW_SIZE;
+
+        for window_size in [0, 1, 512, MIN_WINDOW_SIZE - 1, MIN_WINDOW_SIZE] {
+            let header = FrameHeader {
+                frame_content_size: None,
+                single_segment: false,
+                content_checksum: false,
+                dictionary_id: None,
+                window_size: Some(window_size),
+            };
+            let mut serialized_header = Vec::new();
+            header.serialize(&mut serialized_header);
+            let parsed_header = read_frame_header(serialized_header.as_slice())
+                .unwrap()
+                .0
+                .header;
+            assert_eq!(parsed_header.window_size().unwrap(), MIN_WINDOW_SIZE);
+        }
+    }
+
+    #[test]
+    fn fcs_field_size() {
+        // The 1 byte field needs the single segment flag, the 2 byte field stores the size minus 256
+        for (size, single_segment, field) in [
+            (0, true, &[0][..]),
+            (255, true, &[255]),
+            (255, false, &[255, 0, 0, 0]),
+            (256, true, &[0, 0]),
+            (256, false, &[0, 0]),
+            (65791, false, &[0xFF, 0xFF]),
+            (65792, false, &[0x00, 0x01, 0x01, 0x00]),
+            (u64::from(u32::MAX), false, &[0xFF, 0xFF, 0xFF, 0xFF]),
+            (u64::from(u32::MAX) + 1, false, &[0, 0, 0, 0, 1, 0, 0, 0]),
+        ] {
+            let header = FrameHeader {
+                frame_content_size: Some(size),
+                single_segment,
+                content_checksum: false,
+                dictionary_id: None,
+                window_size: (!single_segment).then_some(1024),
+            };
+            let mut serialized_header = Vec::new();
+            header.serialize(&mut serialized_header);
+            let header_len = if single_segment { 5 } else { 6 };
+            assert_eq!(&serialized_header[header_len..], field);
+
+            let parsed_header = read_frame_header(serialized_header.as_slice())
+                .unwrap()
+                .0
+                .header;
+            assert_eq!(parsed_header.frame_content_size(), size);
+        }
+    }
+
+    #[test]
+    fn frame_header_round_trip() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let interesting_sizes = [
+            0u64,
+            1,
+            255,
+            256,
+            257,
+            65535,
+            65791,
+            65792,
+            u64::from(u32::MAX),
+            u64::from(u32::MAX) + 1,
+            u64::MAX,
+        ];
+
+        for _ in 0..10_000 {
+            let single_segment = rng.gen_bool(0.5);
+            let content_checksum = rng.gen_bool(0.5);
+            let dictionary_id = match rng.gen_range(0..4) {
+                0 => None,
+                1 => Some(rng.gen_range(1..=0xFF)),
+                2 => Some(rng.gen_range(0x100..=0xFFFF)),
+                _ => Some(rng.gen_range(0x1_0000..=u32::MAX)),
+            };
+            let frame_content_size = if single_segment || rng.gen_bool(0.5) {
+                let size = if rng.gen_bool(0.5) {
+                    interesting_sizes[rng.gen_range(0..interesting_sizes.len())]
+                } else {
+                    rng.gen_range(0..=u64::from(u32::MAX) * 2)
+                };
+                Some(size)
+            } else {
+                None
+            };
+            let window_size = if single_segment {
+                None
+            } else {
+                let exponent = rng.gen_range(0..=20u64);
+                let mantissa = rng.gen_range(0..=7u64);
+                let window_base = 1u64 << (exponent + 10);
+                Some(window_base + (window_base / 8) * mantissa)
+            };
+
+            let header = FrameHeader {
+                frame_content_size,
+                single_segment,
+                content_checksum,
+                dictionary_id,
+                window_size,
+            };
+            let mut serialized_header = Vec::new();
+            header.serialize(&mut serialized_header);
+
+            let (frame, bytes_read) = read_frame_header(serialized_header.as_slice()).unwrap();
+            assert_eq!(bytes_read as usize, serialized_header.len());
+            let parsed_header = frame.header;
+            assert_eq!(
+                parsed_header.descriptor.content_checksum_flag(),
+                content_checksum
+            );
+            assert_eq!(
+                parsed_header.descriptor.single_segment_flag(),
+                single_segment
+            );
+            assert_eq!(parsed_header.dictionary_id(), dictionary_id);
+            assert_eq!(
+                parsed_header.frame_content_size(),
+                frame_content_size.unwrap_or(0)
+            );
+            match window_size {
+                Some(window_size) => {
+                    assert_eq!(parsed_header.window_size().unwrap(), window_size)
+                }
+                None => assert_eq!(
+                    parsed_header.window_size().unwrap(),
+                    frame_content_size.unwrap()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn dictionary_id_sizes() {
+        for (dictionary_id, field_size) in [
+            (None, 0),
+            (Some(0), 0),
+            (Some(0x7F), 1),
+            (Some(0xFF), 1),
+            (Some(0x7FFF), 2),
+            (Some(0xFFFF), 2),
+            (Some(0x1_0000), 4),
+            (Some(0x7FFF_FFFF), 4),
+            (Some(u32::MAX), 4),
+        ] {
+            let header = FrameHeader {
+                frame_content_size: None,
+                single_segment: false,
+                content_checksum: false,
+                dictionary_id,
+                window_size: Some(1024),
+            };
+            let mut serialized_header = Vec::new();
+            header.serialize(&mut serialized_header);
+            // Magic number, descriptor and window descriptor
+            assert_eq!(serialized_header.len(), 6 + field_size);
+
+            let (frame, _) = read_frame_header(serialized_header.as_slice()).unwrap();
+            assert_eq!(
+                frame.header.descriptor.dictionary_id_bytes().unwrap() as usize,
+                field_size
+            );
+            assert_eq!(
+                frame.header.dictionary_id(),
+                dictionary_id.filter(|id| *id != 0)
+            );
+        }
+    }
 }
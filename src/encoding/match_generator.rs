@@ -13,6 +13,16 @@ use super::Matcher;
 use super::Sequence;
 
 const MIN_MATCH_LEN: usize = 5;
+/// The suffix store never gets smaller than this, even if the memory limit asks for it
+const MIN_SUFFIX_SLOTS: usize = 256;
+/// The search for the longest match stops at the first match that is at least this long
+const GOOD_ENOUGH_MATCH_LEN: usize = 64;
+/// The window [CompressionLevel::Default] finds matches in
+const DEFAULT_LEVEL_WINDOW_SIZE: usize = 1024 * 1024;
+/// How many positions [CompressionLevel::Default] remembers for each key, so it can pick the longest match among them
+const DEFAULT_LEVEL_BUCKET_LEN: usize = 4;
+/// Single segment frames with a declared content size up to this size get a window that covers the whole content
+const MAX_SINGLE_SEGMENT_WINDOW_SIZE: usize = DEFAULT_LEVEL_WINDOW_SIZE;
 
 /// Takes care of allocating and reusing vecs
 pub struct MatchGeneratorDriver {
@@ -20,23 +30,124 @@ pub struct MatchGeneratorDriver {
     suffix_pool: Vec<SuffixStore>,
     match_generator: MatchGenerator,
     slice_size: usize,
+    /// Overrides the suffix stride that would be chosen based on the compression level
+    suffix_stride: Option<NonZeroUsize>,
+    /// Limits how many slots the suffix stores get, see [Matcher::set_max_memory]
+    max_memory: Option<usize>,
+    /// How many slices the window has at [CompressionLevel::Fastest]
+    fastest_slices_in_window: usize,
+    /// How many positions the suffix stores keep per key, depends on the compression level
+    bucket_len: usize,
+    /// The window size reported to the encoder, depends on the compression level
+    window_size: u64,
+    /// The content size of the next frame, see [Matcher::set_content_size]
+    content_size: Option<u64>,
 }
 
 impl MatchGeneratorDriver {
     /// slice_size says how big the slices should be that are allocated to work with
-    /// max_slices_in_window says how many slices should at most be used while looking for matches
+    /// max_slices_in_window says how many slices should at most be used while looking for matches at [CompressionLevel::Fastest],
+    /// [CompressionLevel::Default] uses a window of 1MB
     pub(crate) fn new(slice_size: usize, max_slices_in_window: usize) -> Self {
         Self {
             vec_pool: Vec::new(),
             suffix_pool: Vec::new(),
             match_generator: MatchGenerator::new(max_slices_in_window * slice_size),
             slice_size,
+            suffix_stride: None,
+            max_memory: None,
+            fastest_slices_in_window: max_slices_in_window,
+            bucket_len: 1,
+            window_size: 256 * 1024,
+            content_size: None,
+        }
+    }
+
+    /// Only insert every n-th suffix into the suffix store, trading compression ratio for speed.
+    ///
+    /// Passing `None` goes back to choosing the stride based on the compression level.
+    /// Takes effect on the next call to [Matcher::reset].
+    pub(crate) fn set_suffix_stride(&mut self, suffix_stride: Option<NonZeroUsize>) {
+        self.suffix_stride = suffix_stride;
+    }
+
+    /// The suffix stride used if none was set explicitly
+    fn suffix_stride_for_level(level: CompressionLevel) -> NonZeroUsize {
+        let stride = match level {
+            CompressionLevel::Fastest => 2,
+            _ => 1,
+        };
+        NonZeroUsize::new(stride).unwrap()
+    }
+
+    /// Whether a frame with the declared content size gets a single segment window that covers the whole content.
+    ///
+    /// The window may not need more memory than the limit set with [Matcher::set_max_memory] allows for the data buffers.
+    fn single_segment_window(&self) -> Option<usize> {
+        let content_size = self.content_size?;
+        if content_size > MAX_SINGLE_SEGMENT_WINDOW_SIZE as u64 {
+            return None;
+        }
+        let content_size = content_size as usize;
+        let memory = content_size + self.slice_size;
+        let fits_memory = self
+            .max_memory
+            .is_none_or(|max_memory| memory <= max_memory);
+        fits_memory.then_some(content_size)
+    }
+
+    /// How many slots the suffix store for a space of `space_len` bytes gets
+    ///
+    /// Without a memory limit this is one slot per byte. With a limit, the memory left after the data buffers
+    /// of the window and of the block that is being filled is split between the suffix stores of the window.
+    /// The capacity is always a multiple of the bucket length.
+    fn suffix_store_capacity(&self, space_len: usize) -> usize {
+        let max_memory = match self.max_memory {
+            Some(max_memory) => max_memory,
+            None => return (space_len / self.bucket_len).max(1) * self.bucket_len,
+        };
+        let max_window_size = self.match_generator.max_window_size;
+        let stores_in_window = max_window_size.div_ceil(self.slice_size).max(1);
+        let data_memory = max_window_size + self.slice_size;
+        let slots = max_memory.saturating_sub(data_memory)
+            / stores_in_window
+            / core::mem::size_of::<Option<NonZeroUsize>>();
+        if slots >= space_len {
+            (space_len / self.bucket_len).max(1) * self.bucket_len
+        } else {
+            // Powers of two keep the distribution of keys even
+            1 << slots.max(MIN_SUFFIX_SLOTS).ilog2()
         }
     }
 }
 
 impl Matcher for MatchGeneratorDriver {
-    fn reset(&mut self, _level: CompressionLevel) {
+    fn reset(&mut self, level: CompressionLevel) {
+        let (max_window_size, bucket_len, window_size) = match level {
+            CompressionLevel::Default => (
+                DEFAULT_LEVEL_WINDOW_SIZE,
+                DEFAULT_LEVEL_BUCKET_LEN,
+                DEFAULT_LEVEL_WINDOW_SIZE as u64,
+            ),
+            _ => (
+                self.fastest_slices_in_window * self.slice_size,
+                1,
+                256 * 1024,
+            ),
+        };
+        // The decoder keeps the whole content of a single segment frame, so matches may reach back to its start
+        let (max_window_size, window_size) = match self.single_segment_window() {
+            Some(content_size) => (max_window_size.max(content_size), content_size as u64),
+            None => (max_window_size, window_size),
+        };
+        self.bucket_len = bucket_len;
+        self.window_size = window_size;
+
+        self.match_generator.suffix_stride = self
+            .suffix_stride
+            .unwrap_or_else(|| Self::suffix_stride_for_level(level))
+            .get();
+
         let vec_pool = &mut self.vec_pool;
         let suffix_pool = &mut self.suffix_pool;
 
@@ -47,10 +158,19 @@ impl Matcher for MatchGeneratorDriver {
             suffixes.slots.resize(suffixes.slots.capacity(), None);
             suffix_pool.push(suffixes);
         });
+        self.match_generator.max_window_size = max_window_size;
     }
 
     fn window_size(&self) -> u64 {
-        256 * 1024
+        self.window_size
+    }
+
+    fn set_max_memory(&mut self, max_memory: Option<usize>) {
+        self.max_memory = max_memory;
+    }
+
+    fn set_content_size(&mut self, content_size: Option<u64>) {
+        self.content_size = content_size;
     }
 
     fn get_next_space(&mut self) -> Vec<u8> {
@@ -66,11 +186,17 @@ impl Matcher for MatchGeneratorDriver {
     }
 
     fn commit_space(&mut self, space: Vec<u8>) {
+        let suffix_capacity = self.suffix_store_capacity(space.len());
+        let bucket_len = self.bucket_len;
         let vec_pool = &mut self.vec_pool;
         let suffixes = self
             .suffix_pool
             .pop()
-            .unwrap_or_else(|| SuffixStore::with_capacity(space.len()));
+            // Stores sized for a different limit, a shorter space or another level are not reused
+            .filter(|suffixes| {
+                suffixes.slots.len() == suffix_capacity && suffixes.bucket_len == bucket_len
+            })
+            .unwrap_or_else(|| SuffixStore::with_capacity(suffix_capacity, bucket_len));
         let suffix_pool = &mut self.suffix_pool;
         self.match_generator
             .add_data(space, suffixes, |mut data, mut suffixes| {
@@ -92,42 +218,65 @@ impl Matcher for MatchGeneratorDriver {
 
 /// This stores the index of a suffix of a string by hashing the first few bytes of that suffix
 /// This means that collisions just overwrite and that you need to check validity after a get
+///
+/// Each key has a bucket of `bucket_len` slots. With a single slot, the first index inserted for a key is kept.
+/// With more slots, the most recent indexes are kept, the newest first.
 struct SuffixStore {
     // We use NonZeroUsize to enable niche optimization here.
     // On store we do +1 and on get -1
     // This is ok since usize::MAX is never a valid offset
     slots: Vec<Option<NonZeroUsize>>,
+    bucket_len: usize,
     len_log: u32,
 }
 
 impl SuffixStore {
-    fn with_capacity(capacity: usize) -> Self {
+    /// `capacity` is the number of slots and has to be a multiple of `bucket_len`
+    fn with_capacity(capacity: usize, bucket_len: usize) -> Self {
+        assert!(capacity >= bucket_len && capacity.is_multiple_of(bucket_len));
         Self {
             slots: alloc::vec![None; capacity],
-            len_log: capacity.ilog2(),
+            bucket_len,
+            len_log: (capacity / bucket_len).ilog2(),
         }
     }
 
     #[inline(always)]
     fn insert(&mut self, suffix: &[u8], idx: usize) {
-        let key = self.key(suffix);
-        self.slots[key] = Some(NonZeroUsize::new(idx + 1).unwrap());
+        let value = Some(NonZeroUsize::new(idx + 1).unwrap());
+        let bucket = self.bucket(Self::hash(suffix));
+        let bucket = &mut self.slots[bucket];
+        if bucket.len() == 1 {
+            if bucket[0].is_none() {
+                bucket[0] = value;
+            }
+        } else {
+            bucket.rotate_right(1);
+            bucket[0] = value;
+        }
     }
 
+    /// The indexes stored for the key with this [SuffixStore::hash], the newest first
     #[inline(always)]
-    fn contains_key(&self, suffix: &[u8]) -> bool {
-        let key = self.key(suffix);
-        self.slots[key].is_some()
+    fn candidates(&self, hash: u64) -> impl Iterator<Item = usize> + '_ {
+        self.slots[self.bucket(hash)]
+            .iter()
+            .map_while(|slot| slot.map(|x| <NonZeroUsize as Into<usize>>::into(x) - 1))
     }
 
     #[inline(always)]
-    fn get(&self, suffix: &[u8]) -> Option<usize> {
-        let key = self.key(suffix);
-        self.slots[key].map(|x| <NonZeroUsize as Into<usize>>::into(x) - 1)
+    fn bucket(&self, hash: u64) -> core::ops::Range<usize> {
+        // A store with a single bucket has a len_log of 0, shifting by 64 would overflow
+        let key = hash.checked_shr(64 - self.len_log).unwrap_or(0) as usize
+            % (self.slots.len() / self.bucket_len);
+        let start = key * self.bucket_len;
+        start..start + self.bucket_len
     }
 
+    /// Hash of the first bytes of the suffix, independent of the size of the store
+    /// so it only needs to be computed once to look up the suffix in all stores of the window
     #[inline(always)]
-    fn key(&self, suffix: &[u8]) -> usize {
+    fn hash(suffix: &[u8]) -> u64 {
         let s0 = suffix[0] as u64;
         let s1 = suffix[1] as u64;
         let s2 = suffix[2] as u64;
@@ -142,9 +291,7 @@ impl SuffixStore {
         let s3 = (s3 << 48).wrapping_mul(POLY);
         let s4 = (s4 << 56).wrapping_mul(POLY);
 
-        let index = s0 ^ s1 ^ s2 ^ s3 ^ s4;
-        let index = index >> (64 - self.len_log);
-        index as usize % self.slots.len()
+        s0 ^ s1 ^ s2 ^ s3 ^ s4
     }
 }
 
@@ -164,12 +311,14 @@ pub(crate) struct MatchGenerator {
     /// The data we want to find matches for is in the last slice
     window: Vec<WindowEntry>,
     window_size: usize,
-    #[cfg(debug_assertions)]
+    #[cfg(any(test, feature = "match_verify"))]
     concat_window: Vec<u8>,
     /// Index in the last slice that we already processed
     suffix_idx: usize,
     /// Gets updated when a new sequence is returned to point right behind that sequence
     last_idx_in_sequence: usize,
+    /// Only suffixes starting at a multiple of this are added to the suffix store
+    suffix_stride: usize,
 }
 
 impl MatchGenerator {
@@ -179,16 +328,17 @@ impl MatchGenerator {
             max_window_size: max_size,
             window: Vec::new(),
             window_size: 0,
-            #[cfg(debug_assertions)]
+            #[cfg(any(test, feature = "match_verify"))]
             concat_window: Vec::new(),
             suffix_idx: 0,
             last_idx_in_sequence: 0,
+            suffix_stride: 1,
         }
     }
 
     fn reset(&mut self, mut reuse_space: impl FnMut(Vec<u8>, SuffixStore)) {
         self.window_size = 0;
-        #[cfg(debug_assertions)]
+        #[cfg(any(test, feature = "match_verify"))]
         self.concat_window.clear();
         self.suffix_idx = 0;
         self.last_idx_in_sequence = 0;
@@ -233,58 +383,66 @@ impl MatchGenerator {
             // This is the key we are looking to find a match for
             let key = &data_slice[..MIN_MATCH_LEN];
 
-            // Look in each window entry
-            for (match_entry_idx, match_entry) in self.window.iter().enumerate() {
-                let is_last = match_entry_idx == self.window.len() - 1;
-                if let Some(match_index) = match_entry.suffixes.get(key) {
-                    let match_slice = if is_last {
-                        &match_entry.data[match_index..self.suffix_idx]
-                    } else {
-                        &match_entry.data[match_index..]
-                    };
+            // Look in each window entry, starting with the newest so the shortest offset wins between matches of the same length
+            let hash = SuffixStore::hash(key);
+            let mut best_match: Option<(usize, usize)> = None;
+            'search: for match_entry in self.window.iter().rev() {
+                for match_index in match_entry.suffixes.candidates(hash) {
+                    // In the current entry the match may run into the data it is matching,
+                    // decoders copy such overlapping matches byte by byte
+                    let match_slice = &match_entry.data[match_index..];
 
                     // Check how long the common prefix actually is
                     let match_len = Self::common_prefix_len(match_slice, data_slice);
 
                     // Collisions in the suffix store might make this check fail
-                    if match_len >= MIN_MATCH_LEN {
+                    if match_len >= MIN_MATCH_LEN
+                        && best_match.is_none_or(|(_, best_len)| match_len > best_len)
+                    {
                         let offset = match_entry.base_offset + self.suffix_idx - match_index;
-
-                        // If we are in debug/tests make sure the match we found is actually at the offset we calculated
-                        #[cfg(debug_assertions)]
-                        {
-                            let unprocessed = last_entry.data.len() - self.suffix_idx;
-                            let start = self.concat_window.len() - unprocessed - offset;
-                            let end = start + match_len;
-                            let check_slice = &self.concat_window[start..end];
-                            debug_assert_eq!(check_slice, &match_slice[..match_len]);
+                        best_match = Some((offset, match_len));
+                        if match_len >= GOOD_ENOUGH_MATCH_LEN {
+                            // Longer matches barely improve compression, but comparing them is expensive
+                            break 'search;
                         }
+                    }
+                }
+            }
 
-                        // For each index in the match we found we do not need to look for another match
-                        // But we still want them registered in the suffix store
-                        self.add_suffixes_till(self.suffix_idx + match_len);
-
-                        // All literals that were not included between this match and the last are now included here
-                        let last_entry = self.window.last().unwrap();
-                        let literals = &last_entry.data[self.last_idx_in_sequence..self.suffix_idx];
+            if let Some((offset, match_len)) = best_match {
+                // If verification is enabled make sure the match we found is actually at the offset we calculated
+                #[cfg(any(test, feature = "match_verify"))]
+                {
+                    let unprocessed = last_entry.data.len() - self.suffix_idx;
+                    let start = self.concat_window.len() - unprocessed - offset;
+                    let end = start + match_len;
+                    let check_slice = &self.concat_window[start..end];
+                    assert_eq!(check_slice, &data_slice[..match_len]);
+                }
 
-                        // Update the indexes, all indexes upto and including the current index have been included in a sequence now
-                        self.suffix_idx += match_len;
-                        self.last_idx_in_sequence = self.suffix_idx;
-                        handle_sequence(Sequence::Triple {
-                            literals,
-                            offset,
-                            match_len,
-                        });
+                // For each index in the match we found we do not need to look for another match
+                // But we still want them registered in the suffix store
+                self.add_suffixes_till(self.suffix_idx + match_len);
+
+                // All literals that were not included between this match and the last are now included here
+                let last_entry = self.window.last().unwrap();
+                let literals = &last_entry.data[self.last_idx_in_sequence..self.suffix_idx];
+
+                // Update the indexes, all indexes upto and including the current index have been included in a sequence now
+                self.suffix_idx += match_len;
+                self.last_idx_in_sequence = self.suffix_idx;
+                handle_sequence(Sequence::Triple {
+                    literals,
+                    offset,
+                    match_len,
+                });
 
-                        return true;
-                    }
-                }
+                return true;
             }
 
-            let last_entry = self.window.last_mut().unwrap();
-            let key = &last_entry.data[self.suffix_idx..self.suffix_idx + MIN_MATCH_LEN];
-            if !last_entry.suffixes.contains_key(key) {
+            if self.suffix_idx.is_multiple_of(self.suffix_stride) {
+                let last_entry = self.window.last_mut().unwrap();
+                let key = &last_entry.data[self.suffix_idx..self.suffix_idx + MIN_MATCH_LEN];
                 last_entry.suffixes.insert(key, self.suffix_idx);
             }
             self.suffix_idx += 1;
@@ -318,9 +476,11 @@ impl MatchGenerator {
         }
         let slice = &last_entry.data[self.suffix_idx..idx];
         for (key_index, key) in slice.windows(MIN_MATCH_LEN).enumerate() {
-            if !last_entry.suffixes.contains_key(key) {
-                last_entry.suffixes.insert(key, self.suffix_idx + key_index);
+            let suffix_idx = self.suffix_idx + key_index;
+            if !suffix_idx.is_multiple_of(self.suffix_stride) {
+                continue;
             }
+            last_entry.suffixes.insert(key, suffix_idx);
         }
     }
 
@@ -344,7 +504,7 @@ impl MatchGenerator {
             self.window.is_empty() || self.suffix_idx == self.window.last().unwrap().data.len()
         );
         self.reserve(data.len(), reuse_space);
-        #[cfg(debug_assertions)]
+        #[cfg(any(test, feature = "match_verify"))]
         self.concat_window.extend_from_slice(&data);
 
         if let Some(last_len) = self.window.last().map(|last| last.data.len()) {
@@ -371,7 +531,7 @@ impl MatchGenerator {
         while self.window_size + amount > self.max_window_size {
             let removed = self.window.remove(0);
             self.window_size -= removed.data.len();
-            #[cfg(debug_assertions)]
+            #[cfg(any(test, feature = "match_verify"))]
             self.concat_window.drain(0..removed.data.len());
 
             let WindowEntry {
@@ -401,15 +561,16 @@ fn matches() {
             } => {
                 reconstructed.extend_from_slice(literals);
                 let start = reconstructed.len() - offset;
-                let end = start + match_len;
-                reconstructed.extend_from_within(start..end);
+                for idx in start..start + match_len {
+                    reconstructed.push(reconstructed[idx]);
+                }
             }
         }
     };
 
     matcher.add_data(
         alloc::vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
@@ -418,9 +579,9 @@ fn matches() {
         assert_seq_equal(
             seq,
             Sequence::Triple {
-                literals: &[0, 0, 0, 0, 0],
-                offset: 5,
-                match_len: 5,
+                literals: &[0],
+                offset: 1,
+                match_len: 9,
             },
             &mut reconstructed,
         )
@@ -430,7 +591,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![1, 2, 3, 4, 5, 6, 1, 2, 3, 4, 5, 6, 1, 2, 3, 4, 5, 6, 0, 0, 0, 0, 0,],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[
@@ -443,18 +604,7 @@ fn matches() {
             Sequence::Triple {
                 literals: &[1, 2, 3, 4, 5, 6],
                 offset: 6,
-                match_len: 6,
-            },
-            &mut reconstructed,
-        )
-    });
-    matcher.next_sequence(|seq| {
-        assert_seq_equal(
-            seq,
-            Sequence::Triple {
-                literals: &[],
-                offset: 12,
-                match_len: 6,
+                match_len: 12,
             },
             &mut reconstructed,
         )
@@ -474,7 +624,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0, 0, 0, 0, 0],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0, 0, 0, 0, 0]);
@@ -495,7 +645,7 @@ fn matches() {
             seq,
             Sequence::Triple {
                 literals: &[7, 8, 9, 10, 11],
-                offset: 44,
+                offset: 16,
                 match_len: 5,
             },
             &mut reconstructed,
@@ -505,7 +655,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![0, 0, 0, 0, 0],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[0, 0, 0, 0, 0]);
@@ -515,7 +665,7 @@ fn matches() {
             seq,
             Sequence::Triple {
                 literals: &[],
-                offset: 49,
+                offset: 5,
                 match_len: 5,
             },
             &mut reconstructed,
@@ -525,7 +675,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![7, 8, 9, 10, 11],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[7, 8, 9, 10, 11]);
@@ -545,7 +695,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![1, 3, 5, 7, 9],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     matcher.skip_matching();
@@ -555,7 +705,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![1, 3, 5, 7, 9],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[1, 3, 5, 7, 9]);
@@ -575,7 +725,7 @@ fn matches() {
 
     matcher.add_data(
         alloc::vec![0, 0, 11, 13, 15, 17, 20, 11, 13, 15, 17, 20, 21, 23],
-        SuffixStore::with_capacity(100),
+        SuffixStore::with_capacity(100, 1),
         |_, _| {},
     );
     original_data.extend_from_slice(&[0, 0, 11, 13, 15, 17, 20, 11, 13, 15, 17, 20, 21, 23]);
@@ -604,3 +754,155 @@ fn matches() {
 
     assert_eq!(reconstructed, original_data);
 }
+
+#[test]
+fn longest_candidate_is_picked() {
+    let mut matcher = MatchGenerator::new(1000);
+    // The key 1, 2, 3, 4, 5 occurs three times, followed by matches of different lengths with the end
+    let data = alloc::vec![
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 40, 1, 2, 3, 4, 5, 20, 50, 1, 2, 3, 4, 5, 6, 7, 30, 60, 1, 2, 3,
+        4, 5, 6, 7, 8, 9,
+    ];
+    matcher.add_data(data, SuffixStore::with_capacity(128, 4), |_, _| {});
+
+    let mut sequences = Vec::new();
+    while matcher.next_sequence(|seq| {
+        if let Sequence::Triple {
+            offset, match_len, ..
+        } = seq
+        {
+            sequences.push((offset, match_len));
+        }
+    }) {}
+    // The last occurrence matches the first one best, even though the others are closer
+    assert_eq!(sequences.last(), Some(&(26, 9)));
+}
+
+#[test]
+fn default_level_round_trip() {
+    use crate::decoding::{FrameDecoder, FrameInfo};
+    use crate::encoding::compress_to_vec;
+    use rand::{RngCore, SeedableRng};
+
+    // Repetitions 300kB apart can only be found with the bigger window
+    let mut part = alloc::vec![0u8; 300 * 1024];
+    rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut part);
+    let mut data = Vec::new();
+    for i in 0..3u8 {
+        data.extend_from_slice(&part);
+        data.extend_from_slice(&[i; 100]);
+    }
+
+    let fastest = compress_to_vec(data.as_slice(), CompressionLevel::Fastest);
+    let default = compress_to_vec(data.as_slice(), CompressionLevel::Default);
+    assert!(default.len() < fastest.len() / 2);
+    assert_eq!(
+        FrameInfo::read(fastest.as_slice()).unwrap().window_size,
+        256 * 1024
+    );
+    assert_eq!(
+        FrameInfo::read(default.as_slice()).unwrap().window_size,
+        DEFAULT_LEVEL_WINDOW_SIZE as u64
+    );
+
+    let text = include_bytes!("../../decodecorpus_files/z000033");
+    for input in [data.as_slice(), text] {
+        let compressed = compress_to_vec(input, CompressionLevel::Default);
+        let mut decoded = Vec::with_capacity(input.len());
+        FrameDecoder::new()
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(input, decoded);
+        assert_eq!(input, zstd::decode_all(compressed.as_slice()).unwrap());
+    }
+}
+
+#[test]
+fn short_inputs_round_trip() {
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::{compress_to_vec, LEVEL_LADDER};
+
+    // Inputs of a few bytes get a suffix store with a single bucket
+    let data: Vec<u8> = (0..64u8).map(|i| i % 5).collect();
+    for len in 0..=data.len() {
+        for level in LEVEL_LADDER {
+            let compressed = compress_to_vec(&data[..len], level);
+            let mut decoded = Vec::with_capacity(len);
+            FrameDecoder::new()
+                .decode_all_to_vec(&compressed, &mut decoded)
+                .unwrap();
+            assert_eq!(&data[..len], decoded, "{} bytes at {:?}", len, level);
+        }
+    }
+}
+
+#[test]
+fn suffix_strides_round_trip() {
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::FrameCompressor;
+
+    let mut data = Vec::new();
+    for i in 0..20_000u32 {
+        data.extend_from_slice(&(i % 251).to_le_bytes());
+        data.extend_from_slice(b"some text that repeats");
+    }
+
+    for stride in 1..=4 {
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_suffix_stride(NonZeroUsize::new(stride));
+        let mut compressed = Vec::new();
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(&mut compressed);
+        compressor.compress().unwrap();
+
+        let mut decoded = Vec::with_capacity(data.len());
+        FrameDecoder::new()
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(data, decoded);
+    }
+}
+
+#[test]
+fn max_memory_limits_suffix_store() {
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::{EncoderParams, FrameCompressor};
+
+    let mut driver = MatchGeneratorDriver::new(1024 * 128, 1);
+    assert_eq!(driver.suffix_store_capacity(1024 * 128), 1024 * 128);
+    driver.set_max_memory(Some(1024 * 1024));
+    assert_eq!(driver.suffix_store_capacity(1024 * 128), 1024 * 64);
+    // Small spaces don't need all the slots the limit allows
+    assert_eq!(driver.suffix_store_capacity(1000), 1000);
+    driver.set_max_memory(Some(0));
+    assert_eq!(driver.suffix_store_capacity(1024 * 128), MIN_SUFFIX_SLOTS);
+
+    let mut data = Vec::new();
+    for i in 0..60_000u32 {
+        data.extend_from_slice(&(i % 1013).to_le_bytes());
+        data.extend_from_slice(b"some text that repeats");
+    }
+
+    let mut sizes = Vec::new();
+    for max_memory in [None, Some(512 * 1024), Some(0)] {
+        let params = EncoderParams {
+            max_memory,
+            ..Default::default()
+        };
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_params(params);
+        let mut compressed = Vec::new();
+        compressor.set_source(data.as_slice());
+        compressor.set_drain(&mut compressed);
+        compressor.compress().unwrap();
+
+        let mut decoded = Vec::with_capacity(data.len());
+        FrameDecoder::new()
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(data, decoded);
+        sizes.push(compressed.len());
+    }
+    assert!(sizes[0] <= sizes[1]);
+    assert!(sizes[1] <= sizes[2]);
+}
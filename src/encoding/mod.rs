@@ -5,13 +5,18 @@ pub(crate) mod block_header;
 pub(crate) mod blocks;
 pub(crate) mod frame_header;
 pub(crate) mod match_generator;
+pub(crate) mod scratch;
 pub(crate) mod util;
 
+pub mod errors;
 mod frame_compressor;
-pub use frame_compressor::FrameCompressor;
+mod streaming_encoder;
+pub use frame_compressor::{BlockReport, CompressStats, FrameCompressor, MatchStats, ProgressInfo};
+pub use streaming_encoder::StreamingEncoder;
 
-use crate::io::{Read, Write};
+use crate::io::{Error, Read, Write};
 use alloc::vec::Vec;
+use errors::{CompressError, InvalidSequencesError};
 
 /// Convenience function to compress some source into a target without reusing any resources of the compressor
 /// ```rust
@@ -20,11 +25,33 @@ use alloc::vec::Vec;
 /// let mut target = Vec::new();
 /// compress(data, &mut target, CompressionLevel::Fastest);
 /// ```
+///
+/// # Panics
+/// If reading from the source or writing to the target fails, or the level is not implemented.
+/// Use [try_compress] to handle these errors.
 pub fn compress<R: Read, W: Write>(source: R, target: W, level: CompressionLevel) {
+    try_compress(source, target, level).expect("Error while compressing");
+}
+
+/// Like [compress], but returns [CompressError::ReadError] or [CompressError::WriteError] if reading from the source
+/// or writing to the target fails, and [CompressError::UnsupportedLevel] for levels that are not implemented,
+/// instead of panicking
+/// ```rust
+/// use ruzstd::encoding::{try_compress, CompressionLevel};
+/// let data: &[u8] = &[0,0,0,0,0,0,0,0,0,0,0,0];
+/// let mut target = [0u8; 8];
+/// // The frame does not fit into the target
+/// assert!(try_compress(data, &mut target[..], CompressionLevel::Fastest).is_err());
+/// ```
+pub fn try_compress<R: Read, W: Write>(
+    source: R,
+    target: W,
+    level: CompressionLevel,
+) -> Result<(), CompressError> {
     let mut frame_enc = FrameCompressor::new(level);
     frame_enc.set_source(source);
     frame_enc.set_drain(target);
-    frame_enc.compress();
+    frame_enc.compress()
 }
 
 /// Convenience function to compress some source into a Vec without reusing any resources of the compressor
@@ -33,16 +60,450 @@ pub fn compress<R: Read, W: Write>(source: R, target: W, level: CompressionLevel
 /// let data: &[u8] = &[0,0,0,0,0,0,0,0,0,0,0,0];
 /// let compressed = compress_to_vec(data, CompressionLevel::Fastest);
 /// ```
+///
+/// Use [compress_slice_to_vec] to also write the size of the input into the frame header.
+///
+/// # Panics
+/// If reading from the source fails, or the level is not implemented. Use [try_compress_to_vec] to handle these errors.
 pub fn compress_to_vec<R: Read>(source: R, level: CompressionLevel) -> Vec<u8> {
+    try_compress_to_vec(source, level).expect("Error while compressing")
+}
+
+/// Like [compress_to_vec], but returns [CompressError::ReadError] if reading from the source fails,
+/// and [CompressError::UnsupportedLevel] for levels that are not implemented, instead of panicking
+pub fn try_compress_to_vec<R: Read>(
+    source: R,
+    level: CompressionLevel,
+) -> Result<Vec<u8>, CompressError> {
     let mut vec = Vec::new();
-    compress(source, &mut vec, level);
+    try_compress(source, &mut vec, level)?;
+    Ok(vec)
+}
+
+/// Convenience function to compress a slice into a Vec, with the size of the slice in the frame header.
+///
+/// Unlike [compress_to_vec], which can't know how much data a reader will return, this sets
+/// [FrameCompressor::set_content_size], so decoders learn the decompressed size before decoding the frame.
+/// ```rust
+/// use ruzstd::encoding::{compress_slice_to_vec, CompressionLevel};
+/// let data: &[u8] = &[0,0,0,0,0,0,0,0,0,0,0,0];
+/// let compressed = compress_slice_to_vec(data, CompressionLevel::Fastest);
+/// let info = ruzstd::decoding::FrameInfo::read(compressed.as_slice()).unwrap();
+/// assert_eq!(info.content_size, Some(data.len() as u64));
+/// ```
+pub fn compress_slice_to_vec(data: &[u8], level: CompressionLevel) -> Vec<u8> {
+    let mut vec = Vec::new();
+    let mut frame_enc = FrameCompressor::new(level);
+    frame_enc.set_source(data);
+    frame_enc.set_drain(&mut vec);
+    frame_enc.set_content_size(Some(data.len() as u64));
+    frame_enc
+        .compress()
+        .expect("The content size is the length of the source, and verification is not enabled");
     vec
 }
 
+/// How well some data compresses, see [compressibility_report]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressibilityReport {
+    /// How each block of the frame was encoded
+    pub blocks: Vec<BlockReport>,
+    /// The size of the input
+    pub input_bytes: u64,
+    /// The size of the whole frame, including the frame header
+    pub compressed_bytes: u64,
+}
+
+impl CompressibilityReport {
+    /// The compression ratio, `input_bytes / compressed_bytes`. Values below 1 mean the frame is bigger than the input.
+    pub fn ratio(&self) -> f64 {
+        self.input_bytes as f64 / self.compressed_bytes as f64
+    }
+}
+
+/// Compress the data and report how each block would be encoded and how big the frame would be, without keeping the compressed data.
+///
+/// This does the same work as [compress_to_vec], so it takes as long, but only one block is kept in memory at a time.
+/// ```rust
+/// use ruzstd::decoding::BlockType;
+/// use ruzstd::encoding::{compressibility_report, CompressionLevel};
+/// let data = std::vec![7u8; 300 * 1024];
+/// let report = compressibility_report(data.as_slice(), CompressionLevel::Fastest);
+/// assert!(report.blocks.iter().all(|block| block.block_type == BlockType::RLE));
+/// assert!(report.ratio() > 1000.0);
+/// ```
+pub fn compressibility_report(data: &[u8], level: CompressionLevel) -> CompressibilityReport {
+    let mut frame_enc: FrameCompressor<&[u8], Vec<u8>, _> = FrameCompressor::new(level);
+    frame_enc.set_source(data);
+    let mut blocks = Vec::new();
+    let mut compressed_bytes = 0;
+    // Reading from a slice does not fail
+    while let Some(part) = frame_enc.next_compressed_block().unwrap() {
+        compressed_bytes += part.len() as u64;
+        blocks.extend_from_slice(frame_enc.last_block_reports());
+    }
+    CompressibilityReport {
+        blocks,
+        input_bytes: data.len() as u64,
+        compressed_bytes,
+    }
+}
+
+/// The maximum size of a frame that the convenience functions of this module produce for an input of `input_len` bytes.
+///
+/// Blocks that would not get smaller by compressing them are stored raw, so the frame is at most as big as the input
+/// plus the [frame_overhead].
+/// ```rust
+/// use ruzstd::encoding::{compress_bound, compress_into, CompressionLevel};
+/// let data: &[u8] = &[0,0,0,0,0,0,0,0,0,0,0,0];
+/// let mut target = vec![0; compress_bound(data.len())];
+/// let written = compress_into(data, &mut target, CompressionLevel::Fastest).unwrap();
+/// target.truncate(written);
+/// ```
+pub fn compress_bound(input_len: usize) -> usize {
+    input_len + frame_overhead(&EncoderParams::default(), input_len as u64)
+}
+
+/// Convenience function to compress a slice into a caller provided buffer, returning the number of bytes written.
+///
+/// This works without any lifetimes or generics, which makes it easy to wrap for other languages.
+/// If `target` is at least [compress_bound] bytes long the frame always fits,
+/// otherwise [CompressError::TargetTooSmall] is returned if it doesn't fit. The target then holds the start of the frame.
+pub fn compress_into(
+    source: &[u8],
+    target: &mut [u8],
+    level: CompressionLevel,
+) -> Result<usize, CompressError> {
+    let available = target.len();
+    let mut writer = SliceWriter { target, written: 0 };
+    // Reading from a slice and writing to a SliceWriter do not fail
+    try_compress(source, &mut writer, level).unwrap();
+    if writer.written > available {
+        return Err(CompressError::TargetTooSmall {
+            needed: writer.written,
+            available,
+        });
+    }
+    Ok(writer.written)
+}
+
+/// Writes into a slice. What doesn't fit is only counted, so the size of the whole frame is known in the end.
+struct SliceWriter<'a> {
+    target: &'a mut [u8],
+    written: usize,
+}
+
+impl Write for SliceWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if let Some(target) = self.target.get_mut(self.written..) {
+            let len = target.len().min(buf.len());
+            target[..len].copy_from_slice(&buf[..len]);
+        }
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// The levels [compress_to_budget] tries, ordered from the least to the most effort
+pub(crate) const LEVEL_LADDER: [CompressionLevel; 4] = [
+    CompressionLevel::Uncompressed,
+    CompressionLevel::RleOnly,
+    CompressionLevel::Fastest,
+    CompressionLevel::Default,
+];
+
+/// Compress the data with the least effort that makes the frame fit into `max_output` bytes.
+///
+/// The levels are tried from [CompressionLevel::Uncompressed] up to the strongest level the encoder implements,
+/// and the first frame that is at most `max_output` bytes long is returned. This may compress the data
+/// once for each level, so it can take several times as long as [compress_to_vec].
+/// If no level produces a small enough frame, [CompressError::TargetTooSmall] reports the size of the smallest frame.
+/// ```rust
+/// use ruzstd::encoding::{compress_to_budget, errors::CompressError};
+/// let data = std::vec![b'a'; 10_000];
+/// let compressed = compress_to_budget(&data, 100).unwrap();
+/// assert!(compressed.len() <= 100);
+///
+/// let err = compress_to_budget(&data, 5).unwrap_err();
+/// assert!(matches!(err, CompressError::TargetTooSmall { available: 5, .. }));
+/// ```
+pub fn compress_to_budget(data: &[u8], max_output: usize) -> Result<Vec<u8>, CompressError> {
+    let mut smallest = usize::MAX;
+    for level in LEVEL_LADDER {
+        let compressed = compress_to_vec(data, level);
+        if compressed.len() <= max_output {
+            return Ok(compressed);
+        }
+        smallest = smallest.min(compressed.len());
+    }
+    Err(CompressError::TargetTooSmall {
+        needed: smallest,
+        available: max_output,
+    })
+}
+
+/// Encode the content of a compressed block from literals and sequences found by the caller, and append it to `out`.
+///
+/// This only runs the entropy coding of the encoder, the literals are Huffman coded and the sequences FSE coded,
+/// so data that a domain specific match finder already split into sequences does not have to go through a [Matcher].
+/// The sequences use the same representation as [crate::decoding::decode_block_sequences] returns: the literals
+/// of all sequences are concatenated in `literals` followed by the literals after the last match, and offset values
+/// from 1 to 3 refer to the repeat offsets of the frame.
+///
+/// Only the block content is written. It is the content of a compressed block whose header declares `out.len()`
+/// (minus what was in `out` before) as its size, and it can be decoded with [crate::decoding::BlockContentDecoder].
+/// The FSE tables are built for this block alone and are never repeated from another block.
+///
+/// ```
+/// use ruzstd::decoding::{BlockContentDecoder, BlockHeader, BlockType, Sequence};
+/// use ruzstd::encoding::encode_block_from_sequences;
+///
+/// // "abc" followed by a match repeating it three times and a final "!"
+/// let sequences = [Sequence { ll: 3, ml: 9, of: 3 + 3 }];
+/// let mut content = Vec::new();
+/// encode_block_from_sequences(b"abc!", &sequences, &mut content).unwrap();
+///
+/// let header = BlockHeader {
+///     last_block: true,
+///     block_type: BlockType::Compressed,
+///     decompressed_size: 0,
+///     content_size: content.len() as u32,
+/// };
+/// let mut decoder = BlockContentDecoder::new(1024);
+/// assert_eq!(decoder.decode_block(&header, &content).unwrap(), b"abcabcabcabc!");
+/// ```
+///
+/// Fails with [CompressError::InvalidSequences] without writing anything if the sequences need more literals than there are,
+/// a sequence has a match shorter than 3 bytes or an offset value of 0, or the block would decode to more than 128kb.
+pub fn encode_block_from_sequences(
+    literals: &[u8],
+    sequences: &[crate::decoding::Sequence],
+    out: &mut Vec<u8>,
+) -> Result<(), CompressError> {
+    const MAX_BLOCK_SIZE: usize = 128 * 1024;
+    let mut sequence_literals = 0usize;
+    let mut match_len = 0usize;
+    for (index, seq) in sequences.iter().enumerate() {
+        if seq.ml < 3 {
+            return Err(InvalidSequencesError::MatchTooShort { index, ml: seq.ml }.into());
+        }
+        if seq.of == 0 {
+            return Err(InvalidSequencesError::ZeroOffset { index }.into());
+        }
+        sequence_literals += seq.ll as usize;
+        match_len += seq.ml as usize;
+    }
+    if sequence_literals > literals.len() {
+        return Err(InvalidSequencesError::MissingLiterals {
+            needed: sequence_literals,
+            available: literals.len(),
+        }
+        .into());
+    }
+    let size = literals.len() + match_len;
+    if size > MAX_BLOCK_SIZE {
+        return Err(InvalidSequencesError::BlockTooBig { size }.into());
+    }
+    let mut scratch = scratch::EncoderScratch::new();
+    blocks::encode_block_content(
+        literals,
+        sequences,
+        &mut scratch,
+        &EncoderParams::default(),
+        out,
+    );
+    Ok(())
+}
+
+/// The number of bytes a frame with `input_len` bytes of content written by [FrameCompressor] takes up in addition to the block content.
+///
+/// These are the 4 byte magic number, the 1 byte frame header descriptor, the 1 byte window descriptor
+/// and a 3 byte block header for each block. The frame can't be smaller than the payload plus this overhead if the payload
+/// does not compress at all, so payloads that are not much bigger than this are usually best stored uncompressed.
+/// Inputs are split into blocks of 128kb, an input that is a multiple of that ends with an additional empty block.
+///
+/// Apart from the 4 byte checksum that is appended with `EncoderParams::content_checksum`, the params only change
+/// how the block content is encoded. With [EncoderParams::content_size] the window descriptor of inputs smaller than one block
+/// is replaced by the content size which takes 1 to 8 bytes depending on the size of the input.
+/// [FrameCompressor::frame_overhead] also includes the settings of a compressor, like [FrameCompressor::set_magicless].
+///
+/// ```rust
+/// use ruzstd::encoding::{compress_to_vec, frame_overhead, CompressionLevel, EncoderParams};
+/// let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+/// let compressed = compress_to_vec(&data[..], CompressionLevel::Uncompressed);
+/// assert_eq!(compressed.len(), data.len() + frame_overhead(&EncoderParams::default(), 10));
+/// ```
+pub fn frame_overhead(params: &EncoderParams, input_len: u64) -> usize {
+    #[cfg(feature = "hash")]
+    let content_checksum = params.content_checksum;
+    #[cfg(not(feature = "hash"))]
+    let content_checksum = false;
+    framing_size(
+        input_len,
+        128 * 1024,
+        params.content_size,
+        content_checksum,
+        false,
+    )
+}
+
+/// The number of bytes the headers and the checksum of a frame take up, see [frame_overhead].
+///
+/// `single_block_content_size` writes the content size instead of the window descriptor if the input fits into one block.
+pub(crate) fn framing_size(
+    input_len: u64,
+    block_size: u64,
+    single_block_content_size: bool,
+    content_checksum: bool,
+    magicless: bool,
+) -> usize {
+    const MAGIC_NUM_SIZE: usize = 4;
+    const BLOCK_HEADER_SIZE: usize = 3;
+    const CHECKSUM_SIZE: usize = 4;
+    // A block that is filled completely is not known to be the last one, so the frame ends with an empty block
+    let blocks = (input_len / block_size + 1) as usize;
+    let single_segment = single_block_content_size && blocks == 1;
+    let mut header = Vec::new();
+    frame_header::FrameHeader {
+        frame_content_size: single_segment.then_some(input_len),
+        single_segment,
+        content_checksum,
+        dictionary_id: None,
+        window_size: (!single_segment).then_some(block_size),
+    }
+    .serialize_magicless(&mut header);
+    let magic_num_size = if magicless { 0 } else { MAGIC_NUM_SIZE };
+    let checksum_size = if content_checksum { CHECKSUM_SIZE } else { 0 };
+    magic_num_size + header.len() + blocks * BLOCK_HEADER_SIZE + checksum_size
+}
+
+/// Parameters to fine tune the encoder beyond what the [CompressionLevel] decides.
+///
+/// Lowering the limits of the entropy coding tables makes decoding faster, because the decoder
+/// has to build and look up smaller tables, at the cost of compression ratio.
+/// Limits that are higher than what the format allows are ignored, limits that are too low
+/// to encode a block are raised as far as necessary for that block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EncoderParams {
+    /// Maximum length of the huffman codes for literals. At most 11.
+    pub max_huffman_bits: u8,
+    /// Maximum accuracy log of the FSE table for literal lengths. At most 9.
+    pub max_ll_log: u8,
+    /// Maximum accuracy log of the FSE table for match lengths. At most 9.
+    pub max_ml_log: u8,
+    /// Maximum accuracy log of the FSE table for offsets. At most 8.
+    pub max_of_log: u8,
+    /// Upper bound for the memory in bytes the match finder uses, passed to [Matcher::set_max_memory]. `None` by default.
+    ///
+    /// The builtin match finder uses about 1.3MB without a limit at [CompressionLevel::Fastest] and about 9MB at [CompressionLevel::Default].
+    /// Most of that is its hash table of previously seen positions, which shrinks to fit the limit.
+    /// Its buffers for the window and the block being compressed are never shrunk, so limits below ~260kB
+    /// (~1.2MB at [CompressionLevel::Default]) are not met and behave like the smallest hash table of 256 entries.
+    ///
+    /// A smaller hash table finds fewer matches because more positions collide and overwrite each other.
+    /// On source code, a limit of 1MB makes the output ~1% larger, 512kB ~2% and 384kB ~5%.
+    /// With the smallest hash table the output is ~60% larger. Binary data with fewer repetitions suffers more.
+    pub max_memory: Option<usize>,
+    /// Write the content size into the frame header whenever it is known before the header is written. `false` by default.
+    ///
+    /// Currently that is only the case if the whole input fits into a single block, so it has to be smaller than 128kB.
+    /// Such frames are written as single segment frames, like with [FrameCompressor::set_single_segment_if_single_block].
+    pub content_size: bool,
+    /// Append a checksum of the uncompressed content to each frame, like [FrameCompressor::set_content_checksum]. `false` by default.
+    ///
+    /// The checksum is appended if either this or [FrameCompressor::set_content_checksum] enables it.
+    #[cfg(feature = "hash")]
+    pub content_checksum: bool,
+}
+
+impl EncoderParams {
+    /// Parameters for frames that every zstd decoder, including old and strict ones, accepts.
+    ///
+    /// The frames have a magic number, no checksum and use a window of at most 256kB, which is what
+    /// the encoder does anyway. On top of that the content size is written into the header
+    /// of frames that consist of a single block, so decoders that need it to preallocate
+    /// their output can decode them. Larger frames don't contain the content size.
+    ///
+    /// Compared to the default parameters this costs nothing for larger inputs. Single block frames
+    /// need 1 to 8 bytes for the content size instead of the 1 byte window descriptor, see [frame_overhead],
+    /// and the first block is written to the drain only after the source signaled the end of the input
+    /// or provided more data for the next block.
+    ///
+    /// ```
+    /// use ruzstd::encoding::{CompressionLevel, EncoderParams, FrameCompressor};
+    ///
+    /// let data = b"hello hello hello hello hello";
+    /// let mut frame = std::vec::Vec::new();
+    /// let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+    /// compressor.set_params(EncoderParams::compatibility());
+    /// compressor.set_source(&data[..]);
+    /// compressor.set_drain(&mut frame);
+    /// compressor.compress().unwrap();
+    ///
+    /// let info = ruzstd::decoding::FrameInfo::read(frame.as_slice()).unwrap();
+    /// assert_eq!(info.content_size, Some(data.len() as u64));
+    /// ```
+    pub fn compatibility() -> Self {
+        Self {
+            content_size: true,
+            ..Self::default()
+        }
+    }
+
+    /// Parameters for frames that are stored for a long time and should be able to prove their integrity when they are read again.
+    ///
+    /// These are the [EncoderParams::compatibility] parameters with a content checksum, so decoders detect
+    /// corrupted frames instead of returning corrupted data. The checksum adds 4 bytes to each frame, see [frame_overhead],
+    /// and hashing the input takes an additional pass over it while compressing.
+    ///
+    /// ```
+    /// use ruzstd::encoding::{CompressionLevel, EncoderParams, FrameCompressor};
+    ///
+    /// let data = b"hello hello hello hello hello";
+    /// let mut frame = std::vec::Vec::new();
+    /// let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+    /// compressor.set_params(EncoderParams::archival());
+    /// compressor.set_source(&data[..]);
+    /// compressor.set_drain(&mut frame);
+    /// compressor.compress().unwrap();
+    ///
+    /// let info = ruzstd::decoding::FrameInfo::read(frame.as_slice()).unwrap();
+    /// assert!(info.content_checksum);
+    /// assert_eq!(info.content_size, Some(data.len() as u64));
+    /// ```
+    #[cfg(feature = "hash")]
+    pub fn archival() -> Self {
+        Self {
+            content_checksum: true,
+            ..Self::compatibility()
+        }
+    }
+}
+
+impl Default for EncoderParams {
+    fn default() -> Self {
+        Self {
+            max_huffman_bits: crate::huff0::MAX_MAX_NUM_BITS,
+            max_ll_log: crate::decoding::sequence_section_decoder::LL_MAX_LOG,
+            max_ml_log: crate::decoding::sequence_section_decoder::ML_MAX_LOG,
+            max_of_log: crate::decoding::sequence_section_decoder::OF_MAX_LOG,
+            max_memory: None,
+            content_size: false,
+            #[cfg(feature = "hash")]
+            content_checksum: false,
+        }
+    }
+}
+
 /// The compression mode used impacts the speed of compression,
 /// and resulting compression ratios. Faster compression will result
 /// in worse compression ratios, and vice versa.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum CompressionLevel {
     /// This level does not compress the data at all, and simply wraps
     /// it in a Zstandard frame.
@@ -53,16 +514,24 @@ pub enum CompressionLevel {
     /// or the one used by the official compressor when no level
     /// is specified.
     ///
-    /// UNIMPLEMENTED
+    /// Compared to [CompressionLevel::Fastest] it looks for matches in a window of 1MB instead of 128kB
+    /// and remembers several earlier positions for each sequence of bytes, picking the longest match among them.
+    /// On text this makes the output about a third smaller and takes about twice as long.
     Default,
     /// This level is roughly equivalent to Zstd level 7.
     ///
-    /// UNIMPLEMENTED
+    /// UNIMPLEMENTED, compressing with it returns [errors::CompressError::UnsupportedLevel]
     Better,
     /// This level is roughly equivalent to Zstd level 11.
     ///
-    /// UNIMPLEMENTED
+    /// UNIMPLEMENTED, compressing with it returns [errors::CompressError::UnsupportedLevel]
     Best,
+    /// This level only compresses blocks that consist of a single repeated byte, all other blocks are stored as they are.
+    ///
+    /// The matcher and the entropy coding are skipped entirely, so this is barely slower than [CompressionLevel::Uncompressed].
+    /// It is meant for cases where CPU time is much scarcer than bandwidth, but runs of a single byte
+    /// like zeroed regions are still worth squashing.
+    RleOnly,
 }
 
 /// Trait used by the encoder that users can use to extend the matching facilities with their own algorithm
@@ -79,8 +548,76 @@ pub enum CompressionLevel {
 /// Then it will either call `start_matching` or, if the space is deemed not worth compressing, `skip_matching` is called.
 ///
 /// This is repeated until no more data is left to be compressed.
+///
+/// # Contract
+///
+/// * `reset` is called at the start of each frame. Data committed before it must not be referenced by later matches.
+/// * The space returned by `get_next_space` may be truncated before it is committed, if there is less input left.
+///   Its length is the maximum size of a block and must not exceed 128kB.
+/// * Each committed space is one block. Either `start_matching` or `skip_matching` is called for it before the next space is requested.
+///   Data passed to `skip_matching` is still part of the window and can be referenced by matches in later blocks.
+/// * The sequences passed to the callback of `start_matching` must cover the committed space exactly once and in order:
+///   the literals and match lengths of all sequences add up to the length of the space.
+///   Only the last sequence may be a [Sequence::Literals].
+/// * The offset of a [Sequence::Triple] counts back from the position right after its literals,
+///   so an offset of 1 repeats the previous byte. It may reach into earlier blocks of the same frame,
+///   but never further back than `window_size`. The match may overlap the data it produces, i.e. `match_len` may be bigger than `offset`.
+/// * `match_len` must be at least 3, the smallest match the format can express.
+/// * `window_size` is written into the frame header. It needs to be correct after `reset`, since the header is written
+///   after the first block has been committed.
+///
+/// # Examples
+///
+/// A matcher that never finds any matches. Blocks compressed with it only contain literals:
+/// ```
+/// use ruzstd::encoding::{CompressionLevel, FrameCompressor, Matcher, Sequence};
+///
+/// struct LiteralsOnly {
+///     last_space: Vec<u8>,
+/// }
+///
+/// impl Matcher for LiteralsOnly {
+///     fn get_next_space(&mut self) -> Vec<u8> {
+///         vec![0; 128 * 1024]
+///     }
+///     fn get_last_space(&mut self) -> &[u8] {
+///         &self.last_space
+///     }
+///     fn commit_space(&mut self, space: Vec<u8>) {
+///         self.last_space = space;
+///     }
+///     fn skip_matching(&mut self) {}
+///     fn start_matching(&mut self, mut handle_sequence: impl for<'a> FnMut(Sequence<'a>)) {
+///         handle_sequence(Sequence::Literals {
+///             literals: &self.last_space,
+///         });
+///     }
+///     fn reset(&mut self, _level: CompressionLevel) {
+///         self.last_space.clear();
+///     }
+///     fn window_size(&self) -> u64 {
+///         128 * 1024
+///     }
+/// }
+///
+/// let data = b"a little bit of text, a little bit of text, a little bit of text".repeat(100);
+/// let mut compressed = Vec::new();
+/// let matcher = LiteralsOnly { last_space: Vec::new() };
+/// let mut compressor = FrameCompressor::new_with_matcher(matcher, CompressionLevel::Fastest);
+/// compressor.set_source(data.as_slice());
+/// compressor.set_drain(&mut compressed);
+/// compressor.compress().unwrap();
+///
+/// let mut decoded = Vec::with_capacity(data.len());
+/// ruzstd::decoding::FrameDecoder::new()
+///     .decode_all_to_vec(&compressed, &mut decoded)
+///     .unwrap();
+/// assert_eq!(decoded, data);
+/// ```
 pub trait Matcher {
     /// Get a space where we can put data to be matched on. Will be encoded as one block. The maximum allowed size is 128 kB.
+    ///
+    /// The [FrameCompressor] commits each space with [Matcher::commit_space] before it asks for the next one.
     fn get_next_space(&mut self) -> alloc::vec::Vec<u8>;
     /// Get a reference to the last commited space
     fn get_last_space(&mut self) -> &[u8];
@@ -96,6 +633,22 @@ pub trait Matcher {
     ///
     /// May change after a call to reset with a different compression level
     fn window_size(&self) -> u64;
+    /// Limit the memory used by this matcher to roughly `max_memory` bytes, `None` removes the limit.
+    ///
+    /// Called with [EncoderParams::max_memory] before each call to reset. Matchers that can't limit their memory
+    /// usage may ignore this, which is what the default implementation does.
+    fn set_max_memory(&mut self, max_memory: Option<usize>) {
+        let _ = max_memory;
+    }
+    /// The content size declared in the header of the next frame, `None` if the size is unknown.
+    ///
+    /// Called before each call to reset. If the content size is at most the `window_size` reported after reset,
+    /// the frame is written as a single segment and decoders keep the whole content as their window.
+    /// Matchers can use this to report the content size as their window size and match against the whole content,
+    /// even if their usual window is smaller. The default implementation ignores the content size.
+    fn set_content_size(&mut self, content_size: Option<u64>) {
+        let _ = content_size;
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -106,12 +659,18 @@ pub enum Sequence<'data> {
     /// First the literals will be copied to the decoded data,
     /// then `match_len` bytes are copied from `offset` bytes back in the buffer
     Triple {
+        /// The bytes before the match that are copied as they are
         literals: &'data [u8],
+        /// How far back from the end of the literals the match starts, at least 1
         offset: usize,
+        /// How many bytes the match repeats, at least 3
         match_len: usize,
     },
     /// This is returned as the last sequence in a block
     ///
     /// These literals will just be copied at the end of the sequence execution by the decoder
-    Literals { literals: &'data [u8] },
+    Literals {
+        /// The bytes at the end of the block
+        literals: &'data [u8],
+    },
 }
@@ -0,0 +1,63 @@
+//! Buffers and tables that are reused across blocks to avoid allocating them for every block.
+
+use super::blocks::FseTableScratch;
+use crate::blocks::sequence_section::Sequence;
+use crate::fse::fse_encoder::{default_ll_table, default_ml_table, default_of_table};
+use alloc::vec::Vec;
+
+/// The repeat offsets at the start of a frame, as defined by the format
+const INITIAL_OFFSET_HIST: [u32; 3] = [1, 4, 8];
+
+/// A block level encoding buffer.
+pub(crate) struct EncoderScratch {
+    /// The literals of the block currently being compressed
+    pub literals: Vec<u8>,
+    /// The sequences of the block currently being compressed
+    pub sequences: Vec<Sequence>,
+    /// The codes of one of the sequence fields, used while choosing its table
+    pub codes: Vec<u8>,
+    pub ll_tables: FseTableScratch,
+    pub ml_tables: FseTableScratch,
+    pub of_tables: FseTableScratch,
+    /// The repeat offsets the decoder has after executing the sequences of the block currently being compressed
+    pub offset_hist: [u32; 3],
+    /// The repeat offsets the decoder has before the block currently being compressed
+    pub previous_offset_hist: [u32; 3],
+}
+
+impl EncoderScratch {
+    pub fn new() -> Self {
+        Self {
+            literals: Vec::new(),
+            sequences: Vec::new(),
+            codes: Vec::new(),
+            ll_tables: FseTableScratch::new(default_ll_table()),
+            ml_tables: FseTableScratch::new(default_ml_table()),
+            of_tables: FseTableScratch::new(default_of_table()),
+            offset_hist: INITIAL_OFFSET_HIST,
+            previous_offset_hist: INITIAL_OFFSET_HIST,
+        }
+    }
+
+    /// Forget the tables of the previous block and start over with the initial repeat offsets.
+    /// Needs to be called at the start of each frame.
+    pub fn begin_frame(&mut self) {
+        self.forget_previous_tables();
+        self.offset_hist = INITIAL_OFFSET_HIST;
+        self.previous_offset_hist = INITIAL_OFFSET_HIST;
+    }
+
+    /// Undo the changes the block that was just compressed made to the state the next block builds on.
+    /// Needs to be called whenever a compressed block is not emitted as such (e.g. if it was replaced by a raw block)
+    pub fn discard_block(&mut self) {
+        // Tables are not restored but forgotten, the next block can do without them
+        self.forget_previous_tables();
+        self.offset_hist = self.previous_offset_hist;
+    }
+
+    fn forget_previous_tables(&mut self) {
+        self.ll_tables.forget_previous();
+        self.ml_tables.forget_previous();
+        self.of_tables.forget_previous();
+    }
+}
@@ -0,0 +1,332 @@
+//! The [StreamingEncoder] wraps a [FrameCompressor] and provides a Write impl that compresses data as it is written
+
+use alloc::vec::Vec;
+
+use super::{
+    match_generator::MatchGeneratorDriver, CompressError, CompressionLevel, FrameCompressor,
+    Matcher,
+};
+use crate::io::{Error, Read, Write};
+
+/// How much input is collected before it is compressed into a block, the maximum block size a [Matcher] may use
+const BLOCK_SIZE: usize = 128 * 1024;
+
+/// High level Zstandard encoder that compresses everything written to it into a single frame.
+///
+/// This encoder implements `io::Write`, so you can write the data to compress with `io::Write::write_all`
+/// or pass this to another library / module as a drain for the content, without knowing its length in advance.
+///
+/// Written data is collected until a full block of 128kB is available, which is then compressed and written to the inner writer.
+/// [Write::flush] compresses the data collected so far into a smaller block right away.
+/// The frame is only complete after [StreamingEncoder::finish] has been called, which writes the remaining data as the last block.
+/// Dropping the encoder without calling it leaves an incomplete frame in the inner writer.
+///
+/// The produced frame is the same [FrameCompressor::compress] produces for the same data.
+///
+/// ```
+/// use ruzstd::encoding::{CompressionLevel, StreamingEncoder};
+/// use ruzstd::io::Write;
+///
+/// let mut encoder = StreamingEncoder::new(std::vec::Vec::new(), CompressionLevel::Fastest);
+/// encoder.write_all(b"Hello ").unwrap();
+/// encoder.write_all(b"world!").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// let mut decoded = std::vec::Vec::with_capacity(12);
+/// ruzstd::decoding::FrameDecoder::new()
+///     .decode_all_to_vec(&compressed, &mut decoded)
+///     .unwrap();
+/// assert_eq!(decoded, b"Hello world!");
+/// ```
+pub struct StreamingEncoder<W: Write, M: Matcher> {
+    compressor: FrameCompressor<PendingInput, W, M>,
+}
+
+/// The written data that has not been compressed yet, used as the source of the [FrameCompressor]
+struct PendingInput {
+    data: Vec<u8>,
+    read_pos: usize,
+}
+
+impl PendingInput {
+    fn len(&self) -> usize {
+        self.data.len() - self.read_pos
+    }
+
+    /// Remove the data that has been read by the compressor
+    fn discard_read(&mut self) {
+        self.data.drain(..self.read_pos);
+        self.read_pos = 0;
+    }
+}
+
+impl Read for PendingInput {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(self.len());
+        buf[..len].copy_from_slice(&self.data[self.read_pos..self.read_pos + len]);
+        self.read_pos += len;
+        Ok(len)
+    }
+}
+
+impl<W: Write> StreamingEncoder<W, MatchGeneratorDriver> {
+    /// Create a new encoder that writes the compressed frame to `drain`
+    pub fn new(drain: W, level: CompressionLevel) -> Self {
+        Self::new_with_compressor(FrameCompressor::new(level), drain)
+    }
+}
+
+impl<W: Write, M: Matcher> StreamingEncoder<W, M> {
+    /// Create a new encoder with a custom matching algorithm implementation that writes the compressed frame to `drain`
+    pub fn new_with_matcher(drain: W, matcher: M, level: CompressionLevel) -> Self {
+        Self::new_with_compressor(FrameCompressor::new_with_matcher(matcher, level), drain)
+    }
+
+    fn new_with_compressor(mut compressor: FrameCompressor<PendingInput, W, M>, drain: W) -> Self {
+        compressor.set_source(PendingInput {
+            data: Vec::with_capacity(BLOCK_SIZE),
+            read_pos: 0,
+        });
+        compressor.set_drain(drain);
+        Self { compressor }
+    }
+
+    /// Append a checksum of the uncompressed content to the frame, see [FrameCompressor::set_content_checksum].
+    ///
+    /// This needs to be set before any data is written.
+    #[cfg(feature = "hash")]
+    pub fn set_content_checksum(&mut self, content_checksum: bool) {
+        self.compressor.set_content_checksum(content_checksum);
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.compressor.drain().unwrap()
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.compressor.drain_mut().unwrap()
+    }
+
+    /// Compress the remaining data as the last block of the frame and return the inner writer.
+    ///
+    /// This also works if no data was written at all, the frame then contains a single empty block.
+    pub fn finish(mut self) -> Result<W, Error> {
+        while let Some(block) = self
+            .compressor
+            .next_compressed_block()
+            .map_err(to_io_error)?
+        {
+            self.get_mut().write_all(&block)?;
+        }
+        Ok(self.compressor.take_drain().unwrap())
+    }
+
+    fn pending_input(&mut self) -> &mut PendingInput {
+        self.compressor.source_mut().unwrap()
+    }
+
+    /// Compress the full block of pending input and write it to the inner writer
+    fn write_block(&mut self) -> Result<(), Error> {
+        // More data follows, so this is never the last block and the frame is not finished
+        let block = self
+            .compressor
+            .next_compressed_block()
+            .map_err(to_io_error)?
+            .unwrap();
+        self.get_mut().write_all(&block)?;
+        self.pending_input().discard_read();
+        Ok(())
+    }
+}
+
+/// Reading the pending input is the only thing that can fail while producing a block, so pass its error on
+fn to_io_error(e: CompressError) -> Error {
+    match e {
+        CompressError::ReadError(e) => e,
+        #[cfg(feature = "std")]
+        e => Error::other(e),
+        #[cfg(not(feature = "std"))]
+        e => Error::new(crate::io::ErrorKind::Other, alloc::boxed::Box::new(e)),
+    }
+}
+
+impl<W: Write, M: Matcher> core::fmt::Debug for StreamingEncoder<W, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StreamingEncoder")
+            .field("compressor", &self.compressor)
+            .field(
+                "pending_bytes",
+                &self.compressor.source().map(PendingInput::len),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write, M: Matcher> Write for StreamingEncoder<W, M> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A full block is only compressed once more data arrives, until then it could be the last block of the frame
+        if self.pending_input().len() == BLOCK_SIZE {
+            self.write_block()?;
+        }
+        let pending = self.pending_input();
+        let len = buf.len().min(BLOCK_SIZE - pending.len());
+        pending.data.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    /// Compress all data written so far into blocks, write them to the inner writer and flush it.
+    ///
+    /// The frame is not finished, more data can be written afterwards. Each flush ends the current block early,
+    /// so flushing often makes the output bigger.
+    fn flush(&mut self) -> Result<(), Error> {
+        while self.pending_input().len() > 0 {
+            let block = self.compressor.next_partial_block().map_err(to_io_error)?;
+            self.get_mut().write_all(&block)?;
+            self.pending_input().discard_read();
+        }
+        self.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamingEncoder;
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+    use crate::io::Write;
+    use alloc::vec::Vec;
+
+    fn decode(compressed: &[u8], len: usize) -> Vec<u8> {
+        let mut decoded = Vec::with_capacity(len);
+        FrameDecoder::new()
+            .decode_all_to_vec(compressed, &mut decoded)
+            .unwrap();
+        decoded
+    }
+
+    #[test]
+    fn matches_frame_compressor() {
+        let data = &include_bytes!("../../decodecorpus_files/z000033")[..300_000];
+        for level in [
+            CompressionLevel::Uncompressed,
+            CompressionLevel::Fastest,
+            CompressionLevel::Default,
+        ] {
+            let expected = compress_to_vec(data, level);
+
+            // Writes of odd sizes cross the block boundaries
+            let mut encoder = StreamingEncoder::new(Vec::new(), level);
+            for chunk in data.chunks(7777) {
+                encoder.write_all(chunk).unwrap();
+            }
+            let compressed = encoder.finish().unwrap();
+            assert_eq!(compressed, expected);
+            assert_eq!(decode(&compressed, data.len()), data);
+
+            // A single big write
+            let mut encoder = StreamingEncoder::new(Vec::new(), level);
+            encoder.write_all(data).unwrap();
+            assert_eq!(encoder.finish().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn blocks_are_written_before_finish() {
+        let data = &include_bytes!("../../decodecorpus_files/z000033")[..300_000];
+        let mut encoder = StreamingEncoder::new(Vec::new(), CompressionLevel::Fastest);
+        encoder.write_all(&data[..128 * 1024]).unwrap();
+        // The block could still be the last one
+        assert!(encoder.get_ref().is_empty());
+        encoder.write_all(&data[128 * 1024..]).unwrap();
+        assert!(!encoder.get_ref().is_empty());
+
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decode(&compressed, data.len()), data);
+    }
+
+    #[test]
+    fn flush_writes_block() {
+        use crate::io::Error;
+
+        /// Like a `BufWriter`, only passes data on when flushed
+        #[derive(Default)]
+        struct Buffered {
+            buffer: Vec<u8>,
+            flushed: Vec<u8>,
+        }
+        impl Write for Buffered {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                self.buffer.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> Result<(), Error> {
+                self.flushed.append(&mut self.buffer);
+                Ok(())
+            }
+        }
+
+        let data = &include_bytes!("../../decodecorpus_files/z000033")[..200_000];
+        let mut encoder = StreamingEncoder::new(Buffered::default(), CompressionLevel::Fastest);
+        encoder.write_all(&data[..1000]).unwrap();
+        assert!(encoder.get_ref().flushed.is_empty());
+        encoder.flush().unwrap();
+
+        // The flushed part of the frame contains everything written so far,
+        // an empty last block completes it
+        let flushed = encoder.get_ref().flushed.clone();
+        let mut completed = flushed.clone();
+        completed.extend_from_slice(&[1, 0, 0]);
+        assert_eq!(decode(&completed, 1000), &data[..1000]);
+
+        // Flushing without new data does not add anything
+        encoder.flush().unwrap();
+        assert_eq!(encoder.get_ref().flushed, flushed);
+
+        encoder.write_all(&data[1000..]).unwrap();
+        encoder.flush().unwrap();
+        let mut compressed = encoder.finish().unwrap();
+        compressed.flush().unwrap();
+        assert_eq!(decode(&compressed.flushed, data.len()), data);
+    }
+
+    #[test]
+    fn empty_input() {
+        let mut encoder = StreamingEncoder::new(Vec::new(), CompressionLevel::Fastest);
+        encoder.write_all(&[]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(
+            compressed,
+            compress_to_vec(&[][..], CompressionLevel::Fastest)
+        );
+        assert!(decode(&compressed, 0).is_empty());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn content_checksum() {
+        let data = &include_bytes!("../../decodecorpus_files/z000033")[..200_000];
+        let mut encoder = StreamingEncoder::new(Vec::new(), CompressionLevel::Fastest);
+        encoder.set_content_checksum(true);
+        encoder.write_all(data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::with_capacity(data.len());
+        decoder
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+        assert!(decoder.get_checksum_from_data().is_some());
+        assert_eq!(
+            decoder.get_checksum_from_data(),
+            decoder.get_calculated_checksum()
+        );
+    }
+}
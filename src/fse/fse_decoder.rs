@@ -134,6 +134,22 @@ impl FSETable {
         self.accuracy_log = 0;
     }
 
+    /// The number of bytes [FSETable::reserve_max] allocates for tables with an accuracy log of up to `max_log`
+    pub(crate) fn max_growth(&self, max_log: u8) -> usize {
+        (1usize << max_log).saturating_sub(self.decode.capacity()) * core::mem::size_of::<Entry>()
+    }
+
+    /// Reserve space for tables with an accuracy log of up to `max_log`, so building them does not allocate
+    pub(crate) fn reserve_max(&mut self, max_log: u8) {
+        self.decode
+            .reserve_exact((1usize << max_log).saturating_sub(self.decode.len()));
+        let symbols = self.max_symbol as usize + 1;
+        self.symbol_probabilities
+            .reserve_exact(symbols.saturating_sub(self.symbol_probabilities.len()));
+        self.symbol_counter
+            .reserve_exact(symbols.saturating_sub(self.symbol_counter.len()));
+    }
+
     /// returns how many BYTEs (not bits) were read while building the decoder
     pub fn build_decoder(&mut self, source: &[u8], max_log: u8) -> Result<usize, FSETableError> {
         self.accuracy_log = 0;
@@ -153,7 +169,8 @@ impl FSETable {
         if acc_log == 0 {
             return Err(FSETableError::AccLogIsZero);
         }
-        self.symbol_probabilities = probs.to_vec();
+        self.symbol_probabilities.clear();
+        self.symbol_probabilities.extend_from_slice(probs);
         self.accuracy_log = acc_log;
         self.build_decoding_table()
     }
@@ -319,7 +336,7 @@ impl FSETable {
             });
         }
 
-        let bytes_read = if br.bits_read() % 8 == 0 {
+        let bytes_read = if br.bits_read().is_multiple_of(8) {
             br.bits_read() / 8
         } else {
             (br.bits_read() / 8) + 1
@@ -50,60 +50,45 @@ impl<V: AsMut<Vec<u8>>> FSEEncoder<'_, V> {
     /// * Encoded data with two interleaved states
     /// * Both Last state indexes
     /// * Padding bits to fill up last byte
+    ///
+    /// The decoder alternates between the two states, starting with the first. Because each state
+    /// needs a final symbol at least two symbols are needed.
     pub fn encode_interleaved(&mut self, data: &[u8]) {
+        assert!(
+            data.len() >= 2,
+            "Interleaved encoding needs at least 2 symbols, got {}",
+            data.len()
+        );
         self.write_table();
 
-        let mut state_1 = self.table.start_state(data[data.len() - 1]);
-        let mut state_2 = self.table.start_state(data[data.len() - 2]);
-
-        // The first two symbols are represented by the start states
-        // Then encode the state transitions for two symbols at a time
-        let mut idx = data.len() - 4;
-        loop {
-            {
-                let state = state_1;
-                let x = data[idx + 1];
-                let next = self.table.next_state(x, state.index);
-                let diff = state.index - next.baseline;
-                self.writer.write_bits(diff as u64, next.num_bits as usize);
-                state_1 = next;
-            }
-            {
-                let state = state_2;
-                let x = data[idx];
-                let next = self.table.next_state(x, state.index);
-                let diff = state.index - next.baseline;
-                self.writer.write_bits(diff as u64, next.num_bits as usize);
-                state_2 = next;
-            }
-
-            if idx < 2 {
-                break;
-            }
-            idx -= 2;
-        }
-
-        // Determine if we have an even or odd number of symbols to encode
-        // If odd we need to encode the last states transition and encode the final states in the flipped order
-        if idx == 1 {
-            let state = state_1;
-            let x = data[0];
+        // The first decoder state decodes the symbols at even indexes, the second those at odd indexes.
+        // Their final symbols are the last two symbols, which are represented by the start states.
+        let last_idx = data.len() - 1;
+        let (even_last, odd_last) = if last_idx.is_multiple_of(2) {
+            (last_idx, last_idx - 1)
+        } else {
+            (last_idx - 1, last_idx)
+        };
+        let mut states = [
+            self.table.start_state(data[even_last]),
+            self.table.start_state(data[odd_last]),
+        ];
+
+        // The decoder reads the state transitions from the front, so they are written starting from the back
+        for (idx, x) in data[..data.len() - 2].iter().copied().enumerate().rev() {
+            let state = &mut states[idx % 2];
             let next = self.table.next_state(x, state.index);
             let diff = state.index - next.baseline;
             self.writer.write_bits(diff as u64, next.num_bits as usize);
-            state_1 = next;
-
-            self.writer
-                .write_bits(state_2.index as u64, self.acc_log() as usize);
-            self.writer
-                .write_bits(state_1.index as u64, self.acc_log() as usize);
-        } else {
-            self.writer
-                .write_bits(state_1.index as u64, self.acc_log() as usize);
-            self.writer
-                .write_bits(state_2.index as u64, self.acc_log() as usize);
+            *state = next;
         }
 
+        // The decoder reads the initial state of the first stream first
+        self.writer
+            .write_bits(states[1].index as u64, self.acc_log() as usize);
+        self.writer
+            .write_bits(states[0].index as u64, self.acc_log() as usize);
+
         let bits_to_fill = self.writer.misaligned();
         if bits_to_fill == 0 {
             self.writer.write_bits(1u32, 8);
@@ -113,7 +98,57 @@ impl<V: AsMut<Vec<u8>>> FSEEncoder<'_, V> {
     }
 
     fn write_table(&mut self) {
-        self.writer.write_bits(self.acc_log() - 5, 4);
+        self.table.write_table(self.writer);
+    }
+
+    pub(super) fn acc_log(&self) -> u8 {
+        self.table.acc_log()
+    }
+}
+
+#[derive(Debug)]
+pub struct FSETable {
+    /// Indexed by symbol
+    pub(super) states: [SymbolStates; 256],
+    /// Sum of all states.states.len()
+    pub(crate) table_size: usize,
+}
+
+impl FSETable {
+    /// A table without any states. Its storage can be filled by [build_table_from_counts_into].
+    pub(crate) fn empty() -> FSETable {
+        FSETable {
+            states: core::array::from_fn(|_| SymbolStates {
+                states: Vec::new(),
+                probability: 0,
+            }),
+            table_size: 0,
+        }
+    }
+
+    pub(crate) fn next_state(&self, symbol: u8, idx: usize) -> &State {
+        let states = &self.states[symbol as usize];
+        states.get(idx, self.table_size)
+    }
+
+    pub(crate) fn start_state(&self, symbol: u8) -> &State {
+        let states = &self.states[symbol as usize];
+        &states.states[0]
+    }
+
+    /// The normalized probability of the symbol. A value of -1 denotes a probability of "less than 1"
+    pub(crate) fn symbol_probability(&self, symbol: u8) -> i32 {
+        self.states[symbol as usize].probability
+    }
+
+    pub(crate) fn acc_log(&self) -> u8 {
+        self.table_size.ilog2() as u8
+    }
+
+    /// Writes the description of this table as used in the FSE_Compressed mode. The description
+    /// is padded with zeros to end on a byte boundary.
+    pub(crate) fn write_table<V: AsMut<Vec<u8>>>(&self, writer: &mut BitWriter<V>) {
+        writer.write_bits(self.acc_log() - 5, 4);
         let mut probability_counter = 0usize;
         let probability_sum = 1 << self.acc_log();
 
@@ -124,16 +159,15 @@ impl<V: AsMut<Vec<u8>>> FSEEncoder<'_, V> {
             let low_threshold = ((1 << bits_to_write) - 1) - (max_remaining_value);
             let mask = (1 << (bits_to_write - 1)) - 1;
 
-            let prob = self.table.states[prob_idx].probability;
+            let prob = self.states[prob_idx].probability;
             prob_idx += 1;
             let value = (prob + 1) as u32;
             if value < low_threshold as u32 {
-                self.writer.write_bits(value, bits_to_write as usize - 1);
+                writer.write_bits(value, bits_to_write as usize - 1);
             } else if value > mask {
-                self.writer
-                    .write_bits(value + low_threshold as u32, bits_to_write as usize);
+                writer.write_bits(value + low_threshold as u32, bits_to_write as usize);
             } else {
-                self.writer.write_bits(value, bits_to_write as usize);
+                writer.write_bits(value, bits_to_write as usize);
             }
 
             if prob == -1 {
@@ -142,42 +176,18 @@ impl<V: AsMut<Vec<u8>>> FSEEncoder<'_, V> {
                 probability_counter += prob as usize;
             } else {
                 let mut zeros = 0u8;
-                while self.table.states[prob_idx].probability == 0 {
+                while self.states[prob_idx].probability == 0 {
                     zeros += 1;
                     prob_idx += 1;
                     if zeros == 3 {
-                        self.writer.write_bits(3u8, 2);
+                        writer.write_bits(3u8, 2);
                         zeros = 0;
                     }
                 }
-                self.writer.write_bits(zeros, 2);
+                writer.write_bits(zeros, 2);
             }
         }
-        self.writer.write_bits(0u8, self.writer.misaligned());
-    }
-
-    pub(super) fn acc_log(&self) -> u8 {
-        self.table.table_size.ilog2() as u8
-    }
-}
-
-#[derive(Debug)]
-pub struct FSETable {
-    /// Indexed by symbol
-    pub(super) states: [SymbolStates; 256],
-    /// Sum of all states.states.len()
-    pub(crate) table_size: usize,
-}
-
-impl FSETable {
-    pub(crate) fn next_state(&self, symbol: u8, idx: usize) -> &State {
-        let states = &self.states[symbol as usize];
-        states.get(idx, self.table_size)
-    }
-
-    pub(crate) fn start_state(&self, symbol: u8) -> &State {
-        let states = &self.states[symbol as usize];
-        &states.states[0]
+        writer.write_bits(0u8, writer.misaligned());
     }
 }
 
@@ -225,7 +235,23 @@ pub fn build_table_from_data(data: &[u8], max_log: u8, avoid_0_numbit: bool) ->
     build_table_from_counts(&counts, max_log, avoid_0_numbit)
 }
 
-fn build_table_from_counts(counts: &[usize], max_log: u8, avoid_0_numbit: bool) -> FSETable {
+pub(crate) fn build_table_from_counts(
+    counts: &[usize],
+    max_log: u8,
+    avoid_0_numbit: bool,
+) -> FSETable {
+    let mut table = FSETable::empty();
+    build_table_from_counts_into(counts, max_log, avoid_0_numbit, &mut table);
+    table
+}
+
+/// Same as [build_table_from_counts] but reuses the allocations of an existing table
+pub(crate) fn build_table_from_counts_into(
+    counts: &[usize],
+    max_log: u8,
+    avoid_0_numbit: bool,
+    table: &mut FSETable,
+) {
     let mut probs = [0; 256];
     let mut min_count = 0;
     for (idx, count) in counts.iter().copied().enumerate() {
@@ -278,14 +304,21 @@ fn build_table_from_counts(counts: &[usize], max_log: u8, avoid_0_numbit: bool)
         *second_max += redistribute;
         assert!(*second_max <= max);
     }
-    build_table_from_probabilities(&probs, acc_log)
+    build_table_from_probabilities_into(&probs, acc_log, table)
 }
 
 pub(super) fn build_table_from_probabilities(probs: &[i32], acc_log: u8) -> FSETable {
-    let mut states = core::array::from_fn::<SymbolStates, 256, _>(|_| SymbolStates {
-        states: Vec::new(),
-        probability: 0,
-    });
+    let mut table = FSETable::empty();
+    build_table_from_probabilities_into(probs, acc_log, &mut table);
+    table
+}
+
+fn build_table_from_probabilities_into(probs: &[i32], acc_log: u8, table: &mut FSETable) {
+    let states = &mut table.states;
+    for state in states.iter_mut() {
+        state.states.clear();
+        state.probability = 0;
+    }
 
     // distribute -1 symbols
     let mut negative_idx = (1 << acc_log) - 1;
@@ -340,7 +373,7 @@ pub(super) fn build_table_from_probabilities(probs: &[i32], acc_log: u8) -> FSET
         let state = &mut states[symbol];
 
         // We process the states in their order in the table
-        state.states.sort_by(|l, r| l.index.cmp(&r.index));
+        state.states.sort_by_key(|l| l.index);
 
         let prob_log = if prob.is_power_of_two() {
             prob.ilog2()
@@ -372,13 +405,10 @@ pub(super) fn build_table_from_probabilities(probs: &[i32], acc_log: u8) -> FSET
         }
 
         // For encoding we use the states ordered by the indexes they target
-        state.states.sort_by(|l, r| l.baseline.cmp(&r.baseline));
+        state.states.sort_by_key(|l| l.baseline);
     }
 
-    FSETable {
-        table_size: 1 << acc_log,
-        states,
-    }
+    table.table_size = 1 << acc_log;
 }
 
 /// Calculate the position of the next entry of the table given the current
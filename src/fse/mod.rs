@@ -28,6 +28,33 @@ fn tables_equal() {
     check_tables(&dec_table, &enc_table);
 }
 
+#[test]
+fn default_tables_equal() {
+    use crate::decoding::sequence_section_decoder as seq;
+
+    for (acc_log, probs, enc_table) in [
+        (
+            seq::LL_DEFAULT_ACC_LOG,
+            &seq::LITERALS_LENGTH_DEFAULT_DISTRIBUTION[..],
+            fse_encoder::default_ll_table(),
+        ),
+        (
+            seq::ML_DEFAULT_ACC_LOG,
+            &seq::MATCH_LENGTH_DEFAULT_DISTRIBUTION[..],
+            fse_encoder::default_ml_table(),
+        ),
+        (
+            seq::OF_DEFAULT_ACC_LOG,
+            &seq::OFFSET_DEFAULT_DISTRIBUTION[..],
+            fse_encoder::default_of_table(),
+        ),
+    ] {
+        let mut dec_table = FSETable::new(255);
+        dec_table.build_from_probabilities(acc_log, probs).unwrap();
+        check_tables(&dec_table, &enc_table);
+    }
+}
+
 #[cfg(any(test, feature = "fuzz_exports"))]
 fn check_tables(dec_table: &fse_decoder::FSETable, enc_table: &fse_encoder::FSETable) {
     for (idx, dec_state) in dec_table.decode.iter().enumerate() {
@@ -137,3 +164,64 @@ pub fn round_trip(data: &[u8]) {
 
     assert_eq!(br.bits_remaining(), 0);
 }
+
+#[test]
+fn roundtrip_interleaved_short() {
+    use alloc::vec::Vec;
+    for len in 2..=8 {
+        let alternating: Vec<u8> = (0..len).map(|x| (x % 2) as u8 + 1).collect();
+        round_trip_interleaved(&alternating);
+        let ascending: Vec<u8> = (0..len).map(|x| x as u8).collect();
+        round_trip_interleaved(&ascending);
+        let skewed: Vec<u8> = (0..len).map(|x| if x == 1 { 5 } else { 3 }).collect();
+        round_trip_interleaved(&skewed);
+        round_trip_interleaved(&alloc::vec![4; len]);
+    }
+}
+
+/// Encodes the data with two interleaved states and decodes it again the same way the huffman weights are decoded
+#[cfg(test)]
+fn round_trip_interleaved(data: &[u8]) {
+    use crate::{decoding::bit_reader_reverse::BitReaderReversed, encoding::bit_writer::BitWriter};
+    use fse_encoder::FSEEncoder;
+
+    let mut writer = BitWriter::new();
+    let mut encoder = FSEEncoder::new(
+        fse_encoder::build_table_from_data(data, 6, true),
+        &mut writer,
+    );
+    encoder.encode_interleaved(data);
+    let acc_log = encoder.acc_log();
+    let encoded = writer.dump();
+
+    let mut dec_table = FSETable::new(255);
+    let table_bytes = dec_table.build_decoder(&encoded, acc_log).unwrap();
+    let mut br = BitReaderReversed::new(&encoded[table_bytes..]);
+    let mut skipped_bits = 0;
+    while br.get_bits(1) == 0 {
+        skipped_bits += 1;
+        assert!(skipped_bits < 8, "Corrupted end marker");
+    }
+
+    let mut dec1 = FSEDecoder::new(&dec_table);
+    let mut dec2 = FSEDecoder::new(&dec_table);
+    dec1.init_state(&mut br).unwrap();
+    dec2.init_state(&mut br).unwrap();
+    let mut decoded = alloc::vec::Vec::new();
+    loop {
+        decoded.push(dec1.decode_symbol());
+        dec1.update_state(&mut br);
+        if br.bits_remaining() <= -1 {
+            decoded.push(dec2.decode_symbol());
+            break;
+        }
+        decoded.push(dec2.decode_symbol());
+        dec2.update_state(&mut br);
+        if br.bits_remaining() <= -1 {
+            decoded.push(dec1.decode_symbol());
+            break;
+        }
+        assert!(decoded.len() <= data.len());
+    }
+    assert_eq!(decoded, data);
+}
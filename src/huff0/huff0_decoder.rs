@@ -43,6 +43,9 @@ pub struct Entry {
 /// The Zstandard specification limits the maximum length of a code to 11 bits.
 pub(crate) const MAX_MAX_NUM_BITS: u8 = 11;
 
+/// The Zstandard specification limits the accuracy log of the FSE table used to compress the weights to 6.
+pub(crate) const MAX_WEIGHTS_ACC_LOG: u8 = 6;
+
 /// Assert that the provided value is greater than zero, and returns the
 /// 32 - the number of leading zeros
 fn highest_bit_set(x: u32) -> u32 {
@@ -122,6 +125,30 @@ impl HuffmanTable {
         self.fse_table.reinit_from(&other.fse_table);
     }
 
+    /// The number of bytes [HuffmanTable::reserve_max] allocates
+    pub(crate) fn max_growth(&self) -> usize {
+        (1usize << MAX_MAX_NUM_BITS).saturating_sub(self.decode.capacity())
+            * core::mem::size_of::<Entry>()
+            + self.fse_table.max_growth(MAX_WEIGHTS_ACC_LOG)
+    }
+
+    /// Reserve space for the biggest table the format allows, so building tables does not allocate
+    pub(crate) fn reserve_max(&mut self) {
+        self.decode
+            .reserve_exact((1usize << MAX_MAX_NUM_BITS).saturating_sub(self.decode.len()));
+        // The small buffers used while building the table, at most one weight per byte value
+        self.weights
+            .reserve_exact(256usize.saturating_sub(self.weights.len()));
+        self.bits
+            .reserve_exact(257usize.saturating_sub(self.bits.len()));
+        let ranks = MAX_MAX_NUM_BITS as usize + 1;
+        self.bit_ranks
+            .reserve_exact(ranks.saturating_sub(self.bit_ranks.len()));
+        self.rank_indexes
+            .reserve_exact(ranks.saturating_sub(self.rank_indexes.len()));
+        self.fse_table.reserve_max(MAX_WEIGHTS_ACC_LOG);
+    }
+
     /// Completely empty the table of all data.
     pub fn reset(&mut self) {
         self.decode.clear();
@@ -173,7 +200,9 @@ impl HuffmanTable {
                     });
                 }
                 //fse decompress weights
-                let bytes_used_by_fse_header = self.fse_table.build_decoder(fse_stream, 6)?;
+                let bytes_used_by_fse_header = self
+                    .fse_table
+                    .build_decoder(fse_stream, MAX_WEIGHTS_ACC_LOG)?;
 
                 if bytes_used_by_fse_header > header as usize {
                     return Err(err::FSETableUsedTooManyBytes {
@@ -267,7 +296,7 @@ impl HuffmanTable {
                 let num_weights = header - 127;
                 self.weights.resize(num_weights as usize, 0);
 
-                let bytes_needed = if num_weights % 2 == 0 {
+                let bytes_needed = if num_weights.is_multiple_of(2) {
                     num_weights as usize / 2
                 } else {
                     (num_weights as usize / 2) + 1
@@ -35,7 +35,7 @@ impl<V: AsMut<Vec<u8>>> HuffmanEncoder<'_, V> {
         assert!(data.len() >= 4);
 
         // Split data in 4 equally sized parts (the last one might be a bit smaller than the rest)
-        let split_size = (data.len() + 3) / 4;
+        let split_size = data.len().div_ceil(4);
         let src1 = &data[..split_size];
         let src2 = &data[split_size..split_size * 2];
         let src3 = &data[split_size * 2..split_size * 3];
@@ -108,39 +108,60 @@ impl<V: AsMut<Vec<u8>>> HuffmanEncoder<'_, V> {
         weights
     }
 
-    fn write_table(&mut self) {
-        // TODO strategy for determining this?
+    /// Writes the weights either compressed with FSE or directly as 4 bit values, whichever is smaller.
+    ///
+    /// The direct representation can hold at most 128 weights and the FSE representation needs at least 2.
+    pub(crate) fn write_table(&mut self) {
         let weights = self.weights();
         let weights = &weights[..weights.len() - 1]; // dont encode last weight
-        if weights.len() > 16 {
-            let size_idx = self.writer.index();
-            self.writer.write_bits(0u8, 8);
-            let idx_before = self.writer.index();
-            let mut encoder = FSEEncoder::new(
-                fse_encoder::build_table_from_data(weights, 6, true),
-                self.writer,
-            );
-            encoder.encode_interleaved(weights);
-            let encoded_len = (self.writer.index() - idx_before) / 8;
-            assert!(encoded_len < 128);
-            self.writer.change_bits(size_idx, encoded_len as u8, 8);
-        } else {
-            self.writer.write_bits(weights.len() as u8 + 127, 8);
-            let pairs = weights.chunks_exact(2);
-            let remainder = pairs.remainder();
-            for pair in pairs.into_iter() {
-                let weight1 = pair[0];
-                let weight2 = pair[1];
-                assert!(weight1 < 16);
-                assert!(weight2 < 16);
-                self.writer.write_bits(weight2, 4);
-                self.writer.write_bits(weight1, 4);
+        let fits_direct = weights.len() <= 128;
+        if weights.len() >= 2 {
+            let start_idx = self.writer.index();
+            let encoded_len = self.write_weights_fse(weights);
+            if !fits_direct {
+                assert!(encoded_len < 128);
+                return;
             }
-            if !remainder.is_empty() {
-                let weight = remainder[0];
-                assert!(weight < 16);
-                self.writer.write_bits(weight << 4, 8);
+            let direct_len = weights.len().div_ceil(2);
+            if encoded_len < 128 && encoded_len < direct_len {
+                return;
             }
+            self.writer.reset_to(start_idx);
+        }
+        self.write_weights_direct(weights);
+    }
+
+    /// Returns the number of bytes the compressed weights take up, excluding the header byte
+    fn write_weights_fse(&mut self, weights: &[u8]) -> usize {
+        let size_idx = self.writer.index();
+        self.writer.write_bits(0u8, 8);
+        let idx_before = self.writer.index();
+        let mut encoder = FSEEncoder::new(
+            fse_encoder::build_table_from_data(weights, super::MAX_WEIGHTS_ACC_LOG, true),
+            self.writer,
+        );
+        encoder.encode_interleaved(weights);
+        let encoded_len = (self.writer.index() - idx_before) / 8;
+        self.writer.change_bits(size_idx, encoded_len as u8, 8);
+        encoded_len
+    }
+
+    fn write_weights_direct(&mut self, weights: &[u8]) {
+        self.writer.write_bits(weights.len() as u8 + 127, 8);
+        let pairs = weights.chunks_exact(2);
+        let remainder = pairs.remainder();
+        for pair in pairs.into_iter() {
+            let weight1 = pair[0];
+            let weight2 = pair[1];
+            assert!(weight1 < 16);
+            assert!(weight2 < 16);
+            self.writer.write_bits(weight2, 4);
+            self.writer.write_bits(weight1, 4);
+        }
+        if !remainder.is_empty() {
+            let weight = remainder[0];
+            assert!(weight < 16);
+            self.writer.write_bits(weight << 4, 8);
         }
     }
 }
@@ -151,7 +172,9 @@ pub struct HuffmanTable {
 }
 
 impl HuffmanTable {
-    pub fn build_from_data(data: &[u8]) -> Self {
+    /// Build a table for the data. No code will be longer than `max_num_bits`,
+    /// unless there are too many different symbols to fit into codes of that length.
+    pub fn build_from_data(data: &[u8], max_num_bits: u8) -> Self {
         let mut counts = [0; 256];
         let mut max = 0;
         for x in data {
@@ -159,19 +182,24 @@ impl HuffmanTable {
             max = max.max(*x);
         }
 
-        Self::build_from_counts(&counts[..=max as usize])
+        Self::build_from_counts(&counts[..=max as usize], max_num_bits)
     }
 
-    pub fn build_from_counts(counts: &[usize]) -> Self {
+    /// Build a table for the symbol counts. No code will be longer than `max_num_bits`,
+    /// unless there are too many different symbols to fit into codes of that length.
+    pub fn build_from_counts(counts: &[usize], max_num_bits: u8) -> Self {
         assert!(counts.len() <= 256);
         let zeros = counts.iter().filter(|x| **x == 0).count();
         let mut weights = distribute_weights(counts.len() - zeros);
-        let limit = weights.len().ilog2() as usize + 2;
+        let min_limit = weights.len().next_power_of_two().ilog2() as usize + 1;
+        let limit = (weights.len().ilog2() as usize + 2)
+            .min(usize::from(max_num_bits.min(super::MAX_MAX_NUM_BITS)) + 1)
+            .max(min_limit);
         redistribute_weights(&mut weights, limit);
 
         weights.reverse();
         let mut counts_sorted = counts.iter().enumerate().collect::<Vec<_>>();
-        counts_sorted.sort_by(|(_, c1), (_, c2)| c1.cmp(c2));
+        counts_sorted.sort_by_key(|(_, c1)| *c1);
 
         let mut weights_distributed = alloc::vec![0; counts.len()];
         for (idx, count) in counts_sorted {
@@ -426,7 +454,7 @@ fn weights() {
 #[test]
 fn counts() {
     let counts = &[3, 0, 4, 1, 5];
-    let table = HuffmanTable::build_from_counts(counts).codes;
+    let table = HuffmanTable::build_from_counts(counts, super::MAX_MAX_NUM_BITS).codes;
 
     assert_eq!(table[1].1, 0);
     assert!(table[3].1 >= table[0].1);
@@ -434,7 +462,7 @@ fn counts() {
     assert!(table[2].1 >= table[4].1);
 
     let counts = &[3, 0, 4, 0, 7, 2, 2, 2, 0, 2, 2, 1, 5];
-    let table = HuffmanTable::build_from_counts(counts).codes;
+    let table = HuffmanTable::build_from_counts(counts, super::MAX_MAX_NUM_BITS).codes;
 
     assert_eq!(table[1].1, 0);
     assert_eq!(table[3].1, 0);
@@ -453,10 +481,45 @@ fn counts() {
 #[test]
 fn from_data() {
     let counts = &[3, 0, 4, 1, 5];
-    let table = HuffmanTable::build_from_counts(counts).codes;
+    let table = HuffmanTable::build_from_counts(counts, super::MAX_MAX_NUM_BITS).codes;
 
     let data = &[0, 2, 4, 4, 0, 3, 2, 2, 0, 2];
-    let table2 = HuffmanTable::build_from_data(data).codes;
+    let table2 = HuffmanTable::build_from_data(data, super::MAX_MAX_NUM_BITS).codes;
 
     assert_eq!(table, table2);
 }
+
+#[test]
+fn max_num_bits_limit() {
+    for num_symbols in [2usize, 3, 5, 17, 40, 100, 200, 256] {
+        let counts: Vec<usize> = (0..num_symbols).map(|i| 1 + (1 << (i % 20))).collect();
+        let min_bits = num_symbols.next_power_of_two().ilog2() as u8;
+        for max_num_bits in 1..=11 {
+            let table = HuffmanTable::build_from_counts(&counts, max_num_bits).codes;
+            let longest = table.iter().map(|(_, num_bits)| *num_bits).max().unwrap();
+            assert!(longest <= max_num_bits.max(min_bits));
+        }
+    }
+}
+
+#[test]
+fn small_weight_tables() {
+    // Symbols 0..=len result in len encoded weights, the weight of the last symbol is implied
+    for len in 1..=8u8 {
+        let mut skewed = Vec::new();
+        let mut uniform = Vec::new();
+        for symbol in 0..=len {
+            skewed.extend(core::iter::repeat_n(symbol, 1 << symbol.min(6)));
+            uniform.extend(core::iter::repeat_n(symbol, 3));
+        }
+        super::round_trip(&skewed);
+        super::round_trip(&uniform);
+
+        // FSE compressed weights are only used if they are smaller than the direct representation
+        let table = HuffmanTable::build_from_data(&skewed, super::MAX_MAX_NUM_BITS);
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(table, &mut writer).write_table();
+        let encoded = writer.dump();
+        assert!(encoded.len() <= 1 + usize::from(len).div_ceil(2));
+    }
+}
@@ -23,7 +23,7 @@ pub fn round_trip(data: &[u8]) {
         return;
     }
     let mut writer = BitWriter::new();
-    let encoder_table = huff0_encoder::HuffmanTable::build_from_data(data);
+    let encoder_table = huff0_encoder::HuffmanTable::build_from_data(data, MAX_MAX_NUM_BITS);
     let mut encoder = huff0_encoder::HuffmanEncoder::new(encoder_table, &mut writer);
 
     encoder.encode(data);
@@ -82,3 +82,23 @@ fn roundtrip() {
         }
     }
 }
+
+#[test]
+fn weights_acc_log_too_big() {
+    use crate::decoding::errors::{FSETableError, HuffmanTableError};
+
+    // FSE compressed weights (header < 128) whose table declares an accuracy log of 7
+    let source = [10, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut table = HuffmanTable::new();
+    let result = table.build_decoder(&source);
+    assert!(
+        matches!(
+            result,
+            Err(HuffmanTableError::FSETableError(
+                FSETableError::AccLogTooBig { got: 7, max: 6 }
+            ))
+        ),
+        "{:?}",
+        result
+    );
+}
@@ -10,6 +10,9 @@
 //! Decompression can be achieved by using the [`encoding::compress`]/[`encoding::compress_to_vec`]
 //! functions or the [`encoding::FrameCompressor`]
 //!
+//! ## Dictionaries
+//! The [dictionary] module contains the code for building dictionaries from sample data.
+//!
 #![doc = include_str!("../Readme.md")]
 #![no_std]
 #![deny(trivial_casts, trivial_numeric_casts, rust_2018_idioms)]
@@ -33,6 +36,7 @@ macro_rules! vprintln {
 }
 
 pub mod decoding;
+pub mod dictionary;
 pub mod encoding;
 
 pub(crate) mod blocks;
@@ -0,0 +1,178 @@
+//! Checks that reusing decoders actually reuses their allocations.
+//!
+//! Installs a global allocator for the test binary that counts the allocations of the current thread,
+//! so tests running in parallel don't influence each other.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::vec::Vec;
+
+struct CountingAllocator;
+
+std::thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations (including reallocations) the closure made on the current thread
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(|count| count.get());
+    f();
+    ALLOCATIONS.with(|count| count.get()) - before
+}
+
+#[test]
+fn test_streaming_decoder_reuses_allocations() {
+    use crate::decoding::{FrameDecoder, StreamingDecoder};
+    use std::io::Read;
+
+    let first = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000035.zst");
+    let mut result = Vec::with_capacity(2 * 1024 * 1024);
+
+    let mut decoder = FrameDecoder::new();
+    let fresh = count_allocations(|| {
+        let mut stream = StreamingDecoder::new_with_decoder(&first[..], &mut decoder).unwrap();
+        stream.read_to_end(&mut result).unwrap();
+    });
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000033"));
+
+    result.clear();
+    let reused = count_allocations(|| {
+        let mut stream = StreamingDecoder::new_with_decoder(&second[..], &mut decoder).unwrap();
+        stream.read_to_end(&mut result).unwrap();
+    });
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000035"));
+
+    assert!(fresh > 0);
+    assert_eq!(reused, 0);
+}
+
+#[test]
+fn test_frame_info_does_not_allocate() {
+    use crate::decoding::FrameInfo;
+
+    let frame = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let mut info = None;
+    let allocations = count_allocations(|| {
+        info = Some(FrameInfo::read(&frame[..]).unwrap());
+    });
+    assert!(info.is_some());
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_decoder_with_window_capacity_does_not_allocate() {
+    use crate::decoding::{BlockDecodingStrategy, FrameDecoder, FrameInfo};
+
+    let compressed = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let expected = include_bytes!("../../decodecorpus_files/z000033");
+    let window_size = FrameInfo::read(&compressed[..]).unwrap().window_size as usize;
+    let mut result = Vec::with_capacity(expected.len());
+
+    // Collect after every block, so the decoder never holds more than the window and one block
+    let decode = |decoder: &mut FrameDecoder, result: &mut Vec<u8>| {
+        let mut source = &compressed[..];
+        decoder.reset(&mut source).unwrap();
+        while !decoder
+            .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap()
+        {
+            decoder.collect_to_writer(&mut *result).unwrap();
+        }
+        decoder.collect_to_writer(&mut *result).unwrap();
+    };
+
+    let mut decoder = FrameDecoder::new();
+    let fresh = count_allocations(|| decode(&mut decoder, &mut result));
+    assert_eq!(result, expected);
+
+    result.clear();
+    let mut decoder = FrameDecoder::with_window_capacity(window_size).unwrap();
+    let reserved = count_allocations(|| decode(&mut decoder, &mut result));
+    assert_eq!(result, expected);
+
+    assert!(fresh > 0);
+    assert_eq!(reserved, 0);
+}
+
+#[test]
+fn test_reserve_window_for_many_small_frames() {
+    use crate::decoding::{AllocKind, FrameDecoder};
+    use crate::encoding::{compress_slice_to_vec, CompressionLevel};
+    use std::boxed::Box;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Frames of growing size, each with a window of its content size
+    let frames: Vec<(Vec<u8>, Vec<u8>)> = (1..=64)
+        .map(|i| {
+            let data: Vec<u8> = (0..i * 500)
+                .map(|x| (x % 251) as u8 ^ (x / 7) as u8)
+                .collect();
+            (
+                compress_slice_to_vec(&data, CompressionLevel::Fastest),
+                data,
+            )
+        })
+        .collect();
+    let mut result = Vec::with_capacity(64 * 500);
+    let decode_all = |decoder: &mut FrameDecoder, result: &mut Vec<u8>| {
+        for (compressed, data) in &frames {
+            result.clear();
+            decoder.decode_all_to_vec(compressed, result).unwrap();
+            assert_eq!(result, data);
+        }
+    };
+
+    let mut decoder = FrameDecoder::new();
+    let growing = count_allocations(|| decode_all(&mut decoder, &mut result));
+
+    let mut decoder = FrameDecoder::new();
+    decoder.reserve_window(64 * 500).unwrap();
+    let reserved = count_allocations(|| decode_all(&mut decoder, &mut result));
+
+    // Frames with smaller windows than the previous ones reuse the window buffer instead of regrowing it
+    let window_allocated = Arc::new(AtomicUsize::new(0));
+    let hook_allocated = window_allocated.clone();
+    let mut decoder = FrameDecoder::new();
+    decoder.set_alloc_hook(Some(Box::new(move |kind: AllocKind, bytes: usize| {
+        if kind == AllocKind::Window {
+            hook_allocated.fetch_add(bytes, Ordering::Relaxed);
+        }
+        true
+    })));
+    let (largest, _) = frames.last().unwrap();
+    decoder
+        .decode_all_to_vec(largest, &mut Vec::with_capacity(64 * 500))
+        .unwrap();
+    let after_largest = window_allocated.load(Ordering::Relaxed);
+    for (compressed, data) in frames.iter().rev() {
+        result.clear();
+        decoder.decode_all_to_vec(compressed, &mut result).unwrap();
+        assert_eq!(&result, data);
+    }
+
+    assert!(growing > 0);
+    assert_eq!(reserved, 0);
+    assert!(after_largest >= 64 * 500);
+    assert_eq!(after_largest, window_allocated.load(Ordering::Relaxed));
+}
@@ -0,0 +1,156 @@
+//! The decoder hashes the decoded bytes when they are drained. These tests make sure the hash covers
+//! exactly the content of the frame, in order, no matter how the output is collected.
+//! The encoder has to hash the same bytes, so its checksums are accepted by both decoders.
+
+use crate::decoding::{
+    extract_block, BlockDecodingStrategy, BlockType, FrameDecoder, StreamingDecoder,
+};
+use crate::io::Read;
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+fn compress_with_checksum(data: &[u8], level: i32) -> Vec<u8> {
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), level).unwrap();
+    encoder.include_checksum(true).unwrap();
+    std::io::Write::write_all(&mut encoder, data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn inputs() -> Vec<Vec<u8>> {
+    let mut rng = SmallRng::seed_from_u64(0x2241);
+    let mut random = vec![0u8; 10_000];
+    rng.fill_bytes(&mut random);
+    let repetitive: Vec<u8> = (0..300 * 1024).map(|x| (x % 251) as u8).collect();
+    let mut mixed = include_bytes!("../../decodecorpus_files/z000088").to_vec();
+    mixed.extend_from_slice(&random);
+    vec![Vec::new(), b"a".to_vec(), random, repetitive, mixed]
+}
+
+#[test]
+fn checksum_matches_for_all_ways_of_collecting() {
+    for data in inputs() {
+        for level in [1, 19] {
+            let compressed = compress_with_checksum(&data, level);
+
+            let mut decoder = FrameDecoder::new();
+            let mut output = Vec::with_capacity(data.len());
+            decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+            assert_eq!(output, data);
+            assert_eq!(
+                decoder.get_calculated_checksum(),
+                decoder.get_checksum_from_data()
+            );
+
+            // Small reads drain the buffer in many pieces that wrap around the ring buffer
+            let mut streaming = StreamingDecoder::new(compressed.as_slice()).unwrap();
+            let mut output = Vec::new();
+            let mut buf = [0u8; 7];
+            loop {
+                let read = streaming.read(&mut buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buf[..read]);
+            }
+            assert_eq!(output, data);
+            let decoder = streaming.into_frame_decoder();
+            assert_eq!(
+                decoder.get_calculated_checksum(),
+                decoder.get_checksum_from_data()
+            );
+
+            // Collecting while decoding drains everything but the window before the frame is finished
+            let mut decoder = FrameDecoder::new();
+            let mut source = compressed.as_slice();
+            decoder.reset(&mut source).unwrap();
+            let mut output = Vec::new();
+            while !decoder.is_finished() {
+                decoder
+                    .decode_blocks(&mut source, BlockDecodingStrategy::UptoBytes(1000))
+                    .unwrap();
+                decoder.collect_to_writer(&mut output).unwrap();
+            }
+            decoder.collect_to_writer(&mut output).unwrap();
+            assert_eq!(output, data);
+            assert!(decoder.get_checksum_from_data().is_some());
+            assert_eq!(
+                decoder.get_calculated_checksum(),
+                decoder.get_checksum_from_data()
+            );
+        }
+    }
+}
+
+#[test]
+fn checksum_detects_changed_content() {
+    let data = &inputs()[2];
+    let mut compressed = compress_with_checksum(data, 1);
+
+    // Random data ends up in a raw block, so changing a byte of the content still decodes
+    let (header, _) = extract_block(compressed.as_slice(), 0).unwrap();
+    assert_eq!(header.block_type, BlockType::Raw);
+    let idx = compressed.len() / 2;
+    compressed[idx] ^= 1;
+
+    let mut decoder = FrameDecoder::new();
+    let mut output = Vec::with_capacity(data.len());
+    decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+    assert_ne!(&output, data);
+    assert_ne!(
+        decoder.get_calculated_checksum(),
+        decoder.get_checksum_from_data()
+    );
+}
+
+#[test]
+fn encoder_checksum_round_trip() {
+    use crate::decoding::FrameInfo;
+    use crate::encoding::{CompressionLevel, FrameCompressor};
+    use core::convert::TryInto;
+    use core::hash::Hasher;
+
+    let mut inputs = inputs();
+    // All blocks of this input are encoded as RLE blocks
+    inputs.push(vec![7; 300 * 1024]);
+    for data in inputs {
+        let mut hasher = twox_hash::XxHash64::with_seed(0);
+        hasher.write(&data);
+        let expected = hasher.finish() as u32;
+
+        for level in [
+            CompressionLevel::Uncompressed,
+            CompressionLevel::Fastest,
+            CompressionLevel::Default,
+        ] {
+            let mut compressor = FrameCompressor::new(level);
+            compressor.set_content_checksum(true);
+            compressor.set_source(data.as_slice());
+            compressor.set_drain(Vec::new());
+            compressor.compress().unwrap();
+            let mut compressed = compressor.take_drain().unwrap();
+
+            assert!(
+                FrameInfo::read(compressed.as_slice())
+                    .unwrap()
+                    .content_checksum
+            );
+            let checksum =
+                u32::from_le_bytes(compressed[compressed.len() - 4..].try_into().unwrap());
+            assert_eq!(checksum, expected);
+
+            let mut decoder = FrameDecoder::new();
+            let mut output = Vec::with_capacity(data.len());
+            decoder.decode_all_to_vec(&compressed, &mut output).unwrap();
+            assert_eq!(output, data);
+            assert_eq!(decoder.get_checksum_from_data(), Some(expected));
+            assert_eq!(decoder.get_calculated_checksum(), Some(expected));
+
+            // The reference decoder verifies the checksum
+            assert_eq!(zstd::decode_all(compressed.as_slice()).unwrap(), data);
+            let last = compressed.len() - 1;
+            compressed[last] ^= 1;
+            assert!(zstd::decode_all(compressed.as_slice()).is_err());
+        }
+    }
+}
@@ -83,7 +83,10 @@ fn test_decode_corpus_files() {
         let mut original_p = p.clone();
         original_p.truncate(original_p.len() - 4);
         let original_f = fs::File::open(original_p).unwrap();
-        let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+        let original: Vec<u8> = std::io::BufReader::new(original_f)
+            .bytes()
+            .map(|x| x.unwrap())
+            .collect();
 
         println!("Results for file: {}", p.clone());
         let mut success = true;
@@ -192,3 +195,52 @@ fn test_decode_corpus_files() {
 
     assert!(failed.is_empty());
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn test_decode_corpus_files_against_reference() {
+    extern crate std;
+    use crate::decoding::FrameDecoder;
+    use alloc::vec::Vec;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::println;
+
+    let mut files: Vec<PathBuf> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("zst")))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut failed: Vec<PathBuf> = Vec::new();
+    for path in files {
+        println!("Trying file: {:?}", path);
+        let compressed = fs::read(&path).unwrap();
+
+        let mut expected = Vec::new();
+        zstd::stream::copy_decode(compressed.as_slice(), &mut expected).unwrap();
+
+        let mut result = Vec::with_capacity(expected.len());
+        match frame_dec.decode_all_to_vec(&compressed, &mut result) {
+            Ok(()) if result == expected => {}
+            Ok(()) => {
+                println!("Result differs from the reference decoder");
+                failed.push(path);
+            }
+            Err(e) => {
+                println!("Decoding failed: {}", e);
+                failed.push(path);
+            }
+        }
+    }
+
+    assert!(
+        failed.is_empty(),
+        "Output differed from the reference decoder for: {:?}",
+        failed
+    );
+}
@@ -98,7 +98,10 @@ fn test_dict_decoding() {
 
     let mut files: Vec<_> = fs::read_dir("./dict_tests/files").unwrap().collect();
     let dict = fs::File::open("./dict_tests/dictionary").unwrap();
-    let dict: Vec<u8> = dict.bytes().map(|x| x.unwrap()).collect();
+    let dict: Vec<u8> = std::io::BufReader::new(dict)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     files.sort_by_key(|x| match x {
         Err(_) => "".to_owned(),
@@ -156,7 +159,10 @@ fn test_dict_decoding() {
         let mut original_p = p.clone();
         original_p.truncate(original_p.len() - 4);
         let original_f = fs::File::open(original_p).unwrap();
-        let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+        let original: Vec<u8> = std::io::BufReader::new(original_f)
+            .bytes()
+            .map(|x| x.unwrap())
+            .collect();
 
         println!("Results for file: {}", p.clone());
         let mut success = true;
@@ -264,3 +270,92 @@ fn test_dict_decoding() {
 
     assert!(failed.is_empty());
 }
+
+#[test]
+fn test_create_dict() {
+    extern crate std;
+    use crate::decoding::{Dictionary, FrameDecoder};
+    use crate::dictionary::create_dict_from_source;
+    use alloc::vec::Vec;
+    use std::fs;
+
+    let mut paths: Vec<_> = fs::read_dir("./dict_tests/files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_none_or(|ext| ext != "zst"))
+        .collect();
+    paths.sort();
+    let files: Vec<Vec<u8>> = paths.iter().map(|path| fs::read(path).unwrap()).collect();
+    let samples: Vec<u8> = files.concat();
+
+    let mut raw_dict = Vec::new();
+    create_dict_from_source(samples.as_slice(), &mut raw_dict, 16 * 1024).unwrap();
+    assert!(raw_dict.len() <= 16 * 1024);
+    assert!(raw_dict.len() > 15 * 1024);
+
+    let dict = Dictionary::decode_dict(&raw_dict).unwrap();
+    let dict_id = dict.id;
+    let mut decoder = FrameDecoder::new();
+    decoder.add_dict(dict).unwrap();
+
+    // The reference implementation compresses with the content and the entropy tables of the dictionary
+    let mut compressor = zstd::bulk::Compressor::with_dictionary(3, &raw_dict).unwrap();
+    let mut reference_decompressor = zstd::bulk::Decompressor::with_dictionary(&raw_dict).unwrap();
+    let mut with_dict = 0;
+    let mut without_dict = 0;
+    for file in &files {
+        let compressed = compressor.compress(file).unwrap();
+        with_dict += compressed.len();
+        without_dict += zstd::bulk::compress(file, 3).unwrap().len();
+
+        let mut decoded = Vec::with_capacity(file.len());
+        decoder
+            .decode_all_to_vec(&compressed, &mut decoded)
+            .unwrap();
+        assert_eq!(&decoded, file);
+        assert_eq!(
+            &reference_decompressor
+                .decompress(&compressed, file.len())
+                .unwrap(),
+            file
+        );
+    }
+    assert_eq!(
+        crate::decoding::FrameInfo::read(compressor.compress(&files[0]).unwrap().as_slice())
+            .unwrap()
+            .dictionary_id,
+        Some(dict_id)
+    );
+    assert!(
+        with_dict * 2 < without_dict,
+        "with dictionary: {}, without: {}",
+        with_dict,
+        without_dict
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_create_dict_from_dir() {
+    use crate::decoding::Dictionary;
+    use crate::dictionary::{create_dict_from_dir, errors::DictionaryBuildError};
+    use alloc::vec::Vec;
+
+    let mut raw_dict = Vec::new();
+    create_dict_from_dir("./dict_tests/files", &mut raw_dict, 8 * 1024).unwrap();
+    assert!(Dictionary::decode_dict(&raw_dict).is_ok());
+
+    // Deterministic
+    let mut again = Vec::new();
+    create_dict_from_dir("./dict_tests/files", &mut again, 8 * 1024).unwrap();
+    assert_eq!(raw_dict, again);
+
+    assert!(matches!(
+        create_dict_from_dir("./dict_tests/files", &mut Vec::new(), 100),
+        Err(DictionaryBuildError::DictSizeTooSmall { size: 100, .. })
+    ));
+    assert!(matches!(
+        create_dict_from_dir("./does_not_exist", &mut Vec::new(), 8 * 1024),
+        Err(DictionaryBuildError::ReadError(_))
+    ));
+}
@@ -34,7 +34,7 @@ fn test_encode_corpus_files_uncompressed_our_decompressor() {
         compressor.set_source(input.as_slice());
         compressor.set_drain(&mut compressed_file);
 
-        compressor.compress();
+        compressor.compress().unwrap();
         let mut decompressed_output = Vec::new();
         let mut decoder =
             crate::decoding::StreamingDecoder::new(compressed_file.as_slice()).unwrap();
@@ -89,7 +89,7 @@ fn test_encode_corpus_files_uncompressed_original_decompressor() {
         let mut compressor = FrameCompressor::new(crate::encoding::CompressionLevel::Fastest);
         compressor.set_source(input.as_slice());
         compressor.set_drain(&mut compressed_file);
-        compressor.compress();
+        compressor.compress().unwrap();
         let mut decompressed_output = Vec::new();
         // zstd::stream::copy_decode(compressed_file.as_slice(), &mut decompressed_output).unwrap();
         match zstd::stream::copy_decode(compressed_file.as_slice(), &mut decompressed_output) {
@@ -151,7 +151,7 @@ fn test_encode_corpus_files_compressed_our_decompressor() {
         compressor.set_source(input.as_slice());
         compressor.set_drain(&mut compressed_file);
 
-        compressor.compress();
+        compressor.compress().unwrap();
         let mut decompressed_output = Vec::new();
         let mut decoder =
             crate::decoding::StreamingDecoder::new(compressed_file.as_slice()).unwrap();
@@ -206,7 +206,7 @@ fn test_encode_corpus_files_compressed_original_decompressor() {
         let mut compressor = FrameCompressor::new(crate::encoding::CompressionLevel::Fastest);
         compressor.set_source(input.as_slice());
         compressor.set_drain(&mut compressed_file);
-        compressor.compress();
+        compressor.compress().unwrap();
         let mut decompressed_output = Vec::new();
         // zstd::stream::copy_decode(compressed_file.as_slice(), &mut decompressed_output).unwrap();
         match zstd::stream::copy_decode(compressed_file.as_slice(), &mut decompressed_output) {
@@ -231,3 +231,133 @@ fn test_encode_corpus_files_compressed_original_decompressor() {
         }
     }
 }
+
+#[test]
+fn test_encode_all_levels() {
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::{compress_to_vec, LEVEL_LADDER};
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    let mut text = Vec::new();
+    text.extend_from_slice(include_bytes!("../../Readme.md"));
+    text.extend_from_slice(include_bytes!("../encoding/frame_compressor.rs"));
+    text.extend_from_slice(include_bytes!("../decoding/frame_decoder.rs"));
+    let mut binary = Vec::new();
+    for i in 0..40_000u32 {
+        binary.extend_from_slice(&i.to_le_bytes());
+        binary.extend_from_slice(&((i * i) as u16).to_le_bytes());
+        binary.push((i % 7) as u8);
+    }
+    let compressed = include_bytes!("../../decodecorpus_files/z000033.zst").to_vec();
+    let mut repetitive = vec![b'a'; 200 * 1024];
+    for _ in 0..1000 {
+        repetitive.extend_from_slice(b"abcdefgh12345678");
+    }
+
+    let mut inputs: Vec<(String, Vec<u8>)> = vec![(String::from("empty"), Vec::new())];
+    // Tiny inputs are smaller than a match or a bucket of the matcher
+    for len in 1..=16 {
+        inputs.push((format!("{} bytes of text", len), text[..len].to_vec()));
+        inputs.push((format!("{} equal bytes", len), vec![42; len]));
+    }
+    inputs.push((String::from("text"), text));
+    inputs.push((String::from("binary"), binary));
+    inputs.push((String::from("already compressed"), compressed));
+    inputs.push((String::from("repetitive"), repetitive));
+    for (name, input) in inputs.iter() {
+        let mut previous_size = usize::MAX;
+        // The levels are ordered from the least to the most effort
+        for level in LEVEL_LADDER {
+            let compressed = compress_to_vec(input.as_slice(), level);
+
+            let mut decoded = Vec::with_capacity(input.len());
+            FrameDecoder::new()
+                .decode_all_to_vec(&compressed, &mut decoded)
+                .unwrap();
+            assert!(decoded == *input, "{} at {:?}: our decoder", name, level);
+            let decoded = zstd::decode_all(compressed.as_slice()).unwrap();
+            assert!(
+                decoded == *input,
+                "{} at {:?}: original decoder",
+                name,
+                level
+            );
+
+            assert!(
+                compressed.len() <= previous_size,
+                "{} at {:?}: {} bytes, more than the previous level",
+                name,
+                level,
+                compressed.len()
+            );
+            previous_size = compressed.len();
+        }
+    }
+}
+
+/// Compares the output size of our encoder with the reference implementation at the equivalent level.
+///
+/// Run with `--nocapture` to see the ratios per file. Only the totals over the whole corpus are checked against
+/// a recorded baseline, to catch changes that make the compression noticeably worse.
+/// Update the baseline when a change improves the ratio.
+#[test]
+fn test_compare_size_with_reference() {
+    extern crate std;
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+    use alloc::vec::Vec;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::println;
+
+    /// How much bigger the output may get than the baseline before the test fails
+    const MARGIN: f64 = 1.01;
+    // Our total output size divided by the total output size of the reference implementation
+    let levels = [
+        (CompressionLevel::Fastest, 1, 1.163),
+        (CompressionLevel::Default, 3, 1.053),
+    ];
+
+    let mut files: Vec<_> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() != Some(OsStr::new("zst")))
+        .collect();
+    files.sort();
+    let inputs: Vec<_> = files.iter().map(|path| fs::read(path).unwrap()).collect();
+
+    for (level, reference_level, baseline) in levels {
+        let mut ours_total = 0;
+        let mut reference_total = 0;
+        for (path, input) in files.iter().zip(&inputs) {
+            let ours = compress_to_vec(input.as_slice(), level).len();
+            let reference = zstd::encode_all(input.as_slice(), reference_level)
+                .unwrap()
+                .len();
+            println!(
+                "{:?} at {:?}: {} bytes, reference {} bytes, ratio {:.3}",
+                path,
+                level,
+                ours,
+                reference,
+                ours as f64 / reference as f64
+            );
+            ours_total += ours;
+            reference_total += reference;
+        }
+        let ratio = ours_total as f64 / reference_total as f64;
+        println!(
+            "Total at {:?}: {} bytes, reference at level {}: {} bytes, ratio {:.3} (baseline {:.3})",
+            level, ours_total, reference_level, reference_total, ratio, baseline
+        );
+        assert!(
+            ratio <= baseline * MARGIN,
+            "Compression at {:?} got worse: ratio {:.3} to the reference, baseline {:.3}",
+            level,
+            ratio,
+            baseline
+        );
+    }
+}
@@ -25,3 +25,202 @@ fn test_all_artifacts() {
             .and_then(|()| frame_dec.decode_blocks(&mut f, BlockDecodingStrategy::All));
     }
 }
+
+#[test]
+fn test_block_content_size_exceeds_sections() {
+    use crate::decoding::errors::{
+        DecodeBlockContentError, DecompressBlockError, FrameDecoderError,
+    };
+    use crate::decoding::BlockDecodingStrategy;
+    use crate::decoding::FrameDecoder;
+    use alloc::vec::Vec;
+
+    // Single segment frame with a content size of 2
+    let mut frame: Vec<u8> = Vec::new();
+    frame.extend_from_slice(&crate::decoding::frame::MAGIC_NUM.to_le_bytes());
+    frame.extend_from_slice(&[0x20, 2]);
+
+    // Compressed block with 2 raw literals and no sequences, followed by 4 bytes not belonging to any section
+    let content = [0x10, b'a', b'b', 0, 0xFF, 0xFF, 0xFF, 0xFF];
+    let block_header = 1 | (2 << 1) | ((content.len() as u32) << 3);
+    frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+    frame.extend_from_slice(&content);
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut source = frame.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    let result = frame_dec.decode_blocks(&mut source, BlockDecodingStrategy::All);
+    assert!(
+        matches!(
+            result,
+            Err(FrameDecoderError::FailedToReadBlockBody(
+                DecodeBlockContentError::DecompressBlockError(
+                    DecompressBlockError::TrailingBlockBytes {
+                        expected: 8,
+                        got: 4
+                    }
+                )
+            ))
+        ),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn test_treeless_literals_in_first_block() {
+    use crate::decoding::errors::{
+        DecodeBlockContentError, DecompressBlockError, DecompressLiteralsError, FrameDecoderError,
+    };
+    use crate::decoding::BlockDecodingStrategy;
+    use crate::decoding::FrameDecoder;
+    use alloc::vec::Vec;
+
+    // Single segment frame with a content size of 4
+    let mut frame: Vec<u8> = Vec::new();
+    frame.extend_from_slice(&crate::decoding::frame::MAGIC_NUM.to_le_bytes());
+    frame.extend_from_slice(&[0x20, 4]);
+
+    // Compressed block with 4 treeless literals in a single 2 byte stream and no sequences.
+    // There is no previous block whose huffman table could be repeated.
+    let literals_header: u32 = 3 | (4 << 4) | (2 << 14);
+    let mut content = Vec::new();
+    content.extend_from_slice(&literals_header.to_le_bytes()[..3]);
+    content.extend_from_slice(&[0xAA, 0x01, 0]);
+    let block_header = 1 | (2 << 1) | ((content.len() as u32) << 3);
+    frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+    frame.extend_from_slice(&content);
+
+    let check = |frame_dec: &mut FrameDecoder| {
+        let mut source = frame.as_slice();
+        frame_dec.reset(&mut source).unwrap();
+        let result = frame_dec.decode_blocks(&mut source, BlockDecodingStrategy::All);
+        assert!(
+            matches!(
+                result,
+                Err(FrameDecoderError::FailedToReadBlockBody(
+                    DecodeBlockContentError::DecompressBlockError(
+                        DecompressBlockError::DecompressLiteralsError(
+                            DecompressLiteralsError::UninitializedHuffmanTable
+                        )
+                    )
+                ))
+            ),
+            "{:?}",
+            result
+        );
+    };
+
+    let mut frame_dec = FrameDecoder::new();
+    check(&mut frame_dec);
+
+    // The huffman table of a previous frame must not be used either
+    let mut previous = Vec::with_capacity(1024 * 1024);
+    frame_dec
+        .decode_all_to_vec(
+            include_bytes!("../../decodecorpus_files/z000033.zst"),
+            &mut previous,
+        )
+        .unwrap();
+    check(&mut frame_dec);
+}
+
+#[test]
+fn test_literals_regenerated_size_too_large() {
+    use crate::decoding::errors::{
+        DecodeBlockContentError, DecompressBlockError, DecompressLiteralsError, FrameDecoderError,
+    };
+    use crate::decoding::BlockDecodingStrategy;
+    use crate::decoding::FrameDecoder;
+    use alloc::vec::Vec;
+
+    // Frame with the given window descriptor and one compressed block with the given content
+    fn frame(window_descriptor: u8, content: &[u8]) -> Vec<u8> {
+        let mut frame: Vec<u8> = Vec::new();
+        frame.extend_from_slice(&crate::decoding::frame::MAGIC_NUM.to_le_bytes());
+        frame.extend_from_slice(&[0, window_descriptor]);
+        let block_header = 1 | (2 << 1) | ((content.len() as u32) << 3);
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(content);
+        frame
+    }
+
+    // RLE literals with a 3 byte header, followed by the literal and a sequences header without sequences
+    fn rle_literals(regenerated_size: u32) -> Vec<u8> {
+        let literals_header = 1 | (3 << 2) | (regenerated_size << 4);
+        let mut content = literals_header.to_le_bytes()[..3].to_vec();
+        content.extend_from_slice(&[b'a', 0]);
+        content
+    }
+
+    let decode = |frame: &[u8]| {
+        let mut frame_dec = FrameDecoder::new();
+        let mut source = frame;
+        frame_dec.reset(&mut source).unwrap();
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::All)
+            .map(|_| frame_dec.collect().unwrap())
+    };
+    let is_too_large = |result: &Result<Vec<u8>, FrameDecoderError>, size: u32, limit: u32| {
+        matches!(
+            result,
+            Err(FrameDecoderError::FailedToReadBlockBody(
+                DecodeBlockContentError::DecompressBlockError(
+                    DecompressBlockError::DecompressLiteralsError(
+                        DecompressLiteralsError::RegeneratedSizeTooLarge { got, max }
+                    )
+                )
+            )) if *got == size && *max == limit
+        )
+    };
+
+    // 1KB window, the block can't contain more than the window
+    assert_eq!(decode(&frame(0, &rle_literals(1024))).unwrap().len(), 1024);
+    let result = decode(&frame(0, &rle_literals(0xFFFFF)));
+    assert!(is_too_large(&result, 0xFFFFF, 1024), "{:?}", result);
+
+    // 8MB window, compressed literals with 4 streams declaring more than 128KB
+    let literals_header: u64 = 2 | (3 << 2) | (200_000 << 4) | (10 << 22);
+    let mut content = literals_header.to_le_bytes()[..5].to_vec();
+    content.extend_from_slice(&[0xFF; 10]);
+    content.push(0);
+    let result = decode(&frame(13 << 3, &content));
+    assert!(is_too_large(&result, 200_000, 128 * 1024), "{:?}", result);
+}
+
+#[test]
+fn test_literals_invalid_num_streams() {
+    use crate::blocks::literals_section::{LiteralsSection, LiteralsSectionType};
+    use crate::decoding::errors::DecompressLiteralsError;
+    use crate::decoding::literals_section_decoder::decode_literals;
+    use crate::decoding::scratch::HuffmanScratch;
+    use alloc::vec::Vec;
+
+    // Parsing a header only yields 1 or 4 streams, but the section can be built by hand
+    let source = [0xFF; 16];
+    for num_streams in [0, 2, 3, 5, 255] {
+        let mut section = LiteralsSection::new();
+        section.ls_type = LiteralsSectionType::Compressed;
+        section.regenerated_size = 100;
+        section.compressed_size = Some(source.len() as u32);
+        section.num_streams = Some(num_streams);
+
+        let mut target = Vec::new();
+        let result = decode_literals(
+            &section,
+            &mut HuffmanScratch::new(),
+            &source,
+            &mut target,
+            128 * 1024,
+        );
+        assert!(
+            matches!(
+                result,
+                Err(DecompressLiteralsError::InvalidNumStreams { got }) if got == num_streams
+            ),
+            "{:?}",
+            result
+        );
+        assert!(target.is_empty());
+    }
+}
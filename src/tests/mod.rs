@@ -23,6 +23,52 @@ impl crate::io_nostd::Read for std::fs::File {
     }
 }
 
+#[test]
+fn test_decode_all_content_in_window() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{frame::MAGIC_NUM, FrameDecoder};
+
+    // Frame with a 1KiB window, a 4 byte content size field and one raw block
+    fn frame(declared: u32, content: &[u8]) -> Vec<u8> {
+        let mut frame = MAGIC_NUM.to_le_bytes().to_vec();
+        frame.push(0b1000_0000);
+        frame.push(0);
+        frame.extend_from_slice(&declared.to_le_bytes());
+        let block_header = (content.len() as u32) << 3 | 1;
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(content);
+        frame
+    }
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut input = frame(13, b"small content");
+    input.extend(frame(5, b"other"));
+    let mut output = [0u8; 18];
+    let written = frame_dec.decode_all(&input, &mut output).unwrap();
+    assert_eq!(written, 18);
+    assert_eq!(&output, b"small contentother");
+
+    // The declared size is known to not fit before anything is decoded
+    let mut output = [0u8; 12];
+    let err = frame_dec
+        .decode_all(&frame(13, b"small content"), &mut output)
+        .unwrap_err();
+    assert!(matches!(err, FrameDecoderError::TargetTooSmall));
+    assert_eq!(output, [0u8; 12]);
+
+    // Frames that are not single segment are checked against their declared size as well
+    let err = frame_dec
+        .decode_all(&frame(5, b"more than declared"), &mut [0u8; 32])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::ContentSizeExceeded {
+            declared: 5,
+            decoded: 18
+        }
+    ));
+}
+
 #[cfg(all(test, feature = "std"))]
 #[allow(dead_code)]
 fn assure_error_impl() {
@@ -126,6 +172,37 @@ fn test_frame_decoder() {
         .unwrap();
 }
 
+#[test]
+fn test_buffered_bytes() {
+    use crate::decoding::{BlockDecodingStrategy, FrameDecoder};
+
+    let mut content = &include_bytes!("../../decodecorpus_files/z000088.zst")[..];
+    let (frame, _) = crate::decoding::frame::read_frame_header(content).unwrap();
+    let window_size = frame.header.window_size().unwrap() as usize;
+    let mut frame_dec = FrameDecoder::new();
+    assert_eq!(frame_dec.buffered_bytes(), 0);
+    frame_dec.reset(&mut content).unwrap();
+    assert_eq!(frame_dec.buffered_bytes(), 0);
+
+    let mut decoded = 0;
+    while !frame_dec.is_finished() {
+        frame_dec
+            .decode_blocks(&mut content, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap();
+        let buffered = frame_dec.buffered_bytes();
+        if !frame_dec.is_finished() {
+            assert_eq!(
+                buffered,
+                frame_dec.can_collect() + buffered.min(window_size)
+            );
+        }
+        decoded += frame_dec.collect().map_or(0, |collected| collected.len());
+        assert!(frame_dec.buffered_bytes() <= window_size);
+    }
+    assert!(decoded > window_size);
+    assert_eq!(frame_dec.buffered_bytes(), 0);
+}
+
 #[test]
 fn test_decode_from_to() {
     use crate::decoding::FrameDecoder;
@@ -134,7 +211,10 @@ fn test_decode_from_to() {
     let f = File::open("./decodecorpus_files/z000088.zst").unwrap();
     let mut frame_dec = FrameDecoder::new();
 
-    let content: Vec<u8> = f.bytes().map(|x| x.unwrap()).collect();
+    let content: Vec<u8> = std::io::BufReader::new(f)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     let mut target = vec![0u8; 1024 * 1024];
 
@@ -143,6 +223,7 @@ fn test_decode_from_to() {
     let (read1, written1) = frame_dec
         .decode_from_to(source1, target.as_mut_slice())
         .unwrap();
+    assert!(!frame_dec.last_decode_consumed_checksum());
 
     //second part explicitely without checksum
     let source2 = &content[read1..content.len() - 4];
@@ -152,6 +233,15 @@ fn test_decode_from_to() {
 
     //must have decoded until checksum
     assert!(read1 + read2 == content.len() - 4);
+    assert!(!frame_dec.last_decode_consumed_checksum());
+
+    //an incomplete checksum is not consumed
+    let partial_chksum = &content[read1 + read2..content.len() - 1];
+    let (read_partial, written_partial) = frame_dec
+        .decode_from_to(partial_chksum, &mut target[written1 + written2..])
+        .unwrap();
+    assert_eq!((read_partial, written_partial), (0, 0));
+    assert!(!frame_dec.last_decode_consumed_checksum());
 
     //insert checksum separatly to test that this is handled correctly
     let chksum_source = &content[read1 + read2..];
@@ -162,6 +252,15 @@ fn test_decode_from_to() {
     //this must result in these values because just the checksum was processed
     assert!(read3 == 4);
     assert!(written3 == 0);
+    assert!(frame_dec.last_decode_consumed_checksum());
+
+    //the checksum can also be consumed together with the last block
+    let mut single_call_dec = FrameDecoder::new();
+    let (read_all, _) = single_call_dec
+        .decode_from_to(&content, &mut vec![0u8; 1024 * 1024])
+        .unwrap();
+    assert_eq!(read_all, content.len());
+    assert!(single_call_dec.last_decode_consumed_checksum());
 
     let read = read1 + read2 + read3;
     let written = written1 + written2;
@@ -198,7 +297,10 @@ fn test_decode_from_to() {
     }
 
     let original_f = File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = std::io::BufReader::new(original_f)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     if original.len() != result.len() {
         panic!(
@@ -257,7 +359,10 @@ fn test_specific_file() {
     let result = frame_dec.collect().unwrap();
 
     let original_f = fs::File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = std::io::BufReader::new(original_f)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     std::println!("Results for file: {}", path);
 
@@ -302,7 +407,10 @@ fn test_streaming() {
     Read::read_to_end(&mut stream, &mut result).unwrap();
 
     let original_f = fs::File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = std::io::BufReader::new(original_f)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     if original.len() != result.len() {
         panic!(
@@ -344,7 +452,10 @@ fn test_streaming() {
     Read::read_to_end(&mut stream, &mut result).unwrap();
 
     let original_f = fs::File::open("./decodecorpus_files/z000068").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = std::io::BufReader::new(original_f)
+        .bytes()
+        .map(|x| x.unwrap())
+        .collect();
 
     std::println!("Results for file:");
 
@@ -376,6 +487,48 @@ fn test_streaming() {
     }
 }
 
+#[test]
+fn test_decode_from_to_truncated() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::FrameDecoder;
+
+    let content = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let original = include_bytes!("../../decodecorpus_files/z000088");
+
+    // Cut into the last block, then only the checksum is missing
+    for cut in [100, 4] {
+        let mut frame_dec = FrameDecoder::new();
+        let mut target = vec![0u8; original.len()];
+        let mut source = &content[..content.len() - cut];
+        let mut written = 0;
+        let mut calls = 0;
+        // The caller feeds all input it has and then keeps calling with an empty source
+        let result = loop {
+            calls += 1;
+            assert!(calls < 100, "decode_from_to does not terminate");
+            match frame_dec.decode_from_to(source, &mut target[written..]) {
+                Ok((read, new_written)) => {
+                    source = &source[read..];
+                    written += new_written;
+                    if read == 0 {
+                        source = &[];
+                    }
+                }
+                Err(e) => break e,
+            }
+        };
+        assert!(
+            matches!(result, FrameDecoderError::UnexpectedEof),
+            "{:?}",
+            result
+        );
+        if cut == 4 {
+            // Everything but the checksum was there, so all of the content was decoded
+            assert_eq!(&target[..written], original);
+        }
+    }
+}
+
 #[test]
 fn test_incremental_read() {
     use crate::decoding::FrameDecoder;
@@ -400,6 +553,64 @@ fn test_incremental_read() {
     assert_eq!(output.map(char::from), ['d', 'e', 'f']);
 }
 
+#[test]
+fn test_streaming_one_byte_reads() {
+    use crate::decoding::StreamingDecoder;
+    use crate::io::{Error, ErrorKind, Read};
+
+    /// Returns at most one byte per call and is interrupted before every other byte
+    struct OneByteReader<'a> {
+        data: &'a [u8],
+        interrupt: bool,
+    }
+    impl Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(Error::from(ErrorKind::Interrupted));
+            }
+            let len = buf.len().min(self.data.len()).min(1);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    let compressed = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let original = include_bytes!("../../decodecorpus_files/z000088");
+    let mut source = OneByteReader {
+        data: compressed,
+        interrupt: false,
+    };
+    let mut header = [0u8; 14];
+    source.read_exact(&mut header).unwrap();
+    assert_eq!(header, compressed[..14]);
+
+    let source = OneByteReader {
+        data: compressed,
+        interrupt: false,
+    };
+    let mut decoder = StreamingDecoder::new(source).unwrap();
+    let mut decoded = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        match decoder.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => decoded.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+    assert_eq!(decoded, original);
+
+    // A reader that ends in the middle of the header is still reported as too short
+    let source = OneByteReader {
+        data: &compressed[..5],
+        interrupt: false,
+    };
+    assert!(StreamingDecoder::new(source).is_err());
+}
+
 #[test]
 #[cfg(not(feature = "std"))]
 fn test_streaming_no_std() {
@@ -484,6 +695,63 @@ fn test_streaming_no_std() {
     }
 }
 
+#[test]
+fn test_streaming_from_slice() {
+    use crate::decoding::StreamingDecoder;
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+    use crate::io::Read;
+
+    let first = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000068.zst");
+    let mut input = first.to_vec();
+    input.extend_from_slice(second);
+    input.extend_from_slice(&compress_to_vec(&[][..], CompressionLevel::Fastest));
+    input.extend_from_slice(b"trailing");
+
+    let mut offset = 0;
+    for original in [
+        &include_bytes!("../../decodecorpus_files/z000088")[..],
+        &include_bytes!("../../decodecorpus_files/z000068")[..],
+        &[],
+    ] {
+        let mut stream = StreamingDecoder::from_slice(&input[offset..]).unwrap();
+        assert!(stream.bytes_consumed() > 0);
+        let mut result = vec![0; original.len()];
+        stream.read_exact(&mut result).unwrap();
+        assert_eq!(result, original);
+        assert_eq!(stream.read(&mut [0u8; 1]).unwrap(), 0);
+        offset += stream.bytes_consumed();
+        assert_eq!(stream.get_ref(), &&input[offset..]);
+    }
+    assert_eq!(&input[offset..], b"trailing");
+    assert_eq!(offset - first.len(), second.len() + 9);
+}
+
+#[test]
+#[cfg(not(feature = "std"))]
+fn test_encode_no_std() {
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+    use crate::io::Read;
+
+    for original in [
+        include_bytes!("../../decodecorpus_files/z000088").as_slice(),
+        include_bytes!("../../decodecorpus_files/z000068").as_slice(),
+    ] {
+        for level in [CompressionLevel::Uncompressed, CompressionLevel::Fastest] {
+            let compressed = compress_to_vec(original, level);
+
+            let mut content = compressed.as_slice();
+            let mut stream = crate::decoding::StreamingDecoder::new(&mut content).unwrap();
+            let mut result = vec![0; original.len()];
+            Read::read_exact(&mut stream, &mut result).unwrap();
+            assert_eq!(original, result.as_slice());
+
+            // Nothing may be left over after the original content
+            assert_eq!(Read::read(&mut stream, &mut [0]).unwrap(), 0);
+        }
+    }
+}
+
 #[test]
 fn test_decode_all() {
     use crate::decoding::errors::FrameDecoderError;
@@ -570,9 +838,1333 @@ fn test_decode_all() {
     assert_eq!(output, original);
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn test_skippable_handler() {
+    use crate::decoding::FrameDecoder;
+    use std::sync::{Arc, Mutex};
+
+    let mut input = Vec::new();
+    input.extend_from_slice(&0x184D2A5Eu32.to_le_bytes());
+    input.extend_from_slice(&3u32.to_le_bytes());
+    input.extend_from_slice(b"abc");
+    input.extend_from_slice(include_bytes!("../../decodecorpus_files/z000089.zst"));
+    input.extend_from_slice(&0x184D2A50u32.to_le_bytes());
+    input.extend_from_slice(&0u32.to_le_bytes());
+    let original = include_bytes!("../../decodecorpus_files/z000089");
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut decoder = FrameDecoder::new();
+    let seen_clone = seen.clone();
+    decoder.set_skippable_handler(move |magic_number, payload| {
+        seen_clone
+            .lock()
+            .unwrap()
+            .push((magic_number, payload.to_vec()))
+    });
+
+    let mut output = Vec::with_capacity(original.len());
+    decoder.decode_all_to_vec(&input, &mut output).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [(0x184D2A5E, b"abc".to_vec()), (0x184D2A50, Vec::new())]
+    );
+
+    // Without a handler the frames are just skipped
+    decoder.clear_skippable_handler();
+    let mut output = Vec::with_capacity(original.len());
+    decoder.decode_all_to_vec(&input, &mut output).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(seen.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_skippable_handler_limits() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{AllocKind, FrameDecoder};
+    use alloc::boxed::Box;
+
+    // Without a handler the payload is not collected, so it is not limited
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_window_size(2);
+    decoder.skip_frame(&b"abc"[..], 0x184D2A50, 3).unwrap();
+
+    decoder.set_skippable_handler(|_, _| {});
+    assert!(matches!(
+        decoder.skip_frame(&b"abc"[..], 0x184D2A50, 3),
+        Err(FrameDecoderError::SkippableFrameTooBig { length: 3, max: 2 })
+    ));
+
+    decoder.set_max_window_size(3);
+    decoder.set_alloc_hook(Some(Box::new(|kind: AllocKind, _: usize| {
+        kind != AllocKind::SkippablePayload
+    })));
+    assert!(matches!(
+        decoder.skip_frame(&b"abc"[..], 0x184D2A50, 3),
+        Err(FrameDecoderError::AllocationRejected {
+            kind: AllocKind::SkippablePayload,
+            bytes: 3
+        })
+    ));
+    decoder.set_alloc_hook(None);
+    decoder.skip_frame(&b"abc"[..], 0x184D2A50, 3).unwrap();
+}
+
+#[test]
+fn test_decode_all_concatenated_frames() {
+    use crate::decoding::FrameDecoder;
+
+    let first = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000089.zst");
+    let mut input = first.to_vec();
+    // Skippable frame with a 3 byte payload between the two frames
+    input.extend_from_slice(&0x184D2A50u32.to_le_bytes());
+    input.extend_from_slice(&3u32.to_le_bytes());
+    input.extend_from_slice(b"abc");
+    input.extend_from_slice(second);
+
+    let mut expected = include_bytes!("../../decodecorpus_files/z000088").to_vec();
+    expected.extend_from_slice(include_bytes!("../../decodecorpus_files/z000089"));
+
+    let mut decoder = FrameDecoder::new();
+    let mut output = Vec::with_capacity(expected.len());
+    decoder.decode_all_to_vec(&input, &mut output).unwrap();
+    assert_eq!(output, expected);
+
+    // An incomplete frame at the end is an error and leaves the output untouched
+    let mut output = Vec::with_capacity(expected.len());
+    assert!(decoder
+        .decode_all_to_vec(&input[..input.len() - 10], &mut output)
+        .is_err());
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_max_frames() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::FrameDecoder;
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+
+    let frame = compress_to_vec(&[][..], CompressionLevel::Fastest);
+    let mut input = Vec::new();
+    for _ in 0..3 {
+        input.extend_from_slice(&frame);
+    }
+    // Skippable frames count as well
+    input.extend_from_slice(&0x184D2A50u32.to_le_bytes());
+    input.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_frames(Some(4));
+    decoder.decode_all(&input, &mut []).unwrap();
+
+    decoder.set_max_frames(Some(3));
+    let err = decoder.decode_all(&input, &mut []).unwrap_err();
+    assert!(matches!(err, FrameDecoderError::TooManyFrames { max: 3 }));
+    let mut output = Vec::new();
+    decoder
+        .decode_all_to_vec(&input[..frame.len() * 3], &mut output)
+        .unwrap();
+
+    decoder.set_max_frames(None);
+    decoder.decode_all(&input, &mut []).unwrap();
+}
+
+#[test]
+fn test_streaming_concatenated() {
+    use crate::decoding::{FrameDecoder, StreamingDecoder};
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+    use crate::io::Read;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    fn read_all(mut decoder: impl Read) -> (Vec<u8>, bool) {
+        let mut output = Vec::new();
+        let mut buf = [0u8; 1000];
+        loop {
+            match decoder.read(&mut buf) {
+                Ok(0) => return (output, true),
+                Ok(read) => output.extend_from_slice(&buf[..read]),
+                Err(_) => return (output, false),
+            }
+        }
+    }
+    fn skippable(payload: &[u8]) -> Vec<u8> {
+        let mut frame = 0x184D2A50u32.to_le_bytes().to_vec();
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    let first = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000089.zst");
+    let mut input = skippable(b"abc");
+    input.extend_from_slice(first);
+    input.extend_from_slice(&skippable(b"defg"));
+    input.extend_from_slice(&compress_to_vec(&[][..], CompressionLevel::Fastest));
+    input.extend_from_slice(second);
+    input.extend_from_slice(&skippable(b""));
+
+    let mut expected = include_bytes!("../../decodecorpus_files/z000088").to_vec();
+    expected.extend_from_slice(include_bytes!("../../decodecorpus_files/z000089"));
+
+    let decoder = StreamingDecoder::new_concatenated(input.as_slice()).unwrap();
+    assert_eq!(read_all(decoder), (expected.clone(), true));
+
+    // Without concatenation only the first frame is decoded
+    let mut source = &input[11..];
+    let decoder = StreamingDecoder::new(&mut source).unwrap();
+    assert_eq!(
+        read_all(decoder),
+        (
+            include_bytes!("../../decodecorpus_files/z000088").to_vec(),
+            true
+        )
+    );
+    assert_eq!(source.len(), input.len() - 11 - first.len());
+
+    // An empty source has no frames and no content
+    let decoder = StreamingDecoder::new_concatenated(&[][..]).unwrap();
+    assert_eq!(read_all(decoder), (Vec::new(), true));
+
+    // Trailing garbage and truncated frames are errors, after the content before them
+    let mut garbage = input.clone();
+    garbage.extend_from_slice(b"garbage");
+    let decoder = StreamingDecoder::new_concatenated(garbage.as_slice()).unwrap();
+    assert_eq!(read_all(decoder), (expected.clone(), false));
+    let truncated = &input[..input.len() - 20];
+    let decoder = StreamingDecoder::new_concatenated(truncated).unwrap();
+    assert!(!read_all(decoder).1);
+
+    // The frame limit counts the skippable frames as well
+    let mut frame_decoder = FrameDecoder::new();
+    let skipped = alloc::sync::Arc::new(AtomicUsize::new(0));
+    let skipped_clone = skipped.clone();
+    frame_decoder.set_skippable_handler(move |_, payload| {
+        skipped_clone.fetch_add(payload.len() + 1, Ordering::Relaxed);
+    });
+    frame_decoder.set_max_frames(Some(5));
+    let decoder =
+        StreamingDecoder::new_concatenated_with_decoder(input.as_slice(), &mut frame_decoder)
+            .unwrap();
+    assert_eq!(read_all(decoder), (expected.clone(), false));
+    frame_decoder.set_max_frames(Some(6));
+    let decoder =
+        StreamingDecoder::new_concatenated_with_decoder(input.as_slice(), &mut frame_decoder)
+            .unwrap();
+    assert_eq!(read_all(decoder), (expected, true));
+    // The first run stopped before the last skippable frame
+    assert_eq!(skipped.load(Ordering::Relaxed), (4 + 5) + (4 + 5 + 1));
+}
+
+#[test]
+fn test_decode_to_writer() {
+    use crate::decoding::FrameDecoder;
+
+    let mut decoder = FrameDecoder::new();
+    for (compressed, original) in [
+        (
+            &include_bytes!("../../decodecorpus_files/z000033.zst")[..],
+            &include_bytes!("../../decodecorpus_files/z000033")[..],
+        ),
+        (
+            &include_bytes!("../../decodecorpus_files/z000089.zst")[..],
+            &include_bytes!("../../decodecorpus_files/z000089")[..],
+        ),
+    ] {
+        let mut output = Vec::new();
+        let written = decoder.decode_to_writer(compressed, &mut output).unwrap();
+        assert_eq!(written, original.len() as u64);
+        assert_eq!(output, original);
+        assert_eq!(decoder.bytes_read_from_source(), compressed.len() as u64);
+    }
+
+    // a sink that is too small results in an error instead of an endless loop
+    let mut output = [0u8; 100];
+    let result = decoder.decode_to_writer(
+        &include_bytes!("../../decodecorpus_files/z000089.zst")[..],
+        &mut output[..],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_read_to_vec() {
+    use crate::decoding::read_to_vec;
+
+    let compressed = &include_bytes!("../../decodecorpus_files/z000033.zst")[..];
+    let original = &include_bytes!("../../decodecorpus_files/z000033")[..];
+
+    let mut input = compressed.to_vec();
+    input.extend_from_slice(b"trailing");
+    let mut source = input.as_slice();
+    assert_eq!(read_to_vec(&mut source).unwrap(), original);
+    assert_eq!(source, b"trailing");
+
+    assert!(read_to_vec(&compressed[..compressed.len() - 10]).is_err());
+    assert!(read_to_vec(&[][..]).is_err());
+}
+
+#[test]
+fn test_decode_magicless() {
+    use crate::decoding::errors::ReadFrameHeaderError;
+    use crate::decoding::frame::{read_frame_header, read_frame_header_magicless};
+    use crate::decoding::FrameDecoder;
+
+    // The magicless format of the reference implementation is a regular frame without the magic number
+    let original = include_bytes!("../../decodecorpus_files/z000033");
+    let compressed = zstd::encode_all(&original[..], 3).unwrap();
+    let magicless = &compressed[4..];
+
+    let (_, header_size) = read_frame_header(compressed.as_slice()).unwrap();
+    let (_, magicless_size) = read_frame_header_magicless(magicless).unwrap();
+    assert_eq!(magicless_size + 4, header_size);
+    assert!(matches!(
+        read_frame_header_magicless(&[][..]),
+        Err(ReadFrameHeaderError::IncompleteHeader { got: 0, needed: 2 })
+    ));
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_magicless(true);
+    let mut decoded = Vec::with_capacity(original.len());
+    decoder.decode_all_to_vec(magicless, &mut decoded).unwrap();
+    assert_eq!(decoded, original);
+
+    decoder.set_magicless(false);
+    decoded.clear();
+    decoder
+        .decode_all_to_vec(&compressed, &mut decoded)
+        .unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[test]
+fn test_decode_frame_from_slice() {
+    use crate::decoding::decode_frame_from_slice;
+
+    let compressed = &include_bytes!("../../decodecorpus_files/z000033.zst")[..];
+    let original = &include_bytes!("../../decodecorpus_files/z000033")[..];
+
+    let mut input = compressed.to_vec();
+    input.extend_from_slice(b"trailing");
+    let (output, frame_len) = decode_frame_from_slice(&input).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(frame_len, compressed.len());
+
+    // The checksum belongs to the frame
+    #[cfg(feature = "hash")]
+    {
+        use crate::encoding::{CompressionLevel, FrameCompressor};
+
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_content_checksum(true);
+        compressor.set_source(original);
+        compressor.set_drain(Vec::new());
+        compressor.compress().unwrap();
+        let mut input = compressor.take_drain().unwrap();
+        let frame_end = input.len();
+        input.extend_from_slice(compressed);
+        let (output, frame_len) = decode_frame_from_slice(&input).unwrap();
+        assert_eq!(output, original);
+        assert_eq!(frame_len, frame_end);
+        let (output, frame_len) = decode_frame_from_slice(&input[frame_end..]).unwrap();
+        assert_eq!(output, original);
+        assert_eq!(frame_end + frame_len, input.len());
+    }
+
+    assert!(decode_frame_from_slice(&compressed[..compressed.len() - 10]).is_err());
+    assert!(decode_frame_from_slice(&[]).is_err());
+}
+
+#[test]
+fn test_decode_prefix() {
+    use crate::decoding::FrameDecoder;
+
+    let compressed = &include_bytes!("../../decodecorpus_files/z000033.zst")[..];
+    let original = &include_bytes!("../../decodecorpus_files/z000033")[..];
+
+    let mut decoder = FrameDecoder::new();
+    for n in [0, 1, 1000, 200 * 1024, original.len(), original.len() + 1] {
+        let prefix = decoder.decode_prefix(compressed, n).unwrap();
+        let expected = core::cmp::min(n, original.len());
+        assert_eq!(prefix.len(), expected);
+        assert_eq!(prefix, &original[..expected]);
+    }
+
+    // Only the blocks needed for the prefix are decoded
+    decoder.decode_prefix(compressed, 10).unwrap();
+    assert_eq!(decoder.blocks_decoded(), 1);
+    assert!(!decoder.is_finished());
+}
+
+#[test]
+fn test_extract_block() {
+    use crate::decoding::errors::ExtractBlockError;
+    use crate::decoding::{extract_block, BlockType};
+    use crate::encoding::{compress_to_vec, CompressionLevel};
+
+    let data: Vec<u8> = (0..300 * 1024).map(|x| (x % 251) as u8).collect();
+    let compressed = compress_to_vec(data.as_slice(), CompressionLevel::Uncompressed);
+
+    let mut offset = 0;
+    for index in 0..3 {
+        let (header, content) = extract_block(compressed.as_slice(), index).unwrap();
+        assert_eq!(header.block_type, BlockType::Raw);
+        assert_eq!(header.last_block, index == 2);
+        assert_eq!(content, &data[offset..offset + content.len()]);
+        offset += content.len();
+    }
+    assert_eq!(offset, data.len());
+
+    let result = extract_block(compressed.as_slice(), 3);
+    assert!(
+        matches!(
+            result,
+            Err(ExtractBlockError::BlockIndexOutOfRange {
+                index: 3,
+                num_blocks: 3
+            })
+        ),
+        "{:?}",
+        result
+    );
+
+    let result = extract_block(&compressed[..compressed.len() - 10], 2);
+    assert!(
+        matches!(result, Err(ExtractBlockError::BlockContentReadError(_))),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn test_decode_block_sequences() {
+    use crate::decoding::errors::ExtractBlockError;
+    use crate::decoding::{decode_block_sequences, extract_block, BlockScratch};
+
+    let compressed = &include_bytes!("../../decodecorpus_files/z000033.zst")[..];
+    let original = &include_bytes!("../../decodecorpus_files/z000033")[..];
+
+    // Execute the sequences by hand to check that they describe the original data
+    let mut output: Vec<u8> = Vec::new();
+    let mut rep = [1usize, 4, 8];
+    let mut scratch = BlockScratch::new();
+    let mut index = 0;
+    loop {
+        let (header, raw) = match extract_block(compressed, index) {
+            Ok(block) => block,
+            Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+            Err(e) => panic!("{:?}", e),
+        };
+        let (literals, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+        let mut literals = literals.as_slice();
+        for seq in sequences {
+            output.extend_from_slice(&literals[..seq.ll as usize]);
+            literals = &literals[seq.ll as usize..];
+
+            let offset = if seq.of > 3 {
+                let offset = seq.of as usize - 3;
+                rep = [offset, rep[0], rep[1]];
+                offset
+            } else {
+                let idx = if seq.ll == 0 { seq.of } else { seq.of - 1 };
+                match idx {
+                    0 => rep[0],
+                    1 => {
+                        rep = [rep[1], rep[0], rep[2]];
+                        rep[0]
+                    }
+                    _ => {
+                        let offset = if idx == 2 { rep[2] } else { rep[0] - 1 };
+                        rep = [offset, rep[0], rep[1]];
+                        offset
+                    }
+                }
+            };
+            for _ in 0..seq.ml {
+                output.push(output[output.len() - offset]);
+            }
+        }
+        output.extend_from_slice(literals);
+        index += 1;
+    }
+    assert!(index > 1);
+    assert_eq!(output, original);
+}
+
+#[test]
+fn test_repeat_offsets() {
+    use crate::decoding::errors::ExtractBlockError;
+    use crate::decoding::{decode_block_sequences, extract_block, BlockScratch, FrameDecoder};
+    use alloc::format;
+
+    // Records of the same layout with changing fields make the reference encoder reuse recent offsets
+    let mut original = Vec::new();
+    for i in 0..20_000u32 {
+        let record = format!(
+            "{{\"id\":{},\"name\":\"user{}\",\"group\":{},\"active\":{}}}\n",
+            i,
+            i.wrapping_mul(2_654_435_761) % 1000,
+            i % 7,
+            i % 3 == 0
+        );
+        original.extend_from_slice(record.as_bytes());
+    }
+    let compressed = zstd::encode_all(original.as_slice(), 19).unwrap();
+
+    let mut repeat_offsets = 0;
+    let mut scratch = BlockScratch::new();
+    let mut index = 0;
+    loop {
+        let (header, raw) = match extract_block(compressed.as_slice(), index) {
+            Ok(block) => block,
+            Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+            Err(e) => panic!("{:?}", e),
+        };
+        let (_, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+        repeat_offsets += sequences.iter().filter(|seq| seq.of <= 3).count();
+        index += 1;
+    }
+    assert!(index > 1);
+    assert!(repeat_offsets > 1000, "{}", repeat_offsets);
+
+    let mut decoded = Vec::with_capacity(original.len());
+    FrameDecoder::new()
+        .decode_all_to_vec(&compressed, &mut decoded)
+        .unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[test]
+fn test_block_content_decoder() {
+    use crate::decoding::errors::ExtractBlockError;
+    use crate::decoding::errors::{BlockSizeError, DecodeBlockContentError, DecompressBlockError};
+    use crate::decoding::{
+        decode_block_sequences, extract_block, BlockContentDecoder, BlockHeader, BlockScratch,
+        BlockType,
+    };
+
+    let mut decoder = BlockContentDecoder::new(0);
+    let frames: [(&[u8], &[u8]); 2] = [
+        (
+            include_bytes!("../../decodecorpus_files/z000033.zst"),
+            include_bytes!("../../decodecorpus_files/z000033"),
+        ),
+        (
+            include_bytes!("../../decodecorpus_files/z000088.zst"),
+            include_bytes!("../../decodecorpus_files/z000088"),
+        ),
+    ];
+    for (compressed, original) in frames {
+        let (frame, _) = crate::decoding::frame::read_frame_header(compressed).unwrap();
+        decoder.reset(frame.header.window_size().unwrap() as usize);
+
+        let mut output = Vec::new();
+        let mut index = 0;
+        loop {
+            let (header, raw) = match extract_block(compressed, index) {
+                Ok(block) => block,
+                Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+                Err(e) => panic!("{:?}", e),
+            };
+            output.extend(decoder.decode_block(&header, &raw).unwrap());
+            index += 1;
+        }
+        assert!(index > 1);
+        assert_eq!(output, original);
+    }
+
+    let rle = BlockHeader {
+        last_block: true,
+        block_type: BlockType::RLE,
+        decompressed_size: 1000,
+        content_size: 1,
+    };
+    assert_eq!(decoder.decode_block(&rle, &[3]).unwrap(), vec![3; 1000]);
+    assert!(matches!(
+        decoder.decode_block(&rle, &[3, 3]),
+        Err(DecodeBlockContentError::DecompressBlockError(
+            DecompressBlockError::TrailingBlockBytes {
+                expected: 1,
+                got: 2
+            }
+        ))
+    ));
+    let reserved = BlockHeader {
+        block_type: BlockType::Reserved,
+        ..rle
+    };
+    assert!(matches!(
+        decoder.decode_block(&reserved, &[3]),
+        Err(DecodeBlockContentError::DecompressBlockError(
+            DecompressBlockError::ReservedBlockType
+        ))
+    ));
+
+    // Headers that were not read by this crate can declare any size, it is checked before anything is allocated
+    let huge_rle = BlockHeader {
+        decompressed_size: u32::MAX,
+        ..rle
+    };
+    assert!(matches!(
+        decoder.decode_block(&huge_rle, &[3]),
+        Err(DecodeBlockContentError::DecompressBlockError(
+            DecompressBlockError::BlockSizeError(BlockSizeError::BlockSizeTooLarge {
+                size: u32::MAX
+            })
+        ))
+    ));
+    let mut scratch = BlockScratch::new();
+    assert!(matches!(
+        decode_block_sequences(&huge_rle, &[3], &mut scratch),
+        Err(DecompressBlockError::BlockSizeError(
+            BlockSizeError::BlockSizeTooLarge { size: u32::MAX }
+        ))
+    ));
+}
+
+#[test]
+fn test_encode_block_from_sequences() {
+    use crate::decoding::errors::ExtractBlockError;
+    use crate::decoding::{
+        decode_block_sequences, extract_block, BlockContentDecoder, BlockHeader, BlockScratch,
+        BlockType,
+    };
+    use crate::encoding::encode_block_from_sequences;
+
+    let compressed = &include_bytes!("../../decodecorpus_files/z000033.zst")[..];
+    let original = &include_bytes!("../../decodecorpus_files/z000033")[..];
+    let (frame, _) = crate::decoding::frame::read_frame_header(compressed).unwrap();
+
+    // Encode the sequences of every block again and decode the new blocks in order
+    let mut decoder = BlockContentDecoder::new(frame.header.window_size().unwrap() as usize);
+    let mut scratch = BlockScratch::new();
+    let mut output = Vec::new();
+    let mut compressed_blocks = 0;
+    let mut index = 0;
+    loop {
+        let (header, raw) = match extract_block(compressed, index) {
+            Ok(block) => block,
+            Err(ExtractBlockError::BlockIndexOutOfRange { .. }) => break,
+            Err(e) => panic!("{:?}", e),
+        };
+        let (literals, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+        if !sequences.is_empty() {
+            compressed_blocks += 1;
+        }
+        let mut content = Vec::new();
+        encode_block_from_sequences(&literals, &sequences, &mut content).unwrap();
+        let header = BlockHeader {
+            last_block: header.last_block,
+            block_type: BlockType::Compressed,
+            decompressed_size: 0,
+            content_size: content.len() as u32,
+        };
+        output.extend(decoder.decode_block(&header, &content).unwrap());
+        index += 1;
+    }
+    assert!(compressed_blocks > 1);
+    assert_eq!(output, original);
+
+    // Literals only, long enough to be huffman coded
+    let literals: Vec<u8> = (0..5000u32).map(|i| b'a' + (i % 5) as u8).collect();
+    let mut content = Vec::new();
+    encode_block_from_sequences(&literals, &[], &mut content).unwrap();
+    assert!(content.len() < literals.len());
+    let header = BlockHeader {
+        last_block: true,
+        block_type: BlockType::Compressed,
+        decompressed_size: 0,
+        content_size: content.len() as u32,
+    };
+    let mut decoder = BlockContentDecoder::new(1024 * 1024);
+    assert_eq!(decoder.decode_block(&header, &content).unwrap(), literals);
+}
+
+#[test]
+fn test_encode_block_from_sequences_invalid() {
+    use crate::decoding::Sequence;
+    use crate::encoding::encode_block_from_sequences;
+    use crate::encoding::errors::{CompressError, InvalidSequencesError};
+
+    let sequence = Sequence {
+        ll: 1,
+        ml: 3,
+        of: 4,
+    };
+    let cases = [
+        (
+            [sequence, Sequence { ml: 2, ..sequence }],
+            InvalidSequencesError::MatchTooShort { index: 1, ml: 2 },
+        ),
+        (
+            [Sequence { of: 0, ..sequence }, sequence],
+            InvalidSequencesError::ZeroOffset { index: 0 },
+        ),
+        (
+            [sequence, Sequence { ll: 10, ..sequence }],
+            InvalidSequencesError::MissingLiterals {
+                needed: 11,
+                available: 3,
+            },
+        ),
+        (
+            [
+                sequence,
+                Sequence {
+                    ml: 128 * 1024,
+                    ..sequence
+                },
+            ],
+            InvalidSequencesError::BlockTooBig {
+                size: 3 + 3 + 128 * 1024,
+            },
+        ),
+    ];
+    for (sequences, expected) in cases {
+        let mut content = Vec::new();
+        match encode_block_from_sequences(b"abc", &sequences, &mut content) {
+            Err(CompressError::InvalidSequences(e)) => assert_eq!(e, expected),
+            other => panic!("{:?}", other),
+        }
+        assert!(content.is_empty());
+    }
+}
+
+#[test]
+fn test_validate_structure() {
+    use crate::decoding::errors::{ReadFrameHeaderError, ValidateStructureError};
+    use crate::decoding::validate_structure;
+
+    let first = include_bytes!("../../decodecorpus_files/z000088.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let mut input = first.to_vec();
+    input.extend_from_slice(&0x184D2A50u32.to_le_bytes());
+    input.extend_from_slice(&3u32.to_le_bytes());
+    input.extend_from_slice(b"abc");
+    input.extend_from_slice(second);
+    validate_structure(input.as_slice()).unwrap();
+    validate_structure(&first[..]).unwrap();
+
+    // Every truncation is detected, the cuts fall into headers, block contents and the checksum
+    for cut in [1, 4, first.len() / 2, first.len() - 5] {
+        assert!(validate_structure(&first[..first.len() - cut]).is_err());
+    }
+    assert!(matches!(
+        validate_structure(&first[..first.len() - 2]),
+        Err(ValidateStructureError::ChecksumReadError(_))
+    ));
+    assert!(matches!(
+        validate_structure(&input[..first.len() + 9]),
+        Err(ValidateStructureError::SkippableFrameReadError(_))
+    ));
+
+    // Empty input and trailing garbage are not valid
+    assert!(matches!(
+        validate_structure(&[][..]),
+        Err(ValidateStructureError::ReadFrameHeaderError(
+            ReadFrameHeaderError::IncompleteHeader { got: 0, .. }
+        ))
+    ));
+    let mut garbage = first.to_vec();
+    garbage.extend_from_slice(b"garbage");
+    assert!(matches!(
+        validate_structure(garbage.as_slice()),
+        Err(ValidateStructureError::ReadFrameHeaderError(
+            ReadFrameHeaderError::BadMagicNumber(_)
+        ))
+    ));
+}
+
+#[test]
+fn test_last_block_stats() {
+    use crate::decoding::{BlockDecodingStrategy, BlockType, FrameDecoder};
+
+    let mut content = include_bytes!("../../decodecorpus_files/z000033.zst").as_slice();
+    let original = include_bytes!("../../decodecorpus_files/z000033");
+
+    let mut frame_dec = FrameDecoder::new();
+    assert_eq!(frame_dec.last_block_stats(), None);
+    frame_dec.reset(&mut content).unwrap();
+    assert_eq!(frame_dec.last_block_stats(), None);
+
+    let mut decompressed = 0;
+    let mut saw_sequences = false;
+    while !frame_dec.is_finished() {
+        let bytes_read_before = frame_dec.bytes_read_from_source();
+        frame_dec
+            .decode_blocks(&mut content, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap();
+        let stats = frame_dec.last_block_stats().unwrap();
+
+        let mut bytes_read = frame_dec.bytes_read_from_source() - bytes_read_before;
+        if frame_dec.is_finished() && frame_dec.get_checksum_from_data().is_some() {
+            bytes_read -= 4;
+        }
+        assert_eq!(u64::from(stats.compressed_size) + 3, bytes_read);
+        if stats.block_type == BlockType::Compressed {
+            assert!(stats.literals_size <= stats.decompressed_size);
+            saw_sequences |= stats.num_sequences > 0;
+        } else {
+            assert_eq!(stats.num_sequences, 0);
+            assert_eq!(stats.literals_size, 0);
+        }
+        decompressed += stats.decompressed_size as usize;
+    }
+    assert!(saw_sequences);
+    assert_eq!(decompressed, original.len());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_streaming_decoder_slice_reuse() {
+    use crate::decoding::StreamingDecoder;
+    use std::io::Read;
+
+    let first = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000068.zst");
+    let mut content = first.to_vec();
+    content.extend_from_slice(second);
+
+    let mut decoder = StreamingDecoder::from_slice(&content).unwrap();
+    let mut result = Vec::new();
+    decoder.read_to_end(&mut result).unwrap();
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000033"));
+    let (remaining, frame_dec) = decoder.into_parts();
+    assert_eq!(remaining, second);
+
+    let mut decoder = StreamingDecoder::new_with_decoder(remaining, frame_dec).unwrap();
+    let mut result = Vec::new();
+    decoder.read_to_end(&mut result).unwrap();
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000068"));
+    assert!(decoder.get_ref().is_empty());
+
+    // A truncated frame is an error, not a short read
+    let mut decoder = StreamingDecoder::from_slice(&first[..first.len() - 10]).unwrap();
+    assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_decode_blocks_after_finish() {
+    use crate::decoding::{BlockDecodingStrategy, FrameDecoder};
+
+    let first = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let second = include_bytes!("../../decodecorpus_files/z000068.zst");
+    let mut content = first.to_vec();
+    content.extend_from_slice(second);
+
+    let mut source = content.as_slice();
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(&mut source).unwrap();
+    while !frame_dec.is_finished() {
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap();
+    }
+    assert_eq!(source, second);
+
+    // Decoding more blocks must neither touch the next frame nor change the decoded data
+    let finished = frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap();
+    assert!(finished);
+    assert_eq!(source, second);
+    let finished = frame_dec
+        .decode_blocks(&[][..], BlockDecodingStrategy::All)
+        .unwrap();
+    assert!(finished);
+
+    let mut result = Vec::new();
+    frame_dec.collect_to_writer(&mut result).unwrap();
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000033"));
+}
+
+#[test]
+fn test_streaming_into_inner() {
+    use crate::decoding::StreamingDecoder;
+    use crate::io::Read;
+
+    let frame = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let mut content = frame.to_vec();
+    content.extend_from_slice(b"trailing data that is not zstd");
+
+    let mut decoder = StreamingDecoder::new(content.as_slice()).unwrap();
+    let mut result = Vec::new();
+    let mut buf = [0u8; 1000];
+    loop {
+        let read = decoder.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        result.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(result, include_bytes!("../../decodecorpus_files/z000033"));
+
+    let rest = decoder.into_inner();
+    assert_eq!(rest, b"trailing data that is not zstd");
+}
+
+#[test]
+fn test_single_segment_content_size() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{frame::MAGIC_NUM, BlockDecodingStrategy, FrameDecoder};
+
+    // Single segment frame with a 4 byte content size field and one raw block
+    fn frame(declared: u32, content: &[u8]) -> Vec<u8> {
+        let mut frame = MAGIC_NUM.to_le_bytes().to_vec();
+        frame.push(0b1010_0000);
+        frame.extend_from_slice(&declared.to_le_bytes());
+        let block_header = (content.len() as u32) << 3 | 1;
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(content);
+        frame
+    }
+
+    // The declared size is bigger than the maximum window size, but not needed for decoding the actual content.
+    // Decode two frames so the path reusing the decoder state is covered too.
+    let mut frame_dec = FrameDecoder::new();
+    for _ in 0..2 {
+        let frame = frame(1024 * 1024 * 1024, b"small content");
+        let mut source = frame.as_slice();
+        frame_dec.reset(&mut source).unwrap();
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::All)
+            .unwrap();
+        let mut result = Vec::new();
+        frame_dec.collect_to_writer(&mut result).unwrap();
+        assert_eq!(result, b"small content");
+    }
+
+    let frame = frame(5, b"more than declared");
+    let mut source = frame.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    let err = frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::ContentSizeExceeded {
+            declared: 5,
+            decoded: 18
+        }
+    ));
+}
+
+#[test]
+fn test_debug_impls() {
+    use crate::decoding::{FrameDecoder, StreamingDecoder};
+    use crate::encoding::{CompressionLevel, FrameCompressor};
+    use alloc::format;
+
+    let frame = include_bytes!("../../decodecorpus_files/z000033.zst");
+
+    let decoder = FrameDecoder::new();
+    assert!(format!("{:?}", decoder).contains("initialized: false"));
+
+    let decoder = StreamingDecoder::new(&frame[..]).unwrap();
+    let debug = format!("{:?}", decoder);
+    assert!(debug.starts_with("StreamingDecoder { decoder: FrameDecoder {"));
+    assert!(debug.contains("frame_finished: false"));
+    assert!(debug.contains("window_size"));
+
+    let mut compressor: FrameCompressor<&[u8], Vec<u8>, _> =
+        FrameCompressor::new(CompressionLevel::Fastest);
+    compressor.set_source(&frame[..]);
+    let debug = format!("{:?}", compressor);
+    assert!(debug.contains("compression_level: Fastest"));
+    assert!(debug.contains("has_source: true"));
+    assert!(debug.contains("has_drain: false"));
+}
+
+#[test]
+fn test_single_segment_output_limit() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{frame::MAGIC_NUM, BlockDecodingStrategy, FrameDecoder};
+
+    // Single segment frame declaring 1TB of content in an 8 byte content size field, followed by RLE blocks of 128KB
+    let mut frame = MAGIC_NUM.to_le_bytes().to_vec();
+    frame.push(0b1110_0000);
+    frame.extend_from_slice(&(1u64 << 40).to_le_bytes());
+    for _ in 0..64 {
+        let block_header = (128 * 1024) << 3 | 1 << 1;
+        frame.extend_from_slice(&(block_header as u32).to_le_bytes()[..3]);
+        frame.push(0xAA);
+    }
+
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.set_max_window_size(1024 * 1024);
+    let mut source = frame.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    let err = frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::WindowSizeTooBig {
+            requested,
+            max: 0x100000
+        } if requested == 1 << 40
+    ));
+    // Decoding stops at the first block that goes over the limit
+    assert_eq!(frame_dec.blocks_decoded(), 8);
+}
+
+#[test]
+fn test_window_budget() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{frame::read_frame_header, FrameDecoder};
+
+    let data = alloc::vec![7u8; 1000];
+    let compressed = crate::encoding::compress_to_vec(
+        data.as_slice(),
+        crate::encoding::CompressionLevel::Fastest,
+    );
+    let window_size = read_frame_header(compressed.as_slice())
+        .unwrap()
+        .0
+        .header
+        .window_size()
+        .unwrap();
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_window_budget(Some(window_size as usize - 1));
+    let err = decoder.reset(compressed.as_slice()).unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::WindowExceedsBudget { requested, budget }
+            if requested == window_size && budget == window_size as usize - 1
+    ));
+    // Also checked when the decoder is initialized implicitly
+    let err = decoder
+        .decode_all_to_vec(&compressed, &mut Vec::with_capacity(data.len()))
+        .unwrap_err();
+    assert!(matches!(err, FrameDecoderError::WindowExceedsBudget { .. }));
+
+    decoder.set_window_budget(Some(window_size as usize));
+    let mut result = Vec::with_capacity(data.len());
+    decoder.decode_all_to_vec(&compressed, &mut result).unwrap();
+    assert_eq!(result, data);
+
+    decoder.set_window_budget(None);
+    let mut result = Vec::with_capacity(data.len());
+    decoder.decode_all_to_vec(&compressed, &mut result).unwrap();
+    assert_eq!(result, data);
+}
+
+#[test]
+fn test_max_window_size() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{frame::read_frame_header, FrameDecoder};
+    use crate::encoding::{compress_slice_to_vec, compress_to_vec, CompressionLevel};
+
+    // Without a declared content size the frame has a window descriptor of 1MB
+    let data = alloc::vec![7u8; 1000];
+    let compressed = compress_to_vec(data.as_slice(), CompressionLevel::Default);
+    let window_size = read_frame_header(compressed.as_slice())
+        .unwrap()
+        .0
+        .header
+        .window_size()
+        .unwrap();
+    assert_eq!(window_size, 1024 * 1024);
+
+    // Checked for the first frame of a new decoder and for frames of a reused one
+    for reused in [false, true] {
+        let mut decoder = FrameDecoder::new();
+        if reused {
+            decoder.init(compressed.as_slice()).unwrap();
+        }
+        decoder.set_max_window_size(window_size - 1);
+        let err = decoder.init(compressed.as_slice()).unwrap_err();
+        assert!(matches!(
+            err,
+            FrameDecoderError::WindowSizeTooBig { requested, max }
+                if requested == window_size && max == window_size - 1
+        ));
+    }
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_window_size(window_size);
+    let mut result = Vec::with_capacity(data.len());
+    decoder.decode_all_to_vec(&compressed, &mut result).unwrap();
+    assert_eq!(result, data);
+
+    // Single segment frames have no window descriptor, they are limited by their output
+    decoder.set_max_window_size(10);
+    let single_segment = compress_slice_to_vec(&data, CompressionLevel::Default);
+    let mut result = Vec::with_capacity(data.len());
+    let err = decoder
+        .decode_all_to_vec(&single_segment, &mut result)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::WindowSizeTooBig {
+            requested: 1000,
+            max: 10
+        }
+    ));
+    decoder.set_max_window_size(1000);
+    decoder
+        .decode_all_to_vec(&single_segment, &mut result)
+        .unwrap();
+    assert_eq!(result, data);
+
+    // Also applies to the frame that is currently decoded
+    let mut source = single_segment.as_slice();
+    decoder.reset(&mut source).unwrap();
+    decoder.set_max_window_size(10);
+    assert!(matches!(
+        decoder.decode_blocks(&mut source, crate::decoding::BlockDecodingStrategy::All),
+        Err(FrameDecoderError::WindowSizeTooBig { max: 10, .. })
+    ));
+}
+
+/// Needs about 500MB of memory, run with `cargo test --release -- --ignored test_long_window`
+#[test]
+#[ignore]
+fn test_long_window() {
+    use crate::decoding::block_decoder;
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{decode_block_sequences, frame::read_frame_header};
+    use crate::decoding::{BlockScratch, FrameDecoder};
+    use crate::io::Read;
+    use rand::{RngCore, SeedableRng};
+    use std::io::Write;
+
+    const WINDOW_LOG: u32 = 27;
+
+    // Random data can only be compressed by matching its repetition 49MB later, far beyond
+    // the 32MB that offset codes up to 24 can reach
+    let mut random = vec![0u8; 48 * 1024 * 1024];
+    rand::rngs::SmallRng::seed_from_u64(0).fill_bytes(&mut random);
+    let mut data = random.clone();
+    for _ in 0..1024 {
+        data.extend_from_slice(&include_bytes!("../../decodecorpus_files/z000033")[..1024]);
+    }
+    data.extend_from_slice(&random);
+    drop(random);
+
+    // Like `zstd --long=27 --check`
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).unwrap();
+    encoder.long_distance_matching(true).unwrap();
+    encoder.window_log(WINDOW_LOG).unwrap();
+    encoder.include_checksum(true).unwrap();
+    encoder.write_all(&data).unwrap();
+    let compressed = encoder.finish().unwrap();
+    assert!(compressed.len() < data.len() / 2 + 2 * 1024 * 1024);
+
+    let mut source = compressed.as_slice();
+    let (frame, _) = read_frame_header(&mut source).unwrap();
+    assert_eq!(frame.header.window_size().unwrap(), 1 << WINDOW_LOG);
+
+    // The matches into the first copy of the random data need offset codes above 24
+    let mut block_dec = block_decoder::new();
+    let mut scratch = BlockScratch::new();
+    let mut max_offset = 0;
+    loop {
+        let (header, _) = block_dec.read_block_header(&mut source).unwrap();
+        let mut raw = vec![0; header.content_size as usize];
+        source.read_exact(&mut raw).unwrap();
+        let (_, sequences) = decode_block_sequences(&header, &raw, &mut scratch).unwrap();
+        max_offset = sequences
+            .iter()
+            .filter(|seq| seq.of > 3)
+            .map(|seq| seq.of - 3)
+            .fold(max_offset, u32::max);
+        if header.last_block {
+            break;
+        }
+    }
+    assert!(max_offset > 1 << 25, "{}", max_offset);
+
+    // The window is bigger than the default limit of 100MB
+    let mut decoder = FrameDecoder::new();
+    assert!(matches!(
+        decoder.init(compressed.as_slice()),
+        Err(FrameDecoderError::WindowSizeTooBig { .. })
+    ));
+    decoder.set_max_window_size(1 << WINDOW_LOG);
+    let mut result = Vec::with_capacity(data.len());
+    decoder.decode_all_to_vec(&compressed, &mut result).unwrap();
+    assert!(result == data);
+}
+
+#[test]
+fn test_alloc_hook() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::{AllocKind, FrameDecoder, FrameInfo};
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    const KINDS: [AllocKind; 5] = [
+        AllocKind::Window,
+        AllocKind::Tables,
+        AllocKind::BlockContent,
+        AllocKind::Literals,
+        AllocKind::Sequences,
+    ];
+    let compressed = include_bytes!("../../decodecorpus_files/z000033.zst");
+    let expected = include_bytes!("../../decodecorpus_files/z000033");
+
+    let allocated: Arc<[AtomicUsize; 5]> = Arc::new(Default::default());
+    let hook_allocated = allocated.clone();
+    let mut decoder = FrameDecoder::new();
+    decoder.set_alloc_hook(Some(Box::new(move |kind: AllocKind, bytes: usize| {
+        let idx = KINDS.iter().position(|k| *k == kind).unwrap();
+        hook_allocated[idx].fetch_add(bytes, Ordering::Relaxed);
+        true
+    })));
+    let mut result = Vec::with_capacity(expected.len());
+    decoder.decode_all_to_vec(compressed, &mut result).unwrap();
+    assert_eq!(result, expected);
+    let first: Vec<usize> = allocated
+        .iter()
+        .map(|a| a.load(Ordering::Relaxed))
+        .collect();
+    for (kind, bytes) in KINDS.iter().zip(&first) {
+        assert!(*bytes > 0, "nothing allocated for {:?}", kind);
+    }
+    assert!(first[0] as u64 >= FrameInfo::read(&compressed[..]).unwrap().window_size);
+
+    // The buffers are reused for the next frame
+    let mut result = Vec::with_capacity(expected.len());
+    decoder.decode_all_to_vec(compressed, &mut result).unwrap();
+    assert_eq!(result, expected);
+    let second: Vec<usize> = allocated
+        .iter()
+        .map(|a| a.load(Ordering::Relaxed))
+        .collect();
+    assert_eq!(first, second);
+
+    // A fresh decoder with a limit below what the frame needs
+    let limit = first.iter().sum::<usize>() / 2;
+    let mut used = 0;
+    let mut decoder = FrameDecoder::new();
+    decoder.set_alloc_hook(Some(Box::new(move |_: AllocKind, bytes: usize| {
+        used += bytes;
+        used <= limit
+    })));
+    let err = decoder
+        .decode_all_to_vec(compressed, &mut Vec::with_capacity(expected.len()))
+        .unwrap_err();
+    assert!(format!("{:?}", err).contains("AllocationRejected"));
+
+    // The window for the next frame is requested when the decoder is reset
+    let mut decoder = FrameDecoder::new();
+    decoder
+        .reset(&[0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x00, 0x01, 0x00, 0x00][..])
+        .unwrap();
+    decoder.set_alloc_hook(Some(Box::new(|kind: AllocKind, _: usize| {
+        kind != AllocKind::Window
+    })));
+    let err = decoder.reset(&compressed[..]).unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::AllocationRejected {
+            kind: AllocKind::Window,
+            ..
+        }
+    ));
+
+    decoder.set_alloc_hook(None);
+    let mut result = Vec::with_capacity(expected.len());
+    decoder.decode_all_to_vec(compressed, &mut result).unwrap();
+    assert_eq!(result, expected);
+
+    // Reserving the window up front asks the hook too, also before the first frame
+    let mut decoder = FrameDecoder::new();
+    decoder.set_alloc_hook(Some(Box::new(|kind: AllocKind, _: usize| {
+        kind != AllocKind::Window
+    })));
+    let err = decoder.reserve_window(1024 * 1024).unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::AllocationRejected {
+            kind: AllocKind::Window,
+            ..
+        }
+    ));
+    decoder.set_alloc_hook(None);
+    // Windows above the maximum window size are rejected before anything is allocated
+    assert!(matches!(
+        decoder.reserve_window(usize::MAX),
+        Err(FrameDecoderError::WindowSizeTooBig { .. })
+    ));
+    decoder.set_max_window_size(1024);
+    assert!(matches!(
+        decoder.reserve_window(1024 * 1024),
+        Err(FrameDecoderError::WindowSizeTooBig {
+            requested: 0x100000,
+            max: 1024
+        })
+    ));
+    assert!(FrameDecoder::with_window_capacity(usize::MAX).is_err());
+    decoder.set_max_window_size(1024 * 1024);
+    decoder.reserve_window(1024 * 1024).unwrap();
+    let mut result = Vec::with_capacity(expected.len());
+    decoder.decode_all_to_vec(compressed, &mut result).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[cfg(all(test, feature = "std"))]
+pub mod allocations;
 pub mod bit_reader;
+#[cfg(all(test, feature = "std", feature = "hash"))]
+pub mod checksum;
 pub mod decode_corpus;
 pub mod dict_test;
 #[cfg(feature = "std")]
 pub mod encode_corpus;
 pub mod fuzz_regressions;
+#[cfg(all(test, feature = "parallel"))]
+pub mod parallel;
+#[cfg(test)]
+pub mod spec_vectors;
+
+#[test]
+fn test_lenient() {
+    use crate::decoding::errors::FrameDecoderError;
+    use crate::decoding::FrameDecoder;
+
+    // No content size, window descriptor for 1KB
+    let mut frame = alloc::vec![0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x00];
+    // Raw block "hello "
+    frame.extend_from_slice(&[6 << 3, 0, 0]);
+    frame.extend_from_slice(b"hello ");
+    // Compressed block with one sequence and reserved bits set in the compression modes
+    frame.extend_from_slice(&[(3 << 3) | (2 << 1), 0, 0, 0x00, 0x01, 0xFF]);
+    // Last block, RLE "xxxxx"
+    frame.extend_from_slice(&[(5 << 3) | (1 << 1) | 1, 0, 0, b'x']);
+
+    let mut decoder = FrameDecoder::new();
+    let mut output = [0u8; 100];
+    let err = decoder.decode_all(&frame, &mut output).unwrap_err();
+    assert!(matches!(err, FrameDecoderError::FailedToReadBlockBody(_)));
+
+    decoder.set_lenient(true);
+    let written = decoder.decode_all(&frame, &mut output).unwrap();
+    assert_eq!(&output[..written], b"hello xxxxx");
+    assert!(decoder.is_finished());
+    let corrupt = decoder.corrupt_blocks();
+    assert_eq!(corrupt.len(), 1);
+    assert_eq!(corrupt[0].block_index, 1);
+    assert_eq!(corrupt[0].output_position, 6);
+    assert_eq!(corrupt[0].decoded_size, 0);
+
+    // The same when decoding from slices
+    let mut decoder = FrameDecoder::new();
+    decoder.set_lenient(true);
+    let (read, written) = decoder.decode_from_to(&frame, &mut output).unwrap();
+    assert_eq!(read, frame.len());
+    assert_eq!(&output[..written], b"hello xxxxx");
+    assert_eq!(decoder.corrupt_blocks().len(), 1);
+
+    // A truncated block can not be skipped
+    let mut decoder = FrameDecoder::new();
+    decoder.set_lenient(true);
+    assert!(decoder
+        .decode_all(&frame[..frame.len() - 7], &mut output)
+        .is_err());
+}
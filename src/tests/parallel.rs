@@ -0,0 +1,129 @@
+//! Decoding with [FrameDecoder::decode_blocks_parallel] has to give exactly the same output and errors as
+//! [FrameDecoder::decode_blocks], including for corrupted input where the batches fall back to serial decoding.
+
+extern crate std;
+
+use crate::decoding::{BlockDecodingStrategy, FrameDecoder};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::fs;
+
+/// What decoding all frames of a source results in
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    output: Vec<u8>,
+    error: Option<String>,
+    corrupt_blocks: String,
+    checksums: Vec<Option<u32>>,
+}
+
+fn decode(
+    compressed: &[u8],
+    parallel: bool,
+    strat: fn() -> BlockDecodingStrategy,
+    lenient: bool,
+) -> Outcome {
+    let mut decoder = FrameDecoder::new();
+    decoder.set_lenient(lenient);
+    let mut source = compressed;
+    let mut outcome = Outcome {
+        output: Vec::new(),
+        error: None,
+        corrupt_blocks: String::new(),
+        checksums: Vec::new(),
+    };
+    while !source.is_empty() {
+        if let Err(e) = decoder.reset(&mut source) {
+            outcome.error = Some(format!("{:?}", e));
+            return outcome;
+        }
+        while !decoder.is_finished() {
+            let result = if parallel {
+                decoder.decode_blocks_parallel(&mut source, strat())
+            } else {
+                decoder.decode_blocks(&mut source, strat())
+            };
+            if let Err(e) = result {
+                outcome.error = Some(format!("{:?}", e));
+                outcome.output.extend(decoder.collect().unwrap_or_default());
+                return outcome;
+            }
+            outcome.output.extend(decoder.collect().unwrap_or_default());
+        }
+        outcome.output.extend(decoder.collect().unwrap_or_default());
+        outcome.corrupt_blocks += &format!("{:?}", decoder.corrupt_blocks());
+        outcome.checksums.push(decoder.get_checksum_from_data());
+    }
+    outcome
+}
+
+fn strategies() -> [fn() -> BlockDecodingStrategy; 3] {
+    [
+        || BlockDecodingStrategy::All,
+        || BlockDecodingStrategy::UptoBlocks(1),
+        || BlockDecodingStrategy::UptoBytes(200 * 1024),
+    ]
+}
+
+fn assert_same_as_serial(compressed: &[u8], lenient: bool) -> Outcome {
+    let serial = decode(compressed, false, || BlockDecodingStrategy::All, lenient);
+    for strat in strategies() {
+        assert_eq!(decode(compressed, true, strat, lenient), serial);
+    }
+    serial
+}
+
+/// Several blocks compressed by the reference implementation, which repeats tables from previous blocks
+fn multi_block_frame() -> (Vec<u8>, Vec<u8>) {
+    let mut data = Vec::new();
+    for name in ["z000033", "z000088", "z000068", "z000098"] {
+        data.extend(fs::read(format!("./decodecorpus_files/{}", name)).unwrap());
+    }
+    let text = include_bytes!("../../Readme.md");
+    while data.len() < 1024 * 1024 {
+        data.extend_from_slice(text);
+        data.extend((0..4096).map(|x| (x * 7 % 253) as u8));
+    }
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), 19).unwrap();
+    encoder.include_checksum(true).unwrap();
+    std::io::Write::write_all(&mut encoder, &data).unwrap();
+    (encoder.finish().unwrap(), data)
+}
+
+#[test]
+fn parallel_decoding_matches_corpus() {
+    for file in fs::read_dir("./decodecorpus_files").unwrap() {
+        let path = file.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "zst") {
+            continue;
+        }
+        let compressed = fs::read(&path).unwrap();
+        let outcome = assert_same_as_serial(&compressed, false);
+        assert_eq!(outcome.error, None, "{:?}", path);
+    }
+}
+
+#[test]
+fn parallel_decoding_matches_multi_block_frame() {
+    let (compressed, data) = multi_block_frame();
+    let outcome = assert_same_as_serial(&compressed, false);
+    assert_eq!(outcome.error, None);
+    assert_eq!(outcome.output, data);
+}
+
+#[test]
+fn parallel_decoding_matches_corrupted_frames() {
+    let (compressed, _) = multi_block_frame();
+    for i in 1..16 {
+        let position = i * compressed.len() / 16;
+        let mut corrupted = compressed.clone();
+        corrupted[position] ^= 0x55;
+        assert_same_as_serial(&corrupted, false);
+        assert_same_as_serial(&corrupted, true);
+
+        let truncated = &compressed[..position];
+        assert!(assert_same_as_serial(truncated, false).error.is_some());
+        assert_same_as_serial(truncated, true);
+    }
+}
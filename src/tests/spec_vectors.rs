@@ -0,0 +1,322 @@
+//! Checks the table construction against the examples and tables of the specification (RFC 8878)
+//! instead of against another implementation.
+//!
+//! <https://www.rfc-editor.org/rfc/rfc8878>
+
+use crate::decoding::sequence_section_decoder::{
+    lookup_ll_code, lookup_ml_code, LITERALS_LENGTH_DEFAULT_DISTRIBUTION, LL_DEFAULT_ACC_LOG,
+    MATCH_LENGTH_DEFAULT_DISTRIBUTION, ML_DEFAULT_ACC_LOG, OFFSET_DEFAULT_DISTRIBUTION,
+    OF_DEFAULT_ACC_LOG,
+};
+use crate::fse::FSETable;
+
+/// Appendix A, predefined literal length decoding table as (symbol, number of bits, baseline) per state
+const LL_DEFAULT_STATES: [(u8, u8, u32); 64] = [
+    (0, 4, 0),
+    (0, 4, 16),
+    (1, 5, 32),
+    (3, 5, 0),
+    (4, 5, 0),
+    (6, 5, 0),
+    (7, 5, 0),
+    (9, 5, 0),
+    (10, 5, 0),
+    (12, 5, 0),
+    (14, 6, 0),
+    (16, 5, 0),
+    (18, 5, 0),
+    (19, 5, 0),
+    (21, 5, 0),
+    (22, 5, 0),
+    (24, 5, 0),
+    (25, 5, 32),
+    (26, 5, 0),
+    (27, 6, 0),
+    (29, 6, 0),
+    (31, 6, 0),
+    (0, 4, 32),
+    (1, 4, 0),
+    (2, 5, 0),
+    (4, 5, 32),
+    (5, 5, 0),
+    (7, 5, 32),
+    (8, 5, 0),
+    (10, 5, 32),
+    (11, 5, 0),
+    (13, 6, 0),
+    (16, 5, 32),
+    (17, 5, 0),
+    (19, 5, 32),
+    (20, 5, 0),
+    (22, 5, 32),
+    (23, 5, 0),
+    (25, 4, 0),
+    (25, 4, 16),
+    (26, 5, 32),
+    (28, 6, 0),
+    (30, 6, 0),
+    (0, 4, 48),
+    (1, 4, 16),
+    (2, 5, 32),
+    (3, 5, 32),
+    (5, 5, 32),
+    (6, 5, 32),
+    (8, 5, 32),
+    (9, 5, 32),
+    (11, 5, 32),
+    (12, 5, 32),
+    (15, 6, 0),
+    (17, 5, 32),
+    (18, 5, 32),
+    (20, 5, 32),
+    (21, 5, 32),
+    (23, 5, 32),
+    (24, 5, 32),
+    (35, 6, 0),
+    (34, 6, 0),
+    (33, 6, 0),
+    (32, 6, 0),
+];
+
+/// Appendix A, predefined match length decoding table as (symbol, number of bits, baseline) per state
+const ML_DEFAULT_STATES: [(u8, u8, u32); 64] = [
+    (0, 6, 0),
+    (1, 4, 0),
+    (2, 5, 32),
+    (3, 5, 0),
+    (5, 5, 0),
+    (6, 5, 0),
+    (8, 5, 0),
+    (10, 6, 0),
+    (13, 6, 0),
+    (16, 6, 0),
+    (19, 6, 0),
+    (22, 6, 0),
+    (25, 6, 0),
+    (28, 6, 0),
+    (31, 6, 0),
+    (33, 6, 0),
+    (35, 6, 0),
+    (37, 6, 0),
+    (39, 6, 0),
+    (41, 6, 0),
+    (43, 6, 0),
+    (45, 6, 0),
+    (1, 4, 16),
+    (2, 4, 0),
+    (3, 5, 32),
+    (4, 5, 0),
+    (6, 5, 32),
+    (7, 5, 0),
+    (9, 6, 0),
+    (12, 6, 0),
+    (15, 6, 0),
+    (18, 6, 0),
+    (21, 6, 0),
+    (24, 6, 0),
+    (27, 6, 0),
+    (30, 6, 0),
+    (32, 6, 0),
+    (34, 6, 0),
+    (36, 6, 0),
+    (38, 6, 0),
+    (40, 6, 0),
+    (42, 6, 0),
+    (44, 6, 0),
+    (1, 4, 32),
+    (1, 4, 48),
+    (2, 4, 16),
+    (4, 5, 32),
+    (5, 5, 32),
+    (7, 5, 32),
+    (8, 5, 32),
+    (11, 6, 0),
+    (14, 6, 0),
+    (17, 6, 0),
+    (20, 6, 0),
+    (23, 6, 0),
+    (26, 6, 0),
+    (29, 6, 0),
+    (52, 6, 0),
+    (51, 6, 0),
+    (50, 6, 0),
+    (49, 6, 0),
+    (48, 6, 0),
+    (47, 6, 0),
+    (46, 6, 0),
+];
+
+/// Appendix A, predefined offset decoding table as (symbol, number of bits, baseline) per state
+const OF_DEFAULT_STATES: [(u8, u8, u32); 32] = [
+    (0, 5, 0),
+    (6, 4, 0),
+    (9, 5, 0),
+    (15, 5, 0),
+    (21, 5, 0),
+    (3, 5, 0),
+    (7, 4, 0),
+    (12, 5, 0),
+    (18, 5, 0),
+    (23, 5, 0),
+    (5, 5, 0),
+    (8, 4, 0),
+    (14, 5, 0),
+    (20, 5, 0),
+    (2, 5, 0),
+    (7, 4, 16),
+    (11, 5, 0),
+    (17, 5, 0),
+    (22, 5, 0),
+    (4, 5, 0),
+    (8, 4, 16),
+    (13, 5, 0),
+    (19, 5, 0),
+    (1, 5, 0),
+    (6, 4, 16),
+    (10, 5, 0),
+    (16, 5, 0),
+    (28, 5, 0),
+    (27, 5, 0),
+    (26, 5, 0),
+    (25, 5, 0),
+    (24, 5, 0),
+];
+
+fn check_states(acc_log: u8, distribution: &[i32], expected: &[(u8, u8, u32)]) {
+    let mut table = FSETable::new(255);
+    table
+        .build_from_probabilities(acc_log, distribution)
+        .unwrap();
+    assert_eq!(table.decode.len(), expected.len());
+    for (state, (entry, &(symbol, num_bits, base_line))) in
+        table.decode.iter().zip(expected).enumerate()
+    {
+        assert_eq!(
+            (entry.symbol, entry.num_bits, entry.base_line),
+            (symbol, num_bits, base_line),
+            "state {}",
+            state
+        );
+    }
+}
+
+#[test]
+fn predefined_literal_length_table() {
+    check_states(
+        LL_DEFAULT_ACC_LOG,
+        &LITERALS_LENGTH_DEFAULT_DISTRIBUTION,
+        &LL_DEFAULT_STATES,
+    );
+}
+
+#[test]
+fn predefined_match_length_table() {
+    check_states(
+        ML_DEFAULT_ACC_LOG,
+        &MATCH_LENGTH_DEFAULT_DISTRIBUTION,
+        &ML_DEFAULT_STATES,
+    );
+}
+
+#[test]
+fn predefined_offset_table() {
+    check_states(
+        OF_DEFAULT_ACC_LOG,
+        &OFFSET_DEFAULT_DISTRIBUTION,
+        &OF_DEFAULT_STATES,
+    );
+}
+
+#[test]
+fn literal_length_codes() {
+    // Section 3.1.1.3.2.1.1, literal length codes 16 to 35 as (baseline, number of extra bits)
+    const CODES: [(u32, u8); 20] = [
+        (16, 1),
+        (18, 1),
+        (20, 1),
+        (22, 1),
+        (24, 2),
+        (28, 2),
+        (32, 3),
+        (40, 3),
+        (48, 4),
+        (64, 6),
+        (128, 7),
+        (256, 8),
+        (512, 9),
+        (1024, 10),
+        (2048, 11),
+        (4096, 12),
+        (8192, 13),
+        (16384, 14),
+        (32768, 15),
+        (65536, 16),
+    ];
+    for code in 0..16 {
+        assert_eq!(lookup_ll_code(code), (u32::from(code), 0));
+    }
+    for (code, expected) in (16..).zip(CODES) {
+        assert_eq!(lookup_ll_code(code), expected, "code {}", code);
+    }
+}
+
+#[test]
+fn match_length_codes() {
+    // Section 3.1.1.3.2.1.1, match length codes 32 to 52 as (baseline, number of extra bits)
+    const CODES: [(u32, u8); 21] = [
+        (35, 1),
+        (37, 1),
+        (39, 1),
+        (41, 1),
+        (43, 2),
+        (47, 2),
+        (51, 3),
+        (59, 3),
+        (67, 4),
+        (83, 4),
+        (99, 5),
+        (131, 7),
+        (259, 8),
+        (515, 9),
+        (1027, 10),
+        (2051, 11),
+        (4099, 12),
+        (8195, 13),
+        (16387, 14),
+        (32771, 15),
+        (65539, 16),
+    ];
+    for code in 0..32 {
+        assert_eq!(lookup_ml_code(code), (u32::from(code) + 3, 0));
+    }
+    for (code, expected) in (32..).zip(CODES) {
+        assert_eq!(lookup_ml_code(code), expected, "code {}", code);
+    }
+}
+
+#[test]
+fn huffman_example() {
+    use crate::huff0::{HuffmanDecoder, HuffmanTable};
+
+    // Section 4.2.1.3: Literals 0 to 4 have the weights 4, 3, 2, 0 and 1, the weight of literal 5 is implied.
+    // As direct representation: 5 weights, 4 bits each
+    let description = [127 + 5, 0x43, 0x20, 0x10];
+    let mut table = HuffmanTable::new();
+    assert_eq!(table.build_decoder(&description).unwrap(), 4);
+    assert_eq!(table.max_num_bits, 4);
+
+    // The resulting prefix codes are 1, 01, 001, 0000 and 0001 for the literals 0, 1, 2, 4 and 5
+    let mut decoder = HuffmanDecoder::new(&table);
+    for state in 0..16u64 {
+        let expected = match state {
+            0b1000..=0b1111 => 0,
+            0b0100..=0b0111 => 1,
+            0b0010..=0b0011 => 2,
+            0b0000 => 4,
+            0b0001 => 5,
+            _ => unreachable!(),
+        };
+        decoder.state = state;
+        assert_eq!(decoder.decode_symbol(), expected, "state {:04b}", state);
+    }
+}